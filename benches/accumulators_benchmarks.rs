@@ -16,10 +16,14 @@ use criterion::Criterion;
 
 mod rsa_benches {
     use super::*;
-    use accumulators::group::RSAGroup;
-    use accumulators::traits::{BatchedAccumulator, StaticAccumulator};
+    use accumulators::group::{ModN, RSAGroup};
+    use accumulators::hash::hash_prime;
+    use accumulators::proofs::PoeTranscript;
+    use accumulators::traits::{BatchedAccumulator, DynamicAccumulator, StaticAccumulator};
     use accumulators::Accumulator;
-    use num_bigint::RandPrime;
+    use blake2::Blake2b;
+    use num_bigint::{BigUint, RandBigInt, RandPrime};
+    use num_traits::One;
     use rand::SeedableRng;
     use rand_chacha::ChaChaRng;
 
@@ -80,6 +84,85 @@ mod rsa_benches {
         });
     }
 
+    fn bench_ver_mem_star_16(c: &mut Criterion) {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, N);
+        let xs: Vec<_> = (0..16).map(|_| rng.gen_prime(L)).collect();
+        acc.batch_add(&xs);
+        let pis: Vec<_> = xs
+            .iter()
+            .map(|x| (x.clone(), acc.mem_wit_create_star(x)))
+            .collect();
+        let batch_acc = acc.clone();
+        let batch_pis = pis.clone();
+
+        c.bench_function("bench_ver_mem_star_16", move |b| {
+            b.iter(|| pis.iter().all(|(x, pi)| acc.ver_mem_star(x, pi)))
+        });
+        c.bench_function("bench_ver_mem_star_batch_16", move |b| {
+            b.iter(|| batch_acc.ver_mem_star_batch(&batch_pis))
+        });
+    }
+
+    fn bench_poe_challenges_1000(c: &mut Criterion) {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        // the exponent of a batch of 1000 elements, with 16 statements
+        let mut x = BigUint::one();
+        for _ in 0..1000 {
+            x *= rng.gen_prime(L);
+        }
+        let group = ModN::new(&rng.gen_biguint(N)).unwrap();
+        let us: Vec<_> = (0..16).map(|_| rng.gen_biguint(N)).collect();
+        let shared_x = x.clone();
+        let shared_us = us.clone();
+
+        c.bench_function("bench_poe_challenges_1000", move |b| {
+            b.iter(|| {
+                for u in &us {
+                    let mut to_hash = x.to_bytes_be();
+                    to_hash.extend(u.to_bytes_be());
+                    to_hash.extend(u.to_bytes_be());
+                    hash_prime::<_, Blake2b>(&to_hash);
+                }
+            })
+        });
+        c.bench_function("bench_poe_challenges_shared_1000", move |b| {
+            b.iter(|| {
+                let transcript = PoeTranscript::new(&shared_x);
+                for u in &shared_us {
+                    transcript.challenge(&group, u, u);
+                }
+            })
+        });
+    }
+
+    fn bench_del_1000(c: &mut Criterion) {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, N);
+        let xs: Vec<_> = (0..1000).map(|_| rng.gen_prime(L)).collect();
+        acc.batch_add(&xs);
+        let mut split_acc = acc.clone();
+        split_acc.precompute_powers(1000 * L, 8);
+        let x = xs[0].clone();
+        let split_x = x.clone();
+
+        c.bench_function("bench_del_1000", move |b| {
+            b.iter(|| {
+                let mut acc = acc.clone();
+                acc.del(&x)
+            })
+        });
+        c.bench_function("bench_del_split_1000", move |b| {
+            b.iter(|| {
+                let mut acc = split_acc.clone();
+                acc.del(&split_x)
+            })
+        });
+    }
+
     criterion_group! {
         name = rsa_benches;
         config = Criterion::default();
@@ -88,8 +171,10 @@ mod rsa_benches {
             bench_mem_wit_create_1,
             bench_batch_add_1,
             bench_ver_batch_add_1,
+            bench_ver_mem_star_16,
+            bench_poe_challenges_1000,
+            bench_del_1000,
     }
-
 }
 
 #[cfg(not(feature = "classgroup"))]
@@ -1,17 +1,42 @@
+use blake2::{Blake2b, Digest};
+use num_bigint::prime::probably_prime;
 use num_bigint::traits::{ExtendedGcd, ModInverse};
 use num_bigint::{BigInt, BigUint, IntoBigUint};
 use num_integer::Integer;
 use num_traits::{One, Zero};
+use rand::rngs::OsRng;
 use rand::CryptoRng;
 use rand::Rng;
-
-use crate::math::{modpow_uint_int, root_factor, shamir_trick};
-use crate::proofs;
+use std::borrow::{Borrow, Cow};
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+#[cfg(feature = "metrics")]
+use std::time::Instant;
+
+use crate::encoding::write_part;
+use crate::error::Errors;
+use crate::filter::MembershipFilter;
+use crate::group::ModN;
+use crate::hash::{hash_prime, hash_prime_keyed, HashKey, HASH_PRIME_BITS};
+use crate::hooks::{Hooks, Mutation, MutationKind, MutationObserver};
+use crate::math::{
+    chunk_products, cofactor_pow, modpow_uint_int, multi_pow, product, root_factor, shamir_trick,
+    PowerTable, ProductTree,
+};
+#[cfg(feature = "metrics")]
+use crate::metrics::{self, Metrics, Recorder};
+use crate::proofs::{
+    self, CommittedExponentProof, CommittedWesolowski, KnowledgeProof, PoeTranscript,
+    ProofOfExponentiation, QrWesolowski, Wesolowski,
+};
 use crate::traits::*;
+use crate::undo::{UndoEntry, UndoLog};
 
 // All accumulated values are small odd primes.
 // Arbitrary data values can be hashed to small primes,
 // It is also assumed that no item is added twice to the accumulator !!!
+// Clones are cheap, they share the parameters and the set product until one of them changes.
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub struct Accumulator {
@@ -21,364 +46,3081 @@ pub struct Accumulator {
     /// An accumulator must have a public key divided into two parts.
     /// A common reference string pp generated by the Setup algorithm in place of private/public keys.
     /// Generator
-    g: BigUint,
+    g: Arc<BigUint>,
 
     /// Our Modulus, generated by using a public randomness known by the adversary
-    n: BigUint,
+    n: Arc<BigUint>,
+
+    /// The key of `PublicParams::hash_to_prime`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    hash_key: Option<HashKey>,
+
+    /// Whether proofs are checked in `QR_n`, see `PublicParams::into_qr`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    qr: bool,
 
     /// Current accumulator state
     root: BigUint,
 
     /// The set of elements currently accumulated (product of the current set)
-    set: BigUint,
+    /// Shared between clones until one of them changes it.
+    set: Arc<BigUint>,
+
+    /// The number of elements currently accumulated.
+    count: usize,
+
+    /// The number of changes since the setup.
+    epoch: u64,
+
+    /// The last changes, if enabled with `enable_undo`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    undo: Option<UndoLog>,
+
+    /// A filter over the members, if enabled with `enable_filter`.
+    /// Shared between clones until one of them changes it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    filter: Option<Arc<MembershipFilter>>,
+
+    /// Powers of `g` for splitting exponentiations by the set, see `precompute_powers`.
+    /// These can be recomputed, so they are not persisted.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    powers: Option<Arc<PowerTable>>,
+
+    /// The order of the group, only known to a `TrapdoorManager`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    order: GroupOrder,
+
+    /// Observers notified on every change, these are not persisted.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: Hooks,
+
+    /// Receives counters and timings, this is not persisted.
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    metrics: Metrics,
 }
 
-impl Accumulator {}
+/// `φ(n)`, to reduce exponents before exponentiating.
+/// This is the trapdoor, so clones don't carry it, and it is never printed.
+#[derive(Default)]
+struct GroupOrder(Option<Arc<BigUint>>);
 
-impl StaticAccumulator for Accumulator {
-    /// Returns the current public state.
-    fn state(&self) -> &BigUint {
-        &self.root
+impl Clone for GroupOrder {
+    fn clone(&self) -> Self {
+        GroupOrder(None)
     }
+}
 
-    /// Generates a group of unknown order and initializes the group with a generator of that group.
-    /// Setup(λ, z) → pp, A0 Generate the public parameters
-    fn setup<T, R>(rng: &mut R, int_size_bits: usize) -> Self
+impl fmt::Debug for GroupOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GroupOrder {{ .. }}")
+    }
+}
+
+/// The changes between two states of an accumulator.
+///
+/// Both states are connected through `mid_root = g^{s_1 \cap s_2}`, with NI-PoEs for the
+/// deletions `mid_root^deleted = from_root` and the additions `mid_root^added = root`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateDiff {
+    pub from_epoch: u64,
+    pub from_root: BigUint,
+    pub epoch: u64,
+    pub root: BigUint,
+    /// The number of elements in the new state.
+    pub len: usize,
+    /// Product of the added elements.
+    pub added: BigUint,
+    /// Product of the deleted elements.
+    pub deleted: BigUint,
+    pub mid_root: BigUint,
+    pub del_proof: BigUint,
+    pub add_proof: BigUint,
+}
+
+/// Adds and deletes, queued to be applied as a single transition by `commit`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingOps {
+    added: Vec<BigUint>,
+    deleted: Vec<BigUint>,
+}
+
+impl PendingOps {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn add(&mut self, x: &BigUint) {
+        self.added.push(x.clone());
+    }
+
+    pub fn del(&mut self, x: &BigUint) {
+        self.deleted.push(x.clone());
+    }
+
+    pub fn added(&self) -> &[BigUint] {
+        &self.added
+    }
+
+    pub fn deleted(&self) -> &[BigUint] {
+        &self.deleted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.deleted.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.added.clear();
+        self.deleted.clear();
+    }
+}
+
+/// Hints for the members added by `batch_add_with_hints`, to compute their witnesses.
+///
+/// The batch is split into ranges, for every range the hint contains the product of its
+/// elements `P_j` and `base_j = A_{t-1}^{x^* / P_j}`. The witness of an element `x` in range
+/// `j` is `base_j^{P_j / x}`, a small exponentiation for short ranges.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateHints {
+    pub ranges: Vec<RangeHint>,
+}
+
+/// The hint for the elements `xs[start..end]` of a batch.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RangeHint {
+    pub start: usize,
+    pub end: usize,
+    pub product: BigUint,
+    pub base: BigUint,
+}
+
+impl UpdateHints {
+    /// Returns the hint for the range containing index `i` of the batch.
+    pub fn range(&self, i: usize) -> Option<&RangeHint> {
+        self.ranges.iter().find(|r| r.start <= i && i < r.end)
+    }
+
+    /// Computes the witness of `x`, which was at index `i` in the batch.
+    /// Returns `None` if `i` is out of range, or `x` is not in its range.
+    pub fn witness(&self, i: usize, x: &BigUint, n: &BigUint) -> Option<BigUint> {
+        let range = self.range(i)?;
+        let (e, r) = range.product.div_rem(x);
+        if !r.is_zero() {
+            return None;
+        }
+
+        Some(range.base.modpow(&e, n))
+    }
+}
+
+/// The public parameters of an accumulator: the group and its generator.
+///
+/// Generating these is expensive (and a trusted setup for RSA groups), so they can be
+/// generated once and shared by many accumulators, using `Accumulator::new_with_params`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PublicParams {
+    /// Length of the modulus in bits, the security parameter.
+    pub int_size_bits: usize,
+    /// The modulus, must be odd.
+    pub n: BigUint,
+    /// The generator.
+    pub g: BigUint,
+    /// The key of `hash_to_prime`, `None` for the unkeyed `hash_prime`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub hash_key: Option<HashKey>,
+    /// Whether proofs are checked in `QR_n`, see `into_qr`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub qr: bool,
+}
+
+impl PublicParams {
+    /// Generates a group of unknown order with `|n| = int_size_bits` and a generator.
+    pub fn generate<T, R>(rng: &mut R, int_size_bits: usize) -> Self
     where
         T: PrimeGroup,
         R: CryptoRng + Rng,
     {
-        // Generate n = p q, |n| = int_size_bits
+        #[cfg(feature = "tracing")]
+        let _span = libtracing::info_span!("setup", int_size_bits).entered();
+
         // This is a trusted setup, as we do know `p` and `q`, even though
         // we choose not to store them.
-
         let (n, g) = T::generate_primes(rng, int_size_bits).unwrap();
 
-        Accumulator {
+        PublicParams {
             int_size_bits,
-            root: g.clone(),
-            g,
             n,
-            set: BigUint::one(),
+            g,
+            hash_key: None,
+            qr: false,
         }
     }
 
-    ///Takes the current accumulator At, an element from the odd primes domain, and computes At+1 = At.
-    #[inline]
-    fn add(&mut self, x: &BigUint) {
-        debug_assert!(
-            self.g.clone().modpow(&self.set, &self.n) == self.root,
-            "invalid state - pre add"
-        );
-
-        // assumes x is already a prime
-        self.set *= x;
-        self.root = self.root.modpow(x, &self.n);
+    /// Moves the accumulator into the subgroup `QR_n` of quadratic residues, by squaring the
+    /// generator.
+    ///
+    /// All roots are residues then, and membership, non-membership and batch proofs are
+    /// checked in `QR_n`, mapping the witnesses and proofs into it with `map_element`. This
+    /// removes attacks with elements of small order like `-1`, e.g. proving `u^x = -w`
+    /// instead of `u^x = w`. Batch proofs use `QrWesolowski` instead of `Wesolowski`.
+    pub fn into_qr(mut self) -> Self {
+        if !self.qr {
+            self.g = (&self.g * &self.g) % &self.n;
+            self.qr = true;
+        }
+        self
     }
 
-    //A membership witness is simply the accumulator without the aggregated item.
-    #[inline]
-    fn mem_wit_create(&self, x: &BigUint) -> BigUint {
-        debug_assert!(
-            self.g.clone().modpow(&self.set, &self.n) == self.root,
-            "invalid state"
-        );
+    /// Maps a value supplied by another party into the group, i.e. into `QR_n` by squaring it
+    /// with `into_qr`, and unchanged otherwise.
+    pub fn map_element(&self, x: &BigUint) -> BigUint {
+        if self.qr {
+            (x * x) % &self.n
+        } else {
+            x.clone()
+        }
+    }
 
-        let (set, r) = self.set.clone().div_rem(x);
-        debug_assert!(r.is_zero(), "x was not a valid member of set");
+    /// Sets a random key for `hash_to_prime`.
+    pub fn with_random_hash_key<R: CryptoRng + Rng>(mut self, rng: &mut R) -> Self {
+        let mut key = [0u8; 32];
+        rng.fill(&mut key);
+        self.hash_key = Some(key);
+        self
+    }
 
-        self.g.clone().modpow(&set, &self.n)
+    /// Hashes `input` to an element, see `hash_prime_keyed`, or `hash_prime` without a key.
+    pub fn hash_to_prime(&self, input: &[u8]) -> BigUint {
+        match &self.hash_key {
+            Some(key) => hash_prime_keyed(key, input),
+            None => hash_prime::<_, Blake2b>(input),
+        }
     }
 
-    #[inline]
-    fn ver_mem(&self, w: &BigUint, x: &BigUint) -> bool {
-        w.modpow(x, &self.n) == self.root
+    /// Like `generate`, seeded from the operating system's randomness.
+    /// Panics if no secure randomness is available.
+    pub fn generate_default<T: PrimeGroup>(int_size_bits: usize) -> Self {
+        let rng = &mut OsRng::new().expect("no secure randomness available");
+        Self::generate::<T, _>(rng, int_size_bits)
     }
 }
 
-impl DynamicAccumulator for Accumulator {
-    #[inline]
-    fn del(&mut self, x: &BigUint) -> Option<()> {
-        let old_s = self.set.clone();
-        self.set /= x;
+/// Recommended sizes of an RSA modulus.
+///
+/// Anything below `Rsa2048` is only suitable for tests. The modulus can't be changed
+/// later without a `rotate_modulus`, so prefer the larger sizes for long lived accumulators.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModulusSize {
+    /// About 112 bits of security.
+    Rsa2048,
+    /// About 128 bits of security.
+    Rsa3072,
+    /// About 140 bits of security.
+    Rsa4096,
+}
 
-        if self.set == old_s {
-            return None;
+impl ModulusSize {
+    /// Returns the size in bits, as passed to `setup_default` or `PublicParams::generate`.
+    pub fn bits(self) -> usize {
+        match self {
+            ModulusSize::Rsa2048 => 2048,
+            ModulusSize::Rsa3072 => 3072,
+            ModulusSize::Rsa4096 => 4096,
         }
+    }
+}
 
-        self.root = self.g.clone().modpow(&self.set, &self.n); //Returns (self ^ exponent) % modulus.
-        Some(())
+/// Result of recomputing the accumulator state from the accumulated set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateReport {
+    /// The root as currently stored.
+    pub root: BigUint,
+    /// The root recomputed as `g^set mod n`.
+    pub expected_root: BigUint,
+    /// Bit size of the product of the accumulated set.
+    pub set_bits: usize,
+    /// Whether the stored root is a reduced residue, i.e. `0 < root < n`.
+    pub root_in_range: bool,
+}
+
+impl StateReport {
+    /// Returns `true` iff the stored root matches the accumulated set.
+    pub fn is_valid(&self) -> bool {
+        self.root_in_range && self.root == self.expected_root
     }
 }
 
-impl UniversalAccumulator for Accumulator {
-    fn non_mem_wit_create(&self, x: &BigUint) -> (BigUint, BigInt) {
-        // set* <- \prod_{set\in S} set
-        let s_star = &self.set;
+/// Migrates membership witnesses into a merged accumulator.
+/// See [`Accumulator::merge`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitnessMigration {
+    /// Product of the set of the merged in accumulator.
+    other_set: BigUint,
+    /// Product of the set of the accumulator merged into.
+    self_set: BigUint,
+    n: BigUint,
+}
 
-        // a, b <- Bezout(x, set*)
-        let (_, a, b) = ExtendedGcd::extended_gcd(x, s_star);
-        let d = modpow_uint_int(&self.g, &a, &self.n).expect("prime");
+impl WitnessMigration {
+    /// Migrates a witness, created by the accumulator that was merged into.
+    pub fn migrate_self(&self, w: &BigUint) -> BigUint {
+        w.modpow(&self.other_set, &self.n)
+    }
 
-        (d, b)
+    /// Migrates a witness, created by the accumulator that was merged in.
+    pub fn migrate_other(&self, w: &BigUint) -> BigUint {
+        w.modpow(&self.self_set, &self.n)
     }
+}
 
-    fn ver_non_mem(&self, w: &(BigUint, BigInt), x: &BigUint) -> bool {
-        let (d, b) = w;
+/// The migration of an accumulator to the generator `g' = g^r`, see [`Accumulator::rerandomize`].
+///
+/// Raising both sides of the membership equation `w^x = A` to `r` gives `(w^r)^x = A^r`, so
+/// every witness is transformed by raising it to `r`. The NI-PoEs show that the new generator
+/// and root were derived with the same public `r`, without recomputing the exponentiations.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GeneratorMigration {
+    pub n: BigUint,
+    pub r: BigUint,
+    pub old_g: BigUint,
+    pub old_root: BigUint,
+    pub g: BigUint,
+    pub root: BigUint,
+    /// NI-PoE for `old_g^r = g`.
+    pub g_proof: BigUint,
+    /// NI-PoE for `old_root^r = root`.
+    pub root_proof: BigUint,
+}
 
-        // A^b
-        let a_b = modpow_uint_int(&self.root, b, &self.n).expect("prime");
-        // d^x
-        let d_x = d.modpow(x, &self.n);
+impl GeneratorMigration {
+    /// Checks that the new generator and root were both derived with `r`.
+    pub fn verify(&self) -> bool {
+        !self.r.is_zero()
+            && proofs::ni_poe_verify_many(
+                &self.r,
+                &[
+                    (&self.old_g, &self.g, &self.g_proof),
+                    (&self.old_root, &self.root, &self.root_proof),
+                ],
+                &self.n,
+            )
+    }
 
-        // d^x A^b == g
-        (d_x * &a_b) % &self.n == self.g
+    /// Transforms a membership witness for the old root.
+    pub fn migrate_mem_wit(&self, w: &BigUint) -> BigUint {
+        w.modpow(&self.r, &self.n)
+    }
+
+    /// Transforms a non-membership witness `(d, b)` for the old root, only `d` changes.
+    pub fn migrate_non_mem_wit(&self, w: &(BigUint, BigInt)) -> (BigUint, BigInt) {
+        (w.0.modpow(&self.r, &self.n), w.1.clone())
     }
 }
 
-impl BatchedAccumulator for Accumulator {
-    fn batch_add(&mut self, xs: &[BigUint]) -> BigUint {
-        //begin our summation of the added elements
-        let mut x_star = BigUint::one();
-        for x in xs {
-            x_star *= x;
-            //add into element
-            self.set *= x;
+/// The transition of an accumulator to a new modulus, see [`Accumulator::rotate_modulus`].
+///
+/// Both roots commit to the same set: `set` is the product of all members, and the NI-PoEs
+/// show `old.g^set = old_root` and `new.g^set = root` in the respective groups.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModulusTransition {
+    pub old: PublicParams,
+    pub new: PublicParams,
+    pub epoch: u64,
+    pub old_root: BigUint,
+    pub root: BigUint,
+    /// The number of members.
+    pub len: usize,
+    /// Product of all members.
+    pub set: BigUint,
+    /// NI-PoE for `old.g^set = old_root`.
+    pub old_proof: BigUint,
+    /// NI-PoE for `new.g^set = root`.
+    pub new_proof: BigUint,
+}
+
+impl ModulusTransition {
+    /// Checks that both roots accumulate `set`.
+    pub fn verify(&self) -> bool {
+        let (old, new) = match (ModN::new(&self.old.n), ModN::new(&self.new.n)) {
+            (Some(old), Some(new)) => (old, new),
+            _ => return false,
+        };
+
+        // the set is large, only hash it once
+        let transcript = PoeTranscript::new(&self.set);
+        proofs::ni_poe_verify_with(
+            &old,
+            &transcript,
+            &self.old.g,
+            &self.old_root,
+            &self.old_proof,
+        )
+        .is_ok()
+            && proofs::ni_poe_verify_with(
+                &new,
+                &transcript,
+                &self.new.g,
+                &self.root,
+                &self.new_proof,
+            )
+            .is_ok()
+    }
+
+    /// Returns `true` if `x` was carried over, i.e. it divides the set commitment.
+    pub fn contains(&self, x: &BigUint) -> bool {
+        !x.is_zero() && !x.is_one() && (&self.set % x).is_zero()
+    }
+}
+
+/// Proves that the set of one accumulator is a subset of the set of another, using the same
+/// modulus but possibly a different generator, see [`Accumulator::prove_subset`].
+///
+/// For `a = g_a^s` and `b = g_b^(s k)`, `c = g_b^s` moves `s` to the other generator. A NI-PoKE2
+/// for `(g_a g_b^γ)^s = a c^γ` shows that `a` and `c` use the same `s`, and a NI-PoKE2 for
+/// `c^k = b` that `b` accumulates a multiple of it. Neither reveals the elements.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubsetProof {
+    pub c: BigUint,
+    pub same: KnowledgeProof,
+    pub quotient: KnowledgeProof,
+}
+
+impl SubsetProof {
+    /// Checks that the set of `a` is a subset of the set of `b`.
+    pub fn verify(&self, a: &Accumulator, b: &Accumulator) -> bool {
+        if a.n != b.n {
+            return false;
         }
+        let n = &*a.n;
+        let gamma = subset_challenge(n, &a.g, &a.root, &b.g, &self.c);
+        let u = (&*a.g * b.g.modpow(&gamma, n)) % n;
+        let w = (&a.root * self.c.modpow(&gamma, n)) % n;
 
-        //temp clone our old root
-        let root_t = self.root.clone();
-        //calculate our new root after all the added elements
-        self.root = self.root.modpow(&x_star, &self.n); //Returns (self ^ exponent) % modulus.
-                                                        //create our proof for the procedure
-        proofs::ni_poe_prove(&x_star, &root_t, &self.root, &self.n)
+        proofs::ni_poke2_verify(&u, &w, &self.same, n)
+            && proofs::ni_poke2_verify(&self.c, &b.root, &self.quotient, n)
     }
+}
 
-    fn ver_batch_add(&self, w: &BigUint, root: &BigUint, xs: &[BigUint]) -> bool {
-        let mut x_star = BigUint::one();
-        for x in xs {
-            x_star *= x
+/// Proves that two accumulators commit to the same set, by proving that each set is a subset
+/// of the other, see [`Accumulator::prove_same_set`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetEqualityProof {
+    pub a_in_b: SubsetProof,
+    pub b_in_a: SubsetProof,
+}
+
+impl SetEqualityProof {
+    /// Checks that `a` and `b` accumulate the same set.
+    pub fn verify(&self, a: &Accumulator, b: &Accumulator) -> bool {
+        self.a_in_b.verify(a, b) && self.b_in_a.verify(b, a)
+    }
+}
+
+/// γ <- H(n, g_a, a, g_b, c)
+fn subset_challenge(
+    n: &BigUint,
+    g_a: &BigUint,
+    a: &BigUint,
+    g_b: &BigUint,
+    c: &BigUint,
+) -> BigUint {
+    let mut to_hash = Vec::new();
+    for part in &[n, g_a, a, g_b, c] {
+        write_part(&mut to_hash, &part.to_bytes_be());
+    }
+
+    BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..HASH_PRIME_BITS / 8])
+}
+
+/// The size of the weights of `ver_mem_star_batch`.
+const STAR_BATCH_WEIGHT_BYTES: usize = 16;
+
+/// γ_i <- 2 H(H(n, root, x_1, w_1, Q_1, ..), i) + 1
+fn star_batch_weights(
+    n: &BigUint,
+    root: &BigUint,
+    pis: &[(BigUint, (BigUint, BigUint))],
+) -> Vec<BigUint> {
+    let mut to_hash = Vec::new();
+    write_part(&mut to_hash, &n.to_bytes_be());
+    write_part(&mut to_hash, &root.to_bytes_be());
+    for (x, (w, q)) in pis {
+        for part in &[x, w, q] {
+            write_part(&mut to_hash, &part.to_bytes_be());
         }
+    }
+    let seed = Blake2b::digest(&to_hash);
+
+    (0..pis.len() as u64)
+        .map(|i| {
+            let mut hasher = Blake2b::new();
+            hasher.input(seed.as_slice());
+            hasher.input(&i.to_be_bytes());
+            (BigUint::from_bytes_be(&hasher.result()[..STAR_BATCH_WEIGHT_BYTES]) << 1) + 1u32
+        })
+        .collect()
+}
+
+impl Accumulator {
+    /// Creates an empty accumulator, using existing public parameters.
+    ///
+    /// Panics if `params.n` is even.
+    pub fn new_with_params(params: &PublicParams) -> Self {
+        assert!(params.n.is_odd(), "modulus must be odd");
 
-        proofs::ni_poe_verify(&x_star, root, &self.root, &w, &self.n)
+        Accumulator {
+            int_size_bits: params.int_size_bits,
+            g: Arc::new(params.g.clone()),
+            n: Arc::new(params.n.clone()),
+            hash_key: params.hash_key,
+            qr: params.qr,
+            root: params.g.clone(),
+            set: Arc::new(BigUint::one()),
+            count: 0,
+            epoch: 0,
+            undo: None,
+            filter: None,
+            powers: None,
+            order: GroupOrder::default(),
+            hooks: Hooks::default(),
+            #[cfg(feature = "metrics")]
+            metrics: Metrics::default(),
+        }
     }
 
-    fn batch_del(&mut self, pairs: &[(BigUint, BigUint)]) -> Option<BigUint> {
-        if pairs.is_empty() {
-            return None;
+    /// Reconstructs an accumulator from the full list of its elements, e.g. for disaster
+    /// recovery, or to check a published root against the dataset.
+    ///
+    /// The elements are multiplied with a product tree, and the root is computed with a single
+    /// exponentiation. The epoch of the result is `0`, as the history can't be recovered.
+    /// Panics if `params.n` is even.
+    pub fn rebuild(params: &PublicParams, elements: impl IntoIterator<Item = BigUint>) -> Self {
+        let mut acc = Accumulator::new_with_params(params);
+        acc.accumulate_all(elements);
+        acc
+    }
+
+    /// Like `rebuild`, splitting the exponentiation with the precomputed `powers`, see
+    /// `precompute_powers`. The result keeps using them.
+    /// Fails with `ParameterMismatch` if they were computed for other parameters.
+    pub fn rebuild_with_powers(
+        params: &PublicParams,
+        elements: impl IntoIterator<Item = BigUint>,
+        powers: Arc<PowerTable>,
+    ) -> Result<Self, Errors> {
+        let mut acc = Accumulator::new_with_params(params);
+        acc.set_powers(powers)?;
+        acc.accumulate_all(elements);
+        Ok(acc)
+    }
+
+    /// Replaces the set of an empty accumulator by `elements`, computing the root with a single
+    /// exponentiation, see `rebuild`.
+    pub(crate) fn accumulate_all(&mut self, elements: impl IntoIterator<Item = BigUint>) {
+        let elements = elements.into_iter().collect::<Vec<_>>();
+
+        self.accumulate_product(product(&elements), elements.len());
+    }
+
+    /// Replaces the accumulated set by `count` elements with the product `set`.
+    pub(crate) fn accumulate_product(&mut self, set: BigUint, count: usize) {
+        self.set = Arc::new(set);
+        self.root = self.pow_g(&self.set);
+        self.count = count;
+    }
+
+    /// Sets up an accumulator, seeded from the operating system's randomness.
+    ///
+    /// Use this instead of `setup`, unless the setup has to be reproducible. See [`ModulusSize`]
+    /// for the recommended sizes. Panics if no secure randomness is available.
+    pub fn setup_default<T: PrimeGroup>(int_size_bits: usize) -> Self {
+        Accumulator::new_with_params(&PublicParams::generate_default::<T>(int_size_bits))
+    }
+
+    /// Returns the public parameters of this accumulator.
+    pub fn params(&self) -> PublicParams {
+        PublicParams {
+            int_size_bits: self.int_size_bits,
+            n: (*self.n).clone(),
+            g: (*self.g).clone(),
+            hash_key: self.hash_key,
+            qr: self.qr,
         }
-        let mut pairs = pairs.iter();
-        let root_t = self.root.clone();
+    }
 
-        let (x0, w0) = pairs.next().unwrap();
-        let mut x_star = x0.clone();
-        let mut new_root = w0.clone();
+    /// Returns `true` if proofs are checked in `QR_n`, see `PublicParams::into_qr`.
+    pub fn is_qr(&self) -> bool {
+        self.qr
+    }
 
-        for (xi, wi) in pairs {
-            new_root = shamir_trick(&new_root, wi, &x_star, xi, &self.n).unwrap();
-            x_star *= xi;
-            // for now this is not great, depends on this impl, not on the general design
-            self.set /= xi;
+    /// See `PublicParams::map_element`.
+    pub(crate) fn map_element(&self, x: &BigUint) -> BigUint {
+        if self.qr {
+            (x * x) % &*self.n
+        } else {
+            x.clone()
+        }
+    }
+
+    /// Returns the modulus `n` of the group.
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// Returns the generator `g` of the group.
+    pub fn generator(&self) -> &BigUint {
+        &self.g
+    }
+
+    /// Returns the number of changes since the setup.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Restores the epoch of a rebuilt accumulator.
+    pub(crate) fn set_epoch(&mut self, epoch: u64) {
+        self.epoch = epoch;
+    }
+
+    /// Returns the number of accumulated elements.
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Returns `true` if no elements are accumulated.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Returns the bit size of the product of all accumulated elements.
+    pub fn set_bits(&self) -> usize {
+        self.set.bits()
+    }
+
+    /// Returns the product of all accumulated elements.
+    pub(crate) fn set_product(&self) -> &BigUint {
+        &self.set
+    }
+
+    /// Returns `true` if `x` is a member, i.e. it divides the product of the set.
+    /// This uses the set tracked by the manager, and needs no witness. With a filter, most
+    /// non-members are rejected without dividing the product.
+    pub fn contains(&self, x: &BigUint) -> bool {
+        if let Some(filter) = &self.filter {
+            if !filter.may_contain(x) {
+                return false;
+            }
+        }
+
+        !x.is_zero() && !x.is_one() && (&*self.set % x).is_zero()
+    }
+
+    /// Merges the accumulator `other` into this one.
+    ///
+    /// Both must use the same public parameters and their sets must be disjoint.
+    /// The resulting root is `g^{s_1 s_2}`, the returned [`WitnessMigration`] updates
+    /// witnesses from either side to the merged accumulator.
+    /// Observers registered on `other` are dropped, and the filter is kept only if both
+    /// sides have one of the same size.
+    pub fn merge(mut self, other: Accumulator) -> Result<(Self, WitnessMigration), Errors> {
+        if self.params() != other.params() {
+            return Err(Errors::ParameterMismatch);
+        }
+
+        if !self.set.gcd(&other.set).is_one() {
+            return Err(Errors::NotDisjoint);
+        }
+
+        // A_1^{s_2} = g^{s_1 s_2}
+        self.root = self.root.modpow(&self.reduce_exponent(&other.set), &self.n);
+
+        let migration = WitnessMigration {
+            self_set: (*self.set).clone(),
+            other_set: (*other.set).clone(),
+            n: (*self.n).clone(),
+        };
+
+        *Arc::make_mut(&mut self.set) *= &*other.set;
+        self.count += other.count;
+        self.clear_undo();
+        let merged = match (&mut self.filter, &other.filter) {
+            (Some(filter), Some(o)) => Arc::make_mut(filter).merge(o),
+            _ => false,
+        };
+        if !merged {
+            self.filter = None;
+        }
+
+        Ok((self, migration))
+    }
+
+    /// Calculates the changes from this state to `other`, which must use the same parameters.
+    pub fn diff(&self, other: &Accumulator) -> Result<StateDiff, Errors> {
+        if self.params() != other.params() {
+            return Err(Errors::ParameterMismatch);
+        }
+
+        let common = self.set.gcd(&other.set);
+        let deleted = &*self.set / &common;
+        let added = &*other.set / &common;
+
+        let mid_root = if deleted.is_one() {
+            self.root.clone()
+        } else if added.is_one() {
+            other.root.clone()
+        } else {
+            self.pow_g(&common)
+        };
+
+        let del_proof = proofs::ni_poe_prove(&deleted, &mid_root, &self.root, &self.n);
+        let add_proof = proofs::ni_poe_prove(&added, &mid_root, &other.root, &self.n);
+
+        Ok(StateDiff {
+            from_epoch: self.epoch,
+            from_root: self.root.clone(),
+            epoch: other.epoch,
+            root: other.root.clone(),
+            len: other.count,
+            added,
+            deleted,
+            mid_root,
+            del_proof,
+            add_proof,
+        })
+    }
+
+    /// Verifies and applies a diff, created by `diff` from this state.
+    /// Observers are not notified. The diff only has the products of the changed elements, so
+    /// the filter is disabled.
+    pub fn apply_diff(&mut self, diff: &StateDiff) -> Result<(), Errors> {
+        if self.epoch != diff.from_epoch || self.root != diff.from_root {
+            return Err(Errors::OutOfSync);
+        }
+
+        let (set, r) = self.set.div_rem(&diff.deleted);
+        if !r.is_zero() {
+            return Err(Errors::NotAMember);
+        }
+
+        let n = &*self.n;
+        if !proofs::ni_poe_verify(
+            &diff.deleted,
+            &diff.mid_root,
+            &self.root,
+            &diff.del_proof,
+            n,
+        ) || !proofs::ni_poe_verify(&diff.added, &diff.mid_root, &diff.root, &diff.add_proof, n)
+        {
+            return Err(Errors::InvalidProof);
+        }
+
+        self.record_undo(UndoEntry {
+            from_epoch: self.epoch,
+            from_root: self.root.clone(),
+            from_len: self.count,
+            added: diff.added.clone(),
+            deleted: diff.deleted.clone(),
+        });
+
+        self.set = Arc::new(set * &diff.added);
+        self.filter = None;
+        self.root = diff.root.clone();
+        self.count = diff.len;
+        self.epoch = diff.epoch;
+
+        Ok(())
+    }
+
+    /// Applies all queued adds and deletes as a single transition, advancing the epoch once.
+    /// Returns the proofs of the transition, which replicas can check with `apply_diff`.
+    ///
+    /// An element can be added and deleted in the same commit. Fails with `NotAMember` if
+    /// a deleted element is not a prime in the set, leaving the state unchanged. Observers
+    /// can't be notified of additions and deletions at once, so this fails with
+    /// `Unobservable` if any are registered.
+    pub fn commit(&mut self, ops: &PendingOps) -> Result<StateDiff, Errors> {
+        if !self.hooks.is_empty() {
+            return Err(Errors::Unobservable);
+        }
+        if ops.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        // every deletion removes exactly one element, so it can't be `1` or a product
+        if !ops.deleted.iter().all(|x| probably_prime(x, 20)) {
+            return Err(Errors::NotAMember);
+        }
+        let (new_set, r) = (&*self.set * product(&ops.added)).div_rem(&product(&ops.deleted));
+        if !r.is_zero() {
+            return Err(Errors::NotAMember);
+        }
+        let len = (self.count + ops.added.len())
+            .checked_sub(ops.deleted.len())
+            .ok_or(Errors::NotAMember)?;
+
+        // only the net changes are applied
+        let common = self.set.gcd(&new_set);
+        let deleted = &*self.set / &common;
+        let added = &new_set / &common;
+
+        let mid_root = if deleted.is_one() {
+            self.root.clone()
+        } else {
+            self.pow_g(&common)
+        };
+        let root = mid_root.modpow(&self.reduce_exponent(&added), &self.n);
+
+        let del_proof = proofs::ni_poe_prove(&deleted, &mid_root, &self.root, &self.n);
+        let add_proof = proofs::ni_poe_prove(&added, &mid_root, &root, &self.n);
+
+        let diff = StateDiff {
+            from_epoch: self.epoch,
+            from_root: std::mem::replace(&mut self.root, root),
+            epoch: self.epoch + 1,
+            root: self.root.clone(),
+            len,
+            added,
+            deleted,
+            mid_root,
+            del_proof,
+            add_proof,
+        };
+
+        self.record_undo(UndoEntry {
+            from_epoch: diff.from_epoch,
+            from_root: diff.from_root.clone(),
+            from_len: self.count,
+            added: diff.added.clone(),
+            deleted: diff.deleted.clone(),
+        });
+
+        self.set = Arc::new(new_set);
+        self.count = diff.len;
+        self.epoch = diff.epoch;
+        if let Some(filter) = &mut self.filter {
+            let filter = Arc::make_mut(filter);
+            ops.added.iter().for_each(|x| filter.insert(x));
+            ops.deleted.iter().for_each(|x| filter.remove(x));
+        }
+
+        Ok(diff)
+    }
+
+    /// Migrates to the generator `g^r` and the root `A^r`, keeping the accumulated set.
+    ///
+    /// `r` should be public randomness, e.g. a beacon output hashed with `hash_prime`, so
+    /// that nobody can choose it. Existing witnesses stay valid after transforming them with
+    /// the returned [`GeneratorMigration`]. This changes the public parameters, so observers
+    /// are not notified and histories need to be restarted with the new parameters.
+    pub fn rerandomize(&mut self, r: &BigUint) -> Result<GeneratorMigration, Errors> {
+        if r.is_zero() {
+            return Err(Errors::InvalidParams);
+        }
+
+        let g = self.g.modpow(r, &self.n);
+        let root = self.root.modpow(r, &self.n);
+
+        let mut proofs =
+            proofs::ni_poe_prove_many(r, &[(&self.g, &g), (&self.root, &root)], &self.n);
+        let root_proof = proofs.pop().expect("two statements");
+        let g_proof = proofs.pop().expect("two statements");
+
+        let migration = GeneratorMigration {
+            g_proof,
+            root_proof,
+            n: (*self.n).clone(),
+            r: r.clone(),
+            old_g: (*std::mem::replace(&mut self.g, Arc::new(g.clone()))).clone(),
+            old_root: std::mem::replace(&mut self.root, root.clone()),
+            g,
+            root,
+        };
+        self.powers = None;
+        self.clear_undo();
+
+        Ok(migration)
+    }
+
+    /// Re-accumulates the set under new public parameters, to retire the current modulus.
+    ///
+    /// Returns the new accumulator, at the same epoch, and the transition which verifiers can
+    /// check against the old root. Witnesses can't be transformed between groups, members need
+    /// new witnesses, e.g. from `create_all_mem_wit`. Observers are not carried over.
+    pub fn rotate_modulus(
+        &self,
+        params: &PublicParams,
+    ) -> Result<(Accumulator, ModulusTransition), Errors> {
+        if params.n == *self.n || params.n.is_even() {
+            return Err(Errors::InvalidParams);
+        }
+
+        let mut rotated = Accumulator::new_with_params(params);
+        rotated.root = rotated.g.modpow(&self.set, &rotated.n);
+        rotated.set = self.set.clone();
+        rotated.count = self.count;
+        rotated.epoch = self.epoch;
+
+        let transcript = PoeTranscript::new(&self.set);
+        let old_group = ModN::new(&self.n).expect("the modulus is zero");
+        let new_group = ModN::new(&rotated.n).expect("checked above");
+
+        let transition = ModulusTransition {
+            old: self.params(),
+            new: rotated.params(),
+            epoch: self.epoch,
+            old_root: self.root.clone(),
+            root: rotated.root.clone(),
+            len: self.count,
+            set: (*self.set).clone(),
+            old_proof: proofs::ni_poe_prove_with(&old_group, &transcript, &self.g, &self.root),
+            new_proof: proofs::ni_poe_prove_with(
+                &new_group,
+                &transcript,
+                &rotated.g,
+                &rotated.root,
+            ),
+        };
+
+        Ok((rotated, transition))
+    }
+
+    /// Proves that every member is also a member of `other`, which must use the same modulus.
+    /// Fails with `ParameterMismatch` for different moduli, and `NotASubset` otherwise.
+    pub fn prove_subset(&self, other: &Accumulator) -> Result<SubsetProof, Errors> {
+        if self.n != other.n {
+            return Err(Errors::ParameterMismatch);
+        }
+        let (k, rest) = other.set.div_rem(&self.set);
+        if !rest.is_zero() {
+            return Err(Errors::NotASubset);
+        }
+
+        let n = &*self.n;
+        let c = other.g.modpow(&self.set, n);
+        let gamma = subset_challenge(n, &self.g, &self.root, &other.g, &c);
+        let u = (&*self.g * other.g.modpow(&gamma, n)) % n;
+        let w = (&self.root * c.modpow(&gamma, n)) % n;
+
+        Ok(SubsetProof {
+            same: proofs::ni_poke2_prove((*self.set).clone(), &u, &w, n)?,
+            quotient: proofs::ni_poke2_prove(k, &c, &other.root, n)?,
+            c,
+        })
+    }
+
+    /// Proves that `other` accumulates the same set, e.g. a mirror under another generator.
+    pub fn prove_same_set(&self, other: &Accumulator) -> Result<SetEqualityProof, Errors> {
+        Ok(SetEqualityProof {
+            a_in_b: self.prove_subset(other)?,
+            b_in_a: other.prove_subset(self)?,
+        })
+    }
+
+    /// Checks that `x` can be accumulated: an odd prime, with at most `int_size_bits` bits.
+    /// `add` and `batch_add` assume this without checking, composites break soundness.
+    pub fn check_element(&self, x: &BigUint) -> bool {
+        x.is_odd() && x.bits() <= self.int_size_bits && probably_prime(x, 20)
+    }
+
+    /// Like `add`, but fails with `InvalidElement` if `x` doesn't pass `check_element`.
+    pub fn checked_add(&mut self, x: &BigUint) -> Result<(), Errors> {
+        if !self.check_element(x) {
+            return Err(Errors::InvalidElement(0));
+        }
+
+        self.add(x);
+        Ok(())
+    }
+
+    /// Like `batch_add`, but fails with `InvalidElement(i)` if `xs[i]` is the first element
+    /// that doesn't pass `check_element`. Nothing is added in that case.
+    pub fn checked_batch_add(&mut self, xs: &[BigUint]) -> Result<BigUint, Errors> {
+        if let Some(i) = xs.iter().position(|x| !self.check_element(x)) {
+            return Err(Errors::InvalidElement(i));
         }
 
-        self.root = new_root;
+        Ok(self.batch_add(xs))
+    }
+
+    /// Like `batch_add`, proving the change with the proof system `P`.
+    pub fn batch_add_with<P: ProofOfExponentiation>(&mut self, xs: &[BigUint]) -> P::Proof {
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+
+        //begin our summation of the added elements
+        let x_star = product(xs);
+        #[cfg(feature = "tracing")]
+        let _span = libtracing::info_span!(
+            "batch_add",
+            elements = xs.len(),
+            exponent_bits = x_star.bits()
+        )
+        .entered();
+        //add into element
+        *Arc::make_mut(&mut self.set) *= &x_star;
+        self.count += xs.len();
+
+        //temp clone our old root
+        let root_t = self.root.clone();
+        //calculate our new root after all the added elements
+        self.root = self.root.modpow(&self.reduce_exponent(&x_star), &self.n);
+        self.notify(MutationKind::BatchAdd, xs, &root_t);
+
+        //create our proof for the procedure
+        #[cfg(feature = "metrics")]
+        let proving = Instant::now();
+        let proof = P::prove(&x_star, &root_t, &self.root, &self.n);
+
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.counter(metrics::MODPOW, 1);
+            self.metrics
+                .counter(metrics::ELEMENTS_ADDED, xs.len() as u64);
+            self.metrics.elapsed(metrics::PROVE_SECONDS, proving);
+            self.metrics.elapsed(metrics::BATCH_ADD_SECONDS, start);
+        }
+
+        proof
+    }
+
+    /// Like `ver_batch_add`, for proofs of the proof system `P`.
+    pub fn ver_batch_add_with<P: ProofOfExponentiation>(
+        &self,
+        w: &P::Proof,
+        root: &BigUint,
+        xs: &[BigUint],
+    ) -> bool {
+        let x_star = product(xs);
+
+        P::verify(&x_star, root, &self.root, w, &self.n)
+    }
+
+    /// Commits to a batch by the hash of its product, see `proofs::exponent_commitment`.
+    pub fn batch_commitment(xs: &[BigUint]) -> Vec<u8> {
+        proofs::exponent_commitment(&product(xs))
+    }
+
+    /// Like `batch_add`, with a proof which can also be checked by verifiers that only know
+    /// `batch_commitment(xs)`, see `ver_batch_add_committed`.
+    pub fn batch_add_committed(&mut self, xs: &[BigUint]) -> CommittedExponentProof {
+        self.batch_add_with::<CommittedWesolowski>(xs)
+    }
+
+    /// Verifies the update from `root` to the current root by the batch with `commitment`,
+    /// without its elements, e.g. for light clients of a data availability layer.
+    ///
+    /// The proof shows that the prover knows the exponent of the update, that this is the
+    /// product of the committed batch relies on the source of `commitment`. Verifiers with
+    /// the elements use `ver_batch_add_with::<CommittedWesolowski>` instead.
+    pub fn ver_batch_add_committed(
+        &self,
+        pi: &CommittedExponentProof,
+        root: &BigUint,
+        commitment: &[u8],
+    ) -> bool {
+        pi.commitment == commitment
+            && proofs::ni_poe_verify_committed(root, &self.root, pi, &self.n)
+    }
+
+    /// Like `batch_add`, for batches too large to hold in memory at once, e.g. imports of
+    /// whole datasets. The elements are iterated twice, `chunk_size` of them at a time, and
+    /// neither the elements nor their product are held, besides the growing set product.
+    /// Returns the proof of the update, see `ver_batch_add_chunked`.
+    ///
+    /// Fails with `EmptyBatch` if there are no elements. Observers can't be notified of all
+    /// elements at once, so this fails with `Unobservable` if any are registered, and forgets
+    /// the changes recorded for `rollback`.
+    pub fn batch_add_chunked<I>(&mut self, xs: I, chunk_size: usize) -> Result<BigUint, Errors>
+    where
+        I: IntoIterator,
+        I::Item: Borrow<BigUint>,
+        I::IntoIter: Clone,
+    {
+        if !self.hooks.is_empty() {
+            return Err(Errors::Unobservable);
+        }
+        let xs = xs.into_iter();
+        if xs.clone().next().is_none() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        let root_t = self.root.clone();
+        let count_t = self.count;
+        for (len, p) in chunk_products(xs.clone(), chunk_size) {
+            self.root = self.root.modpow(&self.reduce_exponent(&p), &self.n);
+            *Arc::make_mut(&mut self.set) *= p;
+            self.count += len;
+        }
+        self.clear_undo();
+        self.epoch += 1;
+        if let Some(filter) = &mut self.filter {
+            let filter = Arc::make_mut(filter);
+            xs.clone().for_each(|x| filter.insert(x.borrow()));
+        }
+
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .counter(metrics::ELEMENTS_ADDED, (self.count - count_t) as u64);
+        #[cfg(not(feature = "metrics"))]
+        let _ = count_t;
+
+        Ok(proofs::ni_poe_prove_chunked(
+            xs, chunk_size, &root_t, &self.root, &self.n,
+        ))
+    }
+
+    /// Verifies the update from `root` to the current root by adding `xs`, with a proof from
+    /// `batch_add_chunked`. The elements are iterated twice.
+    pub fn ver_batch_add_chunked<I>(&self, w: &BigUint, root: &BigUint, xs: I) -> bool
+    where
+        I: IntoIterator,
+        I::Item: Borrow<BigUint>,
+        I::IntoIter: Clone,
+    {
+        proofs::ni_poe_verify_chunked(xs, root, &self.root, w, &self.n)
+    }
+
+    /// Like `batch_del`, proving the change with the proof system `P`.
+    pub fn batch_del_with<P: ProofOfExponentiation>(
+        &mut self,
+        pairs: &[(BigUint, BigUint)],
+    ) -> Option<P::Proof> {
+        if pairs.is_empty() {
+            return None;
+        }
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let span = libtracing::info_span!(
+            "batch_del",
+            elements = pairs.len(),
+            exponent_bits = libtracing::field::Empty
+        )
+        .entered();
+
+        let all_pairs = pairs;
+        let mut pairs = pairs.iter();
+        let root_t = self.root.clone();
+
+        // every pair removes exactly one element, so it can't be `1` or a product
+        if !all_pairs.iter().all(|(x, _)| probably_prime(x, 20)) {
+            return None;
+        }
+
+        let (x0, w0) = pairs.next().unwrap();
+        let mut x_star = x0.clone();
+        let mut new_root = w0.clone();
+
+        for (xi, wi) in pairs {
+            new_root = shamir_trick(&new_root, wi, &x_star, xi, &self.n)?;
+            x_star *= xi;
+        }
+        #[cfg(feature = "tracing")]
+        span.record("exponent_bits", x_star.bits());
+
+        // the pairs only have been checked against each other, not against the root
+        let root_x = new_root.modpow(&x_star, &self.n);
+        if self.map_element(&root_x) != self.map_element(&root_t) {
+            return None;
+        }
+        let (set, r) = self.set.div_rem(&x_star);
+        if !r.is_zero() {
+            return None;
+        }
+
+        self.set = Arc::new(set);
+        self.count -= all_pairs.len();
+        self.root = new_root;
+
+        let xs = all_pairs.iter().map(|(x, _)| x.clone()).collect::<Vec<_>>();
+        self.notify(MutationKind::BatchDel, &xs, &root_t);
+
+        #[cfg(feature = "metrics")]
+        let proving = Instant::now();
+        let proof = P::prove(&x_star, &self.root, &root_t, &self.n);
+
+        // every shamir trick takes four exponentiations, plus one for checking the new root
+        #[cfg(feature = "metrics")]
+        {
+            let len = all_pairs.len() as u64;
+            self.metrics.counter(metrics::MODPOW, 4 * (len - 1) + 1);
+            self.metrics.counter(metrics::ELEMENTS_DELETED, len);
+            self.metrics.elapsed(metrics::PROVE_SECONDS, proving);
+            self.metrics.elapsed(metrics::BATCH_DEL_SECONDS, start);
+        }
+
+        Some(proof)
+    }
+
+    /// Like `ver_batch_del`, for proofs of the proof system `P`.
+    pub fn ver_batch_del_with<P: ProofOfExponentiation>(
+        &self,
+        w: &P::Proof,
+        root: &BigUint,
+        xs: &[BigUint],
+    ) -> bool {
+        let x_star = product(xs);
+
+        P::verify(&x_star, &self.root, root, w, &self.n)
+    }
+
+    /// Like `batch_add`, but also returns hints for the added members, split into at most
+    /// `ranges` ranges, which allow computing their witnesses cheaply.
+    ///
+    /// Witnesses of existing members still need to be raised to the product of the batch.
+    pub fn batch_add_with_hints(
+        &mut self,
+        xs: &[BigUint],
+        ranges: usize,
+    ) -> (BigUint, UpdateHints) {
+        let old_root = self.root.clone();
+        let proof = self.batch_add(xs);
+
+        if xs.is_empty() || ranges == 0 {
+            return (proof, UpdateHints { ranges: Vec::new() });
+        }
+
+        let size = (xs.len() + ranges - 1) / ranges;
+        let products = xs.chunks(size).map(product).collect::<Vec<_>>();
+        let bases = root_factor(&old_root, &products, &self.n);
+
+        let ranges = products
+            .into_iter()
+            .zip(bases.into_iter())
+            .enumerate()
+            .map(|(j, (product, base))| RangeHint {
+                start: j * size,
+                end: ((j + 1) * size).min(xs.len()),
+                product,
+                base,
+            })
+            .collect();
+
+        (proof, UpdateHints { ranges })
+    }
+
+    /// Deletes all `xs`, without needing their witnesses.
+    ///
+    /// The new root is recomputed from the remaining set, so this is more expensive than
+    /// `batch_del`, but works for deployments where nobody retains per-element witnesses.
+    /// Returns a proof, which can be checked with `ver_batch_del`.
+    pub fn batch_del_tracked(&mut self, xs: &[BigUint]) -> Result<BigUint, Errors> {
+        if self.qr {
+            self.batch_del_tracked_with::<QrWesolowski>(xs)
+        } else {
+            self.batch_del_tracked_with::<Wesolowski>(xs)
+        }
+    }
+
+    /// Like `batch_del_tracked`, proving the change with the proof system `P`.
+    pub fn batch_del_tracked_with<P: ProofOfExponentiation>(
+        &mut self,
+        xs: &[BigUint],
+    ) -> Result<P::Proof, Errors> {
+        if xs.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+        // every deletion removes exactly one element, so it can't be `1` or a product
+        if !xs.iter().all(|x| probably_prime(x, 20)) {
+            return Err(Errors::NotAMember);
+        }
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+
+        let x_star = product(xs);
+        #[cfg(feature = "tracing")]
+        let _span = libtracing::info_span!(
+            "batch_del_tracked",
+            elements = xs.len(),
+            exponent_bits = x_star.bits(),
+            set_bits = self.set.bits()
+        )
+        .entered();
+
+        let (set, r) = self.set.div_rem(&x_star);
+        if !r.is_zero() {
+            return Err(Errors::NotAMember);
+        }
+
+        self.set = Arc::new(set);
+        self.count -= xs.len();
+
+        let new_root = self.pow_g(&self.set);
+        let root_t = std::mem::replace(&mut self.root, new_root);
+        self.notify(MutationKind::BatchDel, xs, &root_t);
+
+        #[cfg(feature = "metrics")]
+        let proving = Instant::now();
+        let proof = P::prove(&x_star, &self.root, &root_t, &self.n);
+
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.counter(metrics::MODPOW, 1);
+            self.metrics
+                .counter(metrics::ELEMENTS_DELETED, xs.len() as u64);
+            self.metrics.elapsed(metrics::PROVE_SECONDS, proving);
+            self.metrics.elapsed(metrics::BATCH_DEL_SECONDS, start);
+        }
+
+        Ok(proof)
+    }
+
+    /// Registers an observer, which is called after every successful add or delete.
+    /// Clones of this accumulator share the observers registered so far.
+    pub fn add_observer<O: MutationObserver + 'static>(&mut self, observer: O) {
+        self.hooks.push(Arc::new(observer));
+    }
+
+    /// Removes all registered observers.
+    pub fn clear_observers(&mut self) {
+        self.hooks.clear();
+    }
+
+    /// Reports counters and timings to `recorder`, see the `metrics` module.
+    /// Clones of this accumulator share the recorder.
+    #[cfg(feature = "metrics")]
+    pub fn set_recorder<R: Recorder + 'static>(&mut self, recorder: R) {
+        self.metrics.set(Some(Arc::new(recorder)));
+    }
+
+    /// Stops reporting counters and timings.
+    #[cfg(feature = "metrics")]
+    pub fn clear_recorder(&mut self) {
+        self.metrics.set(None);
+    }
+
+    /// Keeps a filter over the members, so `contains` and `non_mem_wit_create` reject most
+    /// non-members, respectively members, without dividing the set product, see the `filter`
+    /// module. It is sized for `capacity` members at a false positive rate of `fp_rate`.
+    ///
+    /// The set is only known as a product, so the current `members` have to be given. Fails
+    /// with `OutOfSync` if they don't match the set.
+    pub fn enable_filter(
+        &mut self,
+        capacity: usize,
+        fp_rate: f64,
+        members: &[BigUint],
+    ) -> Result<(), Errors> {
+        if members.len() != self.count || product(members) != *self.set {
+            return Err(Errors::OutOfSync);
+        }
+
+        let mut filter = MembershipFilter::new(capacity, fp_rate);
+        members.iter().for_each(|x| filter.insert(x));
+        self.filter = Some(Arc::new(filter));
+        Ok(())
+    }
+
+    pub fn disable_filter(&mut self) {
+        self.filter = None;
+    }
+
+    pub fn filter(&self) -> Option<&MembershipFilter> {
+        self.filter.as_deref()
+    }
+
+    /// Precomputes powers of the generator, so that recomputing the root from the set, e.g. in
+    /// `del`, `batch_del_tracked` and witness creation, is split into `digits` independent
+    /// exponentiations, which run on all cores with the `parallel` feature.
+    ///
+    /// `max_bits` should be the expected size of the set product. Clones share the table.
+    pub fn precompute_powers(&mut self, max_bits: usize, digits: usize) {
+        let table = PowerTable::new(&self.g, &self.n, max_bits, digits);
+        self.powers = Some(Arc::new(table));
+    }
+
+    /// Uses a table computed for the same parameters, e.g. shared between workers.
+    /// Fails with `ParameterMismatch` if it was computed for another generator or modulus.
+    pub fn set_powers(&mut self, table: Arc<PowerTable>) -> Result<(), Errors> {
+        if table.base() != &*self.g || table.modulus() != &*self.n {
+            return Err(Errors::ParameterMismatch);
+        }
+
+        self.powers = Some(table);
+        Ok(())
+    }
+
+    pub fn powers(&self) -> Option<&Arc<PowerTable>> {
+        self.powers.as_ref()
+    }
+
+    /// Reduces all exponents `mod order` from now on, where `order` must be a multiple of the
+    /// order of the group, i.e. `φ(n)`. Set by `TrapdoorManager`.
+    pub(crate) fn set_group_order(&mut self, order: Option<BigUint>) {
+        self.order = GroupOrder(order.map(Arc::new));
+    }
+
+    /// Reduces `e` by the order of the group, if it is known.
+    fn reduce_exponent<'a>(&self, e: &'a BigUint) -> Cow<'a, BigUint> {
+        match self.order.0 {
+            Some(ref order) if e >= &**order => Cow::Owned(e % &**order),
+            _ => Cow::Borrowed(e),
+        }
+    }
+
+    /// Calculates `g^e`, using the precomputed powers if there are any.
+    fn pow_g(&self, e: &BigUint) -> BigUint {
+        let e = self.reduce_exponent(e);
+        match self.powers {
+            Some(ref table) => table.pow(&e),
+            None => self.g.modpow(&e, &self.n),
+        }
+    }
+
+    /// Starts recording the last `depth` changes, so they can be reverted with `rollback`.
+    /// Changes before this call can't be reverted.
+    pub fn enable_undo(&mut self, depth: usize) {
+        self.undo = Some(UndoLog::new(depth));
+    }
+
+    /// Stops recording changes, and drops the recorded ones.
+    pub fn disable_undo(&mut self) {
+        self.undo = None;
+    }
+
+    pub fn undo_log(&self) -> Option<&UndoLog> {
+        self.undo.as_ref()
+    }
+
+    /// Reverts all changes after `epoch`, restoring its root, set and length.
+    ///
+    /// Fails with `UnknownEpoch` if `epoch` is in the future or was skipped by `apply_diff`,
+    /// and with `Pruned` if it is older than the undo log, or the log is disabled. Observers
+    /// are not notified, so histories and logs need to be cut back with `History::truncate`
+    /// and `OpLog::truncate`. The log only has the products of the changed elements, so the
+    /// filter is disabled.
+    pub fn rollback(&mut self, epoch: u64) -> Result<(), Errors> {
+        if epoch > self.epoch {
+            return Err(Errors::UnknownEpoch(epoch));
+        }
+        if epoch == self.epoch {
+            return Ok(());
+        }
+
+        let log = self.undo.as_mut().ok_or(Errors::Pruned(epoch))?;
+        let entries = log
+            .take_since(epoch)
+            .ok_or_else(|| match log.oldest_epoch() {
+                Some(oldest) if oldest < epoch => Errors::UnknownEpoch(epoch),
+                _ => Errors::Pruned(epoch),
+            })?;
+
+        self.filter = None;
+        for entry in entries {
+            self.set = Arc::new(&*self.set * entry.deleted / entry.added);
+            self.root = entry.from_root;
+            self.count = entry.from_len;
+            self.epoch = entry.from_epoch;
+        }
+
+        Ok(())
+    }
+
+    fn record_undo(&mut self, entry: UndoEntry) {
+        if let Some(log) = &mut self.undo {
+            log.push(entry);
+        }
+    }
+
+    /// Forgets the recorded changes, after the state changed in a way they can't revert.
+    fn clear_undo(&mut self) {
+        if let Some(log) = &mut self.undo {
+            log.clear();
+        }
+    }
+
+    /// Concludes a change: advances the epoch and notifies the observers.
+    fn notify(&mut self, kind: MutationKind, elements: &[BigUint], old_root: &BigUint) {
+        if let Some(filter) = &mut self.filter {
+            let filter = Arc::make_mut(filter);
+            if kind.is_add() {
+                elements.iter().for_each(|x| filter.insert(x));
+            } else {
+                elements.iter().for_each(|x| filter.remove(x));
+            }
+        }
+
+        if self.undo.is_some() {
+            let x = product(elements);
+            let (from_len, added, deleted) = if kind.is_add() {
+                (self.count - elements.len(), x, BigUint::one())
+            } else {
+                (self.count + elements.len(), BigUint::one(), x)
+            };
+            self.record_undo(UndoEntry {
+                from_epoch: self.epoch,
+                from_root: old_root.clone(),
+                from_len,
+                added,
+                deleted,
+            });
+        }
+
+        self.epoch += 1;
+        self.hooks.notify(&Mutation {
+            kind,
+            elements,
+            old_root,
+            new_root: &self.root,
+        });
+    }
+
+    /// Recomputes `g^set` and compares it against the current root.
+    /// This is expensive, as it exponentiates by the full set product, but allows
+    /// detecting state corruption in release builds.
+    pub fn validate(&self) -> StateReport {
+        let expected_root = self.pow_g(&self.set);
+
+        StateReport {
+            root: self.root.clone(),
+            expected_root,
+            set_bits: self.set.bits(),
+            root_in_range: !self.root.is_zero() && self.root < *self.n,
+        }
+    }
+}
+
+impl StaticAccumulator for Accumulator {
+    /// Returns the current public state.
+    fn state(&self) -> &BigUint {
+        &self.root
+    }
+
+    /// Generates a group of unknown order and initializes the group with a generator of that group.
+    /// Setup(λ, z) → pp, A0 Generate the public parameters
+    fn setup<T, R>(rng: &mut R, int_size_bits: usize) -> Self
+    where
+        T: PrimeGroup,
+        R: CryptoRng + Rng,
+    {
+        // Generate n = p q, |n| = int_size_bits
+        let params = PublicParams::generate::<T, _>(rng, int_size_bits);
+        Accumulator::new_with_params(&params)
+    }
+
+    ///Takes the current accumulator At, an element from the odd primes domain, and computes At+1 = At.
+    #[inline]
+    fn add(&mut self, x: &BigUint) {
+        // assumes x is already a prime
+        *Arc::make_mut(&mut self.set) *= x;
+        self.count += 1;
+        let new_root = self.root.modpow(x, &self.n);
+        let old_root = std::mem::replace(&mut self.root, new_root);
+
+        self.notify(MutationKind::Add, std::slice::from_ref(x), &old_root);
+
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.counter(metrics::MODPOW, 1);
+            self.metrics.counter(metrics::ELEMENTS_ADDED, 1);
+        }
+    }
+
+    //A membership witness is simply the accumulator without the aggregated item.
+    #[inline]
+    fn mem_wit_create(&self, x: &BigUint) -> BigUint {
+        let (set, r) = self.set.clone().div_rem(x);
+        debug_assert!(r.is_zero(), "x was not a valid member of set");
+
+        self.pow_g(&set)
+    }
+
+    #[inline]
+    fn ver_mem(&self, w: &BigUint, x: &BigUint) -> bool {
+        // (w^2)^x = (w^x)^2 in QR_n
+        let w_x = w.modpow(x, &self.n);
+        self.map_element(&w_x) == self.map_element(&self.root)
+    }
+}
+
+impl DynamicAccumulator for Accumulator {
+    #[inline]
+    fn del(&mut self, x: &BigUint) -> Option<()> {
+        if x.is_zero() || x.is_one() {
+            return None;
+        }
+        let (set, r) = self.set.div_rem(x);
+        if !r.is_zero() {
+            return None;
+        }
+        self.set = Arc::new(set);
+        self.count -= 1;
+
+        let new_root = self.pow_g(&self.set);
+        let old_root = std::mem::replace(&mut self.root, new_root);
+
+        self.notify(MutationKind::Del, std::slice::from_ref(x), &old_root);
+
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics.counter(metrics::MODPOW, 1);
+            self.metrics.counter(metrics::ELEMENTS_DELETED, 1);
+        }
+        Some(())
+    }
+}
+
+impl UniversalAccumulator for Accumulator {
+    fn non_mem_wit_create(&self, x: &BigUint) -> Result<(BigUint, BigInt), Errors> {
+        // members pass the filter, reject them before the Bezout step
+        if self.filter.is_some() && self.contains(x) {
+            return Err(Errors::NotCoprime);
+        }
+
+        // set* <- \prod_{set\in S} set
+        let s_star = &*self.set;
+
+        // a, b <- Bezout(x, set*), only possible for non-members
+        let (gcd, a, b) = ExtendedGcd::extended_gcd(x, s_star);
+        if !gcd.is_one() {
+            return Err(Errors::NotCoprime);
+        }
+        let d = modpow_uint_int(&self.g, &a, &self.n)?;
+
+        Ok((d, b))
+    }
+
+    fn ver_non_mem(&self, w: &(BigUint, BigInt), x: &BigUint) -> bool {
+        let (d, b) = w;
+
+        // A^b
+        let a_b = match modpow_uint_int(&self.root, b, &self.n) {
+            Ok(a_b) => a_b,
+            Err(_) => return false,
+        };
+        // d^x
+        let d_x = d.modpow(x, &self.n);
+
+        // d^x A^b == g
+        let lhs = (d_x * &a_b) % &*self.n;
+        self.map_element(&lhs) == self.map_element(&self.g)
+    }
+}
+
+impl BatchedAccumulator for Accumulator {
+    fn batch_add(&mut self, xs: &[BigUint]) -> BigUint {
+        let proof = if self.qr {
+            self.batch_add_with::<QrWesolowski>(xs)
+        } else {
+            self.batch_add_with::<Wesolowski>(xs)
+        };
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .histogram(metrics::PROOF_BYTES, proof.to_bytes_be().len() as f64);
+
+        proof
+    }
+
+    fn ver_batch_add(&self, w: &BigUint, root: &BigUint, xs: &[BigUint]) -> bool {
+        if self.qr {
+            self.ver_batch_add_with::<QrWesolowski>(w, root, xs)
+        } else {
+            self.ver_batch_add_with::<Wesolowski>(w, root, xs)
+        }
+    }
+
+    fn batch_del(&mut self, pairs: &[(BigUint, BigUint)]) -> Option<BigUint> {
+        let proof = if self.qr {
+            self.batch_del_with::<QrWesolowski>(pairs)?
+        } else {
+            self.batch_del_with::<Wesolowski>(pairs)?
+        };
+        #[cfg(feature = "metrics")]
+        self.metrics
+            .histogram(metrics::PROOF_BYTES, proof.to_bytes_be().len() as f64);
+
+        Some(proof)
+    }
+
+    fn ver_batch_del(&self, w: &BigUint, root: &BigUint, xs: &[BigUint]) -> bool {
+        if self.qr {
+            self.ver_batch_del_with::<QrWesolowski>(w, root, xs)
+        } else {
+            self.ver_batch_del_with::<Wesolowski>(w, root, xs)
+        }
+    }
+
+    fn del_w_mem(&mut self, w: &BigUint, x: &BigUint) -> Option<()> {
+        // `1` passes `ver_mem` with the root as witness
+        if x.is_zero() || x.is_one() || !self.ver_mem(w, x) {
+            return None;
+        }
+        let (set, r) = self.set.div_rem(x);
+        if !r.is_zero() {
+            return None;
+        }
+
+        self.set = Arc::new(set);
+        self.count -= 1;
+        // w is root without x, so need to recompute
+        let old_root = std::mem::replace(&mut self.root, w.clone());
+
+        self.notify(MutationKind::DelWithMem, std::slice::from_ref(x), &old_root);
+        Some(())
+    }
+
+    #[inline]
+    fn create_all_mem_wit(&self, set: &[BigUint]) -> Vec<BigUint> {
+        #[cfg(feature = "metrics")]
+        let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let _span = libtracing::info_span!(
+            "create_all_mem_wit",
+            elements = set.len(),
+            set_bits = set.iter().map(|x| x.bits()).sum::<usize>()
+        )
+        .entered();
+
+        let ws = root_factor(&self.g, &set, &self.n);
+
+        // root_factor takes two exponentiations for every inner node of the product tree
+        #[cfg(feature = "metrics")]
+        {
+            self.metrics
+                .counter(metrics::MODPOW, 2 * set.len().saturating_sub(1) as u64);
+            self.metrics
+                .elapsed(metrics::CREATE_ALL_MEM_WIT_SECONDS, start);
+        }
+
+        ws
+    }
+
+    fn mem_wit_create_batch(&self, xs: &[BigUint]) -> Result<Vec<BigUint>, Errors> {
+        if xs.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // a single division for all queries, g^{set / x^*} is the common part of all witnesses
+        let tree = ProductTree::new(xs);
+        let (others, r) = self.set.div_rem(&tree.root());
+        if !r.is_zero() {
+            return Err(Errors::NotAMember);
+        }
+        let base = self.pow_g(&others);
+
+        Ok(tree.root_factor(&base, &self.n))
+    }
+
+    fn mem_wit_create_from(&self, set: &[BigUint], i: usize) -> Option<BigUint> {
+        if i >= set.len() {
+            return None;
+        }
+
+        Some(cofactor_pow(&self.g, set, i, &self.n))
+    }
+
+    fn agg_mem_wit(
+        &self,
+        w_x: &BigUint,
+        w_y: &BigUint,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> Result<(BigUint, BigUint), Errors> {
+        // TODO: check this matches, sth is not quite right in the paper here
+        let w_xy = shamir_trick(w_x, w_y, x, y, &self.n).ok_or(Errors::InvalidWitness)?;
+        let xy = x.clone() * y;
+
+        debug_assert!(
+            w_xy.modpow(&xy, &self.n) == self.root,
+            "invalid shamir trick"
+        );
+
+        let pi = proofs::ni_poe_prove(&xy, &w_xy, &self.root, &self.n);
+
+        Ok((w_xy, pi))
+    }
+
+    fn ver_agg_mem_wit(&self, w_xy: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool {
+        let xy = x.clone() * y;
+        proofs::ni_poe_verify(&xy, w_xy, &self.root, pi, &self.n)
+    }
+
+    fn mem_wit_create_star(&self, x: &BigUint) -> (BigUint, BigUint) {
+        let w_x = self.mem_wit_create(x);
+        debug_assert!(self.root != w_x, "{} was not a member", x);
+        let p = proofs::ni_poe_prove(x, &w_x, &self.root, &self.n);
+
+        (w_x, p)
+    }
+
+    fn ver_mem_star(&self, x: &BigUint, pi: &(BigUint, BigUint)) -> bool {
+        proofs::ni_poe_verify(x, &pi.0, &self.root, &pi.1, &self.n)
+    }
+
+    /// Combines the NI-PoE checks `Q_i^{l_i} w_i^{r_i} = root` with random weights `γ_i`,
+    /// and checks `\prod (Q_i^{l_i} w_i^{r_i})^{γ_i} = root^{\sum γ_i}` in a single
+    /// multi exponentiation.
+    ///
+    /// Both sides are squared, so the check holds in `QR_n`: proofs that are off by factors of
+    /// `-1` pass the batch, even though they fail `ver_mem_star`. Without squaring, an even
+    /// number of such factors would cancel anyway.
+    fn ver_mem_star_batch(&self, pis: &[(BigUint, (BigUint, BigUint))]) -> bool {
+        if pis.is_empty() {
+            return false;
+        }
+
+        let group = ModN::new(&self.n).expect("the modulus is zero");
+        let gammas = star_batch_weights(&self.n, &self.root, pis);
+
+        let mut exps = Vec::with_capacity(2 * pis.len());
+        for ((x, (w, q)), gamma) in pis.iter().zip(&gammas) {
+            // l <- H_prime(x, n, w, root), r <- x mod l
+            let l = PoeTranscript::new(x).challenge(&group, w, &self.root);
+            let r = x.mod_floor(&l);
+            exps.push((q, l * gamma));
+            exps.push((w, r * gamma));
+        }
+        let terms: Vec<_> = exps.iter().map(|(b, e)| (*b, e)).collect();
+        let gamma_sum = gammas.iter().fold(BigUint::zero(), |acc, g| acc + g);
+
+        let lhs = multi_pow(&terms, &self.n);
+        let rhs = self.root.modpow(&gamma_sum, &self.n);
+
+        (&lhs * &lhs) % &*self.n == (&rhs * &rhs) % &*self.n
+    }
+
+    fn mem_wit_create_for_subset(&self, xs: &[BigUint]) -> Result<(BigUint, BigUint), Errors> {
+        if xs.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        // the product tree keeps this cheap for large subsets
+        let x_star = product(xs);
+        let (others, r) = self.set.div_rem(&x_star);
+        if !r.is_zero() {
+            return Err(Errors::NotAMember);
+        }
+
+        let w = self.pow_g(&others);
+        let p = proofs::ni_poe_prove(&x_star, &w, &self.root, &self.n);
+
+        Ok((w, p))
+    }
+
+    fn ver_mem_for_subset(&self, xs: &[BigUint], pi: &(BigUint, BigUint)) -> bool {
+        !xs.is_empty() && proofs::ni_poe_verify(&product(xs), &pi.0, &self.root, &pi.1, &self.n)
+    }
+
+    fn mem_wit_x(
+        &self,
+        _other: &BigUint,
+        w_x: &BigUint,
+        w_y: &BigUint,
+        _x: &BigUint,
+        _y: &BigUint,
+    ) -> BigUint {
+        (w_x * w_y) % &*self.n
+    }
+
+    fn ver_mem_x(&self, other: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool {
+        self.ver_mem_x_many(std::slice::from_ref(other), pi, &[x.clone(), y.clone()])
+    }
+
+    fn mem_wit_x_star(
+        &self,
+        other: &BigUint,
+        w_x: &BigUint,
+        w_y: &BigUint,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> Result<(BigUint, BigUint), Errors> {
+        if !x.gcd(y).is_one() {
+            return Err(Errors::NotCoprime);
+        }
+        // in QR_n, the witnesses only match the roots up to their sign
+        let w_x_x = w_x.modpow(x, &self.n);
+        let w_y_y = w_y.modpow(y, &self.n);
+        if self.map_element(&w_x_x) != self.map_element(&self.root)
+            || self.map_element(&w_y_y) != self.map_element(other)
+        {
+            return Err(Errors::InvalidWitness);
+        }
+
+        let w_xy = self.mem_wit_x(other, w_x, w_y, x, y);
+
+        // w_xy^{x y} = A_1^y A_2^x
+        let rhs = cross_exp(&[w_x_x, w_y_y], &[x.clone(), y.clone()], &self.n);
+        let p = if self.qr {
+            proofs::ni_poe_prove_qr(&(x * y), &w_xy, &rhs, &self.n)
+        } else {
+            proofs::ni_poe_prove(&(x * y), &w_xy, &rhs, &self.n)
+        };
+
+        Ok((w_xy, p))
+    }
+
+    fn ver_mem_x_star(
+        &self,
+        other: &BigUint,
+        pi: &(BigUint, BigUint),
+        x: &BigUint,
+        y: &BigUint,
+    ) -> bool {
+        if !x.gcd(y).is_one() {
+            return false;
+        }
+
+        let rhs = cross_exp(
+            &[self.root.clone(), other.clone()],
+            &[x.clone(), y.clone()],
+            &self.n,
+        );
+        if self.qr {
+            proofs::ni_poe_verify_qr(&(x * y), &pi.0, &rhs, &pi.1, &self.n)
+        } else {
+            proofs::ni_poe_verify(&(x * y), &pi.0, &rhs, &pi.1, &self.n)
+        }
+    }
+
+    fn mem_wit_x_many(&self, ws: &[BigUint]) -> BigUint {
+        ws.iter()
+            .fold(BigUint::one() % &*self.n, |acc, w| (acc * w) % &*self.n)
+    }
+
+    fn ver_mem_x_many(&self, others: &[BigUint], pi: &BigUint, xs: &[BigUint]) -> bool {
+        if xs.len() != others.len() + 1 || xs.iter().any(Zero::is_zero) {
+            return false;
+        }
+
+        // assert the xs are pairwise coprime
+        let x_star = product(xs);
+        if !xs.iter().all(|x| x.gcd(&(&x_star / x)).is_one()) {
+            return false;
+        }
+
+        let mut roots = Vec::with_capacity(xs.len());
+        roots.push(self.root.clone());
+        roots.extend_from_slice(others);
+
+        // \prod A_i^{x_star / x_i}
+        let rhs = cross_exp(&roots, xs, &self.n);
+        // pi^{x_star}
+        let lhs = pi.modpow(&x_star, &self.n);
+
+        lhs == rhs
+    }
+
+    fn non_mem_wit_create_star(
+        &self,
+        x: &BigUint,
+    ) -> Result<(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint), Errors> {
+        let g = &*self.g;
+        let n = &*self.n;
+
+        // a, b <- Bezout(x, s_star)
+        let (gcd, a, b) = ExtendedGcd::extended_gcd(x, &*self.set);
+        if !gcd.is_one() {
+            return Err(Errors::NotCoprime);
+        }
+
+        // d <- g^a
+        let d = modpow_uint_int(g, &a, n)?;
+        // v <- A^b
+        let v = modpow_uint_int(&self.root, &b, n)?;
+
+        // pi_d <- NI-PoKE2(b, A, v)
+        let pi_d = proofs::ni_poke2_prove(b, &self.root, &v, n)?;
+
+        // k <- g * v^-1
+        let k = (g * mod_inverse(&v, n).ok_or(Errors::NotInvertible)?) % n;
+
+        // pi_g <- NI-PoE(x, d, g * v^-1)
+        let pi_g = proofs::ni_poe_prove(x, &d, &k, n);
+
+        // return {d, v, pi_d, pi_g}
+        Ok((d, v, pi_d, pi_g))
+    }
+
+    fn ver_non_mem_star(
+        &self,
+        x: &BigUint,
+        pi: &(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
+    ) -> bool {
+        let g = &*self.g;
+        let n = &*self.n;
+
+        let (d, v, pi_d, pi_g) = pi;
+
+        // verify NI-PoKE2
+        if !proofs::ni_poke2_verify(&self.root, &v, pi_d, n) {
+            return false;
+        }
+
+        // verify NI-PoE
+        let k = match mod_inverse(v, n) {
+            Some(v_inv) => (g * v_inv) % n,
+            None => return false,
+        };
+
+        if !proofs::ni_poe_verify(x, d, &k, pi_g, n) {
+            return false;
+        }
+
+        true
+    }
+
+    fn non_mem_wit_create_star_batch(
+        &self,
+        xs: &[BigUint],
+    ) -> Result<(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint), Errors> {
+        if xs.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        // the product is coprime to the set, iff every single element is
+        let x_star = product(xs);
+        if !x_star.gcd(&self.set).is_one() {
+            return Err(Errors::NotCoprime);
+        }
+
+        self.non_mem_wit_create_star(&x_star)
+    }
+
+    fn ver_non_mem_star_batch(
+        &self,
+        xs: &[BigUint],
+        pi: &(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
+    ) -> bool {
+        !xs.is_empty() && self.ver_non_mem_star(&product(xs), pi)
+    }
+}
+
+/// An accumulator that proves its batch operations with the proof system `P`,
+/// e.g. `ProvingAccumulator<Pietrzak>`. Use the `_with` methods to choose per call instead.
+#[derive(Debug, Clone)]
+pub struct ProvingAccumulator<P: ProofOfExponentiation> {
+    acc: Accumulator,
+    proofs: PhantomData<P>,
+}
+
+impl<P: ProofOfExponentiation> ProvingAccumulator<P> {
+    pub fn new(acc: Accumulator) -> Self {
+        ProvingAccumulator {
+            acc,
+            proofs: PhantomData,
+        }
+    }
+
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.acc
+    }
+
+    pub fn accumulator_mut(&mut self) -> &mut Accumulator {
+        &mut self.acc
+    }
+
+    pub fn into_accumulator(self) -> Accumulator {
+        self.acc
+    }
+
+    /// See `BatchedAccumulator::batch_add`.
+    pub fn batch_add(&mut self, xs: &[BigUint]) -> P::Proof {
+        self.acc.batch_add_with::<P>(xs)
+    }
+
+    /// See `BatchedAccumulator::ver_batch_add`.
+    pub fn ver_batch_add(&self, w: &P::Proof, root: &BigUint, xs: &[BigUint]) -> bool {
+        self.acc.ver_batch_add_with::<P>(w, root, xs)
+    }
+
+    /// See `BatchedAccumulator::batch_del`.
+    pub fn batch_del(&mut self, pairs: &[(BigUint, BigUint)]) -> Option<P::Proof> {
+        self.acc.batch_del_with::<P>(pairs)
+    }
+
+    /// See `Accumulator::batch_del_tracked`.
+    pub fn batch_del_tracked(&mut self, xs: &[BigUint]) -> Result<P::Proof, Errors> {
+        self.acc.batch_del_tracked_with::<P>(xs)
+    }
+
+    /// See `BatchedAccumulator::ver_batch_del`.
+    pub fn ver_batch_del(&self, w: &P::Proof, root: &BigUint, xs: &[BigUint]) -> bool {
+        self.acc.ver_batch_del_with::<P>(w, root, xs)
+    }
+}
+
+/// Calculates `\prod roots_i^{x / xs_i}` with `x = \prod xs_i`, splitting the products in half:
+/// the result for `L || R` is `res_L^{x_R} res_R^{x_L}`.
+fn cross_exp(roots: &[BigUint], xs: &[BigUint], n: &BigUint) -> BigUint {
+    if roots.len() == 1 {
+        return roots[0].clone() % n;
+    }
+
+    let m = roots.len() / 2;
+    let (roots_l, roots_r) = roots.split_at(m);
+    let (xs_l, xs_r) = xs.split_at(m);
+
+    let l = cross_exp(roots_l, xs_l, n).modpow(&product(xs_r), n);
+    let r = cross_exp(roots_r, xs_r, n).modpow(&product(xs_l), n);
+
+    (l * r) % n
+}
+
+/// Calculates `a^-1 mod n`, if it exists.
+fn mod_inverse(a: &BigUint, n: &BigUint) -> Option<BigUint> {
+    a.clone()
+        .mod_inverse(n)
+        .and_then(|a_inv| a_inv.into_biguint())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::hash::hash_prime;
+    use crate::verify;
+    use blake2::Blake2b;
+    use num_bigint::RandPrime;
+    use num_bigint::Sign;
+    use num_traits::FromPrimitive;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_static() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        for _ in 0..100 {
+            let int_size_bits = 256; // insecure, but faster tests
+            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+            let xs = (0..5)
+                .map(|_| rng.gen_prime(int_size_bits))
+                .collect::<Vec<_>>();
+
+            for x in &xs {
+                acc.add(x);
+            }
+
+            for x in &xs {
+                let w = acc.mem_wit_create(x);
+                assert!(acc.ver_mem(&w, x));
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert!(acc.validate().is_valid());
+
+        let xs = (0..5)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        acc.del(&xs[1]).unwrap();
+
+        let report = acc.validate();
+        assert!(report.is_valid(), "{:?}", report);
+        assert_eq!(report.set_bits, acc.set_bits());
+        assert_eq!(acc.len(), 4);
+
+        // corrupt the state
+        acc.root = acc.root.modpow(&xs[1], &acc.n);
+        let report = acc.validate();
+        assert!(!report.is_valid());
+        assert_ne!(report.root, report.expected_root);
+    }
+
+    #[test]
+    fn test_observers() {
+        use std::sync::Mutex;
+
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let events_inner = events.clone();
+        acc.add_observer(move |m: &Mutation<'_>| {
+            assert_ne!(m.old_root, m.new_root);
+            events_inner
+                .lock()
+                .unwrap()
+                .push((m.kind, m.elements.to_vec(), m.new_root.clone()));
+        });
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0]);
+        acc.batch_add(&xs[1..]);
+        acc.del(&xs[0]).unwrap();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].0, MutationKind::Add);
+        assert_eq!(events[1].0, MutationKind::BatchAdd);
+        assert_eq!(&events[1].1[..], &xs[1..]);
+        assert_eq!(events[2].0, MutationKind::Del);
+        assert_eq!(&events[2].2, acc.state());
+    }
+
+    #[test]
+    fn test_merge() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+
+        let mut left = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut right = left.clone();
+
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        left.batch_add(&xs[..3]);
+        right.batch_add(&xs[3..]);
+
+        let w_left = left.mem_wit_create(&xs[0]);
+        let w_right = right.mem_wit_create(&xs[4]);
+
+        // overlapping sets can not be merged
+        let mut overlap = right.clone();
+        overlap.add(&xs[1]);
+        assert_eq!(
+            left.clone().merge(overlap).unwrap_err(),
+            Errors::NotDisjoint
+        );
+
+        // different parameters can not be merged
+        let other = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(
+            left.clone().merge(other).unwrap_err(),
+            Errors::ParameterMismatch
+        );
+
+        let (merged, migration) = left.merge(right).unwrap();
+        assert_eq!(merged.len(), 6);
+        assert!(merged.validate().is_valid());
+
+        assert!(merged.ver_mem(&migration.migrate_self(&w_left), &xs[0]));
+        assert!(merged.ver_mem(&migration.migrate_other(&w_right), &xs[4]));
+        assert!(!merged.ver_mem(&w_left, &xs[0]));
+    }
+
+    #[test]
+    fn test_shared_params() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+
+        let mut a = Accumulator::new_with_params(&params);
+        let mut b = Accumulator::new_with_params(&params);
+        assert_eq!(a.params(), params);
+        assert_eq!(a.state(), &params.g);
+
+        let x = rng.gen_prime(int_size_bits);
+        a.add(&x);
+        b.add(&x);
+        assert_eq!(a.state(), b.state());
+
+        // witnesses are interchangeable between accumulators over the same parameters
+        let w = a.mem_wit_create(&x);
+        assert!(b.ver_mem(&w, &x));
+    }
+
+    #[test]
+    fn test_cheap_clone() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let x = rng.gen_prime(int_size_bits);
+        acc.add(&x);
+
+        let mut worker = acc.clone();
+        assert!(Arc::ptr_eq(&acc.set, &worker.set));
+        assert!(Arc::ptr_eq(&acc.n, &worker.n));
+
+        // changing a clone leaves the original untouched
+        let y = rng.gen_prime(int_size_bits);
+        worker.add(&y);
+        assert!(!Arc::ptr_eq(&acc.set, &worker.set));
+        assert!(Arc::ptr_eq(&acc.n, &worker.n));
+        assert!(acc.contains(&x) && !acc.contains(&y));
+        assert!(worker.contains(&x) && worker.contains(&y));
+        assert!(acc.validate().is_valid());
+    }
+
+    #[test]
+    fn test_diff() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut primary = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        primary.batch_add(&xs[..4]);
+        let mut replica = primary.clone();
+
+        primary.add(&xs[4]);
+        primary.batch_add(&xs[5..]);
+        primary.batch_del_tracked(&xs[1..3]).unwrap();
+        assert_eq!(primary.epoch(), 4);
+
+        let diff = replica.diff(&primary).unwrap();
+        assert_eq!(diff.added, product(&xs[4..]));
+        assert_eq!(diff.deleted, product(&xs[1..3]));
+
+        let mut tampered = diff.clone();
+        tampered.root = primary.generator().clone();
+        assert_eq!(replica.apply_diff(&tampered), Err(Errors::InvalidProof));
+
+        replica.apply_diff(&diff).unwrap();
+        assert_eq!(replica.state(), primary.state());
+        assert_eq!(replica.len(), primary.len());
+        assert_eq!(replica.epoch(), primary.epoch());
+        assert!(replica.validate().is_valid());
+
+        // already applied
+        assert_eq!(replica.apply_diff(&diff), Err(Errors::OutOfSync));
+
+        // only additions
+        primary.add(&xs[1]);
+        let diff = replica.diff(&primary).unwrap();
+        replica.apply_diff(&diff).unwrap();
+        assert_eq!(replica.state(), primary.state());
+
+        let other = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(replica.diff(&other).unwrap_err(), Errors::ParameterMismatch);
+    }
+
+    #[test]
+    fn test_commit() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..4]);
+        let mut replica = acc.clone();
+
+        let mut ops = PendingOps::new();
+        assert_eq!(acc.commit(&ops).unwrap_err(), Errors::EmptyBatch);
+
+        ops.add(&xs[4]);
+        ops.del(&xs[1]);
+        ops.add(&xs[5]);
+        ops.add(&xs[6]);
+        ops.del(&xs[6]);
+        ops.del(&xs[2]);
+        let diff = acc.commit(&ops).unwrap();
+        assert_eq!(acc.epoch(), 2);
+        assert_eq!(acc.len(), 4);
+        assert_eq!(diff.added, &xs[4] * &xs[5]);
+        assert_eq!(diff.deleted, &xs[1] * &xs[2]);
+        assert!(acc.validate().is_valid());
+
+        let mut expected = Accumulator::new_with_params(&acc.params());
+        expected.batch_add(&[xs[0].clone(), xs[3].clone(), xs[4].clone(), xs[5].clone()]);
+        assert_eq!(acc.state(), expected.state());
+
+        replica.apply_diff(&diff).unwrap();
+        assert_eq!(replica.state(), acc.state());
+        assert_eq!(replica.epoch(), acc.epoch());
+
+        // nothing changes on failure
+        ops.clear();
+        ops.add(&xs[7]);
+        ops.del(&xs[1]);
+        assert_eq!(acc.commit(&ops).unwrap_err(), Errors::NotAMember);
+        assert_eq!(acc.state(), replica.state());
+        assert_eq!(acc.epoch(), 2);
+
+        // deletions of `1` or of a product of members would miscount the elements
+        for x in &[BigUint::one(), &xs[0] * &xs[3]] {
+            ops.clear();
+            ops.del(x);
+            assert_eq!(acc.commit(&ops).unwrap_err(), Errors::NotAMember);
+            assert_eq!(acc.len(), 4);
+        }
+
+        // observers can't be notified
+        let mut observed = acc.clone();
+        observed.add_observer(|_: &Mutation<'_>| {});
+        ops.clear();
+        ops.add(&xs[7]);
+        assert_eq!(observed.commit(&ops).unwrap_err(), Errors::Unobservable);
+        assert_eq!(observed.state(), replica.state());
+
+        // only additions
+        ops.clear();
+        ops.add(&xs[7]);
+        let diff = acc.commit(&ops).unwrap();
+        replica.apply_diff(&diff).unwrap();
+        assert_eq!(replica.state(), acc.state());
+    }
+
+    #[test]
+    fn test_rerandomize() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..3]);
+        let w = acc.mem_wit_create(&xs[0]);
+        let u = acc.non_mem_wit_create(&xs[3]).unwrap();
+
+        let r = hash_prime::<_, Blake2b>(b"beacon round 42");
+        let migration = acc.rerandomize(&r).unwrap();
+        assert!(migration.verify());
+        assert_eq!(acc.generator(), &migration.g);
+        assert_eq!(acc.state(), &migration.root);
+        assert!(acc.validate().is_valid());
+
+        assert!(!acc.ver_mem(&w, &xs[0]));
+        assert!(acc.ver_mem(&migration.migrate_mem_wit(&w), &xs[0]));
+        assert!(acc.ver_non_mem(&migration.migrate_non_mem_wit(&u), &xs[3]));
+
+        // the accumulator keeps working under the new generator
+        acc.add(&xs[3]);
+        let w = acc.mem_wit_create(&xs[3]);
+        assert!(acc.ver_mem(&w, &xs[3]));
+
+        let mut tampered = migration.clone();
+        tampered.r += 2u32;
+        assert!(!tampered.verify());
+
+        assert_eq!(
+            acc.rerandomize(&BigUint::zero()).unwrap_err(),
+            Errors::InvalidParams
+        );
+    }
+
+    #[test]
+    fn test_rotate_modulus() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..5)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..4]);
+        acc.del(&xs[1]).unwrap();
+
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits + 64);
+        let (mut rotated, transition) = acc.rotate_modulus(&params).unwrap();
+        assert!(transition.verify());
+        assert_eq!(transition.old_root, *acc.state());
+        assert_eq!(transition.root, *rotated.state());
+        assert_eq!(rotated.params(), params);
+        assert_eq!(rotated.len(), 3);
+        assert_eq!(rotated.epoch(), acc.epoch());
+        assert!(rotated.validate().is_valid());
+
+        assert!(transition.contains(&xs[0]));
+        assert!(!transition.contains(&xs[1]));
+        assert!(!transition.contains(&BigUint::one()));
+
+        let w = rotated.mem_wit_create(&xs[2]);
+        assert!(rotated.ver_mem(&w, &xs[2]));
+        rotated.del(&xs[2]).unwrap();
+        rotated.add(&xs[4]);
+        assert!(rotated.validate().is_valid());
+
+        // a root that doesn't commit to the same set
+        let mut tampered = transition.clone();
+        tampered.root = params.g.modpow(&xs[0], &params.n);
+        assert!(!tampered.verify());
+        let mut tampered = transition;
+        tampered.set *= &xs[1];
+        assert!(!tampered.verify());
+
+        assert_eq!(
+            acc.rotate_modulus(&acc.params()).unwrap_err(),
+            Errors::InvalidParams
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..3]);
+
+        assert!(acc.contains(&xs[0]));
+        assert!(!acc.contains(&xs[3]));
+        assert!(!acc.contains(&BigUint::zero()));
+        assert!(!acc.contains(&BigUint::one()));
+
+        // members and anything sharing a factor with the set have no non-membership witness
+        assert_eq!(acc.non_mem_wit_create(&xs[1]), Err(Errors::NotCoprime));
+        assert_eq!(
+            acc.non_mem_wit_create(&(&xs[1] * &xs[3])),
+            Err(Errors::NotCoprime)
+        );
+        assert_eq!(acc.non_mem_wit_create_star(&xs[2]), Err(Errors::NotCoprime));
+        let u = acc.non_mem_wit_create(&xs[3]).unwrap();
+        assert!(acc.ver_non_mem(&u, &xs[3]));
+
+        acc.del(&xs[0]).unwrap();
+        assert!(!acc.contains(&xs[0]));
+    }
+
+    #[test]
+    fn test_trait_objects() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc: Box<dyn DynAccumulator> =
+            Box::new(Accumulator::setup::<RSAGroup, _>(rng, int_size_bits));
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let root = acc.state().clone();
+        let proof = acc.batch_add(&xs[..3]);
+        assert!(acc.ver_batch_add(&proof, &root, &xs[..3]));
+
+        let w = acc.mem_wit_create(&xs[0]);
+        assert!(acc.ver_mem(&w, &xs[0]));
+        let u = acc.non_mem_wit_create(&xs[3]).unwrap();
+        assert!(acc.ver_non_mem(&u, &xs[3]));
+
+        let mut acc: Box<dyn DynamicAccumulator> =
+            Box::new(Accumulator::setup::<RSAGroup, _>(rng, int_size_bits));
+        acc.add(&xs[0]);
+        acc.del(&xs[0]).unwrap();
+        assert!(acc.del(&xs[0]).is_none());
+    }
+
+    #[test]
+    fn test_malformed_inputs() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let ws = acc.create_all_mem_wit(&xs);
+        let zero = BigUint::zero();
+
+        // invalid witnesses are rejected by the manager, without any changes
+        let root = acc.state().clone();
+        let pairs = vec![
+            (xs[0].clone(), ws[0].clone()),
+            (xs[1].clone(), ws[2].clone()),
+        ];
+        assert_eq!(acc.batch_del(&pairs), None);
+        assert_eq!(acc.state(), &root);
+        assert_eq!(acc.len(), 4);
+
+        // a single forged pair, and pairs which agree with each other but not with the root
+        let forged = vec![(xs[0].clone(), ws[1].clone())];
+        assert_eq!(acc.batch_del(&forged), None);
+        let z = BigUint::from(5u32);
+        let n = acc.modulus().clone();
+        let consistent = vec![
+            (xs[0].clone(), z.modpow(&xs[1], &n)),
+            (xs[1].clone(), z.modpow(&xs[0], &n)),
+        ];
+        assert_eq!(acc.batch_del(&consistent), None);
+        assert_eq!(acc.batch_del(&[(BigUint::one(), root.clone())]), None);
+        // a product of members, with its valid witness
+        let x_01 = &xs[0] * &xs[1];
+        let w_01 = acc.mem_wit_create(&x_01);
+        assert_eq!(acc.batch_del(&[(x_01, w_01)]), None);
+        assert_eq!(acc.state(), &root);
+        assert_eq!(acc.set_product(), &product(&xs));
+        assert_eq!(acc.len(), 4);
+        assert_eq!(acc.epoch(), 1);
+
+        // `1` with the root as its witness, also on an empty accumulator
+        assert_eq!(acc.del_w_mem(&root, &BigUint::one()), None);
+        let mut empty = Accumulator::new_with_params(&acc.params());
+        let g = empty.state().clone();
+        assert_eq!(empty.del_w_mem(&g, &BigUint::one()), None);
+        assert_eq!(empty.epoch(), 0);
+        assert_eq!(acc.len(), 4);
+
+        assert_eq!(
+            acc.agg_mem_wit(&ws[0], &zero, &xs[0], &xs[1]).unwrap_err(),
+            Errors::InvalidWitness
+        );
+
+        // verifiers return false
+        assert!(!acc.ver_mem_x_many(&[root.clone()], &ws[0], &[zero.clone(), xs[0].clone()]));
+        assert!(!acc.ver_mem_x(&root, &ws[0], &zero, &zero));
+        assert!(!acc.ver_non_mem(&(zero.clone(), BigInt::from(-1)), &xs[0]));
+        assert!(!acc.ver_mem_star(&zero, &(zero.clone(), zero.clone())));
+        let bad = (
+            zero.clone(),
+            zero.clone(),
+            (zero.clone(), zero.clone(), BigInt::from(-3)),
+            zero.clone(),
+        );
+        assert!(!acc.ver_non_mem_star(&xs[0], &bad));
+        assert!(!acc.ver_non_mem_star_batch(&[], &bad));
+        assert!(!acc.ver_batch_add(&zero, &zero, &[]));
+        assert!(!acc.ver_batch_add_with::<proofs::Pietrzak>(&vec![zero.clone()], &root, &xs));
+
+        // also with a malformed modulus
+        let mut migration = acc.clone().rerandomize(&BigUint::from(3u32)).unwrap();
+        migration.n = zero.clone();
+        assert!(!migration.verify());
+        assert!(!proofs::ni_poke2_verify(
+            &root,
+            &root,
+            &(zero.clone(), zero.clone(), BigInt::zero()),
+            &zero
+        ));
+        assert!(!proofs::Pietrzak::verify(
+            &xs[0],
+            &root,
+            &root,
+            &Vec::new(),
+            &zero
+        ));
+    }
+
+    #[test]
+    fn test_rebuild() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..20)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        for x in &xs[..5] {
+            acc.add(x);
+        }
+        acc.batch_add(&xs[5..]);
+        acc.del(&xs[3]).unwrap();
+
+        let elements = xs
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 3)
+            .map(|(_, x)| x.clone());
+        let rebuilt = Accumulator::rebuild(&acc.params(), elements);
+        assert_eq!(rebuilt.state(), acc.state());
+        assert_eq!(rebuilt.len(), 19);
+        assert_eq!(rebuilt.epoch(), 0);
+        assert!(rebuilt.ver_mem(&rebuilt.mem_wit_create(&xs[7]), &xs[7]));
+
+        // a root that doesn't match the dataset
+        let rebuilt = Accumulator::rebuild(&acc.params(), xs.iter().cloned());
+        assert_ne!(rebuilt.state(), acc.state());
+
+        let empty = Accumulator::rebuild(&acc.params(), Vec::new());
+        assert_eq!(empty.state(), acc.generator());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_batch_add_committed() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..7)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[6]);
+        let xs = &xs[..6];
+
+        let root = acc.state().clone();
+        let pi = acc.batch_add_committed(xs);
+        let commitment = Accumulator::batch_commitment(xs);
+        assert_eq!(pi.commitment, commitment);
+
+        // light verifiers only need the commitment
+        let verifier = acc.clone();
+        assert!(verifier.ver_batch_add_committed(&pi, &root, &commitment));
+        assert!(!verifier.ver_batch_add_committed(&pi, acc.generator(), &commitment));
+        let other = Accumulator::batch_commitment(&xs[1..]);
+        assert!(!verifier.ver_batch_add_committed(&pi, &root, &other));
+
+        // full verifiers check the elements against the proof as well
+        assert!(verifier.ver_batch_add_with::<CommittedWesolowski>(&pi, &root, xs));
+        assert!(!verifier.ver_batch_add_with::<CommittedWesolowski>(&pi, &root, &xs[1..]));
+    }
+
+    #[test]
+    fn test_batch_add_chunked() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..11)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[10]);
+        let xs = &xs[..10];
+        let mut whole = acc.clone();
+        whole.batch_add(xs);
+
+        // one epoch, the same state as a single batch
+        let root = acc.state().clone();
+        let mut other = acc.clone();
+        let pi = acc.batch_add_chunked(xs.iter().cloned(), 3).unwrap();
+        assert_eq!(acc.state(), whole.state());
+        assert_eq!(acc.epoch(), whole.epoch());
+        assert_eq!(acc.len(), whole.len());
+        assert!(acc.validate().is_valid());
+
+        // the proof doesn't depend on the chunks
+        assert_eq!(
+            other.clone().batch_add_chunked(xs.to_vec(), 1),
+            Ok(pi.clone())
+        );
+        assert_eq!(
+            other.clone().batch_add_chunked(xs.to_vec(), 100),
+            Ok(pi.clone())
+        );
+        assert!(acc.ver_batch_add_chunked(&pi, &root, xs.iter().cloned()));
+        assert!(acc.ver_batch_add_chunked(&pi, &root, xs));
+        assert!(!acc.ver_batch_add_chunked(&pi, &root, &xs[1..]));
+        assert!(!acc.ver_batch_add_chunked(&pi, acc.generator(), xs));
+        assert_eq!(other.clone().batch_add_chunked(xs, 4), Ok(pi.clone()));
+
+        assert_eq!(
+            acc.batch_add_chunked(Vec::<BigUint>::new(), 3),
+            Err(Errors::EmptyBatch)
+        );
+        other.add_observer(|_: &Mutation<'_>| {});
+        assert_eq!(
+            other.batch_add_chunked(xs.to_vec(), 3),
+            Err(Errors::Unobservable)
+        );
+    }
+
+    #[test]
+    fn test_precomputed_powers() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut plain = acc.clone();
+
+        let xs = (0..12)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.precompute_powers(12 * int_size_bits, 4);
+        acc.batch_add(&xs);
+        plain.batch_add(&xs);
+
+        acc.del(&xs[2]).unwrap();
+        plain.del(&xs[2]).unwrap();
+        assert_eq!(acc.state(), plain.state());
+        acc.batch_del_tracked(&xs[5..7]).unwrap();
+        plain.batch_del_tracked(&xs[5..7]).unwrap();
+        assert_eq!(acc.state(), plain.state());
+        assert_eq!(acc.mem_wit_create(&xs[0]), plain.mem_wit_create(&xs[0]));
+        assert!(acc.validate().is_valid());
+
+        // the table can be shared, but only with accumulators over the same parameters
+        let powers = acc.powers().unwrap().clone();
+        let elements = xs.iter().cloned().filter(|x| acc.contains(x));
+        let rebuilt =
+            Accumulator::rebuild_with_powers(&acc.params(), elements, powers.clone()).unwrap();
+        assert_eq!(rebuilt.state(), acc.state());
+        assert!(Arc::ptr_eq(rebuilt.powers().unwrap(), &powers));
+
+        let other = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(
+            Accumulator::rebuild_with_powers(&other, Vec::new(), powers).err(),
+            Some(Errors::ParameterMismatch)
+        );
 
-        Some(proofs::ni_poe_prove(&x_star, &self.root, &root_t, &self.n))
+        // a new generator needs a new table
+        acc.rerandomize(&BigUint::from(65537u32)).unwrap();
+        assert!(acc.powers().is_none());
     }
 
-    fn ver_batch_del(&self, w: &BigUint, root: &BigUint, xs: &[BigUint]) -> bool {
-        let mut x_star = BigUint::one();
-        for x in xs {
-            x_star *= x
-        }
-
-        proofs::ni_poe_verify(&x_star, &self.root, root, &w, &self.n)
-    }
+    #[test]
+    fn test_checked_add() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-    fn del_w_mem(&mut self, w: &BigUint, x: &BigUint) -> Option<()> {
-        if !self.ver_mem(w, x) {
-            return None;
+        let xs = (0..3)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.checked_add(&xs[0]).unwrap();
+        assert!(acc.check_element(&BigUint::from(3u32)));
+
+        for x in &[
+            BigUint::one(),
+            BigUint::from(2u32),
+            &xs[1] * &xs[2],
+            rng.gen_prime(int_size_bits + 1),
+        ] {
+            assert_eq!(acc.checked_add(x), Err(Errors::InvalidElement(0)));
         }
 
-        self.set /= x;
-        // w is root without x, so need to recompute
-        self.root = w.clone();
-
-        Some(())
-    }
+        let root = acc.state().clone();
+        let bad = vec![xs[1].clone(), BigUint::from(9u32), xs[2].clone()];
+        assert_eq!(acc.checked_batch_add(&bad), Err(Errors::InvalidElement(1)));
+        assert_eq!(acc.state(), &root);
+        assert_eq!(acc.len(), 1);
 
-    #[inline]
-    fn create_all_mem_wit(&self, set: &[BigUint]) -> Vec<BigUint> {
-        root_factor(&self.g, &set, &self.n)
+        let w = acc.checked_batch_add(&xs[1..]).unwrap();
+        assert!(acc.ver_batch_add(&w, &root, &xs[1..]));
     }
 
-    fn agg_mem_wit(
-        &self,
-        w_x: &BigUint,
-        w_y: &BigUint,
-        x: &BigUint,
-        y: &BigUint,
-    ) -> (BigUint, BigUint) {
-        // TODO: check this matches, sth is not quite right in the paper here
-        let w_xy = shamir_trick(w_x, w_y, x, y, &self.n).unwrap();
-        let xy = x.clone() * y;
-
-        debug_assert!(
-            w_xy.modpow(&xy, &self.n) == self.root,
-            "invalid shamir trick"
-        );
+    #[test]
+    fn test_setup_default() {
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup_default::<RSAGroup>(int_size_bits);
+        let other = Accumulator::setup_default::<RSAGroup>(int_size_bits);
+        assert_ne!(acc.params(), other.params());
+        assert_eq!(acc.modulus().bits(), int_size_bits);
 
-        let pi = proofs::ni_poe_prove(&xy, &w_xy, &self.root, &self.n);
+        let x = BigUint::from(1_000_003u32);
+        acc.add(&x);
+        let w = acc.mem_wit_create(&x);
+        assert!(acc.ver_mem(&w, &x));
 
-        (w_xy, pi)
+        assert_eq!(ModulusSize::Rsa3072.bits(), 3072);
     }
 
-    fn ver_agg_mem_wit(&self, w_xy: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool {
-        let xy = x.clone() * y;
-        proofs::ni_poe_verify(&xy, w_xy, &self.root, pi, &self.n)
-    }
+    #[test]
+    fn test_ver_mem_star_batch() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-    fn mem_wit_create_star(&self, x: &BigUint) -> (BigUint, BigUint) {
-        let w_x = self.mem_wit_create(x);
-        debug_assert!(self.root != w_x, "{} was not a member", x);
-        let p = proofs::ni_poe_prove(x, &w_x, &self.root, &self.n);
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
 
-        (w_x, p)
+        let mut pis = xs
+            .iter()
+            .map(|x| (x.clone(), acc.mem_wit_create_star(x)))
+            .collect::<Vec<_>>();
+        assert!(acc.ver_mem_star_batch(&pis));
+        assert!(acc.ver_mem_star_batch(&pis[3..4]));
+        assert!(!acc.ver_mem_star_batch(&[]));
+
+        // a single invalid proof fails the batch
+        let mut bad = pis.clone();
+        (bad[5].1).1 += 1u32;
+        assert!(!acc.ver_mem_star_batch(&bad));
+        let mut bad = pis.clone();
+        bad[2].0 = xs[3].clone();
+        assert!(!acc.ver_mem_star_batch(&bad));
+        let mut bad = pis.clone();
+        bad.swap(0, 1);
+        bad[0].0 = xs[0].clone();
+        assert!(!acc.ver_mem_star_batch(&bad));
+
+        // proofs off by a factor of -1 only fail the single checks, the batch checks in QR_n
+        let n = acc.modulus();
+        let mut negated = pis.clone();
+        (negated[4].1).1 = n - &(negated[4].1).1;
+        assert!(!acc.ver_mem_star(&negated[4].0, &negated[4].1));
+        assert!(acc.ver_mem_star_batch(&negated));
+        (negated[6].1).1 = n - &(negated[6].1).1;
+        assert!(!acc.ver_mem_star(&negated[6].0, &negated[6].1));
+        assert!(acc.ver_mem_star_batch(&negated));
+        // but not an invalid proof besides them
+        (negated[1].1).1 += 1u32;
+        assert!(!acc.ver_mem_star_batch(&negated));
+
+        // stale after an update
+        acc.add(&rng.gen_prime(int_size_bits));
+        assert!(!acc.ver_mem_star_batch(&pis));
+        pis.truncate(2);
+        for pi in &mut pis {
+            pi.1 = acc.mem_wit_create_star(&pi.0);
+        }
+        assert!(acc.ver_mem_star_batch(&pis));
     }
 
-    fn ver_mem_star(&self, x: &BigUint, pi: &(BigUint, BigUint)) -> bool {
-        proofs::ni_poe_verify(x, &pi.0, &self.root, &pi.1, &self.n)
-    }
+    #[test]
+    fn test_mem_wit_for_subset() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-    fn mem_wit_x(
-        &self,
-        _other: &BigUint,
-        w_x: &BigUint,
-        w_y: &BigUint,
-        _x: &BigUint,
-        _y: &BigUint,
-    ) -> BigUint {
-        (w_x * w_y) % &self.n
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..9]);
+
+        let subset = vec![xs[1].clone(), xs[4].clone(), xs[7].clone()];
+        let pi = acc.mem_wit_create_for_subset(&subset).unwrap();
+        assert!(acc.ver_mem_for_subset(&subset, &pi));
+        assert!(!acc.ver_mem_for_subset(&subset[..2], &pi));
+        assert!(!acc.ver_mem_for_subset(&[], &pi));
+
+        // a single element matches mem_wit_create_star
+        let pi = acc.mem_wit_create_for_subset(&xs[2..3]).unwrap();
+        assert_eq!(pi, acc.mem_wit_create_star(&xs[2]));
+
+        let mut with_non_member = subset;
+        with_non_member.push(xs[9].clone());
+        assert_eq!(
+            acc.mem_wit_create_for_subset(&with_non_member),
+            Err(Errors::NotAMember)
+        );
+        assert_eq!(acc.mem_wit_create_for_subset(&[]), Err(Errors::EmptyBatch));
     }
 
-    fn ver_mem_x(&self, other: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool {
-        // assert x and y are coprime
-        let q = x.gcd(y);
-        if !q.is_one() {
-            return false;
-        }
+    #[test]
+    fn test_mem_wit_create_batch() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        // A_1^y
-        let rhs_a = self.root.modpow(y, &self.n);
-        // A_2^x
-        let rhs_b = other.modpow(x, &self.n);
+        let xs = (0..12)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..11]);
 
-        // A_1^y * A_2^x
-        let rhs = (rhs_a * rhs_b) % &self.n;
-        // pi^{x * y}
-        let lhs = pi.modpow(&(x.clone() * y), &self.n);
+        let queries = vec![xs[8].clone(), xs[0].clone(), xs[5].clone(), xs[3].clone()];
+        let ws = acc.mem_wit_create_batch(&queries).unwrap();
+        assert_eq!(ws.len(), queries.len());
+        for (w, x) in ws.iter().zip(queries.iter()) {
+            assert_eq!(w, &acc.mem_wit_create(x));
+        }
 
-        lhs == rhs
+        assert!(acc.mem_wit_create_batch(&[]).unwrap().is_empty());
+        assert_eq!(
+            acc.mem_wit_create_batch(&[xs[1].clone(), xs[11].clone()]),
+            Err(Errors::NotAMember)
+        );
     }
 
-    fn non_mem_wit_create_star(
-        &self,
-        x: &BigUint,
-    ) -> (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint) {
-        let g = &self.g;
-        let n = &self.n;
+    /// Proves nothing, the verifier recomputes the exponentiation.
+    struct Recompute;
 
-        // a, b <- Bezout(x, s_star)
-        let (_, a, b) = ExtendedGcd::extended_gcd(x, &self.set);
+    impl ProofOfExponentiation for Recompute {
+        type Proof = ();
 
-        // d <- g^a
-        let d = modpow_uint_int(g, &a, n).expect("invalid state");
-        // v <- A^b
-        let v = modpow_uint_int(&self.root, &b, n).expect("invalid state");
+        fn prove(_x: &BigUint, _u: &BigUint, _w: &BigUint, _n: &BigUint) {}
 
-        // pi_d <- NI-PoKE2(b, A, v)
-        let pi_d = proofs::ni_poke2_prove(b, &self.root, &v, n);
+        fn verify(x: &BigUint, u: &BigUint, w: &BigUint, _proof: &(), n: &BigUint) -> bool {
+            &u.modpow(x, n) == w
+        }
+    }
 
-        // k <- g * v^-1
-        let k = (g * v
-            .clone()
-            .mod_inverse(n)
-            .expect("invalid state")
-            .into_biguint()
-            .unwrap())
-            % n;
+    #[test]
+    fn test_proof_system() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
 
-        // pi_g <- NI-PoE(x, d, g * v^-1)
-        let pi_g = proofs::ni_poe_prove(x, &d, &k, n);
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let root = acc.state().clone();
+        let mut other = acc.clone();
+        let w = acc.batch_add_with::<Wesolowski>(&xs[..4]);
+        assert_eq!(w, other.batch_add(&xs[..4]));
+        assert!(acc.ver_batch_add(&w, &root, &xs[..4]));
 
-        // return {d, v, pi_d, pi_g}
-        (d, v, pi_d, pi_g)
-    }
+        let root = acc.state().clone();
+        acc.batch_add_with::<Recompute>(&xs[4..]);
+        assert!(acc.ver_batch_add_with::<Recompute>(&(), &root, &xs[4..]));
+        assert!(!acc.ver_batch_add_with::<Recompute>(&(), &root, &xs[3..]));
 
-    fn ver_non_mem_star(
-        &self,
-        x: &BigUint,
-        pi: &(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
-    ) -> bool {
-        let g = &self.g;
-        let n = &self.n;
+        let root = acc.state().clone();
+        let ws = acc.create_all_mem_wit(&xs);
+        let pairs = xs
+            .iter()
+            .cloned()
+            .zip(ws.into_iter())
+            .take(2)
+            .collect::<Vec<_>>();
+        acc.batch_del_with::<Recompute>(&pairs).unwrap();
+        assert!(acc.ver_batch_del_with::<Recompute>(&(), &root, &xs[..2]));
+        assert_eq!(acc.epoch(), 3);
 
-        let (d, v, pi_d, pi_g) = pi;
+        let root = acc.state().clone();
+        let w = acc.batch_del_tracked_with::<Wesolowski>(&xs[2..4]).unwrap();
+        assert!(acc.ver_batch_del(&w, &root, &xs[2..4]));
+    }
 
-        // verify NI-PoKE2
-        if !proofs::ni_poke2_verify(&self.root, &v, pi_d, n) {
-            return false;
-        }
+    #[test]
+    fn test_proving_accumulator() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = ProvingAccumulator::<proofs::Pietrzak>::new(acc);
 
-        // verify NI-PoE
-        let k = (g * v
-            .clone()
-            .mod_inverse(n)
-            .expect("invalid state")
-            .into_biguint()
-            .unwrap())
-            % n;
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let root = acc.accumulator().state().clone();
+        let pi = acc.batch_add(&xs);
+        assert!(acc.ver_batch_add(&pi, &root, &xs));
+        assert!(!acc.ver_batch_add(&pi, &root, &xs[1..]));
 
-        if !proofs::ni_poe_verify(x, d, &k, pi_g, n) {
-            return false;
-        }
+        let root = acc.accumulator().state().clone();
+        let pi = acc.batch_del_tracked(&xs[..2]).unwrap();
+        assert!(acc.ver_batch_del(&pi, &root, &xs[..2]));
 
-        true
+        // or chosen per call
+        let mut acc = acc.into_accumulator();
+        let root = acc.state().clone();
+        let pi = acc.batch_add_with::<proofs::Pietrzak>(&xs[..2]);
+        assert!(acc.ver_batch_add_with::<proofs::Pietrzak>(&pi, &root, &xs[..2]));
+        assert!(acc.ver_mem(&acc.mem_wit_create(&xs[2]), &xs[2]));
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    use crate::group::RSAGroup;
-    use num_bigint::RandPrime;
-    use num_bigint::Sign;
-    use num_traits::FromPrimitive;
-    use rand::SeedableRng;
-    use rand_chacha::ChaChaRng;
 
     #[test]
-    fn test_static() {
+    fn test_set_equality() {
         let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut a = Accumulator::new_with_params(&params);
+        let mut b = Accumulator::new_with_params(&PublicParams {
+            g: params.g.modpow(&BigUint::from(7u32), &params.n),
+            ..params.clone()
+        });
+
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        a.batch_add(&xs[..5]);
+        for x in xs[..5].iter().rev() {
+            b.add(x);
+        }
 
-        for _ in 0..100 {
-            let int_size_bits = 256; // insecure, but faster tests
-            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let pi = a.prove_same_set(&b).unwrap();
+        assert!(pi.verify(&a, &b));
+        assert!(!pi.verify(&b, &a));
 
-            let xs = (0..5)
-                .map(|_| rng.gen_prime(int_size_bits))
-                .collect::<Vec<_>>();
+        b.add(&xs[5]);
+        assert!(!pi.verify(&a, &b));
+        assert_eq!(a.prove_same_set(&b).unwrap_err(), Errors::NotASubset);
+        assert_eq!(b.prove_subset(&a).unwrap_err(), Errors::NotASubset);
 
-            for x in &xs {
-                acc.add(x);
-            }
+        let subset = a.prove_subset(&b).unwrap();
+        assert!(subset.verify(&a, &b));
+        assert!(!subset.verify(&b, &a));
 
-            for x in &xs {
-                let w = acc.mem_wit_create(x);
-                assert!(acc.ver_mem(&w, x));
-            }
-        }
+        let other = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(
+            a.prove_subset(&other).unwrap_err(),
+            Errors::ParameterMismatch
+        );
     }
 
     #[test]
@@ -434,7 +3176,7 @@ mod tests {
             for _ in 0..5 {
                 let y = rng.gen_prime(int_size_bits);
 
-                let w = acc.non_mem_wit_create(&y);
+                let w = acc.non_mem_wit_create(&y).unwrap();
                 assert!(acc.ver_non_mem(&w, &y));
             }
         }
@@ -542,6 +3284,12 @@ mod tests {
             assert!(acc.ver_mem(w, x));
         }
 
+        // single witnesses from the set, without division
+        for i in &[0, set.len() / 2, set.len() - 1] {
+            assert_eq!(acc.mem_wit_create_from(&set, *i).as_ref(), Some(&ws[*i]));
+        }
+        assert_eq!(acc.mem_wit_create_from(&set, set.len()), None);
+
         // batch delete
         let root = acc.state().clone();
         let pairs = set
@@ -556,6 +3304,8 @@ mod tests {
             acc.ver_batch_del(&w, &root, &set[..3]),
             "ver_batch_del failed"
         );
+        assert_eq!(acc.len(), size - 3);
+        assert!(acc.validate().is_valid());
     }
 
     #[test]
@@ -565,6 +3315,75 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_batch_del_tracked() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+
+        let root = acc.state().clone();
+        let w = acc.batch_del_tracked(&xs[2..6]).unwrap();
+        assert!(acc.ver_batch_del(&w, &root, &xs[2..6]));
+        assert_eq!(acc.len(), 6);
+        assert!(acc.validate().is_valid());
+
+        let w_0 = acc.mem_wit_create(&xs[0]);
+        assert!(acc.ver_mem(&w_0, &xs[0]));
+
+        // already deleted
+        let root = acc.state().clone();
+        assert_eq!(
+            acc.batch_del_tracked(&xs[5..7]).unwrap_err(),
+            Errors::NotAMember
+        );
+        assert_eq!(acc.state(), &root);
+        assert_eq!(acc.batch_del_tracked(&[]).unwrap_err(), Errors::EmptyBatch);
+
+        // `1` and products of members don't remove a single element each
+        let one = BigUint::one();
+        assert_eq!(
+            acc.batch_del_tracked(&[one.clone(), one]).unwrap_err(),
+            Errors::NotAMember
+        );
+        assert_eq!(
+            acc.batch_del_tracked(&[&xs[0] * &xs[1]]).unwrap_err(),
+            Errors::NotAMember
+        );
+        assert_eq!(acc.state(), &root);
+        assert_eq!(acc.len(), 6);
+    }
+
+    #[test]
+    fn test_batch_add_with_hints() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        acc.add(&rng.gen_prime(int_size_bits));
+
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let root = acc.state().clone();
+        let (proof, hints) = acc.batch_add_with_hints(&xs, 3);
+        assert!(acc.ver_batch_add(&proof, &root, &xs));
+
+        assert_eq!(hints.ranges.len(), 3);
+        assert_eq!(hints.ranges[2].end, xs.len());
+        for (i, x) in xs.iter().enumerate() {
+            let w = hints.witness(i, x, acc.modulus()).unwrap();
+            assert!(acc.ver_mem(&w, x));
+            assert_eq!(w, acc.mem_wit_create(x));
+        }
+
+        assert_eq!(hints.witness(0, &xs[9], acc.modulus()), None);
+        assert_eq!(hints.witness(10, &xs[9], acc.modulus()), None);
+    }
+
     #[test]
     fn test_batch_add_large() {
         let size = 128;
@@ -621,7 +3440,7 @@ mod tests {
                 let w_x = acc.mem_wit_create(x);
                 let w_y = acc.mem_wit_create(y);
 
-                let (w_xy, p_wxy) = acc.agg_mem_wit(&w_x, &w_y, x, y);
+                let (w_xy, p_wxy) = acc.agg_mem_wit(&w_x, &w_y, x, y).unwrap();
 
                 assert!(
                     acc.ver_agg_mem_wit(&w_xy, &p_wxy, x, y),
@@ -663,9 +3482,109 @@ mod tests {
                     "invalid ver_mem_x witness"
                 );
             }
+
+            // MemWitX*
+            {
+                let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+                let mut other = acc.clone();
+                let x = rng.gen_prime(128);
+                let y = rng.gen_prime(128);
+
+                acc.add(&rng.gen_prime(128));
+                acc.add(&x);
+                other.add(&y);
+
+                let w_x = acc.mem_wit_create(&x);
+                let w_y = other.mem_wit_create(&y);
+
+                let pi = acc
+                    .mem_wit_x_star(other.state(), &w_x, &w_y, &x, &y)
+                    .unwrap();
+                assert!(
+                    acc.ver_mem_x_star(other.state(), &pi, &x, &y),
+                    "invalid ver_mem_x_star proof"
+                );
+                assert!(!acc.ver_mem_x_star(acc.state(), &pi, &x, &y));
+                assert!(!acc.ver_mem_x_star(other.state(), &pi, &y, &x));
+
+                assert_eq!(
+                    acc.mem_wit_x_star(other.state(), &w_y, &w_x, &x, &y),
+                    Err(Errors::InvalidWitness)
+                );
+                assert_eq!(
+                    acc.mem_wit_x_star(other.state(), &w_x, &w_y, &x, &x),
+                    Err(Errors::NotCoprime)
+                );
+            }
+
+            // MemWitX for k accumulators
+            {
+                let empty = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+                let mut accs = vec![empty; 5];
+                let xs = (0..accs.len())
+                    .map(|_| rng.gen_prime(128))
+                    .collect::<Vec<_>>();
+
+                for (acc, x) in accs.iter_mut().zip(&xs) {
+                    acc.add(&rng.gen_prime(128));
+                    acc.add(x);
+                }
+
+                let ws = accs
+                    .iter()
+                    .zip(&xs)
+                    .map(|(acc, x)| acc.mem_wit_create(x))
+                    .collect::<Vec<_>>();
+                let others = accs[1..]
+                    .iter()
+                    .map(|acc| acc.state().clone())
+                    .collect::<Vec<_>>();
+
+                let pi = accs[0].mem_wit_x_many(&ws);
+                assert!(
+                    accs[0].ver_mem_x_many(&others, &pi, &xs),
+                    "invalid ver_mem_x_many witness"
+                );
+
+                // wrong element, and not pairwise coprime
+                let mut bad = xs.clone();
+                bad[2] = rng.gen_prime(128);
+                assert!(!accs[0].ver_mem_x_many(&others, &pi, &bad));
+                bad[2] = xs[1].clone();
+                assert!(!accs[0].ver_mem_x_many(&others, &pi, &bad));
+                assert!(!accs[0].ver_mem_x_many(&others[1..], &pi, &xs));
+            }
         }
     }
 
+    #[test]
+    fn test_non_mem_star_batch() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..5)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+
+        let ys = (0..20)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let pi = acc.non_mem_wit_create_star_batch(&ys).unwrap();
+        assert!(acc.ver_non_mem_star_batch(&ys, &pi));
+        assert!(!acc.ver_non_mem_star_batch(&ys[1..], &pi));
+        assert!(!acc.ver_non_mem_star_batch(&[], &pi));
+
+        let mut with_member = ys.clone();
+        with_member.push(xs[3].clone());
+        assert_eq!(
+            acc.non_mem_wit_create_star_batch(&with_member),
+            Err(Errors::NotCoprime)
+        );
+        assert!(!acc.ver_non_mem_star_batch(&with_member, &pi));
+    }
+
     #[test]
     fn test_aggregation_non_mem_star() {
         let rng = &mut ChaChaRng::from_seed([0u8; 32]);
@@ -684,9 +3603,84 @@ mod tests {
             }
 
             let x = rng.gen_prime(int_size_bits);
-            let pi = acc.non_mem_wit_create_star(&x);
+            let pi = acc.non_mem_wit_create_star(&x).unwrap();
 
             assert!(acc.ver_non_mem_star(&x, &pi), "invalid ver_non_mem_star");
         }
     }
+
+    #[test]
+    fn test_qr_mode() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let n = params.n.clone();
+        let qr = params.clone().into_qr();
+        assert_eq!(qr.g, params.g.modpow(&BigUint::from(2u32), &n));
+        assert_eq!(qr.clone().into_qr(), qr);
+        assert_eq!(qr.map_element(&(&n - 3u32)), BigUint::from(9u32));
+        assert_eq!(params.map_element(&(&n - 3u32)), &n - 3u32);
+
+        let mut acc = Accumulator::new_with_params(&qr);
+        assert!(acc.is_qr());
+        assert_eq!(acc.params(), qr);
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+
+        let root_0 = acc.state().clone();
+        let pi = acc.batch_add(&xs[..4]);
+        assert!(acc.ver_batch_add(&pi, &root_0, &xs[..4]));
+        assert!(verify::verify_batch_add(
+            &qr,
+            &root_0,
+            acc.state(),
+            &xs[..4],
+            &pi
+        ));
+        assert!(!acc.ver_batch_add_with::<Wesolowski>(&pi, &root_0, &xs[..4]));
+        assert!(!acc.ver_batch_add(&pi, &root_0, &xs[..3]));
+
+        // witnesses are checked in QR_n, where w and -w are the same element
+        let w = acc.mem_wit_create(&xs[0]);
+        assert!(acc.ver_mem(&w, &xs[0]));
+        assert!(acc.ver_mem(&(&n - &w), &xs[0]));
+        assert!(verify::verify_mem(&qr, acc.state(), &(&n - &w), &xs[0]));
+        assert!(!acc.ver_mem(&w, &xs[1]));
+        let u = acc.non_mem_wit_create(&xs[5]).unwrap();
+        assert!(acc.ver_non_mem(&u, &xs[5]));
+        assert!(verify::verify_non_mem(&qr, acc.state(), &u, &xs[5]));
+        assert!(!acc.ver_non_mem(&u, &xs[4]));
+
+        let root_1 = acc.state().clone();
+        let pairs = xs[..2]
+            .iter()
+            .map(|x| (x.clone(), acc.mem_wit_create(x)))
+            .collect::<Vec<_>>();
+        let pi = acc.batch_del(&pairs).unwrap();
+        assert!(acc.ver_batch_del(&pi, &root_1, &xs[..2]));
+        assert!(verify::verify_batch_del(
+            &qr,
+            &root_1,
+            acc.state(),
+            &xs[..2],
+            &pi
+        ));
+        let root_2 = acc.state().clone();
+        let pi = acc.batch_del_tracked(&xs[2..3]).unwrap();
+        assert!(acc.ver_batch_del(&pi, &root_2, &xs[2..3]));
+        assert!(acc.ver_mem(&acc.mem_wit_create(&xs[3]), &xs[3]));
+
+        // witnesses of either sign are aggregated across accumulators
+        let mut other = Accumulator::new_with_params(&qr);
+        other.add(&xs[4]);
+        let w_x = &n - acc.mem_wit_create(&xs[3]);
+        let w_y = other.mem_wit_create(&xs[4]);
+        for (w_x, w_y) in &[(w_x.clone(), w_y.clone()), (w_x, &n - &w_y)] {
+            let pi = acc
+                .mem_wit_x_star(other.state(), w_x, w_y, &xs[3], &xs[4])
+                .unwrap();
+            assert!(acc.ver_mem_x_star(other.state(), &pi, &xs[3], &xs[4]));
+        }
+    }
 }
@@ -0,0 +1,558 @@
+//! Combining many proofs against one root into a single object.
+//!
+//! A [`ProofAggregator`] collects membership and non-membership witnesses, and proofs of
+//! exponentiation such as those of `batch_add`, e.g. of all transactions of a block. `finish`
+//! checks them, aggregates the witnesses with the Shamir trick and its non-membership
+//! counterpart, and returns an [`AggregateProof`], which verifiers check with a single `verify`.
+//!
+//! A [`Relayer`] does the same for [`HolderProof`]s submitted by independent holders. The
+//! relayer is not trusted: verifiers that know the submitted [`Statement`]s check with
+//! `verify_statements` that the aggregate proves exactly these, none dropped or altered.
+
+use num_bigint::traits::ExtendedGcd;
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::accumulator::PublicParams;
+use crate::encoding::{finish, read_part, write_part, Encode};
+use crate::error::Errors;
+use crate::math::{modpow_uint_int, product, shamir_trick};
+use crate::proofs::{self, ExponentProof};
+use crate::verify::{verify_mem, verify_non_mem};
+
+/// `(xs, w, Q)`: `w^{\prod xs} = root`, and the NI-PoE of this.
+pub type AggregatedMem = (Vec<BigUint>, BigUint, ExponentProof);
+
+/// `(xs, (d, b))`: `d^{\prod xs} root^b = g`.
+pub type AggregatedNonMem = (Vec<BigUint>, (BigUint, BigInt));
+
+/// A claim about a single element.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Statement {
+    Member(BigUint),
+    NonMember(BigUint),
+}
+
+/// The proof of a [`Statement`] by its holder.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HolderProof {
+    Mem { x: BigUint, w: BigUint },
+    NonMem { x: BigUint, w: (BigUint, BigInt) },
+}
+
+impl HolderProof {
+    pub fn statement(&self) -> Statement {
+        match self {
+            HolderProof::Mem { x, .. } => Statement::Member(x.clone()),
+            HolderProof::NonMem { x, .. } => Statement::NonMember(x.clone()),
+        }
+    }
+
+    pub fn verify(&self, params: &PublicParams, root: &BigUint) -> bool {
+        match self {
+            HolderProof::Mem { x, w } => verify_mem(params, root, w, x),
+            HolderProof::NonMem { x, w } => verify_non_mem(params, root, w, x),
+        }
+    }
+}
+
+/// The claims collected by a [`ProofAggregator`], see `verify`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggregateProof {
+    /// The aggregated membership witness of all members.
+    pub mem: Option<AggregatedMem>,
+    /// The aggregated non-membership witness of all non-members.
+    pub non_mem: Option<AggregatedNonMem>,
+    /// `(x, u, w, Q)`: NI-PoEs of `u^x = w`.
+    pub poes: Vec<(BigUint, BigUint, BigUint, ExponentProof)>,
+}
+
+impl AggregateProof {
+    /// Checks all claims against `root`.
+    /// Returns `false` if there are none.
+    pub fn verify(&self, params: &PublicParams, root: &BigUint) -> bool {
+        let n = &params.n;
+        if self.mem.is_none() && self.non_mem.is_none() && self.poes.is_empty() {
+            return false;
+        }
+
+        let mem = match &self.mem {
+            Some((xs, w, q)) => {
+                !xs.is_empty() && proofs::ni_poe_verify(&product(xs), w, root, q, n)
+            }
+            None => true,
+        };
+        let non_mem = match &self.non_mem {
+            Some((xs, w)) => !xs.is_empty() && verify_non_mem(params, root, w, &product(xs)),
+            None => true,
+        };
+
+        mem && non_mem
+            && self
+                .poes
+                .iter()
+                .all(|(x, u, w, q)| proofs::ni_poe_verify(x, u, w, q, n))
+    }
+
+    /// The statements about single elements, members first, in the order they were added.
+    pub fn statements(&self) -> Vec<Statement> {
+        let members = self.mem.iter().flat_map(|(xs, _, _)| xs);
+        let non_members = self.non_mem.iter().flat_map(|(xs, _)| xs);
+
+        members
+            .cloned()
+            .map(Statement::Member)
+            .chain(non_members.cloned().map(Statement::NonMember))
+            .collect()
+    }
+
+    /// Like `verify`, also checking that this proves exactly `statements`, in any order.
+    pub fn verify_statements(
+        &self,
+        params: &PublicParams,
+        root: &BigUint,
+        statements: &[Statement],
+    ) -> bool {
+        let mut expected = statements.to_vec();
+        expected.sort();
+        let mut proven = self.statements();
+        proven.sort();
+
+        proven == expected && self.verify(params, root)
+    }
+}
+
+impl Encode for AggregateProof {
+    /// `mem || non_mem || poes` as length prefixed parts, with empty parts for missing
+    /// witnesses, and each NI-PoE as `((x, u), (w, Q))`.
+    fn to_bytes(&self) -> Vec<u8> {
+        let poes = self
+            .poes
+            .iter()
+            .map(|(x, u, w, q)| ((x.clone(), u.clone()), (w.clone(), q.clone())))
+            .collect::<Vec<_>>();
+
+        let mut res = Vec::new();
+        write_part(
+            &mut res,
+            &self.mem.as_ref().map_or(Vec::new(), Encode::to_bytes),
+        );
+        write_part(
+            &mut res,
+            &self.non_mem.as_ref().map_or(Vec::new(), Encode::to_bytes),
+        );
+        write_part(&mut res, &poes.to_bytes());
+        res
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, Errors> {
+        let mem = match read_part(&mut bytes)? {
+            [] => None,
+            part => Some(AggregatedMem::from_bytes(part)?),
+        };
+        let non_mem = match read_part(&mut bytes)? {
+            [] => None,
+            part => Some(AggregatedNonMem::from_bytes(part)?),
+        };
+        type EncodedPoe = ((BigUint, BigUint), (BigUint, BigUint));
+        let poes = Vec::<EncodedPoe>::from_bytes(read_part(&mut bytes)?)?
+            .into_iter()
+            .map(|((x, u), (w, q))| (x, u, w, q))
+            .collect();
+        finish(bytes)?;
+
+        Ok(AggregateProof { mem, non_mem, poes })
+    }
+}
+
+/// Collects proofs against the same root, see [`AggregateProof`].
+#[derive(Debug, Clone)]
+pub struct ProofAggregator {
+    params: PublicParams,
+    root: BigUint,
+    mem: Vec<(BigUint, BigUint)>,
+    non_mem: Vec<(BigUint, (BigUint, BigInt))>,
+    poes: Vec<(BigUint, BigUint, BigUint, ExponentProof)>,
+}
+
+impl ProofAggregator {
+    /// Collects proofs against `root`, e.g. `acc.state()`.
+    pub fn new(params: &PublicParams, root: &BigUint) -> Self {
+        ProofAggregator {
+            params: params.clone(),
+            root: root.clone(),
+            mem: Vec::new(),
+            non_mem: Vec::new(),
+            poes: Vec::new(),
+        }
+    }
+
+    /// Adds the membership witness `w` of `x`.
+    pub fn mem(mut self, x: &BigUint, w: &BigUint) -> Self {
+        self.mem.push((x.clone(), w.clone()));
+        self
+    }
+
+    /// Adds the non-membership witness `w` of `x`.
+    pub fn non_mem(mut self, x: &BigUint, w: &(BigUint, BigInt)) -> Self {
+        self.non_mem.push((x.clone(), w.clone()));
+        self
+    }
+
+    /// Adds the proof of a holder.
+    pub fn holder_proof(self, proof: &HolderProof) -> Self {
+        match proof {
+            HolderProof::Mem { x, w } => self.mem(x, w),
+            HolderProof::NonMem { x, w } => self.non_mem(x, w),
+        }
+    }
+
+    /// Adds the NI-PoE `q` of `u^x = w`.
+    pub fn poe(mut self, x: &BigUint, u: &BigUint, w: &BigUint, q: &ExponentProof) -> Self {
+        self.poes.push((x.clone(), u.clone(), w.clone(), q.clone()));
+        self
+    }
+
+    /// Adds the proof returned by `batch_add`, that adding `xs` to `old_root` resulted in
+    /// `new_root`.
+    pub fn batch_add(
+        self,
+        xs: &[BigUint],
+        old_root: &BigUint,
+        new_root: &BigUint,
+        proof: &BigUint,
+    ) -> Self {
+        self.poe(&product(xs), old_root, new_root, proof)
+    }
+
+    /// Adds the proof returned by `batch_del`, that deleting `xs` from `old_root` resulted in
+    /// `new_root`.
+    pub fn batch_del(
+        self,
+        xs: &[BigUint],
+        old_root: &BigUint,
+        new_root: &BigUint,
+        proof: &BigUint,
+    ) -> Self {
+        self.poe(&product(xs), new_root, old_root, proof)
+    }
+
+    /// Checks and aggregates everything collected.
+    ///
+    /// Fails with `EmptyBatch` if nothing was collected, with `InvalidWitness` or
+    /// `InvalidProof` if a witness or proof doesn't verify, and with `NotCoprime` if an
+    /// element was added twice.
+    pub fn finish(self) -> Result<AggregateProof, Errors> {
+        if self.mem.is_empty() && self.non_mem.is_empty() && self.poes.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        let n = &self.params.n;
+        if !self
+            .poes
+            .iter()
+            .all(|(x, u, w, q)| proofs::ni_poe_verify(x, u, w, q, n))
+        {
+            return Err(Errors::InvalidProof);
+        }
+
+        Ok(AggregateProof {
+            mem: self.aggregate_mem()?,
+            non_mem: self.aggregate_non_mem()?,
+            poes: self.poes,
+        })
+    }
+
+    fn aggregate_mem(&self) -> Result<Option<AggregatedMem>, Errors> {
+        let n = &self.params.n;
+        let ((x, w), rest) = match self.mem.split_first() {
+            Some(first) => first,
+            None => return Ok(None),
+        };
+        if w.modpow(x, n) != self.root {
+            return Err(Errors::InvalidWitness);
+        }
+
+        let mut x_star = x.clone();
+        let mut w_star = w.clone();
+        for (x, w) in rest {
+            if !x.gcd(&x_star).is_one() {
+                return Err(Errors::NotCoprime);
+            }
+            // fails if w^x != root
+            w_star = shamir_trick(&w_star, w, &x_star, x, n).ok_or(Errors::InvalidWitness)?;
+            x_star *= x;
+        }
+
+        let pi = proofs::ni_poe_prove(&x_star, &w_star, &self.root, n);
+        let xs = self.mem.iter().map(|(x, _)| x.clone()).collect();
+
+        Ok(Some((xs, w_star, pi)))
+    }
+
+    fn aggregate_non_mem(&self) -> Result<Option<AggregatedNonMem>, Errors> {
+        let params = &self.params;
+        let n = &params.n;
+        let mut xs = self.non_mem.iter();
+        let (mut x_star, (mut d_star, mut b_star)) = match xs.next() {
+            Some((x, w)) if verify_non_mem(params, &self.root, w, x) => (x.clone(), w.clone()),
+            Some(_) => return Err(Errors::InvalidWitness),
+            None => return Ok(None),
+        };
+
+        for (y, w) in xs {
+            if !verify_non_mem(params, &self.root, w, y) {
+                return Err(Errors::InvalidWitness);
+            }
+            if !y.gcd(&x_star).is_one() {
+                return Err(Errors::NotCoprime);
+            }
+            let (d_y, b_y) = w;
+
+            // a x + c y = 1, so g = (d_x^x A^{b_x})^{c y} (d_y^y A^{b_y})^{a x}
+            // = (d_x^c d_y^a)^{x y} A^{c y b_x + a x b_y}
+            let (_, a, c) = ExtendedGcd::extended_gcd(&x_star, y);
+            let d = modpow_uint_int(&d_star, &c, n)? * modpow_uint_int(d_y, &a, n)?;
+            let b =
+                &c * BigInt::from(y.clone()) * &b_star + &a * BigInt::from(x_star.clone()) * b_y;
+            x_star *= y;
+
+            // keep |b| below x y, moving multiples of it into d
+            let (k, b) = b.div_mod_floor(&BigInt::from(x_star.clone()));
+            d_star = (d * modpow_uint_int(&self.root, &k, n)?) % n;
+            b_star = b;
+        }
+
+        let xs = self.non_mem.iter().map(|(x, _)| x.clone()).collect();
+
+        Ok(Some((xs, (d_star, b_star))))
+    }
+}
+
+/// Collects the proofs of independent holders against the same root, and aggregates them.
+///
+/// Every submission is checked on its own, so a single invalid proof doesn't fail the whole
+/// aggregate. The relayer itself needs no trust, see `AggregateProof::verify_statements`.
+#[derive(Debug, Clone)]
+pub struct Relayer {
+    params: PublicParams,
+    root: BigUint,
+    proofs: Vec<HolderProof>,
+    /// Products of the members and non-members submitted so far.
+    members: BigUint,
+    non_members: BigUint,
+}
+
+impl Relayer {
+    pub fn new(params: &PublicParams, root: &BigUint) -> Self {
+        Relayer {
+            params: params.clone(),
+            root: root.clone(),
+            proofs: Vec::new(),
+            members: BigUint::one(),
+            non_members: BigUint::one(),
+        }
+    }
+
+    pub fn root(&self) -> &BigUint {
+        &self.root
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.proofs.is_empty()
+    }
+
+    /// The statements submitted so far, in order.
+    pub fn statements(&self) -> Vec<Statement> {
+        self.proofs.iter().map(HolderProof::statement).collect()
+    }
+
+    /// Queues the proof of a holder.
+    ///
+    /// Fails with `InvalidWitness` if it doesn't verify against the root, and with
+    /// `NotCoprime` if its element shares a factor with another one of the same kind, e.g.
+    /// if it was submitted already.
+    pub fn submit(&mut self, proof: HolderProof) -> Result<(), Errors> {
+        if !proof.verify(&self.params, &self.root) {
+            return Err(Errors::InvalidWitness);
+        }
+
+        let (x, product) = match &proof {
+            HolderProof::Mem { x, .. } => (x, &mut self.members),
+            HolderProof::NonMem { x, .. } => (x, &mut self.non_members),
+        };
+        if !x.gcd(product).is_one() {
+            return Err(Errors::NotCoprime);
+        }
+        *product *= x;
+
+        self.proofs.push(proof);
+        Ok(())
+    }
+
+    /// Aggregates all submitted proofs.
+    /// Fails with `EmptyBatch` if there are none.
+    pub fn aggregate(&self) -> Result<AggregateProof, Errors> {
+        self.proofs
+            .iter()
+            .fold(
+                ProofAggregator::new(&self.params, &self.root),
+                |agg, proof| agg.holder_proof(proof),
+            )
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_proof_aggregator() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let params = acc.params();
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+
+        acc.batch_add(&xs[..3]);
+        let old_root = acc.state().clone();
+        let pi_add = acc.batch_add(&xs[3..6]);
+        let root = acc.state();
+
+        let proof = ProofAggregator::new(&params, root)
+            .mem(&xs[0], &acc.mem_wit_create(&xs[0]))
+            .mem(&xs[4], &acc.mem_wit_create(&xs[4]))
+            .mem(&xs[5], &acc.mem_wit_create(&xs[5]))
+            .non_mem(&xs[6], &acc.non_mem_wit_create(&xs[6]).unwrap())
+            .non_mem(&xs[7], &acc.non_mem_wit_create(&xs[7]).unwrap())
+            .non_mem(&xs[8], &acc.non_mem_wit_create(&xs[8]).unwrap())
+            .batch_add(&xs[3..6], &old_root, root, &pi_add)
+            .finish()
+            .unwrap();
+        assert!(proof.verify(&params, root));
+        assert!(!proof.verify(&params, &old_root));
+
+        // the aggregated witnesses are witnesses for the products
+        let (ms, w, _) = proof.mem.as_ref().unwrap();
+        assert!(acc.ver_mem(w, &product(ms)));
+        let (ns, u) = proof.non_mem.as_ref().unwrap();
+        assert!(acc.ver_non_mem(u, &product(ns)));
+
+        let mut tampered = proof.clone();
+        tampered.mem.as_mut().unwrap().0.pop();
+        assert!(!tampered.verify(&params, root));
+        let mut tampered = proof.clone();
+        tampered.non_mem.as_mut().unwrap().0[0] = xs[9].clone() * &xs[9];
+        assert!(!tampered.verify(&params, root));
+
+        // invalid inputs
+        let aggregator = ProofAggregator::new(&params, root);
+        assert_eq!(aggregator.clone().finish(), Err(Errors::EmptyBatch));
+        let w = acc.mem_wit_create(&xs[1]);
+        assert_eq!(
+            aggregator.clone().mem(&xs[2], &w).finish(),
+            Err(Errors::InvalidWitness)
+        );
+        assert_eq!(
+            aggregator.clone().mem(&xs[1], &w).mem(&xs[1], &w).finish(),
+            Err(Errors::NotCoprime)
+        );
+        let u = acc.non_mem_wit_create(&xs[6]).unwrap();
+        assert_eq!(
+            aggregator.clone().non_mem(&xs[7], &u).finish(),
+            Err(Errors::InvalidWitness)
+        );
+        assert_eq!(
+            aggregator
+                .batch_add(&xs[3..5], &old_root, root, &pi_add)
+                .finish(),
+            Err(Errors::InvalidProof)
+        );
+        assert!(!AggregateProof {
+            mem: None,
+            non_mem: None,
+            poes: Vec::new()
+        }
+        .verify(&params, root));
+    }
+
+    #[test]
+    fn test_relayer() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let params = acc.params();
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..4]);
+
+        // independent holders, the last one submits a stale witness
+        let mut holders = xs[..3]
+            .iter()
+            .map(|x| HolderProof::Mem {
+                x: x.clone(),
+                w: acc.mem_wit_create(x),
+            })
+            .chain(xs[5..7].iter().map(|x| HolderProof::NonMem {
+                x: x.clone(),
+                w: acc.non_mem_wit_create(x).unwrap(),
+            }))
+            .collect::<Vec<_>>();
+        holders.push(HolderProof::Mem {
+            x: xs[3].clone(),
+            w: acc.generator().clone(),
+        });
+
+        let mut relayer = Relayer::new(&params, acc.state());
+        assert_eq!(relayer.aggregate(), Err(Errors::EmptyBatch));
+        for proof in &holders[..5] {
+            relayer.submit(proof.clone()).unwrap();
+        }
+        assert_eq!(
+            relayer.submit(holders[5].clone()),
+            Err(Errors::InvalidWitness)
+        );
+        assert_eq!(relayer.submit(holders[0].clone()), Err(Errors::NotCoprime));
+        assert_eq!(relayer.len(), 5);
+
+        let statements = holders[..5]
+            .iter()
+            .rev()
+            .map(HolderProof::statement)
+            .collect::<Vec<_>>();
+        let proof = relayer.aggregate().unwrap();
+        assert!(proof.verify_statements(&params, acc.state(), &statements));
+
+        // dropped or altered statements are detected
+        assert!(!proof.verify_statements(&params, acc.state(), &statements[1..]));
+        let mut dropped = proof.clone();
+        let (ms, w, _) = dropped.mem.as_mut().unwrap();
+        let x = ms.pop().unwrap();
+        *w = w.modpow(&x, acc.modulus());
+        assert!(!dropped.verify_statements(&params, acc.state(), &statements));
+        let mut altered = proof.clone();
+        altered.non_mem.as_mut().unwrap().0[0] = xs[7].clone();
+        let mut other = statements.clone();
+        other[1] = Statement::NonMember(xs[7].clone());
+        assert!(!altered.verify_statements(&params, acc.state(), &other));
+    }
+}
@@ -0,0 +1,220 @@
+//! Detecting forks between root chains, for gossip based auditing.
+//!
+//! A manager signs the root of every epoch as a [`SignedRoot`]. Auditors exchange the signed
+//! roots they have seen, and [`detect_fork`] finds the first epoch for which the same manager
+//! signed two different roots. The resulting [`Equivocation`] can be checked by anyone
+//! knowing the manager's key. The signature scheme is up to the application, signatures are
+//! opaque bytes checked by a [`SignatureVerifier`].
+
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+
+use crate::accumulator::PublicParams;
+use crate::encoding::write_part;
+use crate::error::Errors;
+
+/// Prefixes every signed message, so these signatures can't be confused with others.
+const ROOT_DOMAIN: &[u8] = b"accumulators/signed-root";
+
+/// Checks the manager's signature over a message.
+pub trait SignatureVerifier {
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool;
+}
+
+impl<F> SignatureVerifier for F
+where
+    F: Fn(&[u8], &[u8]) -> bool,
+{
+    fn verify(&self, message: &[u8], signature: &[u8]) -> bool {
+        self(message, signature)
+    }
+}
+
+/// The root of an epoch, signed by the manager.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedRoot {
+    pub epoch: u64,
+    pub root: BigUint,
+    pub signature: Vec<u8>,
+}
+
+impl SignedRoot {
+    /// Signs `root` as the root of `epoch`, `sign` is called with the message to sign.
+    pub fn new<F>(params: &PublicParams, epoch: u64, root: BigUint, sign: F) -> Self
+    where
+        F: FnOnce(&[u8]) -> Vec<u8>,
+    {
+        let signature = sign(&root_message(params, epoch, &root));
+
+        SignedRoot {
+            epoch,
+            root,
+            signature,
+        }
+    }
+
+    /// Checks the signature, for a manager using `params`.
+    pub fn verify<V: SignatureVerifier>(&self, params: &PublicParams, verifier: &V) -> bool {
+        verifier.verify(
+            &root_message(params, self.epoch, &self.root),
+            &self.signature,
+        )
+    }
+}
+
+/// Two different roots, signed for the same epoch.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Equivocation {
+    pub params: PublicParams,
+    pub first: SignedRoot,
+    pub second: SignedRoot,
+}
+
+impl Equivocation {
+    /// The epoch the chains diverged at.
+    pub fn epoch(&self) -> u64 {
+        self.first.epoch
+    }
+
+    /// Checks that both roots are for the same epoch, differ, and are validly signed.
+    pub fn verify<V: SignatureVerifier>(&self, verifier: &V) -> bool {
+        self.first.epoch == self.second.epoch
+            && self.first.root != self.second.root
+            && self.first.verify(&self.params, verifier)
+            && self.second.verify(&self.params, verifier)
+    }
+}
+
+/// Compares two chains of signed roots, both ordered by epoch, and returns the equivocation
+/// at the first epoch with different roots. Epochs only contained in one chain are skipped.
+///
+/// Returns `Ok(None)` if the chains agree on all common epochs, and `InvalidSignature(epoch)`
+/// if one of the diverging roots isn't validly signed, as that isn't attributable to the manager.
+pub fn detect_fork<V: SignatureVerifier>(
+    params: &PublicParams,
+    a: &[SignedRoot],
+    b: &[SignedRoot],
+    verifier: &V,
+) -> Result<Option<Equivocation>, Errors> {
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() && j < b.len() {
+        let (x, y) = (&a[i], &b[j]);
+        if x.epoch < y.epoch {
+            i += 1;
+        } else if x.epoch > y.epoch {
+            j += 1;
+        } else if x.root == y.root {
+            i += 1;
+            j += 1;
+        } else {
+            if !x.verify(params, verifier) || !y.verify(params, verifier) {
+                return Err(Errors::InvalidSignature(x.epoch));
+            }
+
+            return Ok(Some(Equivocation {
+                params: params.clone(),
+                first: x.clone(),
+                second: y.clone(),
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The message signed for the root of an epoch.
+fn root_message(params: &PublicParams, epoch: u64, root: &BigUint) -> Vec<u8> {
+    let mut e = [0u8; 8];
+    BigEndian::write_u64(&mut e, epoch);
+
+    let mut res = ROOT_DOMAIN.to_vec();
+    write_part(&mut res, &params.n.to_bytes_be());
+    write_part(&mut res, &params.g.to_bytes_be());
+    write_part(&mut res, &e);
+    write_part(&mut res, &root.to_bytes_be());
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use blake2::{Blake2b, Digest};
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    // a keyed hash stands in for a real signature scheme
+    fn sign(key: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut h = Blake2b::new();
+        h.input(key);
+        h.input(message);
+        h.result().to_vec()
+    }
+
+    #[test]
+    fn test_detect_fork() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let key = b"manager key";
+        let verifier = |m: &[u8], s: &[u8]| sign(key, m) == s;
+
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+
+        // the manager shows two auditors different chains from epoch 4 on
+        let mut honest = Accumulator::new_with_params(&params);
+        let mut a = Vec::new();
+        for x in &xs[..5] {
+            honest.add(x);
+            a.push(SignedRoot::new(
+                &params,
+                honest.epoch(),
+                honest.state().clone(),
+                |m| sign(key, m),
+            ));
+        }
+        let mut b = a[..3].to_vec();
+        let mut forked = Accumulator::new_with_params(&params);
+        forked.batch_add(&xs[..3]);
+        forked.add(&xs[5]);
+        b.push(SignedRoot::new(&params, 4, forked.state().clone(), |m| {
+            sign(key, m)
+        }));
+
+        assert!(a.iter().all(|r| r.verify(&params, &verifier)));
+        assert_eq!(detect_fork(&params, &a, &a, &verifier), Ok(None));
+        assert_eq!(detect_fork(&params, &a[..3], &b, &verifier), Ok(None));
+
+        // gaps in one of the chains are skipped
+        let sparse = vec![a[0].clone(), a[2].clone(), a[3].clone()];
+        let fork = detect_fork(&params, &b, &sparse, &verifier)
+            .unwrap()
+            .unwrap();
+        assert_eq!(fork.epoch(), 4);
+        assert_eq!(fork.first, b[3]);
+        assert!(fork.verify(&verifier));
+
+        // forged or foreign signatures aren't attributable
+        let other = |m: &[u8], s: &[u8]| sign(b"other key", m) == s;
+        assert!(!fork.verify(&other));
+        let mut forged = b.clone();
+        forged[3].signature[0] ^= 1;
+        assert_eq!(
+            detect_fork(&params, &a, &forged, &verifier),
+            Err(Errors::InvalidSignature(4))
+        );
+
+        let mut same = fork;
+        same.second = same.first.clone();
+        assert!(!same.verify(&verifier));
+    }
+}
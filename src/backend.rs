@@ -0,0 +1,193 @@
+//! Experimental: offloading bulk modular exponentiation.
+//!
+//! Witness generation and batch verification consist of many independent modpows. The
+//! [`ExpBackend`] trait abstracts over where these run, so that a GPU implementation (e.g.
+//! using CUDA or OpenCL) can be plugged in without depending on it here. [`CpuBackend`] is
+//! the reference implementation, using all cores with the `parallel` feature.
+//!
+//! No GPU backend ships with this crate yet, only the extension point for one.
+
+use num_bigint::BigUint;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::accumulator::Accumulator;
+use crate::math::product;
+use crate::traits::StaticAccumulator;
+
+/// Computes batches of independent modular exponentiations.
+pub trait ExpBackend: Sync {
+    /// Returns `bases[i]^exps[i] mod n`, for all `i`.
+    /// If the lengths differ, the result is as long as the shorter one, it must not panic.
+    fn modpow_batch(&self, bases: &[BigUint], exps: &[BigUint], n: &BigUint) -> Vec<BigUint>;
+}
+
+/// Runs the exponentiations on the CPU.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuBackend;
+
+impl ExpBackend for CpuBackend {
+    #[cfg(feature = "parallel")]
+    fn modpow_batch(&self, bases: &[BigUint], exps: &[BigUint], n: &BigUint) -> Vec<BigUint> {
+        bases
+            .par_iter()
+            .zip(exps.par_iter())
+            .map(|(b, e)| b.modpow(e, n))
+            .collect()
+    }
+
+    #[cfg(not(feature = "parallel"))]
+    fn modpow_batch(&self, bases: &[BigUint], exps: &[BigUint], n: &BigUint) -> Vec<BigUint> {
+        bases
+            .iter()
+            .zip(exps.iter())
+            .map(|(b, e)| b.modpow(e, n))
+            .collect()
+    }
+}
+
+/// Like `math::root_factor`, but processes the tree level by level, handing all
+/// exponentiations of a level to `backend` as a single batch.
+pub fn root_factor_with<B: ExpBackend + ?Sized>(
+    backend: &B,
+    g: &BigUint,
+    x: &[BigUint],
+    n: &BigUint,
+) -> Vec<BigUint> {
+    if x.is_empty() {
+        return Vec::new();
+    }
+
+    // every node is its base and the range of `x` below it
+    let mut nodes = vec![(g.clone(), 0, x.len())];
+
+    while nodes.iter().any(|(_, start, end)| end - start > 1) {
+        let mut next = Vec::with_capacity(2 * nodes.len());
+        let mut bases = Vec::new();
+        let mut exps = Vec::new();
+
+        for (base, start, end) in nodes {
+            if end - start == 1 {
+                next.push((Some(base), start, end));
+                continue;
+            }
+
+            let mid = start + (end - start) / 2;
+            // the left child gets the product of the right, and the other way around
+            bases.push(base.clone());
+            exps.push(product(&x[mid..end]));
+            next.push((None, start, mid));
+            bases.push(base);
+            exps.push(product(&x[start..mid]));
+            next.push((None, mid, end));
+        }
+
+        // leaves are passed through, the children get the results in order
+        let mut results = backend.modpow_batch(&bases, &exps, n).into_iter();
+        nodes = next
+            .into_iter()
+            .map(|(base, start, end)| {
+                let base = base.unwrap_or_else(|| results.next().expect("missing result"));
+                (base, start, end)
+            })
+            .collect();
+    }
+
+    nodes.into_iter().map(|(base, _, _)| base).collect()
+}
+
+impl Accumulator {
+    /// Like `create_all_mem_wit`, running the exponentiations on `backend`.
+    pub fn create_all_mem_wit_with<B: ExpBackend + ?Sized>(
+        &self,
+        backend: &B,
+        set: &[BigUint],
+    ) -> Vec<BigUint> {
+        root_factor_with(backend, self.generator(), set, self.modulus())
+    }
+
+    /// Checks the membership witnesses `ws` of `xs`, running the exponentiations on `backend`.
+    /// Like `ver_mem`, the results are compared in `QR_n` if the accumulator is.
+    /// Returns one result per element, elements without a witness are rejected.
+    pub fn ver_mem_with<B: ExpBackend + ?Sized>(
+        &self,
+        backend: &B,
+        ws: &[BigUint],
+        xs: &[BigUint],
+    ) -> Vec<bool> {
+        let root = self.map_element(self.state());
+
+        let mut ok = backend
+            .modpow_batch(ws, xs, self.modulus())
+            .iter()
+            .map(|a| self.map_element(a) == root)
+            .collect::<Vec<_>>();
+        ok.resize(xs.len(), false);
+
+        ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::PublicParams;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_cpu_backend() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..13)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..12]);
+
+        let expected = acc.create_all_mem_wit(&xs[..12]);
+        let mut ws = acc.create_all_mem_wit_with(&CpuBackend, &xs[..12]);
+        assert_eq!(ws, expected);
+        assert!(acc.create_all_mem_wit_with(&CpuBackend, &[]).is_empty());
+
+        let ok = acc.ver_mem_with(&CpuBackend, &ws, &xs[..12]);
+        assert!(ok.iter().all(|ok| *ok));
+
+        ws[4] = ws[5].clone();
+        let ok = acc.ver_mem_with(&CpuBackend, &ws, &xs[..12]);
+        assert_eq!(ok.iter().filter(|ok| !**ok).count(), 1);
+        assert!(!ok[4]);
+
+        // mismatched lengths don't panic
+        assert_eq!(
+            CpuBackend.modpow_batch(&ws, &xs[..3], acc.modulus()).len(),
+            3
+        );
+        let ok = acc.ver_mem_with(&CpuBackend, &ws[..10], &xs[..12]);
+        assert_eq!(ok.len(), 12);
+        assert!(!ok[10] && !ok[11]);
+        assert_eq!(acc.ver_mem_with(&CpuBackend, &ws, &xs[..2]), vec![true; 2]);
+
+        // in QR_n, witnesses are only checked up to their sign, like with `ver_mem`
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits).into_qr();
+        let n = params.n.clone();
+        let mut acc = Accumulator::new_with_params(&params);
+        acc.batch_add(&xs[..12]);
+
+        let mut ws = acc.create_all_mem_wit_with(&CpuBackend, &xs[..12]);
+        ws[2] = &n - &ws[2];
+        assert!(acc.ver_mem(&ws[2], &xs[2]));
+        let ok = acc.ver_mem_with(&CpuBackend, &ws, &xs[..12]);
+        assert!(ok.iter().all(|ok| *ok));
+
+        ws[4] = ws[5].clone();
+        let ok = acc.ver_mem_with(&CpuBackend, &ws, &xs[..12]);
+        assert_eq!(ok, (0..12).map(|i| i != 4).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,166 @@
+//! Blinded membership witnesses, which can be presented repeatedly without being linkable.
+//!
+//! The holder of the membership witness `w` of `x` presents `w' = w g^r` for a fresh `r`,
+//! together with a proof of knowledge of `x` and `rho = r x`, such that `w'^x g^{-rho} = A`.
+//! Neither `w` nor `x` is revealed, and `w'` is statistically close to uniform in `<g>`.
+//!
+//! The proof does not show that `x` is a well formed element, e.g. a prime of the expected
+//! size, which needs to be shown separately, e.g. with a range proof.
+
+use blake2::{Blake2b, Digest};
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use rand::{CryptoRng, Rng};
+
+use crate::accumulator::Accumulator;
+use crate::encoding::write_part;
+use crate::error::Errors;
+use crate::hash::HASH_PRIME_BITS;
+use crate::math::modpow_uint_int;
+use crate::traits::*;
+
+/// The statistical security of the blinding, in bits.
+const BLINDING_BITS: usize = 128;
+
+/// The proof `(t, s_x, s_rho)` of knowledge of `x` and `rho`, with `w'^x g^{-rho} = A`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindingProof {
+    pub t: BigUint,
+    pub s_x: BigInt,
+    pub s_rho: BigInt,
+}
+
+/// A membership witness `w' = w g^r` of a hidden element.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlindedWitness {
+    pub w: BigUint,
+    pub proof: BlindingProof,
+}
+
+impl Accumulator {
+    /// Blinds the membership witness `w` of `x` with fresh randomness.
+    /// Fails with `InvalidWitness` if `w` is not a membership witness of `x`.
+    pub fn blind_mem_wit<R: CryptoRng + Rng>(
+        &self,
+        rng: &mut R,
+        w: &BigUint,
+        x: &BigUint,
+    ) -> Result<BlindedWitness, Errors> {
+        if !self.ver_mem(w, x) {
+            return Err(Errors::InvalidWitness);
+        }
+
+        let n = self.modulus();
+        let g = self.generator();
+        let n_bits = n.bits();
+
+        // w' <- w g^r
+        let r = rng.gen_biguint(n_bits + BLINDING_BITS);
+        let blinded = (w * g.modpow(&r, n)) % n;
+        let rho = r * x;
+
+        // the masks hide c x and c rho, for x < n, so rho < n 2^(n_bits + BLINDING_BITS)
+        let k_x = rng.gen_biguint(n_bits + HASH_PRIME_BITS + BLINDING_BITS);
+        let k_rho = rng.gen_biguint(2 * n_bits + HASH_PRIME_BITS + 2 * BLINDING_BITS);
+
+        // t <- w'^k_x g^{-k_rho}
+        let g_k_rho = modpow_uint_int(g, &-BigInt::from(k_rho.clone()), n)?;
+        let t = (blinded.modpow(&k_x, n) * g_k_rho) % n;
+
+        let c = blinding_challenge(n, g, self.state(), &blinded, &t);
+        let proof = BlindingProof {
+            t,
+            s_x: (k_x + &c * x).into(),
+            s_rho: (k_rho + c * rho).into(),
+        };
+
+        Ok(BlindedWitness { w: blinded, proof })
+    }
+
+    /// Verifies a blinded membership witness against the current state.
+    pub fn ver_blinded_mem(&self, blinded: &BlindedWitness) -> bool {
+        let n = self.modulus();
+        let g = self.generator();
+        let BlindingProof { t, s_x, s_rho } = &blinded.proof;
+
+        let c = blinding_challenge(n, g, self.state(), &blinded.w, t);
+
+        // w'^s_x g^{-s_rho} == t A^c
+        let lhs = match (
+            modpow_uint_int(&blinded.w, s_x, n),
+            modpow_uint_int(g, &-s_rho, n),
+        ) {
+            (Ok(l), Ok(r)) => (l * r) % n,
+            _ => return false,
+        };
+        let rhs = (t * self.state().modpow(&c, n)) % n;
+
+        lhs == rhs
+    }
+}
+
+/// c <- H(n, g, A, w', t)
+fn blinding_challenge(
+    n: &BigUint,
+    g: &BigUint,
+    root: &BigUint,
+    w: &BigUint,
+    t: &BigUint,
+) -> BigUint {
+    let mut to_hash = Vec::new();
+    for part in &[n, g, root, w, t] {
+        write_part(&mut to_hash, &part.to_bytes_be());
+    }
+
+    BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..HASH_PRIME_BITS / 8])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_blinded_witness() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..3]);
+        let w = acc.mem_wit_create(&xs[0]);
+
+        let first = acc.blind_mem_wit(rng, &w, &xs[0]).unwrap();
+        let second = acc.blind_mem_wit(rng, &w, &xs[0]).unwrap();
+        assert!(acc.ver_blinded_mem(&first));
+        assert!(acc.ver_blinded_mem(&second));
+
+        // presentations share nothing
+        assert_ne!(first.w, second.w);
+        assert_ne!(first.w, w);
+        assert_ne!(first.proof.t, second.proof.t);
+
+        assert_eq!(
+            acc.blind_mem_wit(rng, &w, &xs[3]).unwrap_err(),
+            Errors::InvalidWitness
+        );
+
+        let mut bad = first.clone();
+        bad.proof.s_x += 1;
+        assert!(!acc.ver_blinded_mem(&bad));
+        let mut bad = first.clone();
+        bad.w = second.w.clone();
+        assert!(!acc.ver_blinded_mem(&bad));
+
+        // bound to the state
+        acc.add(&xs[3]);
+        assert!(!acc.ver_blinded_mem(&first));
+    }
+}
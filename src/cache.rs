@@ -0,0 +1,354 @@
+//! Membership witnesses persisted across restarts of a holder service.
+//!
+//! A [`WitnessCache`] keeps witnesses in a [`KeyValueStore`], keyed by the hash of their
+//! element and tagged with their epoch. After a restart, `refresh` brings all of them to the
+//! current epoch of a [`History`], with one combined update per stored epoch. Only the
+//! witnesses it drops, of deleted elements or of epochs that were compacted meanwhile, have to
+//! be requested from the manager again.
+//!
+//! Entries are `epoch || element || witness` as length prefixed parts, with the epoch as `u64`
+//! BE.
+
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+
+use crate::encoding::{finish, read_part, write_part, Encode};
+use crate::error::Errors;
+use crate::history::{EpochWitness, History};
+use crate::recovery::{Recovery, WitnessHolder};
+
+/// A persistent map from byte strings to byte strings, e.g. an embedded database.
+pub trait KeyValueStore {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>>;
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> io::Result<()>;
+
+    /// Does nothing if there is no value for `key`.
+    fn delete(&mut self, key: &[u8]) -> io::Result<()>;
+
+    /// All keys, in any order.
+    fn keys(&self) -> io::Result<Vec<Vec<u8>>>;
+}
+
+/// In memory, e.g. for tests.
+impl KeyValueStore for BTreeMap<Vec<u8>, Vec<u8>> {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        Ok(BTreeMap::get(self, key).cloned())
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        self.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    fn delete(&mut self, key: &[u8]) -> io::Result<()> {
+        self.remove(key);
+        Ok(())
+    }
+
+    fn keys(&self) -> io::Result<Vec<Vec<u8>>> {
+        Ok(BTreeMap::keys(self).cloned().collect())
+    }
+}
+
+/// A store with a file per key in a directory, named by the hex of the key.
+///
+/// Values are written to a temporary file, which then replaces the old one, so a crash never
+/// leaves a partially written value behind.
+#[derive(Debug, Clone)]
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    /// Opens the store in `dir`, creating the directory if it doesn't exist.
+    pub fn open<P: AsRef<Path>>(dir: P) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(DirStore {
+            dir: dir.as_ref().to_path_buf(),
+        })
+    }
+
+    fn path(&self, key: &[u8]) -> PathBuf {
+        self.dir.join(hex::encode(key))
+    }
+}
+
+impl KeyValueStore for DirStore {
+    fn get(&self, key: &[u8]) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(self.path(key)) {
+            Ok(value) => Ok(Some(value)),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn put(&mut self, key: &[u8], value: &[u8]) -> io::Result<()> {
+        let path = self.path(key);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, value)?;
+        fs::rename(tmp, path)
+    }
+
+    fn delete(&mut self, key: &[u8]) -> io::Result<()> {
+        match fs::remove_file(self.path(key)) {
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            res => res,
+        }
+    }
+
+    /// Skips the temporary files of interrupted writes.
+    fn keys(&self) -> io::Result<Vec<Vec<u8>>> {
+        let mut keys = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            if let Some(key) = name.to_str().and_then(|name| hex::decode(name).ok()) {
+                keys.push(key);
+            }
+        }
+
+        Ok(keys)
+    }
+}
+
+/// Membership witnesses of many elements, persisted in a [`KeyValueStore`].
+#[derive(Debug, Clone)]
+pub struct WitnessCache<S> {
+    store: S,
+}
+
+impl<S: KeyValueStore> WitnessCache<S> {
+    /// Uses the witnesses already persisted in `store`, if any.
+    pub fn new(store: S) -> Self {
+        WitnessCache { store }
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Stores the witness of `x`, replacing the previous one.
+    pub fn insert(&mut self, x: &BigUint, witness: &EpochWitness<BigUint>) -> Result<(), Errors> {
+        let mut epoch = [0u8; 8];
+        BigEndian::write_u64(&mut epoch, witness.epoch);
+
+        let mut value = Vec::new();
+        write_part(&mut value, &epoch);
+        write_part(&mut value, &x.to_bytes());
+        write_part(&mut value, &witness.witness.to_bytes());
+        self.store.put(&element_key(x), &value)?;
+
+        Ok(())
+    }
+
+    /// Returns the stored witness of `x`, which may be of an older epoch, see `refresh`.
+    /// Fails with `InvalidEncoding` if the entry is corrupted.
+    pub fn get(&self, x: &BigUint) -> Result<Option<EpochWitness<BigUint>>, Errors> {
+        let value = match self.store.get(&element_key(x))? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+
+        let (y, witness) = decode_entry(&value)?;
+        if &y != x {
+            return Err(Errors::InvalidEncoding);
+        }
+
+        Ok(Some(witness))
+    }
+
+    pub fn remove(&mut self, x: &BigUint) -> Result<(), Errors> {
+        self.store.delete(&element_key(x))?;
+        Ok(())
+    }
+
+    /// Updates all witnesses to the current epoch of `history`, and drops those that can't
+    /// be: of elements deleted meanwhile, or from compacted epochs.
+    /// Returns the elements of the dropped witnesses, which have to be requested anew.
+    ///
+    /// Fails with `InvalidEncoding` if an entry is corrupted, and with `UnknownEpoch` if a
+    /// witness is from an epoch `history` didn't reach yet.
+    pub fn refresh(&mut self, history: &History) -> Result<Vec<BigUint>, Errors> {
+        let n = &history.params().n;
+        let mut recoveries = HashMap::new();
+        let mut dropped = Vec::new();
+
+        for key in self.store.keys()? {
+            let value = match self.store.get(&key)? {
+                Some(value) => value,
+                None => continue,
+            };
+            let (x, witness) = decode_entry(&value)?;
+            if witness.epoch == history.epoch() {
+                continue;
+            }
+
+            // witnesses from the same epoch share the combined update
+            let recovery = match recoveries.entry(witness.epoch) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    entry.insert(match history.combined_update(witness.epoch) {
+                        Ok(update) => Recovery::Update(update),
+                        Err(Errors::Pruned(_)) => Recovery::Rerequest,
+                        Err(err) => return Err(err),
+                    })
+                }
+            };
+
+            let mut holder = WitnessHolder::new(x, witness);
+            if let Ok(true) = holder.apply(recovery, n) {
+                self.insert(holder.element(), holder.witness())?;
+            } else {
+                self.store.delete(&key)?;
+                dropped.push(holder.element().clone());
+            }
+        }
+
+        Ok(dropped)
+    }
+}
+
+/// `H(x)`
+fn element_key(x: &BigUint) -> Vec<u8> {
+    Blake2b::digest(&x.to_bytes_be()).to_vec()
+}
+
+fn decode_entry(mut bytes: &[u8]) -> Result<(BigUint, EpochWitness<BigUint>), Errors> {
+    let epoch = read_part(&mut bytes)?;
+    if epoch.len() != 8 {
+        return Err(Errors::InvalidEncoding);
+    }
+
+    let x = BigUint::from_bytes(read_part(&mut bytes)?)?;
+    let witness = EpochWitness {
+        epoch: BigEndian::read_u64(epoch),
+        witness: BigUint::from_bytes(read_part(&mut bytes)?)?,
+    };
+    finish(bytes)?;
+
+    Ok((x, witness))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{Accumulator, PublicParams};
+    use crate::group::RSAGroup;
+    use crate::hooks::Mutation;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_witness_cache() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let history = Arc::new(Mutex::new(History::new(params)));
+        let observer = history.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..3]);
+        let mut cache = WitnessCache::new(BTreeMap::new());
+        for x in &xs[..2] {
+            cache
+                .insert(x, &acc.mem_wit_create_at_epoch(x).unwrap())
+                .unwrap();
+        }
+        acc.add(&xs[3]);
+        cache
+            .insert(&xs[2], &acc.mem_wit_create_at_epoch(&xs[2]).unwrap())
+            .unwrap();
+        cache
+            .insert(&xs[3], &acc.mem_wit_create_at_epoch(&xs[3]).unwrap())
+            .unwrap();
+        assert_eq!(cache.get(&xs[4]), Ok(None));
+
+        // the service restarts, meanwhile elements are added and deleted
+        let mut cache = WitnessCache::new(cache.into_store());
+        acc.batch_add(&xs[4..6]);
+        acc.del(&xs[1]).unwrap();
+        acc.add(&xs[6]);
+
+        let dropped = cache.refresh(&history.lock().unwrap()).unwrap();
+        assert_eq!(dropped, vec![xs[1].clone()]);
+        assert_eq!(cache.get(&xs[1]), Ok(None));
+        for x in &[&xs[0], &xs[2], &xs[3]] {
+            let w = cache.get(x).unwrap().unwrap();
+            assert_eq!(w.epoch, acc.epoch());
+            assert!(acc.ver_mem(&w.witness, x));
+        }
+
+        // the epochs of the remaining witnesses are compacted
+        let old = acc.mem_wit_create_at_epoch(&xs[4]).unwrap();
+        cache.insert(&xs[4], &old).unwrap();
+        acc.add(&xs[7]);
+        history.lock().unwrap().compact(acc.epoch()).unwrap();
+        cache.remove(&xs[0]).unwrap();
+        let mut dropped = cache.refresh(&history.lock().unwrap()).unwrap();
+        dropped.sort();
+        let mut expected = vec![xs[2].clone(), xs[3].clone(), xs[4].clone()];
+        expected.sort();
+        assert_eq!(dropped, expected);
+        assert!(cache.store().is_empty());
+
+        // witnesses ahead of the history, and corrupted entries
+        let mut ahead = old.clone();
+        ahead.epoch = acc.epoch() + 1;
+        cache.insert(&xs[4], &ahead).unwrap();
+        assert_eq!(
+            cache.refresh(&history.lock().unwrap()),
+            Err(Errors::UnknownEpoch(acc.epoch() + 1))
+        );
+        let mut store = cache.into_store();
+        store.put(&element_key(&xs[5]), &[1, 2, 3]).unwrap();
+        let cache = WitnessCache::new(store);
+        assert_eq!(cache.get(&xs[5]), Err(Errors::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_dir_store() {
+        let dir =
+            std::env::temp_dir().join(format!("accumulators-{}.witnesses", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let x = BigUint::from(65537u32);
+        let w = EpochWitness {
+            epoch: 3,
+            witness: BigUint::from(12345u32),
+        };
+        let mut cache = WitnessCache::new(DirStore::open(&dir).unwrap());
+        cache.insert(&x, &w).unwrap();
+        let interrupted = dir.join(hex::encode(element_key(&w.witness)));
+        fs::write(interrupted.with_extension("tmp"), [0u8]).unwrap();
+        drop(cache);
+
+        let mut cache = WitnessCache::new(DirStore::open(&dir).unwrap());
+        assert_eq!(cache.store().keys().unwrap(), vec![element_key(&x)]);
+        assert_eq!(cache.get(&x), Ok(Some(w)));
+        cache.remove(&x).unwrap();
+        cache.remove(&x).unwrap();
+        assert_eq!(cache.get(&x), Ok(None));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,224 @@
+//! Documenting the trusted setup of an RSA modulus.
+//!
+//! When the modulus is generated by a multi-party computation, nobody should know its
+//! factorization. This library doesn't run the computation itself, but records the
+//! contributions of the participants, sanity checks the resulting modulus and produces a
+//! [`Transcript`], which allows anyone to check how the public parameters were created.
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::prime::probably_prime;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::accumulator::PublicParams;
+use crate::encoding::write_part;
+use crate::error::Errors;
+use crate::math::product;
+
+/// All primes below this bound are checked not to divide the modulus.
+pub const TRIAL_DIVISION_BOUND: u32 = 1 << 16;
+
+/// A single participant's contribution to the setup.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Contribution {
+    /// Identifies the participant.
+    pub participant: String,
+    /// Commitment to the participant's share, as defined by the used protocol.
+    pub commitment: Vec<u8>,
+    /// Attestation of the participant, e.g. a signature over the commitment.
+    pub attestation: Vec<u8>,
+}
+
+/// Collects the contributions, until the ceremony is finalized.
+#[derive(Debug, Clone, Default)]
+pub struct Ceremony {
+    contributions: Vec<Contribution>,
+}
+
+/// The record of a finished ceremony.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transcript {
+    pub params: PublicParams,
+    pub contributions: Vec<Contribution>,
+    /// Hash over the parameters and all contributions.
+    pub digest: Vec<u8>,
+}
+
+impl Ceremony {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Records a contribution, every participant can contribute only once.
+    pub fn contribute(&mut self, contribution: Contribution) -> Result<(), Errors> {
+        if self
+            .contributions
+            .iter()
+            .any(|c| c.participant == contribution.participant)
+        {
+            return Err(Errors::DuplicateContribution);
+        }
+
+        self.contributions.push(contribution);
+        Ok(())
+    }
+
+    pub fn contributions(&self) -> &[Contribution] {
+        &self.contributions
+    }
+
+    /// Checks the resulting parameters and produces the transcript.
+    pub fn finalize(self, params: PublicParams) -> Result<Transcript, Errors> {
+        check_params(&params)?;
+
+        let digest = transcript_digest(&params, &self.contributions);
+        Ok(Transcript {
+            params,
+            contributions: self.contributions,
+            digest,
+        })
+    }
+}
+
+impl Transcript {
+    /// Rechecks the parameters and the digest.
+    pub fn verify(&self) -> bool {
+        check_params(&self.params).is_ok()
+            && transcript_digest(&self.params, &self.contributions) == self.digest
+    }
+}
+
+/// Sanity checks public parameters.
+///
+/// The modulus must have the expected size, must not be prime or a perfect square, and
+/// must not have any prime factors below `TRIAL_DIVISION_BOUND`. The generator must be
+/// in `(1, n - 1)` and coprime to `n`.
+/// This can't prove that nobody knows the factorization, but catches broken setups.
+pub fn check_params(params: &PublicParams) -> Result<(), Errors> {
+    let n = &params.n;
+    let g = &params.g;
+
+    if n.bits() != params.int_size_bits || n.is_even() {
+        return Err(Errors::InvalidParams);
+    }
+
+    if !n.gcd(&small_primes_product()).is_one() {
+        return Err(Errors::InvalidParams);
+    }
+
+    let root = n.sqrt();
+    if &(&root * &root) == n || probably_prime(n, 20) {
+        return Err(Errors::InvalidParams);
+    }
+
+    if g <= &BigUint::one() || g >= &(n - 1u32) || !g.gcd(n).is_one() {
+        return Err(Errors::InvalidParams);
+    }
+
+    Ok(())
+}
+
+/// The product of all primes below `TRIAL_DIVISION_BOUND`.
+fn small_primes_product() -> BigUint {
+    let bound = TRIAL_DIVISION_BOUND as usize;
+    let mut sieve = vec![true; bound];
+    let mut primes = Vec::new();
+
+    for i in 2..bound {
+        if sieve[i] {
+            primes.push(BigUint::from(i as u32));
+            for j in (i * i..bound).step_by(i) {
+                sieve[j] = false;
+            }
+        }
+    }
+
+    product(&primes)
+}
+
+fn transcript_digest(params: &PublicParams, contributions: &[Contribution]) -> Vec<u8> {
+    let mut bits = [0u8; 8];
+    BigEndian::write_u64(&mut bits, params.int_size_bits as u64);
+
+    let mut to_hash = Vec::new();
+    write_part(&mut to_hash, &bits);
+    write_part(&mut to_hash, &params.n.to_bytes_be());
+    write_part(&mut to_hash, &params.g.to_bytes_be());
+    for c in contributions {
+        write_part(&mut to_hash, c.participant.as_bytes());
+        write_part(&mut to_hash, &c.commitment);
+        write_part(&mut to_hash, &c.attestation);
+    }
+
+    Blake2b::digest(&to_hash).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    fn contribution(participant: &str) -> Contribution {
+        Contribution {
+            participant: participant.to_string(),
+            commitment: participant.as_bytes().to_vec(),
+            attestation: vec![1, 2, 3],
+        }
+    }
+
+    #[test]
+    fn test_ceremony() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+
+        let mut ceremony = Ceremony::new();
+        ceremony.contribute(contribution("alice")).unwrap();
+        ceremony.contribute(contribution("bob")).unwrap();
+        assert_eq!(
+            ceremony.contribute(contribution("alice")),
+            Err(Errors::DuplicateContribution)
+        );
+        assert_eq!(ceremony.contributions().len(), 2);
+
+        let mut transcript = ceremony.clone().finalize(params.clone()).unwrap();
+        assert!(transcript.verify());
+
+        transcript.contributions[1].attestation.push(4);
+        assert!(!transcript.verify());
+
+        // prime modulus
+        let mut bad = params.clone();
+        bad.n = rng.gen_prime(int_size_bits);
+        assert_eq!(
+            ceremony.clone().finalize(bad).unwrap_err(),
+            Errors::InvalidParams
+        );
+
+        // small factor
+        let mut bad = params.clone();
+        bad.n = rng.gen_prime(int_size_bits - 7) * 101u32;
+        bad.int_size_bits = bad.n.bits();
+        assert_eq!(check_params(&bad), Err(Errors::InvalidParams));
+
+        // perfect square
+        let mut bad = params.clone();
+        let p = rng.gen_prime(int_size_bits / 2);
+        bad.n = &p * &p;
+        bad.int_size_bits = bad.n.bits();
+        assert_eq!(check_params(&bad), Err(Errors::InvalidParams));
+
+        // trivial generator
+        let mut bad = params;
+        bad.g = BigUint::one();
+        assert_eq!(check_params(&bad), Err(Errors::InvalidParams));
+    }
+}
@@ -0,0 +1,255 @@
+//! Splitting `create_all_mem_wit` across multiple workers.
+//!
+//! The coordinator splits the set into chunks, and calculates for every chunk the base
+//! `g^{product of all other chunks}`, using `root_factor` on the chunk products. Every chunk
+//! is then an independent [`WorkUnit`], which can be serialized and sent to a worker process.
+//! The [`WorkResult`]s are put back together in the original order with [`WitnessJob::combine`].
+//!
+//! For long running jobs, results can instead be collected one by one with
+//! [`WitnessJob::accept`]. The job, including the accepted results, can be serialized as a
+//! checkpoint, and the units of failed workers, see [`WitnessJob::pending`], can be handed out
+//! again. A unit can itself be split into a job of smaller units with [`WorkUnit::split`], e.g.
+//! to spread it over the machines of a worker.
+//!
+//! Workers are not trusted by this, results from untrusted workers should be checked
+//! with `ver_mem`.
+
+use std::collections::BTreeMap;
+
+use num_bigint::BigUint;
+
+use crate::accumulator::Accumulator;
+use crate::error::Errors;
+use crate::math::{product, root_factor};
+
+/// The coordinator side of a distributed `create_all_mem_wit`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitnessJob {
+    /// The number of elements in every work unit.
+    sizes: Vec<usize>,
+    /// The witnesses of the units which are done, see `accept`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    done: BTreeMap<usize, Vec<BigUint>>,
+}
+
+/// The witnesses of a chunk of the set, which can be computed independently.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkUnit {
+    /// The position of this unit in the job.
+    pub index: usize,
+    /// `g^{product of all elements not in this unit}`.
+    pub base: BigUint,
+    pub n: BigUint,
+    pub elements: Vec<BigUint>,
+}
+
+/// The witnesses computed for a single work unit.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkResult {
+    pub index: usize,
+    pub witnesses: Vec<BigUint>,
+}
+
+impl WitnessJob {
+    /// Splits computing the witnesses of all elements in `set` into at most `units` work units.
+    /// `set` must be exactly the set accumulated in `acc`.
+    pub fn new(acc: &Accumulator, set: &[BigUint], units: usize) -> (Self, Vec<WorkUnit>) {
+        WitnessJob::split(acc.generator(), acc.modulus(), set, units)
+    }
+
+    /// Splits the witnesses of `set` with respect to `base` into at most `units` work units.
+    fn split(base: &BigUint, n: &BigUint, set: &[BigUint], units: usize) -> (Self, Vec<WorkUnit>) {
+        let mut job = WitnessJob {
+            sizes: Vec::new(),
+            done: BTreeMap::new(),
+        };
+        if set.is_empty() || units == 0 {
+            return (job, Vec::new());
+        }
+
+        let chunk_size = (set.len() + units - 1) / units;
+        let chunks = set.chunks(chunk_size).collect::<Vec<_>>();
+        let products = chunks.iter().map(|c| product(c)).collect::<Vec<_>>();
+        let bases = root_factor(base, &products, n);
+
+        let work = chunks
+            .iter()
+            .zip(bases.into_iter())
+            .enumerate()
+            .map(|(index, (chunk, base))| WorkUnit {
+                index,
+                base,
+                n: n.clone(),
+                elements: chunk.to_vec(),
+            })
+            .collect();
+        job.sizes = chunks.iter().map(|c| c.len()).collect();
+
+        (job, work)
+    }
+
+    /// Returns the number of work units.
+    pub fn units(&self) -> usize {
+        self.sizes.len()
+    }
+
+    /// Puts the results of all work units together, in the order of the original set.
+    /// The results can be passed in any order.
+    pub fn combine(&self, mut results: Vec<WorkResult>) -> Result<Vec<BigUint>, Errors> {
+        results.sort_by_key(|r| r.index);
+
+        let complete = results.len() == self.sizes.len()
+            && results
+                .iter()
+                .zip(self.sizes.iter())
+                .enumerate()
+                .all(|(i, (r, size))| r.index == i && r.witnesses.len() == *size);
+        if !complete {
+            return Err(Errors::InvalidWorkResult);
+        }
+
+        Ok(results.into_iter().flat_map(|r| r.witnesses).collect())
+    }
+
+    /// Records the result of a single work unit. Results of units that are already done
+    /// replace the earlier ones, so units can be retried without coordination.
+    /// Fails with `InvalidWorkResult` if there is no such unit, or the size doesn't match.
+    pub fn accept(&mut self, result: WorkResult) -> Result<(), Errors> {
+        match self.sizes.get(result.index) {
+            Some(size) if *size == result.witnesses.len() => {
+                self.done.insert(result.index, result.witnesses);
+                Ok(())
+            }
+            _ => Err(Errors::InvalidWorkResult),
+        }
+    }
+
+    /// Returns the indices of the units without an accepted result, e.g. to hand them out
+    /// again after a worker failed.
+    pub fn pending(&self) -> Vec<usize> {
+        (0..self.sizes.len())
+            .filter(|i| !self.done.contains_key(i))
+            .collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.done.len() == self.sizes.len()
+    }
+
+    /// Returns the witnesses of all accepted results, in the order of the original set.
+    /// Fails with `InvalidWorkResult` if a unit is still pending.
+    pub fn finish(self) -> Result<Vec<BigUint>, Errors> {
+        if !self.is_complete() {
+            return Err(Errors::InvalidWorkResult);
+        }
+
+        Ok(self.done.into_values().flatten().collect())
+    }
+}
+
+impl WorkUnit {
+    /// Computes the witnesses of all elements in this unit.
+    pub fn run(&self) -> WorkResult {
+        WorkResult {
+            index: self.index,
+            witnesses: root_factor(&self.base, &self.elements, &self.n),
+        }
+    }
+
+    /// Splits this unit into a job of at most `parts` smaller units.
+    /// The witnesses of the finished job are those of `run`, so they form the result of this
+    /// unit, with its `index`.
+    pub fn split(&self, parts: usize) -> (WitnessJob, Vec<WorkUnit>) {
+        WitnessJob::split(&self.base, &self.n, &self.elements, parts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_witness_job() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..23)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+
+        let expected = acc.create_all_mem_wit(&xs);
+
+        for units in &[1, 4, 23, 50] {
+            let (job, work) = WitnessJob::new(&acc, &xs, *units);
+            assert_eq!(job.units(), work.len());
+
+            let mut results = work.iter().map(WorkUnit::run).collect::<Vec<_>>();
+            results.reverse();
+            assert_eq!(job.combine(results).unwrap(), expected);
+        }
+
+        let (job, work) = WitnessJob::new(&acc, &xs, 4);
+        let mut results = work.iter().map(WorkUnit::run).collect::<Vec<_>>();
+        results.pop();
+        assert_eq!(job.combine(results), Err(Errors::InvalidWorkResult));
+    }
+
+    #[test]
+    fn test_resumable_witness_job() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..23)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let expected = acc.create_all_mem_wit(&xs);
+
+        let (mut job, work) = WitnessJob::new(&acc, &xs, 4);
+        assert_eq!(job.pending(), vec![0, 1, 2, 3]);
+
+        // unit 1 is split further, and unit 2 fails the first time
+        job.accept(work[0].run()).unwrap();
+        let (mut sub_job, sub_work) = work[1].split(3);
+        for unit in &sub_work {
+            sub_job.accept(unit.run()).unwrap();
+        }
+        let witnesses = sub_job.finish().unwrap();
+        assert_eq!(witnesses, work[1].run().witnesses);
+        job.accept(WorkResult {
+            index: 1,
+            witnesses,
+        })
+        .unwrap();
+        job.accept(work[3].run()).unwrap();
+        assert_eq!(job.pending(), vec![2]);
+        assert!(!job.is_complete());
+        assert_eq!(job.clone().finish(), Err(Errors::InvalidWorkResult));
+
+        // results of missing units, or of the wrong size
+        let mut bad = work[2].run();
+        bad.witnesses.pop();
+        assert_eq!(job.accept(bad), Err(Errors::InvalidWorkResult));
+        let mut bad = work[2].run();
+        bad.index = 4;
+        assert_eq!(job.accept(bad), Err(Errors::InvalidWorkResult));
+
+        // retried and duplicated results
+        job.accept(work[2].run()).unwrap();
+        job.accept(work[0].run()).unwrap();
+        assert!(job.is_complete());
+        assert_eq!(job.finish().unwrap(), expected);
+    }
+}
@@ -0,0 +1,178 @@
+//! A pair of accumulators for a set and its complement in a bounded universe.
+//!
+//! Allow and deny lists query both directions. Non-membership witnesses need a Bezout
+//! computation over the whole set and two exponentiations to verify, while a member of the
+//! complement has a plain membership witness. A [`DualAccumulator`] keeps the set `S` in
+//! one accumulator and `U \ S` in the other, and proves non-membership with whichever
+//! applies, falling back to a non-membership witness for elements outside of `U`.
+
+use num_bigint::{BigInt, BigUint};
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::error::Errors;
+use crate::traits::*;
+
+/// A proof for or against the membership of an element, see `DualAccumulator::prove`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DualProof {
+    /// A membership witness in the set.
+    Member(BigUint),
+    /// A membership witness in the complement.
+    InComplement(BigUint),
+    /// A non-membership witness in the set, for elements outside of the universe.
+    NonMember((BigUint, BigInt)),
+}
+
+impl DualProof {
+    pub fn is_member(&self) -> bool {
+        match self {
+            DualProof::Member(_) => true,
+            DualProof::InComplement(_) | DualProof::NonMember(_) => false,
+        }
+    }
+}
+
+/// The set and its complement, using the same public parameters.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct DualAccumulator {
+    set: Accumulator,
+    complement: Accumulator,
+}
+
+impl DualAccumulator {
+    /// Starts out with an empty set, so the complement is the whole `universe`.
+    pub fn new(params: &PublicParams, universe: &[BigUint]) -> Self {
+        DualAccumulator {
+            set: Accumulator::new_with_params(params),
+            complement: Accumulator::rebuild(params, universe.iter().cloned()),
+        }
+    }
+
+    pub fn set(&self) -> &Accumulator {
+        &self.set
+    }
+
+    pub fn complement(&self) -> &Accumulator {
+        &self.complement
+    }
+
+    /// Moves `x` from the complement into the set.
+    /// Fails with `NotAMember` if `x` is not in the complement.
+    pub fn add(&mut self, x: &BigUint) -> Result<(), Errors> {
+        self.complement.del(x).ok_or(Errors::NotAMember)?;
+        self.set.add(x);
+
+        Ok(())
+    }
+
+    /// Moves `x` from the set into the complement.
+    /// Fails with `NotAMember` if `x` is not in the set.
+    pub fn del(&mut self, x: &BigUint) -> Result<(), Errors> {
+        self.set.del(x).ok_or(Errors::NotAMember)?;
+        self.complement.add(x);
+
+        Ok(())
+    }
+
+    /// Moves all `xs` from the complement into the set, see `add`.
+    /// Returns the proofs of the deletion from the complement and the addition to the set.
+    pub fn batch_add(&mut self, xs: &[BigUint]) -> Result<(BigUint, BigUint), Errors> {
+        let del_proof = self.complement.batch_del_tracked(xs)?;
+        let add_proof = self.set.batch_add(xs);
+
+        Ok((del_proof, add_proof))
+    }
+
+    /// Moves all `xs` from the set into the complement, see `del`.
+    /// Returns the proofs of the deletion from the set and the addition to the complement.
+    pub fn batch_del(&mut self, xs: &[BigUint]) -> Result<(BigUint, BigUint), Errors> {
+        let del_proof = self.set.batch_del_tracked(xs)?;
+        let add_proof = self.complement.batch_add(xs);
+
+        Ok((del_proof, add_proof))
+    }
+
+    /// Proves the membership of `x`, or its non-membership, with a membership witness in the
+    /// complement where possible.
+    pub fn prove(&self, x: &BigUint) -> Result<DualProof, Errors> {
+        if self.set.contains(x) {
+            Ok(DualProof::Member(self.set.mem_wit_create(x)))
+        } else if self.complement.contains(x) {
+            Ok(DualProof::InComplement(self.complement.mem_wit_create(x)))
+        } else {
+            Ok(DualProof::NonMember(self.set.non_mem_wit_create(x)?))
+        }
+    }
+
+    /// Checks `proof` for `x` against the current states, whichever direction it proves,
+    /// see `is_member`.
+    pub fn verify(&self, x: &BigUint, proof: &DualProof) -> bool {
+        match proof {
+            DualProof::Member(w) => self.set.ver_mem(w, x),
+            DualProof::InComplement(w) => self.complement.ver_mem(w, x),
+            DualProof::NonMember(w) => self.set.ver_non_mem(w, x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_dual_accumulator() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let mut acc = DualAccumulator::new(&params, &xs[..6]);
+        assert_eq!(acc.complement().len(), 6);
+
+        acc.add(&xs[0]).unwrap();
+        acc.batch_add(&xs[1..3]).unwrap();
+        acc.del(&xs[1]).unwrap();
+        assert_eq!(acc.add(&xs[0]), Err(Errors::NotAMember));
+        assert_eq!(acc.del(&xs[3]), Err(Errors::NotAMember));
+        assert_eq!(acc.batch_add(&xs[5..7]), Err(Errors::NotAMember));
+        assert_eq!(acc.set().len(), 2);
+        assert_eq!(acc.complement().len(), 4);
+
+        for (i, x) in xs.iter().enumerate() {
+            let proof = acc.prove(x).unwrap();
+            assert_eq!(proof.is_member(), i == 0 || i == 2);
+            assert!(acc.verify(x, &proof));
+
+            match proof {
+                DualProof::Member(_) => assert!(acc.set().contains(x)),
+                DualProof::InComplement(_) => assert!(i < 6),
+                DualProof::NonMember(_) => assert!(i >= 6),
+            }
+        }
+
+        // proofs are bound to their direction
+        if let DualProof::InComplement(w) = acc.prove(&xs[3]).unwrap() {
+            assert!(!acc.verify(&xs[3], &DualProof::Member(w)));
+        }
+
+        let removed = vec![xs[0].clone(), xs[2].clone()];
+        let set_root = acc.set().state().clone();
+        let complement_root = acc.complement().state().clone();
+        let (del_proof, add_proof) = acc.batch_del(&removed).unwrap();
+        assert!(acc.set().is_empty());
+        assert_eq!(acc.complement().len(), 6);
+        assert!(acc.set().ver_batch_del(&del_proof, &set_root, &removed));
+        assert!(acc
+            .complement()
+            .ver_batch_add(&add_proof, &complement_root, &removed));
+    }
+}
@@ -0,0 +1,136 @@
+//! Hashing structured records to elements.
+//!
+//! A record is hashed to a prime from its canonical bytes, prefixed with a domain tag, so
+//! records of different types never map to the same element. With the `structured` feature
+//! every `Serialize` type is a [`ToElement`], using its bincode encoding and its type name.
+
+use blake2::Blake2b;
+use num_bigint::BigUint;
+
+use crate::encoding::write_part;
+use crate::hash::hash_prime;
+
+/// A record that can be accumulated.
+pub trait ToElement {
+    /// The domain separation tag.
+    ///
+    /// The serde based implementation uses `std::any::type_name`, which is not guaranteed to be
+    /// stable across compiler versions or when types are moved.
+    fn domain(&self) -> &str;
+
+    /// The canonical bytes of the record, equal records must have equal bytes.
+    fn canonical_bytes(&self) -> Vec<u8>;
+
+    /// Hashes the record to a prime.
+    fn to_element(&self) -> BigUint {
+        hash_to_element(self.domain(), &self.canonical_bytes())
+    }
+}
+
+/// `H_prime(domain || bytes)`, both prefixed with their length.
+pub fn hash_to_element(domain: &str, bytes: &[u8]) -> BigUint {
+    let mut to_hash = Vec::with_capacity(domain.len() + bytes.len() + 8);
+    write_part(&mut to_hash, domain.as_bytes());
+    write_part(&mut to_hash, bytes);
+
+    hash_prime::<_, Blake2b>(&to_hash)
+}
+
+/// The bincode encoding is canonical for structs, enums, sequences and ordered maps, but not
+/// for `HashMap` and `HashSet`, whose order is random.
+#[cfg(feature = "structured")]
+impl<T: serde::Serialize + ?Sized> ToElement for T {
+    fn domain(&self) -> &str {
+        std::any::type_name::<T>()
+    }
+
+    fn canonical_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).expect("serializing to memory does not fail")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hash::HASH_PRIME_BITS;
+    use num_bigint::prime::probably_prime;
+
+    struct Token {
+        subject: String,
+        expires_at: u64,
+    }
+
+    impl ToElement for Token {
+        fn domain(&self) -> &str {
+            "token/v1"
+        }
+
+        fn canonical_bytes(&self) -> Vec<u8> {
+            let mut res = Vec::new();
+            write_part(&mut res, self.subject.as_bytes());
+            res.extend(&self.expires_at.to_be_bytes());
+            res
+        }
+    }
+
+    #[cfg(feature = "structured")]
+    #[derive(Serialize)]
+    struct Credential {
+        subject: String,
+        expires_at: u64,
+    }
+
+    #[cfg(feature = "structured")]
+    #[derive(Serialize)]
+    struct Session {
+        subject: String,
+        expires_at: u64,
+    }
+
+    #[test]
+    fn test_to_element() {
+        let a = Token {
+            subject: "alice".into(),
+            expires_at: 10,
+        };
+        let x = a.to_element();
+        assert_eq!(x.bits(), HASH_PRIME_BITS);
+        assert!(probably_prime(&x, 20));
+        assert_eq!(
+            x,
+            hash_to_element(a.domain(), &a.canonical_bytes()),
+            "deterministic"
+        );
+
+        let b = Token {
+            subject: "alice".into(),
+            expires_at: 11,
+        };
+        assert_ne!(b.to_element(), x);
+        assert_ne!(hash_to_element("other", &a.canonical_bytes()), x);
+    }
+
+    #[cfg(feature = "structured")]
+    #[test]
+    fn test_serde_to_element() {
+        let a = Credential {
+            subject: "alice".into(),
+            expires_at: 10,
+        };
+        let b = Session {
+            subject: "alice".into(),
+            expires_at: 10,
+        };
+
+        // same bytes, different types
+        assert_eq!(a.canonical_bytes(), b.canonical_bytes());
+        assert_ne!(a.to_element(), b.to_element());
+        assert!(a.domain().ends_with("Credential"));
+
+        assert_eq!(
+            "alice".to_element(),
+            hash_to_element("str", &bincode::serialize("alice").unwrap())
+        );
+    }
+}
@@ -0,0 +1,398 @@
+//! Hex and base64 encodings of roots, witnesses and proofs.
+//!
+//! Single integers are encoded as their big endian bytes, signed integers with an additional
+//! leading sign byte (`0` for non negative, `1` for negative). Tuples, like proofs and
+//! non-membership witnesses, concatenate their parts, each prefixed with its length as `u32` BE.
+//! Lists do the same with their elements.
+//!
+//! [`Decode`] parses the same encoding into borrowed views, which only check the framing and
+//! slice the integers out of the input. Nothing is allocated until `into_owned` is called,
+//! e.g. right before verification.
+
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::{BigInt, BigUint, Sign};
+
+use crate::error::Errors;
+use crate::version::{read_header, write_header, Migrations, FORMAT_VERSION, VERSION_MAGIC};
+
+/// Conversion to and from bytes, and the hex and base64 encodings of these.
+pub trait Encode: Sized {
+    fn to_bytes(&self) -> Vec<u8>;
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Errors>;
+
+    fn to_hex(&self) -> String {
+        hex::encode(self.to_bytes())
+    }
+
+    fn from_hex(s: &str) -> Result<Self, Errors> {
+        let bytes = hex::decode(s.trim()).map_err(|_| Errors::InvalidEncoding)?;
+        Self::from_bytes(&bytes)
+    }
+
+    fn to_base64(&self) -> String {
+        base64::encode(&self.to_bytes())
+    }
+
+    fn from_base64(s: &str) -> Result<Self, Errors> {
+        let bytes = base64::decode(s.trim()).map_err(|_| Errors::InvalidEncoding)?;
+        Self::from_bytes(&bytes)
+    }
+
+    /// `to_bytes`, prefixed with the format version, for values that are persisted.
+    fn to_versioned_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        write_header(&mut res, VERSION_MAGIC, FORMAT_VERSION);
+        res.extend(self.to_bytes());
+        res
+    }
+
+    /// Parses the output of `to_versioned_bytes` of the current or an older version.
+    /// Fails with `UnsupportedVersion` for newer versions.
+    fn from_versioned_bytes(bytes: &[u8]) -> Result<Self, Errors> {
+        let (version, body) = read_header(bytes, VERSION_MAGIC)?;
+        let body = Migrations::encoding().upgrade(version, body.to_vec(), FORMAT_VERSION)?;
+        Self::from_bytes(&body)
+    }
+}
+
+impl Encode for BigUint {
+    fn to_bytes(&self) -> Vec<u8> {
+        self.to_bytes_be()
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Errors> {
+        if bytes.is_empty() {
+            return Err(Errors::InvalidEncoding);
+        }
+        Ok(BigUint::from_bytes_be(bytes))
+    }
+}
+
+impl Encode for BigInt {
+    fn to_bytes(&self) -> Vec<u8> {
+        let (sign, magnitude) = self.to_bytes_be();
+        let mut res = vec![if sign == Sign::Minus { 1 } else { 0 }];
+        res.extend(magnitude);
+        res
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Errors> {
+        let sign = match bytes.first() {
+            Some(0) => Sign::Plus,
+            Some(1) => Sign::Minus,
+            _ => return Err(Errors::InvalidEncoding),
+        };
+        let magnitude = BigUint::from_bytes(&bytes[1..])?;
+
+        Ok(BigInt::from_biguint(sign, magnitude))
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        write_part(&mut res, &self.0.to_bytes());
+        write_part(&mut res, &self.1.to_bytes());
+        res
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, Errors> {
+        let a = A::from_bytes(read_part(&mut bytes)?)?;
+        let b = B::from_bytes(read_part(&mut bytes)?)?;
+        finish(bytes)?;
+
+        Ok((a, b))
+    }
+}
+
+/// Lists concatenate their elements, the number of elements is implied by the framing.
+impl<T: Encode> Encode for Vec<T> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        for x in self {
+            write_part(&mut res, &x.to_bytes());
+        }
+        res
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, Errors> {
+        let mut res = Vec::new();
+        while !bytes.is_empty() {
+            res.push(T::from_bytes(read_part(&mut bytes)?)?);
+        }
+
+        Ok(res)
+    }
+}
+
+impl<A: Encode, B: Encode, C: Encode> Encode for (A, B, C) {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut res = Vec::new();
+        write_part(&mut res, &self.0.to_bytes());
+        write_part(&mut res, &self.1.to_bytes());
+        write_part(&mut res, &self.2.to_bytes());
+        res
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, Errors> {
+        let a = A::from_bytes(read_part(&mut bytes)?)?;
+        let b = B::from_bytes(read_part(&mut bytes)?)?;
+        let c = C::from_bytes(read_part(&mut bytes)?)?;
+        finish(bytes)?;
+
+        Ok((a, b, c))
+    }
+}
+
+/// A borrowed view of an encoded value, see [`Encode`].
+pub trait Decode<'a>: Sized {
+    type Owned: Encode;
+
+    /// Checks the framing of `bytes`, without allocating.
+    fn decode(bytes: &'a [u8]) -> Result<Self, Errors>;
+
+    fn into_owned(self) -> Self::Owned;
+}
+
+/// An encoded `BigUint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UintRef<'a>(&'a [u8]);
+
+impl<'a> UintRef<'a> {
+    /// The big endian bytes.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+impl<'a> Decode<'a> for UintRef<'a> {
+    type Owned = BigUint;
+
+    fn decode(bytes: &'a [u8]) -> Result<Self, Errors> {
+        if bytes.is_empty() {
+            return Err(Errors::InvalidEncoding);
+        }
+        Ok(UintRef(bytes))
+    }
+
+    fn into_owned(self) -> BigUint {
+        BigUint::from_bytes_be(self.0)
+    }
+}
+
+/// An encoded `BigInt`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntRef<'a> {
+    negative: bool,
+    magnitude: UintRef<'a>,
+}
+
+impl<'a> IntRef<'a> {
+    pub fn is_negative(&self) -> bool {
+        self.negative
+    }
+
+    /// The big endian bytes of the absolute value.
+    pub fn magnitude(&self) -> &'a [u8] {
+        self.magnitude.as_bytes()
+    }
+}
+
+impl<'a> Decode<'a> for IntRef<'a> {
+    type Owned = BigInt;
+
+    fn decode(bytes: &'a [u8]) -> Result<Self, Errors> {
+        let negative = match bytes.first() {
+            Some(0) => false,
+            Some(1) => true,
+            _ => return Err(Errors::InvalidEncoding),
+        };
+
+        Ok(IntRef {
+            negative,
+            magnitude: UintRef::decode(&bytes[1..])?,
+        })
+    }
+
+    fn into_owned(self) -> BigInt {
+        let sign = if self.negative {
+            Sign::Minus
+        } else {
+            Sign::Plus
+        };
+        BigInt::from_biguint(sign, self.magnitude.into_owned())
+    }
+}
+
+impl<'a, A: Decode<'a>, B: Decode<'a>> Decode<'a> for (A, B) {
+    type Owned = (A::Owned, B::Owned);
+
+    fn decode(mut bytes: &'a [u8]) -> Result<Self, Errors> {
+        let a = A::decode(read_part(&mut bytes)?)?;
+        let b = B::decode(read_part(&mut bytes)?)?;
+        finish(bytes)?;
+
+        Ok((a, b))
+    }
+
+    fn into_owned(self) -> Self::Owned {
+        (self.0.into_owned(), self.1.into_owned())
+    }
+}
+
+impl<'a, A: Decode<'a>, B: Decode<'a>, C: Decode<'a>> Decode<'a> for (A, B, C) {
+    type Owned = (A::Owned, B::Owned, C::Owned);
+
+    fn decode(mut bytes: &'a [u8]) -> Result<Self, Errors> {
+        let a = A::decode(read_part(&mut bytes)?)?;
+        let b = B::decode(read_part(&mut bytes)?)?;
+        let c = C::decode(read_part(&mut bytes)?)?;
+        finish(bytes)?;
+
+        Ok((a, b, c))
+    }
+
+    fn into_owned(self) -> Self::Owned {
+        (
+            self.0.into_owned(),
+            self.1.into_owned(),
+            self.2.into_owned(),
+        )
+    }
+}
+
+/// A borrowed membership witness or NI-PoE.
+pub type ExponentProofRef<'a> = UintRef<'a>;
+
+/// A borrowed non-membership witness `(d, b)`.
+pub type NonMemWitnessRef<'a> = (UintRef<'a>, IntRef<'a>);
+
+/// A borrowed NI-PoKE2 `(z, Q, r)`.
+pub type KnowledgeProofRef<'a> = (UintRef<'a>, UintRef<'a>, IntRef<'a>);
+
+/// Appends `part`, prefixed with its length.
+pub(crate) fn write_part(out: &mut Vec<u8>, part: &[u8]) {
+    let mut len = [0u8; 4];
+    BigEndian::write_u32(&mut len, part.len() as u32);
+    out.extend(&len);
+    out.extend(part);
+}
+
+/// Reads a part written by `write_part`, and advances `bytes` past it.
+pub(crate) fn read_part<'a>(bytes: &mut &'a [u8]) -> Result<&'a [u8], Errors> {
+    if bytes.len() < 4 {
+        return Err(Errors::InvalidEncoding);
+    }
+    let len = BigEndian::read_u32(&bytes[..4]) as usize;
+    if bytes.len() < 4 + len {
+        return Err(Errors::InvalidEncoding);
+    }
+
+    let part = &bytes[4..4 + len];
+    *bytes = &bytes[4 + len..];
+    Ok(part)
+}
+
+/// Fails if there are any bytes left.
+pub(crate) fn finish(bytes: &[u8]) -> Result<(), Errors> {
+    if bytes.is_empty() {
+        Ok(())
+    } else {
+        Err(Errors::InvalidEncoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::feed::UpdateMessage;
+    use crate::proofs::KnowledgeProof;
+    use num_bigint::RandBigInt;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_encoding() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let x = rng.gen_biguint(256);
+        assert_eq!(BigUint::from_hex(&x.to_hex()).unwrap(), x);
+        assert_eq!(BigUint::from_base64(&x.to_base64()).unwrap(), x);
+        assert_eq!(BigUint::from_hex("0a0B").unwrap(), BigUint::from(2571u32));
+        assert_eq!(BigUint::from_hex(""), Err(Errors::InvalidEncoding));
+        assert_eq!(BigUint::from_hex("xyz"), Err(Errors::InvalidEncoding));
+
+        let proof: KnowledgeProof = (
+            rng.gen_biguint(256),
+            rng.gen_biguint(256),
+            -rng.gen_bigint(128),
+        );
+        assert_eq!(KnowledgeProof::from_hex(&proof.to_hex()).unwrap(), proof);
+        assert_eq!(
+            KnowledgeProof::from_base64(&proof.to_base64()).unwrap(),
+            proof
+        );
+
+        let mut bytes = proof.to_bytes();
+        bytes.push(0);
+        assert_eq!(
+            KnowledgeProof::from_bytes(&bytes),
+            Err(Errors::InvalidEncoding)
+        );
+        assert_eq!(
+            KnowledgeProof::from_bytes(&bytes[..10]),
+            Err(Errors::InvalidEncoding)
+        );
+
+        let msg = UpdateMessage {
+            epoch: 7,
+            added: rng.gen_biguint(256),
+            deleted: rng.gen_biguint(256),
+            root: rng.gen_biguint(256),
+        };
+        assert_eq!(msg.to_string().parse::<UpdateMessage>().unwrap(), msg);
+        assert!("not a message".parse::<UpdateMessage>().is_err());
+    }
+
+    #[test]
+    fn test_decode() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+
+        let proof: KnowledgeProof = (
+            rng.gen_biguint(256),
+            rng.gen_biguint(256),
+            -rng.gen_bigint(128),
+        );
+        let bytes = proof.to_bytes();
+        let view = KnowledgeProofRef::decode(&bytes).unwrap();
+        assert_eq!(view.0.as_bytes(), &proof.0.to_bytes_be()[..]);
+        assert!(view.2.is_negative());
+        assert_eq!(view.into_owned(), proof);
+
+        let w = (rng.gen_biguint(256), rng.gen_bigint(128));
+        let bytes = w.to_bytes();
+        assert_eq!(NonMemWitnessRef::decode(&bytes).unwrap().into_owned(), w);
+        assert!(KnowledgeProofRef::decode(&bytes).is_err());
+
+        let x = rng.gen_biguint(256);
+        let bytes = x.to_bytes();
+        assert_eq!(ExponentProofRef::decode(&bytes).unwrap().into_owned(), x);
+        assert_eq!(UintRef::decode(&[]), Err(Errors::InvalidEncoding));
+        assert_eq!(IntRef::decode(&[2, 1]), Err(Errors::InvalidEncoding));
+        assert_eq!(IntRef::decode(&[1]), Err(Errors::InvalidEncoding));
+
+        // the same framing as `Encode`
+        let mut bytes = proof.to_bytes();
+        bytes.push(0);
+        assert_eq!(
+            KnowledgeProofRef::decode(&bytes),
+            Err(Errors::InvalidEncoding)
+        );
+        assert_eq!(
+            KnowledgeProofRef::decode(&bytes[..10]),
+            Err(Errors::InvalidEncoding)
+        );
+    }
+}
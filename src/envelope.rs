@@ -0,0 +1,184 @@
+//! A single, self-describing type for all kinds of proofs.
+//!
+//! Proofs are tuples of integers, and many of them have the same shape, e.g. NI-PoEs and
+//! membership witnesses are single integers. Receivers of a [`Proof`] don't have to guess
+//! what they got: its encoding starts with the kind as `u8` and the version of that kind as
+//! `u16` BE, followed by the proof itself. Unknown kinds and versions are rejected, so the
+//! encoding of a kind can change without old proofs being read as new ones.
+
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::{BigInt, BigUint};
+
+use crate::aggregate::AggregateProof;
+use crate::encoding::Encode;
+use crate::error::Errors;
+use crate::proofs::{ExponentProof, KnowledgeProof};
+
+/// The current version of every kind of proof.
+pub const PROOF_VERSION: u16 = 1;
+
+const POE: u8 = 0;
+const POKE2: u8 = 1;
+const BATCH_ADD: u8 = 2;
+const BATCH_DEL: u8 = 3;
+const AGGREGATE: u8 = 4;
+const MEM: u8 = 5;
+const NON_MEM: u8 = 6;
+
+/// Any proof, tagged with its kind.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Proof {
+    /// An NI-PoE, see `proofs::ni_poe_prove`.
+    Poe(ExponentProof),
+    /// An NI-PoKE2, see `proofs::ni_poke2_prove`.
+    Poke2(KnowledgeProof),
+    /// The proof returned by `batch_add`.
+    BatchAdd(ExponentProof),
+    /// The proof returned by `batch_del`.
+    BatchDel(ExponentProof),
+    /// See `aggregate::ProofAggregator`.
+    Aggregate(AggregateProof),
+    /// A membership witness.
+    Mem(BigUint),
+    /// A non-membership witness.
+    NonMem((BigUint, BigInt)),
+}
+
+impl Proof {
+    /// The tag of the kind of this proof in its encoding.
+    pub fn kind(&self) -> u8 {
+        match self {
+            Proof::Poe(_) => POE,
+            Proof::Poke2(_) => POKE2,
+            Proof::BatchAdd(_) => BATCH_ADD,
+            Proof::BatchDel(_) => BATCH_DEL,
+            Proof::Aggregate(_) => AGGREGATE,
+            Proof::Mem(_) => MEM,
+            Proof::NonMem(_) => NON_MEM,
+        }
+    }
+
+    fn body(&self) -> Vec<u8> {
+        match self {
+            Proof::Poe(q) | Proof::BatchAdd(q) | Proof::BatchDel(q) | Proof::Mem(q) => q.to_bytes(),
+            Proof::Poke2(pi) => pi.to_bytes(),
+            Proof::Aggregate(proof) => proof.to_bytes(),
+            Proof::NonMem(w) => w.to_bytes(),
+        }
+    }
+}
+
+impl Encode for Proof {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut res = vec![self.kind(), 0, 0];
+        BigEndian::write_u16(&mut res[1..], PROOF_VERSION);
+        res.extend(self.body());
+        res
+    }
+
+    /// Fails with `UnknownProofKind` for unknown kinds, and with `UnsupportedVersion` for
+    /// versions of a kind other than the current one.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Errors> {
+        if bytes.len() < 3 {
+            return Err(Errors::InvalidEncoding);
+        }
+        let (kind, version, body) = (bytes[0], BigEndian::read_u16(&bytes[1..3]), &bytes[3..]);
+        if kind > NON_MEM {
+            return Err(Errors::UnknownProofKind(kind));
+        }
+        if version != PROOF_VERSION {
+            return Err(Errors::UnsupportedVersion(version));
+        }
+
+        Ok(match kind {
+            POE => Proof::Poe(ExponentProof::from_bytes(body)?),
+            POKE2 => Proof::Poke2(KnowledgeProof::from_bytes(body)?),
+            BATCH_ADD => Proof::BatchAdd(ExponentProof::from_bytes(body)?),
+            BATCH_DEL => Proof::BatchDel(ExponentProof::from_bytes(body)?),
+            AGGREGATE => Proof::Aggregate(AggregateProof::from_bytes(body)?),
+            MEM => Proof::Mem(BigUint::from_bytes(body)?),
+            _ => Proof::NonMem(<(BigUint, BigInt)>::from_bytes(body)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::aggregate::ProofAggregator;
+    use crate::group::RSAGroup;
+    use crate::proofs;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_proof_envelope() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let params = acc.params();
+        let n = acc.modulus().clone();
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+
+        let pi_add = acc.batch_add(&xs[..2]);
+        let pi_del = acc.batch_del_tracked(&xs[1..2]).unwrap();
+        let g = acc.generator().clone();
+        let w = g.modpow(&xs[3], &n);
+        let aggregate = ProofAggregator::new(&params, acc.state())
+            .mem(&xs[0], &acc.mem_wit_create(&xs[0]))
+            .non_mem(&xs[2], &acc.non_mem_wit_create(&xs[2]).unwrap())
+            .poe(&xs[3], &g, &w, &proofs::ni_poe_prove(&xs[3], &g, &w, &n))
+            .finish()
+            .unwrap();
+        let partial = ProofAggregator::new(&params, acc.state())
+            .non_mem(&xs[1], &acc.non_mem_wit_create(&xs[1]).unwrap())
+            .finish()
+            .unwrap();
+
+        let all = vec![
+            Proof::Poe(proofs::ni_poe_prove(&xs[3], &g, &w, &n)),
+            Proof::Poke2(proofs::ni_poke2_prove(xs[3].clone(), &g, &w, &n).unwrap()),
+            Proof::BatchAdd(pi_add),
+            Proof::BatchDel(pi_del.clone()),
+            Proof::Aggregate(aggregate),
+            Proof::Aggregate(partial),
+            Proof::Mem(acc.mem_wit_create(&xs[0])),
+            Proof::NonMem(acc.non_mem_wit_create(&xs[3]).unwrap()),
+        ];
+        for proof in &all {
+            let bytes = proof.to_bytes();
+            assert_eq!(bytes[0], proof.kind());
+            assert_eq!(Proof::from_bytes(&bytes).as_ref(), Ok(proof));
+            assert_eq!(
+                Proof::from_versioned_bytes(&proof.to_versioned_bytes()).as_ref(),
+                Ok(proof)
+            );
+
+            let mut trailing = bytes.clone();
+            trailing.push(0);
+            assert!(Proof::from_bytes(&trailing) != Ok(proof.clone()));
+        }
+
+        // same shape, different kinds
+        let del = Proof::BatchDel(pi_del.clone());
+        assert_ne!(Proof::from_bytes(&del.to_bytes()), Ok(Proof::Poe(pi_del)));
+
+        let mut bytes = del.to_bytes();
+        bytes[0] = 7;
+        assert_eq!(Proof::from_bytes(&bytes), Err(Errors::UnknownProofKind(7)));
+        bytes[0] = del.kind();
+        bytes[2] = 2;
+        assert_eq!(
+            Proof::from_bytes(&bytes),
+            Err(Errors::UnsupportedVersion(2))
+        );
+        assert_eq!(Proof::from_bytes(&bytes[..2]), Err(Errors::InvalidEncoding));
+    }
+}
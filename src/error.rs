@@ -1,3 +1,84 @@
+use std::io;
 
+#[derive(Debug, Clone, PartialEq, Eq, Fail)]
+pub enum Errors {
+    #[fail(display = "the accumulators use different public parameters")]
+    ParameterMismatch,
+    #[fail(display = "the accumulated sets are not disjoint")]
+    NotDisjoint,
+    #[fail(display = "the value is not invertible modulo n")]
+    NotInvertible,
+    #[fail(display = "the element is not a member")]
+    NotAMember,
+    #[fail(display = "the batch is empty")]
+    EmptyBatch,
+    #[fail(display = "the state is out of sync with the accumulator")]
+    OutOfSync,
+    #[fail(display = "the work results are incomplete or don't match the job")]
+    InvalidWorkResult,
+    #[fail(display = "the encoding is invalid")]
+    InvalidEncoding,
+    #[fail(display = "the public parameters are invalid")]
+    InvalidParams,
+    #[fail(display = "the participant has contributed already")]
+    DuplicateContribution,
+    #[fail(display = "the witness is invalid")]
+    InvalidWitness,
+    #[fail(display = "the elements are not coprime")]
+    NotCoprime,
+    #[fail(display = "the delta of epoch {} is invalid", _0)]
+    InvalidDelta(u64),
+    #[fail(display = "expected the delta of epoch {}, got {}", expected, got)]
+    Gap { expected: u64, got: u64 },
+    #[fail(display = "epoch {} is unknown", _0)]
+    UnknownEpoch(u64),
+    #[fail(display = "the root of epoch {} does not match", _0)]
+    Diverged(u64),
+    #[fail(display = "epoch {} was compacted", _0)]
+    Pruned(u64),
+    #[fail(display = "the proof is invalid")]
+    InvalidProof,
+    #[fail(display = "element {} is not an odd prime of the configured size", _0)]
+    InvalidElement(usize),
+    #[fail(display = "decryption failed, the key is wrong or the data was modified")]
+    DecryptionFailed,
+    #[fail(display = "the signature of epoch {} is invalid", _0)]
+    InvalidSignature(u64),
+    #[fail(display = "the set is not a subset of the other set")]
+    NotASubset,
+    #[fail(display = "the value is not an element of the group")]
+    NotAGroupElement,
+    #[fail(display = "format version {} is not supported", _0)]
+    UnsupportedVersion(u16),
+    #[fail(display = "the change can't be reported to the registered observers")]
+    Unobservable,
+    #[fail(display = "proof kind {} is unknown", _0)]
+    UnknownProofKind(u8),
+    #[fail(display = "the store failed: {:?}", _0)]
+    Store(io::ErrorKind),
+}
 
-pub enum Errors {}
\ No newline at end of file
+impl From<io::Error> for Errors {
+    fn from(err: io::Error) -> Self {
+        Errors::Store(err.kind())
+    }
+}
+
+/// The check of a proof that failed, see `proofs::ni_poe_verify_detailed`,
+/// `proofs::ni_poke2_verify_detailed` and `proofs::ni_poke_star_verify_detailed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Fail)]
+pub enum VerifyFailure {
+    #[fail(display = "the modulus is zero")]
+    ZeroModulus,
+    #[fail(display = "the batch is empty")]
+    EmptyBatch,
+    /// NI-PoE and NI-PoKE*: `Q^l u^r != w`, the proof doesn't answer the challenge `l`.
+    #[fail(display = "the NI-PoE does not match its challenge")]
+    ChallengeMismatch,
+    /// NI-PoKE*: the residue `r` is not below the challenge `l`.
+    #[fail(display = "the residue of the NI-PoKE* is not reduced")]
+    ResidueOutOfRange,
+    /// NI-PoKE2: `Q^l (u g^alpha)^r != w z^alpha`.
+    #[fail(display = "the final equality of the NI-PoKE2 does not hold")]
+    FinalEquality,
+}
@@ -0,0 +1,236 @@
+//! NI-PoE in a format that can be checked by a Solidity contract.
+//!
+//! Fiat-Shamir uses Keccak256 instead of Blake2b, and all integers are tightly packed big
+//! endian words, like `abi.encodePacked`. Group elements are left padded to the length of the
+//! modulus, rounded up to whole 32 byte words, which is also the layout expected by the
+//! `MODEXP` precompile. The prover includes the counter of `hash_prime_with_nonce`, so the
+//! contract needs a single primality check to recompute the challenge.
+
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::Zero;
+use sha3::Keccak256;
+
+use crate::error::Errors;
+use crate::hash::{hash_prime_at, hash_prime_with_nonce};
+use crate::proofs::ProofOfExponentiation;
+
+/// The size of an EVM word, in bytes.
+pub const WORD_BYTES: usize = 32;
+
+/// The packed length of an integer of `x`'s size, in whole words.
+pub fn word_len(x: &BigUint) -> usize {
+    let bytes = (x.bits() + 7) / 8;
+    ((bytes + WORD_BYTES - 1) / WORD_BYTES).max(1) * WORD_BYTES
+}
+
+/// `x` as `len` big endian bytes, left padded with zeros.
+/// Returns `None` if `x` doesn't fit.
+pub fn pack(x: &BigUint, len: usize) -> Option<Vec<u8>> {
+    let bytes = if x.is_zero() {
+        Vec::new()
+    } else {
+        x.to_bytes_be()
+    };
+    if bytes.len() > len {
+        return None;
+    }
+
+    let mut res = vec![0u8; len - bytes.len()];
+    res.extend(bytes);
+    Some(res)
+}
+
+/// The Fiat-Shamir transcript `abi.encodePacked(uint256(len(x)), x, u, w)`, where `x` is
+/// padded to whole words and `u` and `w` to `word_len(n)`.
+/// Returns `None` if `u` or `w` are not reduced `mod n`.
+pub fn transcript(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Option<Vec<u8>> {
+    if u >= n || w >= n {
+        return None;
+    }
+
+    let x_len = word_len(x);
+    let mut res = pack(&BigUint::from(x_len), WORD_BYTES)?;
+    res.extend(pack(x, x_len)?);
+    res.extend(pack(u, word_len(n))?);
+    res.extend(pack(w, word_len(n))?);
+    Some(res)
+}
+
+/// A NI-PoE `Q = u^{floor(x / l)}`, with the counter that hashes the transcript to `l`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EvmProof {
+    pub q: BigUint,
+    pub counter: u64,
+}
+
+impl EvmProof {
+    /// `abi.encodePacked(Q, uint64(counter))`, with `Q` padded to `word_len(n)`.
+    pub fn to_packed(&self, n: &BigUint) -> Vec<u8> {
+        let mut res = pack(&self.q, word_len(n)).expect("the proof is not reduced mod n");
+        res.extend(&self.counter.to_be_bytes());
+        res
+    }
+
+    pub fn from_packed(bytes: &[u8], n: &BigUint) -> Result<Self, Errors> {
+        let len = word_len(n);
+        if bytes.len() != len + 8 {
+            return Err(Errors::InvalidEncoding);
+        }
+
+        let mut counter = [0u8; 8];
+        counter.copy_from_slice(&bytes[len..]);
+        Ok(EvmProof {
+            q: BigUint::from_bytes_be(&bytes[..len]),
+            counter: u64::from_be_bytes(counter),
+        })
+    }
+}
+
+/// Keccak256 NI-PoE, for proofs verified on chain.
+/// Can be used as the proof system of the batch operations, e.g. `batch_add_with::<Evm>`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Evm;
+
+impl ProofOfExponentiation for Evm {
+    type Proof = EvmProof;
+
+    /// Assumes `u^x = w`, with `u` and `w` reduced `mod n`.
+    fn prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Self::Proof {
+        debug_assert!(&u.modpow(x, n) == w, "invalid input");
+
+        let to_hash = transcript(x, u, w, n).expect("the input is not reduced mod n");
+        let (l, counter) = hash_prime_with_nonce::<_, Keccak256>(&to_hash);
+
+        EvmProof {
+            q: u.modpow(&x.div_floor(&l), n),
+            counter,
+        }
+    }
+
+    fn verify(x: &BigUint, u: &BigUint, w: &BigUint, proof: &Self::Proof, n: &BigUint) -> bool {
+        if n.is_zero() || &proof.q >= n {
+            return false;
+        }
+        let to_hash = match transcript(x, u, w, n) {
+            Some(to_hash) => to_hash,
+            None => return false,
+        };
+
+        // a single primality check, like the contract, see `verify_hash_prime`
+        let l = match hash_prime_at::<_, Keccak256>(&to_hash, proof.counter) {
+            Some(l) => l,
+            None => return false,
+        };
+
+        // Q^l u^r == w
+        let r = x.mod_floor(&l);
+        &((proof.q.modpow(&l, n) * u.modpow(&r, n)) % n) == w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use sha3::Digest;
+
+    #[test]
+    fn test_keccak() {
+        // Keccak256, not the standardized SHA3-256
+        assert_eq!(
+            hex::encode(Keccak256::digest(b"")),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+    }
+
+    #[test]
+    fn test_golden_vectors() {
+        // RSA-100
+        let n = BigUint::parse_bytes(
+            b"1522605027922533360535618378132637429718068114961380688657908494580122963258952897654000350692006139",
+            10,
+        )
+        .unwrap();
+        let u = BigUint::from(2u32);
+        // a product of the Mersenne primes 2^61 - 1, 2^89 - 1 and 2^127 - 1
+        let one = BigUint::from(1u32);
+        let x = [61, 89, 127]
+            .iter()
+            .map(|e| (&one << *e) - &one)
+            .product::<BigUint>();
+        let w = u.modpow(&x, &n);
+
+        let to_hash = transcript(&x, &u, &w, &n).unwrap();
+        assert_eq!(to_hash.len(), 32 + 64 + 2 * 64);
+        assert_eq!(
+            hex::encode(&to_hash),
+            concat!(
+                "0000000000000000000000000000000000000000000000000000000000000040",
+                "00000000000000000000000000000000000000000000000000000000001fffff",
+                "fffffffffeffffffefffffffffc0000080000000020000001fffffffffffffff",
+                "0000000000000000000000000000000000000000000000000000000000000000",
+                "0000000000000000000000000000000000000000000000000000000000000002",
+                "00000000000000000000000000000000000000000000006ade54d241239d3059",
+                "cbb38f623084f0d812023507c6a841331133f13551788efcda59a1198c634743",
+            )
+        );
+
+        let proof = Evm::prove(&x, &u, &w, &n);
+        assert_eq!(proof.counter, 344);
+        assert_eq!(
+            hex::encode(proof.to_packed(&n)),
+            concat!(
+                "000000000000000000000000000000000000000000000030eec051b249777127",
+                "50a742bbbda3eb45a24ba00a5afdd6394612a5e5c539c7dd8d43576a0d4ff5bf",
+                "0000000000000158",
+            )
+        );
+        assert!(Evm::verify(&x, &u, &w, &proof, &n));
+    }
+
+    #[test]
+    fn test_evm_proofs() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let root = acc.state().clone();
+        let proof = acc.batch_add_with::<Evm>(&xs);
+        assert!(acc.ver_batch_add_with::<Evm>(&proof, &root, &xs));
+        assert!(!acc.ver_batch_add_with::<Evm>(&proof, &root, &xs[1..]));
+
+        let bytes = proof.to_packed(&n);
+        assert_eq!(bytes.len(), WORD_BYTES + 8);
+        assert_eq!(EvmProof::from_packed(&bytes, &n).unwrap(), proof);
+        assert_eq!(
+            EvmProof::from_packed(&bytes[1..], &n),
+            Err(Errors::InvalidEncoding)
+        );
+
+        // the counter doesn't hash to a prime
+        let mut bad = proof.clone();
+        bad.counter += 1;
+        assert!(!acc.ver_batch_add_with::<Evm>(&bad, &root, &xs));
+    }
+
+    #[test]
+    fn test_pack() {
+        assert_eq!(word_len(&BigUint::zero()), 32);
+        assert_eq!(word_len(&(BigUint::from(1u32) << 255)), 32);
+        assert_eq!(word_len(&(BigUint::from(1u32) << 256)), 64);
+        assert_eq!(pack(&BigUint::from(258u32), 3), Some(vec![0, 1, 2]));
+        assert_eq!(pack(&BigUint::from(258u32), 1), None);
+    }
+}
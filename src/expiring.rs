@@ -0,0 +1,200 @@
+//! Elements with an expiry epoch, for short lived credentials and session tokens.
+//!
+//! The expiry epochs are chosen by the application, e.g. hours or days, and are independent
+//! of the epoch of the accumulator. A single `expire(epoch)` deletes all elements that are
+//! due with one `batch_del_tracked`, so no witnesses are needed, and emits one NI-PoE.
+
+use std::collections::BTreeMap;
+
+use num_bigint::BigUint;
+
+use crate::accumulator::Accumulator;
+use crate::error::Errors;
+use crate::math::product;
+use crate::proofs::{self, ExponentProof};
+use crate::traits::*;
+
+/// The elements removed by a single `expire`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expired {
+    /// The epoch passed to `expire`.
+    pub epoch: u64,
+    pub elements: Vec<BigUint>,
+    /// NI-PoE for `root^x = old_root`, where `x` is the product of the elements.
+    pub proof: ExponentProof,
+}
+
+impl Expired {
+    /// Checks that exactly these elements were deleted from `old_root`, resulting in `root`.
+    pub fn verify(&self, old_root: &BigUint, root: &BigUint, n: &BigUint) -> bool {
+        !self.elements.is_empty()
+            && proofs::ni_poe_verify(&product(&self.elements), root, old_root, &self.proof, n)
+    }
+}
+
+/// An accumulator whose elements are deleted once they expire.
+///
+/// Elements added directly to the wrapped accumulator never expire.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct ExpiringAccumulator {
+    acc: Accumulator,
+    /// The elements that are still accumulated, by expiry epoch.
+    expiries: BTreeMap<u64, Vec<BigUint>>,
+}
+
+impl ExpiringAccumulator {
+    pub fn new(acc: Accumulator) -> Self {
+        ExpiringAccumulator {
+            acc,
+            expiries: BTreeMap::new(),
+        }
+    }
+
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.acc
+    }
+
+    pub fn into_accumulator(self) -> Accumulator {
+        self.acc
+    }
+
+    /// Returns the number of elements that will expire.
+    pub fn pending(&self) -> usize {
+        self.expiries.values().map(Vec::len).sum()
+    }
+
+    /// Returns the earliest expiry epoch of any element.
+    pub fn next_expiry(&self) -> Option<u64> {
+        self.expiries.keys().next().cloned()
+    }
+
+    /// Returns the expiry epoch of `x`, if it was added with one.
+    pub fn expires_at(&self, x: &BigUint) -> Option<u64> {
+        self.expiries
+            .iter()
+            .find(|(_, xs)| xs.contains(x))
+            .map(|(epoch, _)| *epoch)
+    }
+
+    /// Adds `x`, which is deleted by the first `expire` with an epoch of at least `expires_at`.
+    pub fn add(&mut self, x: &BigUint, expires_at: u64) {
+        self.acc.add(x);
+        self.expiries.entry(expires_at).or_default().push(x.clone());
+    }
+
+    /// Adds all `xs` with the same expiry, see `add`.
+    /// Returns the proof of `batch_add`.
+    pub fn batch_add(&mut self, xs: &[BigUint], expires_at: u64) -> ExponentProof {
+        let proof = self.acc.batch_add(xs);
+        self.expiries
+            .entry(expires_at)
+            .or_default()
+            .extend_from_slice(xs);
+
+        proof
+    }
+
+    /// Deletes `x` before it expires.
+    /// Returns `None` if `x` is not an element with an expiry.
+    pub fn del(&mut self, x: &BigUint) -> Option<()> {
+        let epoch = self.expires_at(x)?;
+        self.acc.del(x)?;
+
+        let xs = self.expiries.get_mut(&epoch)?;
+        let i = xs.iter().position(|y| y == x)?;
+        xs.swap_remove(i);
+        if xs.is_empty() {
+            self.expiries.remove(&epoch);
+        }
+
+        Some(())
+    }
+
+    /// Deletes all elements that expire at or before `epoch`, with a single batch deletion.
+    /// Returns `None` if nothing was due.
+    pub fn expire(&mut self, epoch: u64) -> Result<Option<Expired>, Errors> {
+        let remaining = match epoch.checked_add(1) {
+            Some(next) => self.expiries.split_off(&next),
+            None => BTreeMap::new(),
+        };
+        let due = std::mem::replace(&mut self.expiries, remaining);
+        if due.is_empty() {
+            return Ok(None);
+        }
+
+        let elements = due.values().flatten().cloned().collect::<Vec<_>>();
+        let proof = match self.acc.batch_del_tracked(&elements) {
+            Ok(proof) => proof,
+            Err(err) => {
+                // nothing was deleted, keep tracking the elements
+                self.expiries.extend(due);
+                return Err(err);
+            }
+        };
+
+        Ok(Some(Expired {
+            epoch,
+            elements,
+            proof,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_expiring() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let mut acc = ExpiringAccumulator::new(acc);
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0], 10);
+        acc.batch_add(&xs[1..4], 12);
+        acc.batch_add(&xs[4..7], 20);
+        acc.add(&xs[7], 11);
+        assert_eq!(acc.pending(), 8);
+        assert_eq!(acc.next_expiry(), Some(10));
+        assert_eq!(acc.expires_at(&xs[5]), Some(20));
+
+        assert_eq!(acc.expire(9).unwrap(), None);
+
+        // revoked early
+        acc.del(&xs[2]).unwrap();
+        assert_eq!(acc.expires_at(&xs[2]), None);
+        assert!(acc.del(&xs[2]).is_none());
+
+        let old_root = acc.accumulator().state().clone();
+        let expired = acc.expire(12).unwrap().unwrap();
+        let root = acc.accumulator().state();
+        assert_eq!(
+            expired.elements,
+            vec![xs[0].clone(), xs[7].clone(), xs[1].clone(), xs[3].clone()]
+        );
+        assert!(expired.verify(&old_root, root, &n));
+        assert!(!expired.verify(root, &old_root, &n));
+
+        assert_eq!(acc.pending(), 3);
+        assert_eq!(acc.accumulator().len(), 3);
+        let w = acc.accumulator().mem_wit_create(&xs[4]);
+        assert!(acc.accumulator().ver_mem(&w, &xs[4]));
+
+        let expired = acc.expire(u64::max_value()).unwrap().unwrap();
+        assert_eq!(expired.elements, xs[4..7].to_vec());
+        assert!(acc.accumulator().is_empty());
+        assert_eq!(acc.next_expiry(), None);
+    }
+}
@@ -0,0 +1,458 @@
+//! Publish/subscribe style distribution of witness updates.
+//!
+//! The manager publishes one [`UpdateMessage`] per epoch, containing the products of the
+//! elements added and deleted in that epoch, as well as the resulting root.
+//! Witness holders subscribe with their element and update their own witnesses
+//! from these messages, so the manager never has to recompute anyone's witness.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::traits::ExtendedGcd;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::encoding::{finish, read_part, write_part, Encode};
+use crate::error::Errors;
+use crate::math::{modpow_uint_int, product};
+
+/// The changes of a single epoch.
+/// Additions are applied before deletions.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateMessage {
+    /// The epoch this message concludes.
+    pub epoch: u64,
+    /// Product of all elements added in this epoch.
+    pub added: BigUint,
+    /// Product of all elements deleted in this epoch.
+    pub deleted: BigUint,
+    /// The root at the end of this epoch.
+    pub root: BigUint,
+}
+
+impl UpdateMessage {
+    /// Updates the membership witness `w` of `x` to the root of this epoch.
+    /// Returns `None` if `x` was deleted in this epoch.
+    /// All operations are `mod n`.
+    pub fn apply(&self, w: &BigUint, x: &BigUint, n: &BigUint) -> Option<BigUint> {
+        update_witness(w, x, &self.added, &self.deleted, &self.root, n)
+    }
+}
+
+impl Encode for UpdateMessage {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut epoch = [0u8; 8];
+        BigEndian::write_u64(&mut epoch, self.epoch);
+
+        let mut res = Vec::new();
+        write_part(&mut res, &epoch);
+        write_part(&mut res, &self.added.to_bytes());
+        write_part(&mut res, &self.deleted.to_bytes());
+        write_part(&mut res, &self.root.to_bytes());
+        res
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, Errors> {
+        let epoch = read_part(&mut bytes)?;
+        if epoch.len() != 8 {
+            return Err(Errors::InvalidEncoding);
+        }
+
+        let msg = UpdateMessage {
+            epoch: BigEndian::read_u64(epoch),
+            added: BigUint::from_bytes(read_part(&mut bytes)?)?,
+            deleted: BigUint::from_bytes(read_part(&mut bytes)?)?,
+            root: BigUint::from_bytes(read_part(&mut bytes)?)?,
+        };
+        finish(bytes)?;
+
+        Ok(msg)
+    }
+}
+
+/// Formats the message as base64.
+impl fmt::Display for UpdateMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_base64())
+    }
+}
+
+impl FromStr for UpdateMessage {
+    type Err = Errors;
+
+    fn from_str(s: &str) -> Result<Self, Errors> {
+        Self::from_base64(s)
+    }
+}
+
+/// Updates the membership witness `w` of `x`, after the elements with product `added` were
+/// added and the ones with product `deleted` were removed, resulting in `root`.
+/// Returns `None` if `x` was deleted.
+/// All operations are `mod n`.
+pub(crate) fn update_witness(
+    w: &BigUint,
+    x: &BigUint,
+    added: &BigUint,
+    deleted: &BigUint,
+    root: &BigUint,
+    n: &BigUint,
+) -> Option<BigUint> {
+    // additions: w^x = A_t => (w^added)^x = A_t^added
+    let w = if added.is_one() {
+        w.clone()
+    } else {
+        w.modpow(added, n)
+    };
+
+    if deleted.is_one() {
+        return Some(w);
+    }
+
+    if !x.gcd(deleted).is_one() {
+        return None;
+    }
+
+    // deletions: a, b <- Bezout(x, deleted)
+    // w' = w^b A_{t+1}^a, as w'^x = A_{t+1}^{b deleted + a x} = A_{t+1}
+    let (_, a, b) = ExtendedGcd::extended_gcd(x, deleted);
+    let l = modpow_uint_int(&w, &b, n).ok()?;
+    let r = modpow_uint_int(root, &a, n).ok()?;
+
+    Some((l * r) % n)
+}
+
+/// Updates the membership witness `w` of `x` over the consecutive epochs of `msgs`, to the
+/// root of the last one.
+///
+/// This is the same as applying the messages one by one, but costs a single exponentiation by
+/// the product of all additions, and a single Bezout step for all deletions, as the roots
+/// satisfy `A_k^{deleted} = A_0^{added}` over all epochs. Holders that were offline for many
+/// epochs should use this.
+///
+/// Fails with `Gap` if the epochs are not consecutive, and with `NotAMember` if `x` was
+/// deleted in any of them.
+/// All operations are `mod n`.
+pub fn catch_up(
+    w: &BigUint,
+    x: &BigUint,
+    msgs: &[UpdateMessage],
+    n: &BigUint,
+) -> Result<BigUint, Errors> {
+    check_consecutive(msgs)?;
+    let last = match msgs.last() {
+        Some(last) => last,
+        None => return Ok(w.clone()),
+    };
+
+    let (added, deleted) = combined_products(msgs);
+    update_witness(w, x, &added, &deleted, &last.root, n).ok_or(Errors::NotAMember)
+}
+
+/// Fails with `Gap` unless every message concludes the epoch after the previous one.
+pub(crate) fn check_consecutive(msgs: &[UpdateMessage]) -> Result<(), Errors> {
+    for pair in msgs.windows(2) {
+        if pair[1].epoch != pair[0].epoch + 1 {
+            return Err(Errors::Gap {
+                expected: pair[0].epoch + 1,
+                got: pair[1].epoch,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// The products of all additions and all deletions of `msgs`.
+pub(crate) fn combined_products(msgs: &[UpdateMessage]) -> (BigUint, BigUint) {
+    let added = msgs.iter().map(|msg| msg.added.clone()).collect::<Vec<_>>();
+    let deleted = msgs
+        .iter()
+        .map(|msg| msg.deleted.clone())
+        .collect::<Vec<_>>();
+
+    (product(&added), product(&deleted))
+}
+
+/// What a subscriber receives when polling the feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Notification {
+    /// An epoch passed, and the witness needs to be updated.
+    Update(UpdateMessage),
+    /// The element was deleted in the given epoch, the subscription has ended.
+    Removed(u64),
+}
+
+/// Distributes per epoch updates to witness holders.
+///
+/// Messages are stored only once, every subscriber keeps a cursor into the log,
+/// and messages are dropped as soon as every subscriber has received them.
+#[derive(Debug, Clone, Default)]
+pub struct UpdateFeed {
+    /// The number of published epochs.
+    epoch: u64,
+    /// Published messages, not yet received by all subscribers.
+    /// `log[i]` concludes epoch `pruned + i + 1`.
+    log: VecDeque<UpdateMessage>,
+    /// The number of messages dropped from the front of the log.
+    pruned: u64,
+    /// Subscribed elements, and the last epoch they have received.
+    subscribers: HashMap<BigUint, u64>,
+    /// Elements deleted while subscribed: the last epoch they have received,
+    /// and the epoch they were deleted in.
+    removed: HashMap<BigUint, (u64, u64)>,
+}
+
+impl UpdateFeed {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns the last published epoch.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Registers interest in the element `x`, with a witness valid for the current epoch.
+    pub fn subscribe(&mut self, x: BigUint) {
+        self.removed.remove(&x);
+        self.subscribers.insert(x, self.epoch);
+    }
+
+    /// Removes the subscription for `x`.
+    pub fn unsubscribe(&mut self, x: &BigUint) {
+        self.subscribers.remove(x);
+        self.removed.remove(x);
+        self.prune();
+    }
+
+    /// Concludes the current epoch, with the given changes and resulting root.
+    /// Returns the new epoch.
+    pub fn publish(&mut self, added: &[BigUint], deleted: &[BigUint], root: &BigUint) -> u64 {
+        self.epoch += 1;
+
+        let mut added_product = BigUint::one();
+        for x in added {
+            added_product *= x;
+        }
+
+        let mut deleted_product = BigUint::one();
+        for x in deleted {
+            deleted_product *= x;
+
+            if let Some(seen) = self.subscribers.remove(x) {
+                self.removed.insert(x.clone(), (seen, self.epoch));
+            }
+        }
+
+        self.log.push_back(UpdateMessage {
+            epoch: self.epoch,
+            added: added_product,
+            deleted: deleted_product,
+            root: root.clone(),
+        });
+
+        self.prune();
+        self.epoch
+    }
+
+    /// Returns all notifications for `x` since the last poll.
+    /// Returns `None` if `x` is not subscribed.
+    pub fn poll(&mut self, x: &BigUint) -> Option<Vec<Notification>> {
+        let res = if let Some(seen) = self.subscribers.get_mut(x) {
+            let res = self.log.iter().skip((*seen - self.pruned) as usize);
+            let res = res.cloned().map(Notification::Update).collect();
+            *seen = self.epoch;
+            res
+        } else if let Some((seen, removed_at)) = self.removed.remove(x) {
+            // updates up to the epoch it was removed in, which only carries the removal
+            let mut res = self
+                .log
+                .iter()
+                .skip((seen - self.pruned) as usize)
+                .take((removed_at - seen - 1) as usize)
+                .cloned()
+                .map(Notification::Update)
+                .collect::<Vec<_>>();
+            res.push(Notification::Removed(removed_at));
+            res
+        } else {
+            return None;
+        };
+
+        self.prune();
+        Some(res)
+    }
+
+    /// Drops all messages every subscriber has received already.
+    fn prune(&mut self) {
+        let min_seen = self
+            .subscribers
+            .values()
+            .chain(self.removed.values().map(|(seen, _)| seen))
+            .min()
+            .cloned()
+            .unwrap_or(self.epoch);
+
+        while self.pruned < min_seen {
+            self.log.pop_front();
+            self.pruned += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_update_feed() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let mut feed = UpdateFeed::new();
+
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..3]);
+
+        // holders of xs[0] and xs[1]
+        let mut w0 = acc.mem_wit_create(&xs[0]);
+        let mut w1 = acc.mem_wit_create(&xs[1]);
+        feed.subscribe(xs[0].clone());
+        feed.subscribe(xs[1].clone());
+
+        // epoch 1: add xs[3], xs[4]
+        acc.batch_add(&xs[3..5]);
+        feed.publish(&xs[3..5], &[], acc.state());
+
+        let n = acc.modulus().clone();
+        for notification in feed.poll(&xs[0]).unwrap() {
+            match notification {
+                Notification::Update(msg) => w0 = msg.apply(&w0, &xs[0], &n).unwrap(),
+                Notification::Removed(_) => panic!("xs[0] was not removed"),
+            }
+        }
+        assert!(acc.ver_mem(&w0, &xs[0]));
+
+        // epoch 2: add xs[5], delete xs[2] and xs[1]
+        acc.add(&xs[5]);
+        acc.del(&xs[2]).unwrap();
+        acc.del(&xs[1]).unwrap();
+        feed.publish(&xs[5..], &[xs[2].clone(), xs[1].clone()], acc.state());
+        assert_eq!(feed.epoch(), 2);
+
+        for notification in feed.poll(&xs[0]).unwrap() {
+            match notification {
+                Notification::Update(msg) => w0 = msg.apply(&w0, &xs[0], &n).unwrap(),
+                Notification::Removed(_) => panic!("xs[0] was not removed"),
+            }
+        }
+        assert!(acc.ver_mem(&w0, &xs[0]));
+        assert!(feed.poll(&xs[0]).unwrap().is_empty());
+
+        // xs[1] gets the first epoch and then the removal
+        let notifications = feed.poll(&xs[1]).unwrap();
+        assert_eq!(notifications.len(), 2);
+        match &notifications[0] {
+            Notification::Update(msg) => w1 = msg.apply(&w1, &xs[1], &n).unwrap(),
+            Notification::Removed(_) => panic!("removed too early"),
+        }
+        assert_eq!(notifications[1], Notification::Removed(2));
+        assert!(!acc.ver_mem(&w1, &xs[1]));
+        assert!(feed.poll(&xs[1]).is_none());
+
+        // everything was received
+        assert!(feed.log.is_empty());
+    }
+
+    #[test]
+    fn test_catch_up() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let mut feed = UpdateFeed::new();
+
+        let xs = (0..12)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..4]);
+        let x = &xs[0];
+        let w = acc.mem_wit_create(x);
+        feed.subscribe(x.clone());
+
+        // ten epochs, some elements are added and deleted again later
+        for i in 4..12 {
+            acc.add(&xs[i]);
+            let deleted = if i % 2 == 1 {
+                acc.del(&xs[i - 2]).unwrap();
+                vec![xs[i - 2].clone()]
+            } else {
+                vec![]
+            };
+            feed.publish(&xs[i..=i], &deleted, acc.state());
+        }
+        feed.publish(&[], &[], acc.state());
+        acc.del(&xs[1]).unwrap();
+        feed.publish(&[], &xs[1..2], acc.state());
+
+        let msgs = feed
+            .poll(x)
+            .unwrap()
+            .into_iter()
+            .map(|notification| match notification {
+                Notification::Update(msg) => msg,
+                Notification::Removed(_) => panic!("x was not removed"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 10);
+
+        let serial = apply_serially(&w, x, &msgs, &n);
+        let combined = catch_up(&w, x, &msgs, &n).unwrap();
+        assert!(acc.ver_mem(&combined, x));
+        assert_eq!(combined, serial);
+        assert_eq!(catch_up(&w, x, &[], &n), Ok(w.clone()));
+        assert_eq!(
+            catch_up(&w, x, &msgs[..4], &n),
+            Ok(apply_serially(&w, x, &msgs[..4], &n))
+        );
+
+        // missing epochs, or x was deleted
+        let mut gap = msgs.clone();
+        gap.remove(3);
+        assert_eq!(
+            catch_up(&w, x, &gap, &n),
+            Err(Errors::Gap {
+                expected: 4,
+                got: 5
+            })
+        );
+        acc.del(x).unwrap();
+        let mut msgs = msgs;
+        msgs.push(UpdateMessage {
+            epoch: 11,
+            added: BigUint::one(),
+            deleted: x.clone(),
+            root: acc.state().clone(),
+        });
+        assert_eq!(catch_up(&w, x, &msgs, &n), Err(Errors::NotAMember));
+    }
+
+    fn apply_serially(w: &BigUint, x: &BigUint, msgs: &[UpdateMessage], n: &BigUint) -> BigUint {
+        msgs.iter()
+            .try_fold(w.clone(), |w, msg| msg.apply(&w, x, n))
+            .unwrap()
+    }
+}
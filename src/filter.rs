@@ -0,0 +1,198 @@
+//! A probabilistic filter over the members of an accumulator.
+//!
+//! The set of an accumulator is only kept as a product, so checking whether an element is a
+//! member takes a division of the whole product. A [`MembershipFilter`], enabled with
+//! `Accumulator::enable_filter`, answers most queries for non-members without it: if the filter
+//! doesn't contain an element, it is not a member. Only elements it may contain, i.e. members
+//! and a small rate of false positives, are checked against the product.
+//!
+//! This is a counting Bloom filter, so deleted elements can be removed again.
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+
+/// A counting Bloom filter over big integers.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MembershipFilter {
+    counters: Vec<u8>,
+    hashes: u32,
+}
+
+impl MembershipFilter {
+    /// A filter which has a false positive rate of about `fp_rate` after inserting `capacity`
+    /// elements.
+    pub fn new(capacity: usize, fp_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let capacity = capacity.max(1) as f64;
+        let fp_rate = fp_rate.clamp(1e-12, 0.5);
+
+        let size = (-capacity * fp_rate.ln() / (ln2 * ln2)).ceil();
+        let hashes = (size / capacity * ln2).round().max(1.0);
+
+        MembershipFilter {
+            counters: vec![0; size as usize],
+            hashes: hashes as u32,
+        }
+    }
+
+    /// The number of counters.
+    pub fn size(&self) -> usize {
+        self.counters.len()
+    }
+
+    /// The number of counters per element.
+    pub fn hashes(&self) -> u32 {
+        self.hashes
+    }
+
+    pub fn insert(&mut self, x: &BigUint) {
+        for i in self.indices(x) {
+            self.counters[i] = self.counters[i].saturating_add(1);
+        }
+    }
+
+    /// Removes an inserted element.
+    /// Counters that overflowed stay set, so this never causes false negatives.
+    pub fn remove(&mut self, x: &BigUint) {
+        for i in self.indices(x) {
+            if self.counters[i] != u8::MAX {
+                self.counters[i] = self.counters[i].saturating_sub(1);
+            }
+        }
+    }
+
+    /// Returns `false` if `x` was definitely not inserted.
+    pub fn may_contain(&self, x: &BigUint) -> bool {
+        self.indices(x).all(|i| self.counters[i] > 0)
+    }
+
+    /// Adds the counters of `other`, which then contains the elements of both.
+    /// Returns `false`, leaving this unchanged, if the filters have different dimensions.
+    pub fn merge(&mut self, other: &MembershipFilter) -> bool {
+        if self.counters.len() != other.counters.len() || self.hashes != other.hashes {
+            return false;
+        }
+
+        for (c, o) in self.counters.iter_mut().zip(&other.counters) {
+            *c = c.saturating_add(*o);
+        }
+        true
+    }
+
+    /// `h_1 + i h_2 mod size`, for the two halves of the hash of `x`.
+    fn indices(&self, x: &BigUint) -> impl Iterator<Item = usize> {
+        let hash = Blake2b::digest(&x.to_bytes_be());
+        let h1 = BigEndian::read_u64(&hash[..8]);
+        let h2 = BigEndian::read_u64(&hash[8..16]);
+        let size = self.counters.len() as u64;
+
+        (0..u64::from(self.hashes))
+            .map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % size) as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{Accumulator, PendingOps};
+    use crate::error::Errors;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_membership_filter() {
+        let mut filter = MembershipFilter::new(1000, 0.01);
+        assert_eq!(filter.hashes(), 7);
+        let xs = (0..2000u32).map(BigUint::from).collect::<Vec<_>>();
+        for x in &xs[..1000] {
+            filter.insert(x);
+        }
+        assert!(xs[..1000].iter().all(|x| filter.may_contain(x)));
+        let false_positives = xs[1000..].iter().filter(|x| filter.may_contain(x)).count();
+        assert!(false_positives < 30);
+
+        for x in &xs[..500] {
+            filter.remove(x);
+        }
+        assert!(xs[500..1000].iter().all(|x| filter.may_contain(x)));
+
+        let mut other = MembershipFilter::new(1000, 0.01);
+        other.insert(&xs[0]);
+        assert!(filter.merge(&other));
+        assert!(filter.may_contain(&xs[0]));
+        assert!(!filter.merge(&MembershipFilter::new(10, 0.01)));
+    }
+
+    #[test]
+    fn test_accumulator_filter() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+
+        acc.add(&xs[0]);
+        assert_eq!(acc.enable_filter(100, 0.01, &[]), Err(Errors::OutOfSync));
+        acc.enable_filter(100, 0.01, &xs[..1]).unwrap();
+
+        acc.batch_add(&xs[1..4]);
+        acc.del(&xs[1]).unwrap();
+        let mut ops = PendingOps::new();
+        ops.add(&xs[4]);
+        ops.del(&xs[2]);
+        acc.commit(&ops).unwrap();
+        acc.batch_add_chunked(xs[5..7].to_vec(), 1).unwrap();
+
+        // members pass the filter, and are rejected without a Bezout step
+        let filter = acc.filter().unwrap();
+        for x in &[&xs[0], &xs[3], &xs[4], &xs[5], &xs[6]] {
+            assert!(filter.may_contain(x) && acc.contains(x));
+            assert_eq!(acc.non_mem_wit_create(x), Err(Errors::NotCoprime));
+        }
+        for x in &[&xs[1], &xs[2], &xs[7]] {
+            assert!(!acc.contains(x));
+            let w = acc.non_mem_wit_create(x).unwrap();
+            assert!(acc.ver_non_mem(&w, x));
+        }
+
+        // merges keep the filter only if both sides have one
+        let mut other = Accumulator::new_with_params(&acc.params());
+        other.add(&xs[7]);
+        let (merged, _) = acc.clone().merge(other.clone()).unwrap();
+        assert!(merged.filter().is_none());
+        other.enable_filter(100, 0.01, &xs[7..]).unwrap();
+        let (merged, _) = acc.clone().merge(other).unwrap();
+        assert!(merged.filter().unwrap().may_contain(&xs[7]));
+        assert!(merged.contains(&xs[7]));
+
+        // the elements of diffs are unknown
+        let mut replica = Accumulator::new_with_params(&acc.params());
+        replica.enable_filter(100, 0.01, &[]).unwrap();
+        let diff = replica.diff(&acc).unwrap();
+        replica.apply_diff(&diff).unwrap();
+        assert!(replica.filter().is_none());
+        assert!(replica.contains(&xs[0]));
+
+        // batch deletions without observers remove the elements, too
+        let pairs = [&xs[3], &xs[4]]
+            .iter()
+            .map(|&x| (x.clone(), acc.mem_wit_create(x)))
+            .collect::<Vec<_>>();
+        acc.batch_del(&pairs).unwrap();
+        let mut expected = MembershipFilter::new(100, 0.01);
+        [&xs[0], &xs[5], &xs[6]]
+            .iter()
+            .for_each(|x| expected.insert(x));
+        assert_eq!(acc.filter(), Some(&expected));
+
+        acc.disable_filter();
+        assert!(acc.filter().is_none());
+    }
+}
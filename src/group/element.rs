@@ -0,0 +1,170 @@
+use std::convert::TryFrom;
+
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use crate::error::Errors;
+use crate::math::{jacobi, modpow_uint_int};
+
+/// An element of `Z_n^*`, checked to be in `(0, n)` and coprime to `n`.
+///
+/// Values supplied by other parties, like witnesses, can be checked when they are received,
+/// instead of feeding arbitrary integers into the verification equations. Deserialization
+/// runs the same checks.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "(BigUint, BigUint)", into = "(BigUint, BigUint)")
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupElement {
+    value: BigUint,
+    n: BigUint,
+}
+
+impl GroupElement {
+    /// Fails with `NotAGroupElement` unless `0 < value < n` and `gcd(value, n) = 1`.
+    pub fn new(value: BigUint, n: &BigUint) -> Result<Self, Errors> {
+        if value.is_zero() || &value >= n || !value.gcd(n).is_one() {
+            return Err(Errors::NotAGroupElement);
+        }
+
+        Ok(GroupElement {
+            value,
+            n: n.clone(),
+        })
+    }
+
+    /// Like `new`, but also requires the Jacobi symbol `(value | n)` to be `1`, which holds for
+    /// all quadratic residues. Residuosity itself can't be checked without the factors of `n`.
+    pub fn new_with_jacobi(value: BigUint, n: &BigUint) -> Result<Self, Errors> {
+        if n.is_even() {
+            return Err(Errors::InvalidParams);
+        }
+        if jacobi(&value, n) != 1 {
+            return Err(Errors::NotAGroupElement);
+        }
+
+        GroupElement::new(value, n)
+    }
+
+    pub fn value(&self) -> &BigUint {
+        &self.value
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    pub fn into_value(self) -> BigUint {
+        self.value
+    }
+
+    /// Fails with `ParameterMismatch` if the moduli differ.
+    pub fn mul(&self, other: &GroupElement) -> Result<GroupElement, Errors> {
+        if self.n != other.n {
+            return Err(Errors::ParameterMismatch);
+        }
+
+        Ok(self.with_value((&self.value * &other.value) % &self.n))
+    }
+
+    pub fn pow(&self, e: &BigUint) -> GroupElement {
+        self.with_value(self.value.modpow(e, &self.n))
+    }
+
+    /// Like `pow`, for negative exponents as well.
+    pub fn pow_signed(&self, e: &BigInt) -> GroupElement {
+        let value = modpow_uint_int(&self.value, e, &self.n).expect("elements are invertible");
+        self.with_value(value)
+    }
+
+    pub fn inverse(&self) -> GroupElement {
+        self.pow_signed(&-BigInt::one())
+    }
+
+    /// Stays in `Z_n^*`, as units are closed under multiplication.
+    fn with_value(&self, value: BigUint) -> GroupElement {
+        GroupElement {
+            value,
+            n: self.n.clone(),
+        }
+    }
+}
+
+impl TryFrom<(BigUint, BigUint)> for GroupElement {
+    type Error = Errors;
+
+    fn try_from((value, n): (BigUint, BigUint)) -> Result<Self, Errors> {
+        GroupElement::new(value, &n)
+    }
+}
+
+impl From<GroupElement> for (BigUint, BigUint) {
+    fn from(el: GroupElement) -> Self {
+        (el.value, el.n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_group_element() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let p = rng.gen_prime(128);
+        let q = rng.gen_prime(128);
+        let n = &p * &q;
+
+        assert_eq!(
+            GroupElement::new(BigUint::zero(), &n),
+            Err(Errors::NotAGroupElement)
+        );
+        assert_eq!(
+            GroupElement::new(n.clone(), &n),
+            Err(Errors::NotAGroupElement)
+        );
+        assert_eq!(
+            GroupElement::new(&n + 2u32, &n),
+            Err(Errors::NotAGroupElement)
+        );
+        assert_eq!(
+            GroupElement::new(p.clone(), &n),
+            Err(Errors::NotAGroupElement)
+        );
+
+        let a = GroupElement::new(BigUint::from(65547u32), &n).unwrap();
+        let b = GroupElement::new(BigUint::from(3u32), &n).unwrap();
+        let ab = a.mul(&b).unwrap();
+        assert_eq!(ab.value(), &BigUint::from(65547u32 * 3));
+        assert!(a.mul(&a.inverse()).unwrap().value().is_one());
+        assert_eq!(a.pow(&BigUint::from(5u32)), a.pow_signed(&BigInt::from(5)));
+        assert_eq!(
+            a.pow_signed(&BigInt::from(-5))
+                .mul(&a.pow(&BigUint::from(5u32))),
+            Ok(GroupElement::new(BigUint::one(), &n).unwrap())
+        );
+
+        let other = GroupElement::new(BigUint::from(3u32), &(&n * 5u32)).unwrap();
+        assert_eq!(a.mul(&other), Err(Errors::ParameterMismatch));
+
+        // squares have Jacobi symbol 1
+        let square = a.pow(&BigUint::from(2u32)).into_value();
+        assert!(GroupElement::new_with_jacobi(square, &n).is_ok());
+        for v in 2u32..100 {
+            let v = BigUint::from(v);
+            let res = GroupElement::new_with_jacobi(v.clone(), &n);
+            assert_eq!(res.is_ok(), jacobi(&v, &n) == 1);
+        }
+        assert_eq!(
+            GroupElement::new_with_jacobi(BigUint::from(3u32), &(&n + 1u32)),
+            Err(Errors::InvalidParams)
+        );
+    }
+}
@@ -0,0 +1,523 @@
+//! Class groups of imaginary quadratic orders, as reduced binary quadratic forms.
+//!
+//! Composing two reduced forms directly gives a form with coefficients of the size of `|D|`,
+//! which then needs a long reduction. NUCOMP and NUDUPL (Shanks, see Cohen, "A Course in
+//! Computational Algebraic Number Theory", 5.4.8 and 5.4.9) instead stop the Euclidean
+//! algorithm once the remainders drop below `|D/4|^(1/4)`, so intermediate values stay around
+//! `sqrt(|D|)` and the result is reduced within a few steps.
+
+use blake2::Blake2b;
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{One, Signed, Zero};
+
+use crate::encoding::write_part;
+use crate::hash::hash_prime_at;
+use crate::math::jacobi;
+use crate::traits::UnknownOrderGroup;
+
+/// A positive definite binary quadratic form `a x^2 + b xy + c y^2`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QuadraticForm {
+    a: BigInt,
+    b: BigInt,
+    c: BigInt,
+}
+
+impl QuadraticForm {
+    pub fn a(&self) -> &BigInt {
+        &self.a
+    }
+
+    pub fn b(&self) -> &BigInt {
+        &self.b
+    }
+
+    pub fn c(&self) -> &BigInt {
+        &self.c
+    }
+
+    /// `b^2 - 4ac`
+    pub fn discriminant(&self) -> BigInt {
+        &self.b * &self.b - BigInt::from(4) * &self.a * &self.c
+    }
+
+    /// Reduced forms satisfy `|b| <= a <= c`, and `b >= 0` if `|b| = a` or `a = c`.
+    /// Each class contains exactly one of them.
+    pub fn is_reduced(&self) -> bool {
+        let a_neg = -&self.a;
+        if self.b <= a_neg || self.b > self.a || self.a > self.c {
+            return false;
+        }
+
+        !(self.a == self.c && self.b.is_negative())
+    }
+
+    /// Moves `b` into `(-a, a]`, keeping the class.
+    fn normalize(self) -> Self {
+        let QuadraticForm { a, b, c } = self;
+        if -&a < b && b <= a {
+            return QuadraticForm { a, b, c };
+        }
+
+        // r = floor((a - b) / 2a)
+        let r = (&a - &b).div_floor(&(&a << 1));
+        let c = &a * &r * &r + &b * &r + c;
+        let b = b + ((&a * &r) << 1);
+
+        QuadraticForm { a, b, c }
+    }
+
+    /// Returns the reduced form of the class.
+    fn reduce(self) -> Self {
+        let mut f = self.normalize();
+        while f.a > f.c || (f.a == f.c && f.b.is_negative()) {
+            // s = floor((c + b) / 2c)
+            let s = (&f.c + &f.b).div_floor(&(&f.c << 1));
+            let QuadraticForm { a, b, c } = f;
+            f = QuadraticForm {
+                a: c.clone(),
+                b: ((&c * &s) << 1) - &b,
+                c: &c * &s * &s - &b * &s + a,
+            };
+        }
+
+        f.normalize()
+    }
+}
+
+/// The class group of discriminant `D < 0`, `D = 1 mod 4`.
+///
+/// For a prime `|D|`, the class number is odd and believed to be hard to compute for large
+/// `|D|`, so no trusted setup is needed.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuadraticFormGroup {
+    discriminant: BigInt,
+    /// `floor(|D/4|^(1/4))`, where NUCOMP and NUDUPL stop the partial Euclidean algorithm.
+    bound: BigInt,
+}
+
+impl QuadraticFormGroup {
+    /// Returns `None` unless `D < 0` and `D = 1 mod 4`.
+    pub fn new(discriminant: BigInt) -> Option<Self> {
+        if !discriminant.is_negative() || discriminant.mod_floor(&BigInt::from(4)) != One::one() {
+            return None;
+        }
+
+        let abs = (-&discriminant).to_biguint().expect("D is negative");
+        let bound = (abs >> 2).nth_root(4);
+
+        Some(QuadraticFormGroup {
+            discriminant,
+            bound: bound.into(),
+        })
+    }
+
+    pub fn discriminant(&self) -> &BigInt {
+        &self.discriminant
+    }
+
+    /// The class of `(2, 1, (1 - D) / 8)`.
+    /// Returns `None` unless `D = 1 mod 8`.
+    pub fn generator(&self) -> Option<QuadraticForm> {
+        self.form(BigInt::from(2), BigInt::one())
+    }
+
+    /// The reduced form in the class of `(a, b, (b^2 - D) / 4a)`.
+    /// Returns `None` if `a <= 0` or `b^2 - D` is not divisible by `4a`.
+    pub fn form(&self, a: BigInt, b: BigInt) -> Option<QuadraticForm> {
+        if !a.is_positive() {
+            return None;
+        }
+
+        let (c, rem) = (&b * &b - &self.discriminant).div_rem(&(&a << 2));
+        if !rem.is_zero() {
+            return None;
+        }
+
+        Some(QuadraticForm { a, b, c }.reduce())
+    }
+
+    /// Composes two reduced forms, with NUCOMP.
+    pub fn compose(&self, f1: &QuadraticForm, f2: &QuadraticForm) -> QuadraticForm {
+        debug_assert!(f1.discriminant() == self.discriminant, "invalid input");
+        debug_assert!(f2.discriminant() == self.discriminant, "invalid input");
+
+        let (f1, f2) = if f1.a < f2.a { (f2, f1) } else { (f1, f2) };
+        let (mut a1, mut a2, c2) = (f1.a.clone(), f2.a.clone(), &f2.c);
+        let mut s = (&f1.b + &f2.b) >> 1;
+        let n = &f2.b - &s;
+
+        // u a2 + v a1 = d
+        let (mut d, u, v) = xgcd(&a2, &a1);
+        let (a, d1) = if d.is_one() {
+            (-&u * &n, BigInt::one())
+        } else {
+            // u1 s + v1 d = d1
+            let (d1, u1, _) = xgcd(&s, &d);
+            if !d1.is_one() {
+                a1 /= &d1;
+                a2 /= &d1;
+                s /= &d1;
+                d /= &d1;
+            }
+
+            let c1 = f1.c.mod_floor(&d);
+            let c2 = c2.mod_floor(&d);
+            let l = (-u1 * (&u * c1 + &v * c2)).mod_floor(&d);
+
+            (-&u * (&n / &d) + l * (&a1 / &d), d1)
+        };
+
+        // the representative of A mod a1 with the smallest absolute value
+        let mut a = a.mod_floor(&a1);
+        let a_neg = &a1 - &a;
+        if a_neg < a {
+            a = -a_neg;
+        }
+
+        let p = self.partial_euclid(&a1, a);
+
+        if p.steps == 0 {
+            let q1 = &a2 * &p.v3;
+            let q2 = &q1 + &n;
+            let f = q2 / &p.d;
+            let g = (&p.v3 * &s + c2) / &p.d;
+
+            return QuadraticForm {
+                a: &p.d * &a2,
+                b: (q1 << 1) + &f2.b,
+                c: &p.v3 * f + g * d1,
+            }
+            .reduce();
+        }
+
+        let b = (&a2 * &p.d + &n * &p.v) / &a1;
+        let q1 = b.clone() * &p.v3;
+        let q2 = &q1 + &n;
+        let f = &q2 / &p.d;
+        let e = (&s * &p.d + c2 * &p.v) / &a1;
+        let q3 = &e * &p.v2;
+        let q4 = &q3 - &s;
+        let g = &q4 / &p.v;
+        let (v, v2) = (&p.v * &d1, &p.v2 * &d1);
+
+        QuadraticForm {
+            a: &p.d * b + e * v,
+            b: q1 + q2 + d1 * (q3 + q4),
+            c: &p.v3 * f + g * v2,
+        }
+        .reduce()
+    }
+
+    /// Squares a reduced form, with NUDUPL.
+    pub fn square(&self, f: &QuadraticForm) -> QuadraticForm {
+        debug_assert!(f.discriminant() == self.discriminant, "invalid input");
+
+        // u b + v a = d1
+        let (d1, u, _) = xgcd(&f.b, &f.a);
+        let a = &f.a / &d1;
+        let b = &f.b / &d1;
+
+        let mut c = (-&f.c * u).mod_floor(&a);
+        let c_neg = &a - &c;
+        if c_neg < c {
+            c = -c_neg;
+        }
+
+        let p = self.partial_euclid(&a, c);
+        let a2 = &p.d * &p.d;
+        let c2 = &p.v3 * &p.v3;
+        let cross = (&p.d + &p.v3) * (&p.d + &p.v3) - &a2 - &c2;
+
+        if p.steps == 0 {
+            let g = (&b * &p.v3 + &f.c) / &p.d;
+
+            return QuadraticForm {
+                a: a2,
+                b: &f.b + cross,
+                c: c2 + g * d1,
+            }
+            .reduce();
+        }
+
+        let e = (&f.c * &p.v + &b * &p.d) / &a;
+        let g = (&e * &p.v2 - b) / &p.v;
+        let b2 = &e * &p.v2 + &p.v * &g;
+        let (v, v2) = (&p.v * &d1, &p.v2 * &d1);
+
+        QuadraticForm {
+            a: a2 + e * v,
+            b: b2 * d1 + cross,
+            c: c2 + g * v2,
+        }
+        .reduce()
+    }
+
+    /// The extended Euclidean algorithm on `(a, b)`, stopped once the remainder is at most
+    /// `self.bound` in absolute value.
+    fn partial_euclid(&self, a: &BigInt, b: BigInt) -> PartialEuclid {
+        let mut p = PartialEuclid {
+            v: BigInt::zero(),
+            d: a.clone(),
+            v2: BigInt::one(),
+            v3: b,
+            steps: 0,
+        };
+
+        while p.v3.abs() > self.bound {
+            // d = q v3 + t3, with 0 <= t3 < |v3|
+            let t3 = p.d.mod_floor(&p.v3.abs());
+            let q = (&p.d - &t3) / &p.v3;
+            let t2 = &p.v - q * &p.v2;
+
+            p.v = std::mem::replace(&mut p.v2, t2);
+            p.d = std::mem::replace(&mut p.v3, t3);
+            p.steps += 1;
+        }
+
+        if p.steps % 2 == 1 {
+            p.v2 = -p.v2;
+            p.v3 = -p.v3;
+        }
+
+        p
+    }
+}
+
+/// The state of `partial_euclid`, named as in Cohen's `PARTEUCL`.
+struct PartialEuclid {
+    v: BigInt,
+    d: BigInt,
+    v2: BigInt,
+    v3: BigInt,
+    steps: usize,
+}
+
+/// Returns `(g, x, y)` with `a x + b y = g = gcd(a, b) >= 0`.
+fn xgcd(a: &BigInt, b: &BigInt) -> (BigInt, BigInt, BigInt) {
+    let (mut r0, mut r1) = (a.clone(), b.clone());
+    let (mut x0, mut x1) = (BigInt::one(), BigInt::zero());
+    let (mut y0, mut y1) = (BigInt::zero(), BigInt::one());
+
+    while !r1.is_zero() {
+        let (q, r) = r0.div_mod_floor(&r1);
+        r0 = std::mem::replace(&mut r1, r);
+        let x = &x0 - &q * &x1;
+        x0 = std::mem::replace(&mut x1, x);
+        let y = &y0 - &q * &y1;
+        y0 = std::mem::replace(&mut y1, y);
+    }
+
+    if r0.is_negative() {
+        (-r0, -x0, -y0)
+    } else {
+        (r0, x0, y0)
+    }
+}
+
+impl UnknownOrderGroup for QuadraticFormGroup {
+    type Elem = QuadraticForm;
+
+    fn id(&self) -> QuadraticForm {
+        self.form(BigInt::one(), BigInt::one())
+            .expect("D = 1 mod 4")
+    }
+
+    fn op(&self, a: &QuadraticForm, b: &QuadraticForm) -> QuadraticForm {
+        if a == b {
+            self.square(a)
+        } else {
+            self.compose(a, b)
+        }
+    }
+
+    fn exp(&self, a: &QuadraticForm, e: &BigUint) -> QuadraticForm {
+        let mut res = self.id();
+        for byte in e.to_bytes_be() {
+            for i in (0..8).rev() {
+                res = self.square(&res);
+                if (byte >> i) & 1 == 1 {
+                    res = self.compose(&res, a);
+                }
+            }
+        }
+
+        res
+    }
+
+    /// `(a, -b, c)`, every form is invertible.
+    fn inv(&self, a: &QuadraticForm) -> Option<QuadraticForm> {
+        let inv = QuadraticForm {
+            a: a.a.clone(),
+            b: -&a.b,
+            c: a.c.clone(),
+        };
+
+        Some(inv.reduce())
+    }
+
+    /// Hashes to the class of a form `(p, b, c)`, for the first prime `p = 3 mod 4` from
+    /// `hash_prime_at` such that `D` is a square mod `p`.
+    fn hash_to_group(&self, input: &[u8]) -> QuadraticForm {
+        let d_mod = |p: &BigUint| {
+            self.discriminant
+                .mod_floor(&BigInt::from_biguint(Sign::Plus, p.clone()))
+                .to_biguint()
+                .expect("reduced mod a positive number")
+        };
+
+        for counter in 0.. {
+            let p = match hash_prime_at::<_, Blake2b>(input, counter) {
+                Some(p) => p,
+                None => continue,
+            };
+            let d = d_mod(&p);
+            if (&p % 4u32) != BigUint::from(3u32) || jacobi(&d, &p) != 1 {
+                continue;
+            }
+
+            // b^2 = D mod p, with b = D mod 2
+            let mut b = d.modpow(&((&p + 1u32) >> 2), &p);
+            if b.is_even() {
+                b = &p - b;
+            }
+
+            return self.form(p.into(), b.into()).expect("b^2 = D mod 4p");
+        }
+
+        unreachable!()
+    }
+
+    /// `a` and `b`, which determine `c`.
+    fn elem_to_bytes(&self, a: &QuadraticForm) -> Vec<u8> {
+        let mut res = Vec::new();
+        write_part(&mut res, &a.a.to_signed_bytes_be());
+        write_part(&mut res, &a.b.to_signed_bytes_be());
+        res
+    }
+
+    fn params_to_bytes(&self) -> Vec<u8> {
+        self.discriminant.to_signed_bytes_be()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::proofs::{ni_poe_prove_in, ni_poe_verify_in};
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    /// Composition followed by a full reduction, see Cohen 5.4.7.
+    fn compose_naive(
+        group: &QuadraticFormGroup,
+        f1: &QuadraticForm,
+        f2: &QuadraticForm,
+    ) -> QuadraticForm {
+        let (f1, f2) = if f1.a > f2.a { (f2, f1) } else { (f1, f2) };
+        let s = (&f1.b + &f2.b) >> 1;
+        let n = &f2.b - &s;
+
+        let (d, y1) = if f2.a.is_multiple_of(&f1.a) {
+            (f1.a.clone(), BigInt::zero())
+        } else {
+            let (d, u, _) = xgcd(&f2.a, &f1.a);
+            (d, u)
+        };
+        let (d1, x2, y2) = if s.is_multiple_of(&d) {
+            (d, BigInt::zero(), -BigInt::one())
+        } else {
+            let (d1, u, v) = xgcd(&s, &d);
+            (d1, u, -v)
+        };
+
+        let v1 = &f1.a / &d1;
+        let v2 = &f2.a / &d1;
+        let r = (y1 * y2 * n - x2 * &f2.c).mod_floor(&v1);
+        let b3 = &f2.b + ((&v2 * r) << 1);
+
+        group.form(v1 * v2, b3).unwrap()
+    }
+
+    fn setup(bits: usize) -> QuadraticFormGroup {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        loop {
+            let p: BigUint = rng.gen_prime(bits);
+            if &p % 8u32 == BigUint::from(7u32) {
+                return QuadraticFormGroup::new(-BigInt::from_biguint(Sign::Plus, p)).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_nucomp_nudupl() {
+        for bits in &[128, 256, 512] {
+            let group = setup(*bits);
+            let g = group.generator().unwrap();
+            assert!(g.is_reduced());
+            assert_eq!(g.discriminant(), group.discriminant().clone());
+
+            let mut x = g.clone();
+            let mut y = g.clone();
+            for i in 0..64u32 {
+                let f = group.hash_to_group(&i.to_be_bytes());
+                assert!(f.is_reduced());
+                assert_eq!(&f.discriminant(), group.discriminant());
+
+                assert_eq!(group.compose(&x, &f), compose_naive(&group, &x, &f));
+                assert_eq!(group.compose(&f, &x), compose_naive(&group, &x, &f));
+                assert_eq!(group.square(&f), compose_naive(&group, &f, &f));
+                assert_eq!(group.compose(&f, &f), group.square(&f));
+
+                let f_inv = group.inv(&f).unwrap();
+                assert_eq!(group.compose(&f, &f_inv), group.id());
+
+                x = group.compose(&x, &g);
+                y = compose_naive(&group, &y, &g);
+                assert_eq!(x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn test_form_group() {
+        let group = setup(256);
+        let g = group.hash_to_group(b"g");
+        let h = group.hash_to_group(b"h");
+        assert_ne!(g, h);
+        assert_eq!(g, group.hash_to_group(b"g"), "deterministic");
+        assert_eq!(group.op(&g, &group.id()), g);
+
+        let e = BigUint::from(1_000_003u32);
+        let e2 = BigUint::from(999_983u32);
+        assert_eq!(
+            group.op(&group.exp(&g, &e), &group.exp(&g, &e2)),
+            group.exp(&g, &(&e + &e2))
+        );
+        assert_eq!(
+            group.exp(&group.op(&g, &h), &e),
+            group.op(&group.exp(&g, &e), &group.exp(&h, &e))
+        );
+        assert_eq!(
+            group.exp_signed(&g, &-BigInt::from(e.clone())),
+            group.inv(&group.exp(&g, &e))
+        );
+
+        // the proofs work in any group of unknown order
+        let x = BigUint::from(1_000_003u64 * 999_983);
+        let w = group.exp(&g, &x);
+        let q = ni_poe_prove_in(&group, &x, &g, &w);
+        assert_eq!(ni_poe_verify_in(&group, &x, &g, &w, &q), Ok(()));
+        assert!(ni_poe_verify_in(&group, &x, &g, &h, &q).is_err());
+
+        assert!(QuadraticFormGroup::new(BigInt::from(5)).is_none());
+        assert!(QuadraticFormGroup::new(BigInt::from(-5)).is_none());
+        assert!(group.form(BigInt::from(2), BigInt::from(2)).is_none());
+        assert!(group.form(BigInt::zero(), BigInt::one()).is_none());
+    }
+}
@@ -1,5 +1,17 @@
 //! Prime Group Backend for our Accumulstors
 
+mod element;
+mod forms;
+mod modn;
+mod qr;
+mod signed;
+
+pub use self::element::GroupElement;
+pub use self::forms::{QuadraticForm, QuadraticFormGroup};
+pub use self::modn::ModN;
+pub use self::qr::{QrElement, QrN};
+pub use self::signed::SignedModN;
+
 //
 //Classgroup Backend
 //
@@ -16,4 +28,4 @@ pub use self::classgroup::ClassGroup;
 #[cfg(feature = "rsa_group")]
 mod rsa;
 #[cfg(feature = "rsa_group")]
-pub use self::rsa::RSAGroup;
+pub use self::rsa::{BlumPrime, BlumRSAGroup, GenPrime, PrimeList, RSAGroup, RSAGroupWith};
@@ -0,0 +1,90 @@
+use blake2::Blake2b;
+use num_bigint::traits::ModInverse;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use crate::hash::hash_group;
+use crate::traits::UnknownOrderGroup;
+
+/// The multiplicative group `Z_n^*` of integers `mod n`, of unknown order if the factors of
+/// `n` are unknown, as for RSA moduli.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModN {
+    n: BigUint,
+}
+
+impl ModN {
+    /// Returns `None` if `n` is zero.
+    pub fn new(n: &BigUint) -> Option<Self> {
+        if n.is_zero() {
+            return None;
+        }
+
+        Some(ModN { n: n.clone() })
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+}
+
+impl UnknownOrderGroup for ModN {
+    type Elem = BigUint;
+
+    fn id(&self) -> BigUint {
+        BigUint::one() % &self.n
+    }
+
+    fn op(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.n
+    }
+
+    fn exp(&self, a: &BigUint, e: &BigUint) -> BigUint {
+        a.modpow(e, &self.n)
+    }
+
+    fn inv(&self, a: &BigUint) -> Option<BigUint> {
+        a.mod_inverse(&self.n).and_then(|a_inv| a_inv.to_biguint())
+    }
+
+    fn hash_to_group(&self, input: &[u8]) -> BigUint {
+        hash_group::<_, Blake2b>(input, &self.n)
+    }
+
+    fn elem_to_bytes(&self, a: &BigUint) -> Vec<u8> {
+        a.to_bytes_be()
+    }
+
+    fn params_to_bytes(&self) -> Vec<u8> {
+        self.n.to_bytes_be()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use num_bigint::{BigInt, RandBigInt, RandPrime};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_mod_n() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let group = ModN::new(&n).unwrap();
+        assert!(ModN::new(&BigUint::zero()).is_none());
+
+        let a = rng.gen_biguint_below(&n);
+        let e = rng.gen_biguint(64);
+        assert_eq!(group.op(&a, &group.id()), a);
+        assert!(group.op(&a, &group.inv(&a).unwrap()).is_one());
+        assert_eq!(
+            group.exp_signed(&a, &-BigInt::from(e.clone())),
+            group.inv(&group.exp(&a, &e))
+        );
+        assert_eq!(group.inv(&BigUint::zero()), None);
+        assert_eq!(group.exp_signed(&BigUint::zero(), &BigInt::from(-1)), None);
+    }
+}
@@ -0,0 +1,194 @@
+//! The subgroup `QR_n` of quadratic residues of `Z_n^*`.
+//!
+//! `Z_n^*` has the element `-1` of order 2, and possibly other elements of known small order,
+//! which let an attacker turn a valid witness or proof into another one, or prove statements
+//! that only hold up to such an element. For a modulus with safe prime factors, `QR_n` has no
+//! elements of known order. Residuosity can't be checked without the factors of `n`, but every
+//! square is a residue, so values supplied by other parties are mapped into `QR_n` by squaring.
+
+use std::convert::TryFrom;
+
+use blake2::Blake2b;
+use num_bigint::traits::ModInverse;
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+
+use crate::error::Errors;
+use crate::group::GroupElement;
+use crate::hash::hash_to_qr;
+use crate::traits::UnknownOrderGroup;
+
+/// The group of quadratic residues `mod n`, of unknown order if the factors of `n` are unknown.
+///
+/// The operations expect elements of `QR_n`, use `map` for any other value.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrN {
+    n: BigUint,
+}
+
+impl QrN {
+    /// Returns `None` if `n` is zero.
+    pub fn new(n: &BigUint) -> Option<Self> {
+        if n.is_zero() {
+            return None;
+        }
+
+        Some(QrN { n: n.clone() })
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// Maps any value into `QR_n`, by squaring it.
+    /// Values which only differ by an element of order 2, e.g. `x` and `-x`, map to the same
+    /// residue.
+    pub fn map(&self, x: &BigUint) -> BigUint {
+        (x * x) % &self.n
+    }
+}
+
+impl UnknownOrderGroup for QrN {
+    type Elem = BigUint;
+
+    fn id(&self) -> BigUint {
+        BigUint::one() % &self.n
+    }
+
+    fn op(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        (a * b) % &self.n
+    }
+
+    fn exp(&self, a: &BigUint, e: &BigUint) -> BigUint {
+        a.modpow(e, &self.n)
+    }
+
+    fn inv(&self, a: &BigUint) -> Option<BigUint> {
+        a.mod_inverse(&self.n).and_then(|a_inv| a_inv.to_biguint())
+    }
+
+    fn hash_to_group(&self, input: &[u8]) -> BigUint {
+        hash_to_qr::<_, Blake2b>(input, &self.n)
+    }
+
+    fn elem_to_bytes(&self, a: &BigUint) -> Vec<u8> {
+        a.to_bytes_be()
+    }
+
+    fn params_to_bytes(&self) -> Vec<u8> {
+        self.n.to_bytes_be()
+    }
+}
+
+/// An element of `QR_n`, given by a square root of it.
+///
+/// Any unit `r` stands for the residue `r^2`, so values received from other parties can't be
+/// outside of the subgroup. Deserialization runs the checks of `new`, and maps the received
+/// value into `QR_n` like this.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "(BigUint, BigUint)", into = "(BigUint, BigUint)")
+)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrElement {
+    root: GroupElement,
+    value: BigUint,
+}
+
+impl QrElement {
+    /// The residue `root^2`.
+    /// Fails with `NotAGroupElement` unless `0 < root < n` and `gcd(root, n) = 1`.
+    pub fn new(root: BigUint, n: &BigUint) -> Result<Self, Errors> {
+        Ok(QrElement::from_root(GroupElement::new(root, n)?))
+    }
+
+    /// The residue, `root^2 mod n`.
+    pub fn value(&self) -> &BigUint {
+        &self.value
+    }
+
+    /// The square root this was given by.
+    pub fn root(&self) -> &BigUint {
+        self.root.value()
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        self.root.modulus()
+    }
+
+    pub fn into_value(self) -> BigUint {
+        self.value
+    }
+
+    /// Fails with `ParameterMismatch` if the moduli differ.
+    pub fn mul(&self, other: &QrElement) -> Result<QrElement, Errors> {
+        Ok(QrElement::from_root(self.root.mul(&other.root)?))
+    }
+
+    /// `(r^2)^e = (r^e)^2`, so this stays in `QR_n`.
+    pub fn pow(&self, e: &BigUint) -> QrElement {
+        QrElement::from_root(self.root.pow(e))
+    }
+
+    fn from_root(root: GroupElement) -> QrElement {
+        let value = root.pow(&BigUint::from(2u32)).into_value();
+        QrElement { root, value }
+    }
+}
+
+impl TryFrom<(BigUint, BigUint)> for QrElement {
+    type Error = Errors;
+
+    fn try_from((root, n): (BigUint, BigUint)) -> Result<Self, Errors> {
+        QrElement::new(root, &n)
+    }
+}
+
+impl From<QrElement> for (BigUint, BigUint) {
+    fn from(el: QrElement) -> Self {
+        el.root.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::math::jacobi;
+    use num_bigint::{RandBigInt, RandPrime};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_qr_n() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let group = QrN::new(&n).unwrap();
+        assert!(QrN::new(&BigUint::zero()).is_none());
+
+        // x and -x map to the same residue
+        let x = rng.gen_biguint_below(&n);
+        let a = group.map(&x);
+        assert_eq!(group.map(&(&n - &x)), a);
+        assert_eq!(jacobi(&a, &n), 1);
+        assert!(group.op(&a, &group.inv(&a).unwrap()).is_one());
+        assert_eq!(jacobi(&group.hash_to_group(b"g"), &n), 1);
+
+        let el = QrElement::new(x.clone(), &n).unwrap();
+        assert_eq!(el.value(), &a);
+        assert_eq!(el.root(), &x);
+        let e = BigUint::from(65537u32);
+        assert_eq!(el.pow(&e).value(), &group.exp(&a, &e));
+        let other = QrElement::new(&n - &x, &n).unwrap();
+        assert_eq!(el.mul(&other).unwrap().value(), &group.op(&a, &a));
+        assert_eq!(
+            QrElement::new(BigUint::zero(), &n),
+            Err(Errors::NotAGroupElement)
+        );
+
+        let (root, m) = el.clone().into();
+        assert_eq!(QrElement::try_from((root, m)), Ok(el));
+    }
+}
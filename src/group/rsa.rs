@@ -1,5 +1,9 @@
-use crate::traits::PrimeGroup;
-use failure::{bail, Error};
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+use crate::traits::{PrimeGroup, PrimeSampler};
+use failure::{bail, format_err, Error};
+use num_bigint::prime::probably_prime;
 use num_bigint::traits::ModInverse;
 use num_bigint::{BigUint, RandPrime};
 use num_traits::{FromPrimitive, One, Zero};
@@ -8,22 +12,183 @@ use rand::Rng;
 
 pub struct RSAGroup;
 
+/// `RSAGroup`, with the primes generated by `S::default()`, e.g.
+/// `Accumulator::setup::<RSAGroupWith<MySampler>, _>`.
+pub struct RSAGroupWith<S>(PhantomData<S>);
+
+// Default exponent for RSA keys, also used as the generator.
+const EXP: u64 = 65547;
+
 impl PrimeGroup for RSAGroup {
-    // Based on https://github.com/RustCrypto/RSA/blob/master/src/algorithms.rs
     fn generate_primes<R: Rng + CryptoRng>(
         rng: &mut R,
         bit_size: usize,
     ) -> Result<(BigUint, BigUint), Error> {
-        // Default exponent for RSA keys.
-        const EXP: u64 = 65547;
+        // This is a trusted setup, as we do know `p` and `q`, even though
+        // we choose not to store them.
+        let (p, q) = RSAGroup::generate_factors(rng, bit_size)?;
+
+        Ok((p * q, RSAGroup::generator()))
+    }
+}
+
+impl<S: PrimeSampler + Default> PrimeGroup for RSAGroupWith<S> {
+    fn generate_primes<R: Rng + CryptoRng>(
+        rng: &mut R,
+        bit_size: usize,
+    ) -> Result<(BigUint, BigUint), Error> {
+        RSAGroup::generate_primes_with(&mut S::default(), rng, bit_size)
+    }
+}
+
+/// `RSAGroup` with a Blum integer modulus, see [`BlumPrime`].
+pub type BlumRSAGroup = RSAGroupWith<BlumPrime>;
+
+/// The default sampler, `RandPrime::gen_prime` with the given rng.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GenPrime;
+
+impl PrimeSampler for GenPrime {
+    fn sample_prime<R: Rng + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        bits: usize,
+    ) -> Result<BigUint, Error> {
+        Ok(rng.gen_prime(bits))
+    }
+}
+
+/// Samples primes `p ≡ 3 (mod 4)` with `S`, so that the modulus is a Blum integer, as
+/// required e.g. by protocols over the quadratic residues.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BlumPrime<S = GenPrime>(pub S);
 
+impl<S: PrimeSampler> PrimeSampler for BlumPrime<S> {
+    fn sample_prime<R: Rng + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        bits: usize,
+    ) -> Result<BigUint, Error> {
+        loop {
+            let p = self.0.sample_prime(rng, bits)?;
+            if is_blum_prime(&p) {
+                return Ok(p);
+            }
+        }
+    }
+}
+
+/// `p ≡ 3 (mod 4)`
+fn is_blum_prime(p: &BigUint) -> bool {
+    p % 4u32 == BigUint::from(3u32)
+}
+
+/// Primes generated in advance, e.g. on an offline machine.
+/// Each is checked and used at most once, the rng is not used.
+#[derive(Debug, Clone, Default)]
+pub struct PrimeList {
+    primes: VecDeque<BigUint>,
+}
+
+impl PrimeList {
+    pub fn new(primes: impl IntoIterator<Item = BigUint>) -> Self {
+        PrimeList {
+            primes: primes.into_iter().collect(),
+        }
+    }
+
+    /// The number of unused primes.
+    pub fn len(&self) -> usize {
+        self.primes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.primes.is_empty()
+    }
+}
+
+impl PrimeSampler for PrimeList {
+    /// Returns the first unused prime of `bits` bits.
+    /// Fails if there is none, or it is not a prime.
+    fn sample_prime<R: Rng + CryptoRng>(
+        &mut self,
+        _rng: &mut R,
+        bits: usize,
+    ) -> Result<BigUint, Error> {
+        let i = self
+            .primes
+            .iter()
+            .position(|p| p.bits() == bits)
+            .ok_or_else(|| format_err!("no prime of {} bits left", bits))?;
+        let p = self.primes.remove(i).expect("index is in range");
+        if !probably_prime(&p, 20) {
+            bail!("{} is not a prime", p);
+        }
+
+        Ok(p)
+    }
+}
+
+impl RSAGroup {
+    /// The generator used for all moduli.
+    pub fn generator() -> BigUint {
+        BigUint::from_u64(EXP).expect("invalid static exponent")
+    }
+
+    /// Like `generate_primes`, with the primes generated by `sampler`.
+    pub fn generate_primes_with<S, R>(
+        sampler: &mut S,
+        rng: &mut R,
+        bit_size: usize,
+    ) -> Result<(BigUint, BigUint), Error>
+    where
+        S: PrimeSampler,
+        R: Rng + CryptoRng,
+    {
+        let (p, q) = RSAGroup::generate_factors_with(sampler, rng, bit_size)?;
+
+        Ok((p * q, RSAGroup::generator()))
+    }
+
+    /// Generates the factors `p ≡ q ≡ 3 (mod 4)` of a Blum integer with `|n| = bit_size`.
+    pub fn generate_blum_factors<R: Rng + CryptoRng>(
+        rng: &mut R,
+        bit_size: usize,
+    ) -> Result<(BigUint, BigUint), Error> {
+        RSAGroup::generate_factors_with(&mut BlumPrime(GenPrime), rng, bit_size)
+    }
+
+    /// Returns `true` if the factors `p, q` of a modulus make it a Blum integer.
+    pub fn is_blum(p: &BigUint, q: &BigUint) -> bool {
+        p != q && is_blum_prime(p) && is_blum_prime(q)
+    }
+
+    /// Generates the factors `p, q` of a modulus with `|n| = bit_size`.
+    /// Whoever holds these can break the accumulator, only keep them for a trapdoor manager.
+    pub fn generate_factors<R: Rng + CryptoRng>(
+        rng: &mut R,
+        bit_size: usize,
+    ) -> Result<(BigUint, BigUint), Error> {
+        RSAGroup::generate_factors_with(&mut GenPrime, rng, bit_size)
+    }
+
+    /// Like `generate_factors`, with the primes generated by `sampler`.
+    // Based on https://github.com/RustCrypto/RSA/blob/master/src/algorithms.rs
+    pub fn generate_factors_with<S, R>(
+        sampler: &mut S,
+        rng: &mut R,
+        bit_size: usize,
+    ) -> Result<(BigUint, BigUint), Error>
+    where
+        S: PrimeSampler,
+        R: Rng + CryptoRng,
+    {
         if bit_size < 64 {
             bail!("too few bits");
         }
 
         let nprimes = 2;
         let mut primes = vec![BigUint::zero(); nprimes];
-        let n_final: BigUint;
         // let d_final: BigUint;
 
         'next: loop {
@@ -44,7 +209,7 @@ impl PrimeGroup for RSAGroup {
             }
 
             for (i, prime) in primes.iter_mut().enumerate() {
-                *prime = rng.gen_prime(todo / (nprimes - i));
+                *prime = sampler.sample_prime(rng, todo / (nprimes - i))?;
                 todo -= prime.bits();
             }
 
@@ -74,20 +239,107 @@ impl PrimeGroup for RSAGroup {
 
             let exp = BigUint::from_u64(EXP).expect("invalid static exponent");
             if let Some(_d) = exp.mod_inverse(totient) {
-                n_final = n;
                 // d_final = d;
                 break;
             }
         }
 
-        // This is a trusted setup, as we do know `p` and `q`, even though
-        // we choose not to store them.
-        let _q = primes.pop().unwrap();
-        let _p = primes.pop().unwrap();
+        let q = primes.pop().unwrap();
+        let p = primes.pop().unwrap();
+
+        Ok((p, q))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::traits::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    /// Counts the primes it generates.
+    #[derive(Default)]
+    struct Counting(usize);
+
+    impl PrimeSampler for Counting {
+        fn sample_prime<R: Rng + CryptoRng>(
+            &mut self,
+            rng: &mut R,
+            bits: usize,
+        ) -> Result<BigUint, Error> {
+            self.0 += 1;
+            GenPrime.sample_prime(rng, bits)
+        }
+    }
+
+    #[test]
+    fn test_prime_samplers() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+
+        // the default sampler gives the same modulus as before
+        let (n, g) = RSAGroup::generate_primes(&mut rng.clone(), int_size_bits).unwrap();
+        let (m, _) =
+            RSAGroup::generate_primes_with(&mut GenPrime, &mut rng.clone(), int_size_bits).unwrap();
+        assert_eq!(n, m);
+        assert_eq!(g, RSAGroup::generator());
+
+        let mut counting = Counting::default();
+        let (p, q) = RSAGroup::generate_factors_with(&mut counting, rng, int_size_bits).unwrap();
+        assert!(counting.0 >= 2);
+        assert_eq!((&p * &q).bits(), int_size_bits);
+
+        // pre-generated primes are used once
+        let mut list = PrimeList::new(vec![BigUint::from(15u32), p.clone(), q.clone()]);
+        let (n, _) = RSAGroup::generate_primes_with(&mut list, rng, int_size_bits).unwrap();
+        assert_eq!(n, &p * &q);
+        assert_eq!(list.len(), 1);
+        assert!(RSAGroup::generate_primes_with(&mut list, rng, int_size_bits).is_err());
+        let mut list = PrimeList::new(vec![&p * &q]);
+        assert!(list.sample_prime(rng, int_size_bits).is_err());
+
+        let mut acc =
+            Accumulator::setup::<RSAGroupWith<BlumPrime<Counting>>, _>(rng, int_size_bits);
+        assert_eq!(acc.modulus().bits(), int_size_bits);
+        let x = BigUint::from(13u32);
+        acc.add(&x);
+        assert!(acc.ver_mem(&acc.mem_wit_create(&x), &x));
+    }
+
+    #[test]
+    fn test_blum_modulus() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let four = BigUint::from(4u32);
+        let three = BigUint::from(3u32);
+
+        for _ in 0..4 {
+            let (p, q) = RSAGroup::generate_blum_factors(rng, int_size_bits).unwrap();
+            assert_eq!(&p % &four, three);
+            assert_eq!(&q % &four, three);
+            assert!(RSAGroup::is_blum(&p, &q));
+            assert_eq!((&p * &q).bits(), int_size_bits);
+        }
+        assert!(!RSAGroup::is_blum(
+            &BigUint::from(7u32),
+            &BigUint::from(5u32)
+        ));
+        assert!(!RSAGroup::is_blum(&three, &three));
+
+        // n = p q ≡ 3 * 3 ≡ 1 (mod 4)
+        let (n, _) = BlumRSAGroup::generate_primes(rng, int_size_bits).unwrap();
+        assert_eq!(&n % &four, BigUint::one());
 
-        Ok((
-            n_final,
-            BigUint::from_u64(EXP).expect("invalid static exponent"),
-        ))
+        // only primes 3 mod 4 are taken from other samplers
+        let mut list = BlumPrime(PrimeList::new(vec![
+            BigUint::from(13u32),
+            BigUint::from(11u32),
+            BigUint::from(17u32),
+        ]));
+        assert_eq!(list.sample_prime(rng, 4).unwrap(), BigUint::from(11u32));
+        assert!(list.sample_prime(rng, 5).is_err());
     }
 }
@@ -0,0 +1,161 @@
+//! The quotient group `Z_n^* / {±1}`.
+//!
+//! Every class `{x, n - x}` is represented by its smaller member, so `-1` becomes the neutral
+//! element and can't be used to turn a proof into a different, also valid one. Unlike `QR_n`,
+//! the representation is unique and membership can be checked, so encodings of other
+//! accumulator and VDF implementations over the same quotient, fixed width and big endian,
+//! can be exchanged directly.
+
+use blake2::Blake2b;
+use num_bigint::traits::ModInverse;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use crate::error::Errors;
+use crate::hash::hash_group;
+use crate::traits::UnknownOrderGroup;
+
+/// `Z_n^* / {±1}`, of unknown order if the factors of `n` are unknown.
+///
+/// The operations expect canonical elements, use `canonicalize` for any other value.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedModN {
+    n: BigUint,
+}
+
+impl SignedModN {
+    /// Returns `None` if `n` is zero or even.
+    pub fn new(n: &BigUint) -> Option<Self> {
+        if n.is_zero() || n.is_even() {
+            return None;
+        }
+
+        Some(SignedModN { n: n.clone() })
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// The representative of the class of `x`, `min(x, n - x)` for `x mod n`.
+    pub fn canonicalize(&self, x: &BigUint) -> BigUint {
+        let x = x % &self.n;
+        let neg = &self.n - &x;
+        if neg < x {
+            neg
+        } else {
+            x
+        }
+    }
+
+    /// Returns `true` if `x` represents its class, i.e. `x <= (n - 1) / 2`.
+    pub fn is_canonical(&self, x: &BigUint) -> bool {
+        x << 1 < self.n
+    }
+
+    /// The number of bytes of `encode`.
+    pub fn encoded_len(&self) -> usize {
+        self.n.to_bytes_be().len()
+    }
+
+    /// The big endian bytes of the representative of `x`, padded to the length of `n`.
+    pub fn encode(&self, x: &BigUint) -> Vec<u8> {
+        let bytes = self.canonicalize(x).to_bytes_be();
+        let mut res = vec![0u8; self.encoded_len() - bytes.len()];
+        res.extend(bytes);
+        res
+    }
+
+    /// Reads an element written by `encode`.
+    /// Fails with `InvalidEncoding` if the length is wrong, and with `NotAGroupElement` if the
+    /// value is not a canonical unit, so every element has a single encoding.
+    pub fn decode(&self, bytes: &[u8]) -> Result<BigUint, Errors> {
+        if bytes.len() != self.encoded_len() {
+            return Err(Errors::InvalidEncoding);
+        }
+        let x = BigUint::from_bytes_be(bytes);
+        if x.is_zero() || !self.is_canonical(&x) || !x.gcd(&self.n).is_one() {
+            return Err(Errors::NotAGroupElement);
+        }
+
+        Ok(x)
+    }
+}
+
+impl UnknownOrderGroup for SignedModN {
+    type Elem = BigUint;
+
+    fn id(&self) -> BigUint {
+        BigUint::one() % &self.n
+    }
+
+    fn op(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        self.canonicalize(&(a * b))
+    }
+
+    fn exp(&self, a: &BigUint, e: &BigUint) -> BigUint {
+        self.canonicalize(&a.modpow(e, &self.n))
+    }
+
+    fn inv(&self, a: &BigUint) -> Option<BigUint> {
+        let a_inv = a.mod_inverse(&self.n)?.to_biguint()?;
+        Some(self.canonicalize(&a_inv))
+    }
+
+    fn hash_to_group(&self, input: &[u8]) -> BigUint {
+        self.canonicalize(&hash_group::<_, Blake2b>(input, &self.n))
+    }
+
+    fn elem_to_bytes(&self, a: &BigUint) -> Vec<u8> {
+        self.encode(a)
+    }
+
+    fn params_to_bytes(&self) -> Vec<u8> {
+        self.n.to_bytes_be()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use num_bigint::{RandBigInt, RandPrime};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_signed_mod_n() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let group = SignedModN::new(&n).unwrap();
+        assert!(SignedModN::new(&BigUint::zero()).is_none());
+        assert!(SignedModN::new(&(&n + 1u32)).is_none());
+
+        // x and -x are the same element
+        let x = rng.gen_biguint_below(&n);
+        let a = group.canonicalize(&x);
+        assert_eq!(group.canonicalize(&(&n - &x)), a);
+        assert!(group.is_canonical(&a));
+        assert_eq!(group.canonicalize(&(&n - 1u32)), group.id());
+        assert_eq!(group.canonicalize(&(&x + &n)), a);
+
+        let e = rng.gen_biguint(64);
+        assert_eq!(group.op(&a, &group.id()), a);
+        assert!(group.op(&a, &group.inv(&a).unwrap()).is_one());
+        assert_eq!(group.exp(&a, &e), group.canonicalize(&x.modpow(&e, &n)));
+        assert!(group.is_canonical(&group.hash_to_group(b"g")));
+
+        // unique encodings
+        let bytes = group.encode(&(&n - &x));
+        assert_eq!(bytes, group.encode(&x));
+        assert_eq!(bytes.len(), 32);
+        assert_eq!(group.decode(&bytes), Ok(a.clone()));
+        assert_eq!(group.decode(&bytes[1..]), Err(Errors::InvalidEncoding));
+        let mut other = vec![0u8; 32 - (&n - &a).to_bytes_be().len()];
+        other.extend((&n - &a).to_bytes_be());
+        assert_eq!(group.decode(&other), Err(Errors::NotAGroupElement));
+        assert_eq!(group.decode(&[0u8; 32]), Err(Errors::NotAGroupElement));
+    }
+}
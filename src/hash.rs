@@ -1,6 +1,8 @@
-use blake2::Digest;
+use std::io::{self, Read};
+
+use blake2::{Blake2b, Digest};
 use byteorder::{BigEndian, WriteBytesExt};
-use generic_array::ArrayLength;
+use generic_array::{ArrayLength, GenericArray};
 use num_bigint::prime::probably_prime;
 use num_bigint::BigUint;
 use num_integer::Integer;
@@ -8,27 +10,212 @@ use num_integer::Integer;
 // When the proofs are made non-interactive, using the
 // Fiat-Shamir heuristic the challenge is generated by hashing the previous transcript
 
+/// The bit size of the primes returned by `hash_prime`.
+pub const HASH_PRIME_BITS: usize = 128;
+
+/// The number of bits beyond the size of `n` that `hash_group` reduces `mod n`.
+pub const HASH_GROUP_EXTRA_BITS: usize = 128;
+
 /// Hash the given numbers to a prime number.
-/// Currently uses only 128bits.
-pub fn hash_prime<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(input: &[u8]) -> BigUint {
-    let mut y = BigUint::from_bytes_be(&D::digest(input)[..16]);
+/// The result always has exactly `HASH_PRIME_BITS` bits.
+pub fn hash_prime<O: ArrayLength<u8>, D: Digest<OutputSize = O> + Clone>(input: &[u8]) -> BigUint {
+    hash_prime_with_nonce::<_, D>(input).0
+}
+
+/// Hash the given numbers to a prime number, returning the prime and the counter
+/// that produced it.
+///
+/// Candidates are `H(input || counter)`, for `counter = 0, 1, ..` encoded as `u64` big endian,
+/// and the first prime candidate is returned. The counter allows verifying the mapping
+/// with a single primality check, using `verify_hash_prime`.
+///
+/// The input is only hashed once, every candidate continues from a copy of that state.
+pub fn hash_prime_with_nonce<O: ArrayLength<u8>, D: Digest<OutputSize = O> + Clone>(
+    input: &[u8],
+) -> (BigUint, u64) {
+    HashToPrime::<D>::with_digest()
+        .update(input)
+        .finalize_with_nonce()
+}
+
+/// Verifies that `p` is the prime candidate for `input` and `counter`.
+///
+/// Note: This does not check that no smaller counter yields a prime as well, which
+/// requires replaying `hash_prime`.
+pub fn verify_hash_prime<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
+    input: &[u8],
+    counter: u64,
+    p: &BigUint,
+) -> bool {
+    &prime_candidate(&counter_digest::<_, D>(input, counter)) == p && probably_prime(p, 20)
+}
+
+/// Returns the candidate for `input` and `counter`, if it is prime.
+/// Like `verify_hash_prime`, for verifiers that are only given the counter.
+pub fn hash_prime_at<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
+    input: &[u8],
+    counter: u64,
+) -> Option<BigUint> {
+    let p = prime_candidate(&counter_digest::<_, D>(input, counter));
+    if probably_prime(&p, 20) {
+        Some(p)
+    } else {
+        None
+    }
+}
+
+/// The key of `hash_prime_keyed`.
+pub type HashKey = [u8; 32];
+
+/// Like `hash_prime`, with BLAKE2b keyed with `key`.
+///
+/// Deployments with different keys map the same input to unrelated primes, so the mapping
+/// can't be precomputed without the key.
+pub fn hash_prime_keyed(key: &HashKey, input: &[u8]) -> BigUint {
+    use blake2::digest::{Input, VariableOutput};
+    use blake2::VarBlake2b;
+
+    let mut hasher = VarBlake2b::new_keyed(key, 64);
+    hasher.input(input);
+
+    first_prime_candidate(|counter| {
+        let mut h = hasher.clone();
+        h.input(counter.to_be_bytes());
+        let mut y = BigUint::from(0u32);
+        h.variable_result(|digest| y = prime_candidate(digest));
+        y
+    })
+    .0
+}
+
+/// Incremental `hash_prime`, for inputs that are too large to be kept in memory, e.g. files.
+///
+/// `HashToPrime::new().update(a).update(b).finalize()` equals `hash_prime::<_, Blake2b>(a || b)`.
+#[derive(Clone)]
+pub struct HashToPrime<D> {
+    hasher: D,
+}
+
+impl HashToPrime<Blake2b> {
+    pub fn new() -> Self {
+        Self::with_digest()
+    }
+}
+
+impl Default for HashToPrime<Blake2b> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<D: Digest + Clone> HashToPrime<D> {
+    /// Like `new`, hashing with `D` instead of Blake2b.
+    pub fn with_digest() -> Self {
+        HashToPrime { hasher: D::new() }
+    }
+
+    /// Appends `chunk` to the input.
+    pub fn update(mut self, chunk: &[u8]) -> Self {
+        self.hasher.input(chunk);
+        self
+    }
+
+    /// Appends everything read from `reader` to the input, in chunks of 64 KiB.
+    pub fn update_reader<R: Read>(mut self, reader: &mut R) -> io::Result<Self> {
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => return Ok(self),
+                Ok(len) => self.hasher.input(&buf[..len]),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// See `hash_prime`.
+    pub fn finalize(self) -> BigUint {
+        self.finalize_with_nonce().0
+    }
+
+    /// See `hash_prime_with_nonce`.
+    pub fn finalize_with_nonce(self) -> (BigUint, u64) {
+        first_prime_candidate(|counter| {
+            let mut hasher = self.hasher.clone();
+            hasher.input(&counter.to_be_bytes());
+            prime_candidate(&hasher.result())
+        })
+    }
+}
+
+/// Returns the first prime of `candidate(0), candidate(1), ..`, and its counter.
+fn first_prime_candidate<F: FnMut(u64) -> BigUint>(mut candidate: F) -> (BigUint, u64) {
+    let mut counter = 0u64;
+
+    loop {
+        let y = candidate(counter);
+        if probably_prime(&y, 20) {
+            return (y, counter);
+        }
 
-    while !probably_prime(&y, 20) {
-        y = BigUint::from_bytes_be(&D::digest(&y.to_bytes_be())[..16]);
+        counter += 1;
     }
+}
+
+/// H(input || counter)
+fn counter_digest<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
+    input: &[u8],
+    counter: u64,
+) -> GenericArray<u8, O> {
+    let mut hasher = D::new();
+    hasher.input(input);
+    hasher.input(&counter.to_be_bytes());
+
+    hasher.result()
+}
+
+/// Takes the leading `HASH_PRIME_BITS` of the digest, with the top bit set,
+/// so that leading zero bytes in the digest can not shorten the candidate.
+fn prime_candidate(digest: &[u8]) -> BigUint {
+    let mut bytes = digest[..HASH_PRIME_BITS / 8].to_vec();
+    bytes[0] |= 0x80;
 
-    y
+    BigUint::from_bytes_be(&bytes)
 }
 
-/// Hash the given numbers into the given group.
-/// Only works for `OutputSize >= |n|`.
+/// Hash the given numbers into the given group, i.e. to an integer in `[0, n)`.
+///
+/// The digests `H(input || 0) || H(input || 1) || ..` are concatenated to the length of `n`
+/// plus `HASH_GROUP_EXTRA_BITS`, and reduced `mod n`. The result is within statistical distance
+/// `2^-HASH_GROUP_EXTRA_BITS` of uniform, for moduli of any size, which the Fiat-Shamir
+/// challenges of the proofs in groups of unknown order rely on.
 pub fn hash_group<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
     input: &[u8],
     n: &BigUint,
 ) -> BigUint {
-    let y = BigUint::from_bytes_be(&D::digest(input)[..]);
+    let len = n.to_bytes_be().len() + HASH_GROUP_EXTRA_BITS / 8;
+    let mut bytes = Vec::with_capacity(len + O::to_usize());
+    let mut counter = 0;
+    while bytes.len() < len {
+        bytes.extend(counter_digest::<_, D>(input, counter));
+        counter += 1;
+    }
+    bytes.truncate(len);
+
+    BigUint::from_bytes_be(&bytes).mod_floor(n)
+}
+
+/// Hash the given numbers to a quadratic residue `mod n`, by squaring the result of
+/// `hash_group`. E.g. for generators or basis points of proofs, which have to be in `QR_n`.
+///
+/// The result is a square of a near-uniform value, so near-uniform in `QR_n` for RSA moduli.
+pub fn hash_to_qr<O: ArrayLength<u8>, D: Digest<OutputSize = O>>(
+    input: &[u8],
+    n: &BigUint,
+) -> BigUint {
+    let y = hash_group::<_, D>(input, n);
 
-    y.mod_floor(n)
+    (&y * &y) % n
 }
 
 /// Nonce based Hash to prime
@@ -72,8 +259,8 @@ pub fn verify_nonce_hash(p: &BigUint) -> bool {
 mod tests {
     use super::*;
 
-    use blake2::Blake2b;
-    use num_bigint::RandBigInt;
+    use num_bigint::{RandBigInt, RandPrime};
+    use num_traits::One;
     use rand::{thread_rng, Rng};
 
     #[test]
@@ -86,9 +273,81 @@ mod tests {
 
             let h = hash_prime::<_, Blake2b>(&val);
             assert!(probably_prime(&h, 20));
+            assert_eq!(h.bits(), HASH_PRIME_BITS);
+        }
+    }
+
+    #[test]
+    fn test_hash_prime_bits() {
+        // short inputs, a fair share of them have digests starting with zero bytes
+        for i in 0..256u32 {
+            let h = hash_prime::<_, Blake2b>(&i.to_be_bytes());
+            assert_eq!(h.bits(), HASH_PRIME_BITS, "input {}", i);
+        }
+
+        // leading zero bytes are ignored for the length
+        let digest = [0u8; 64];
+        assert_eq!(prime_candidate(&digest).bits(), HASH_PRIME_BITS);
+    }
+
+    #[test]
+    fn test_hash_prime_with_nonce() {
+        let mut rng = thread_rng();
+
+        for i in 1..10 {
+            let mut val = vec![0u8; i * 32];
+            rng.fill(&mut val[..]);
+
+            let (h, counter) = hash_prime_with_nonce::<_, Blake2b>(&val);
+            assert_eq!(h, hash_prime::<_, Blake2b>(&val));
+            assert!(verify_hash_prime::<_, Blake2b>(&val, counter, &h));
+
+            assert!(!verify_hash_prime::<_, Blake2b>(&val, counter + 1, &h));
+            val[0] ^= 1;
+            assert!(!verify_hash_prime::<_, Blake2b>(&val, counter, &h));
         }
     }
 
+    #[test]
+    fn test_hash_to_prime_streaming() {
+        let mut rng = thread_rng();
+        let mut val = vec![0u8; 200 * 1024];
+        rng.fill(&mut val[..]);
+
+        let expected = hash_prime_with_nonce::<_, Blake2b>(&val);
+        let streamed = val
+            .chunks(1000)
+            .fold(HashToPrime::new(), |h, chunk| h.update(chunk));
+        assert_eq!(streamed.finalize_with_nonce(), expected);
+
+        let read = HashToPrime::new()
+            .update_reader(&mut &val[..])
+            .unwrap()
+            .finalize();
+        assert_eq!(read, expected.0);
+
+        assert_eq!(HashToPrime::new().finalize(), hash_prime::<_, Blake2b>(&[]));
+        assert_eq!(
+            HashToPrime::<blake2::Blake2s>::with_digest()
+                .update(&val[..10])
+                .finalize(),
+            hash_prime::<_, blake2::Blake2s>(&val[..10])
+        );
+    }
+
+    #[test]
+    fn test_hash_prime_keyed() {
+        let key = [1u8; 32];
+        let h = hash_prime_keyed(&key, b"data");
+        assert!(probably_prime(&h, 20));
+        assert_eq!(h.bits(), HASH_PRIME_BITS);
+        assert_eq!(h, hash_prime_keyed(&key, b"data"), "deterministic");
+
+        assert_ne!(h, hash_prime_keyed(&[2u8; 32], b"data"));
+        assert_ne!(h, hash_prime_keyed(&key, b"other"));
+        assert_ne!(h, hash_prime::<_, Blake2b>(b"data"));
+    }
+
     #[test]
     fn test_hash_group() {
         let mut rng = thread_rng();
@@ -99,8 +358,40 @@ mod tests {
             let n = rng.gen_biguint(1024);
 
             let h = hash_group::<_, Blake2b>(&val, &n);
-            assert!(h <= n);
+            assert!(h < n);
+        }
+
+        // moduli larger than a single digest are covered
+        let n = rng.gen_biguint(2048);
+        assert!((0..8u32).any(|i| hash_group::<_, Blake2b>(&i.to_be_bytes(), &n).bits() > 1024));
+
+        // uniform, a single reduced 512 bit digest would be below n / 2 in 5 / 8 of the cases
+        let n = BigUint::from(3u32) << 510;
+        let half = &n >> 1;
+        let low = (0..4000u32)
+            .filter(|i| hash_group::<_, Blake2b>(&i.to_be_bytes(), &n) < half)
+            .count();
+        assert!(low > 1850 && low < 2150);
+    }
+
+    #[test]
+    fn test_hash_to_qr() {
+        let mut rng = thread_rng();
+        let p = rng.gen_prime(128);
+        let n = &p * rng.gen_prime(128);
+
+        for i in 0..10u32 {
+            let input = i.to_be_bytes();
+            let h = hash_to_qr::<_, Blake2b>(&input, &n);
+            let y = hash_group::<_, Blake2b>(&input, &n);
+            assert_eq!(h, y.modpow(&BigUint::from(2u32), &n));
+            // a residue mod p
+            assert!(h.modpow(&((&p - 1u32) >> 1), &p).is_one());
         }
+        assert_ne!(
+            hash_to_qr::<_, Blake2b>(b"a", &n),
+            hash_to_qr::<_, Blake2b>(b"b", &n)
+        );
     }
 
     #[test]
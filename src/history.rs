@@ -0,0 +1,664 @@
+//! The history of an accumulator's roots.
+//!
+//! Every change of the accumulator is recorded as a [`Delta`], containing the changed
+//! elements, the resulting root and a NI-PoE that the root was changed by exactly these
+//! elements. The deltas can be exported and checked by anyone with [`verify_history`],
+//! starting from the generator, without trusting the manager.
+//!
+//! To bound the storage of long running services, old deltas can be compacted into
+//! [`Checkpoint`]s, according to a [`Retention`] policy or explicitly with `compact`.
+//!
+//! Witnesses tagged with their epoch, see [`EpochWitness`], can be checked against the root
+//! of that epoch later on, to audit claims like "`x` was a member at epoch 41".
+
+use num_bigint::{BigInt, BigUint};
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::error::Errors;
+use crate::hooks::{Mutation, MutationKind};
+use crate::math::product;
+use crate::proofs::{self, ExponentProof};
+use crate::traits::*;
+use crate::verify::{verify_mem, verify_non_mem};
+
+/// A single change of the accumulator.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Delta {
+    /// The epoch this delta concludes, starting at `1`.
+    pub epoch: u64,
+    pub kind: MutationKind,
+    /// The elements that were added or deleted.
+    pub elements: Vec<BigUint>,
+    /// The root after the change.
+    pub root: BigUint,
+    /// NI-PoE for `A_{t-1}^x = A_t` on additions, or `A_t^x = A_{t-1}` on deletions,
+    /// where `x` is the product of the elements.
+    pub proof: ExponentProof,
+}
+
+impl Delta {
+    /// Checks this delta, as the successor of `prev_root`.
+    pub fn verify(&self, prev_root: &BigUint, n: &BigUint) -> bool {
+        if self.elements.is_empty() {
+            return false;
+        }
+
+        let x = product(&self.elements);
+        if self.kind.is_add() {
+            proofs::ni_poe_verify(&x, prev_root, &self.root, &self.proof, n)
+        } else {
+            proofs::ni_poe_verify(&x, &self.root, prev_root, &self.proof, n)
+        }
+    }
+}
+
+/// A range of epochs compacted into a single consistency proof.
+///
+/// With `added` and `deleted` the products of all elements added and deleted in the range,
+/// the roots satisfy `from_root^added = root^deleted = common`, which is shown by two NI-PoEs.
+/// The intermediate roots and proofs are dropped, only the element products are kept.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    pub from_epoch: u64,
+    pub from_root: BigUint,
+    pub epoch: u64,
+    pub root: BigUint,
+    pub added: BigUint,
+    pub deleted: BigUint,
+    pub common: BigUint,
+    /// NI-PoE for `from_root^added = common`.
+    pub add_proof: ExponentProof,
+    /// NI-PoE for `root^deleted = common`.
+    pub del_proof: ExponentProof,
+}
+
+impl Checkpoint {
+    /// Compacts `deltas`, which must directly follow `from_epoch` and `from_root`.
+    fn compact(from_epoch: u64, from_root: &BigUint, deltas: &[Delta], n: &BigUint) -> Self {
+        let mut added = Vec::new();
+        let mut deleted = Vec::new();
+        for delta in deltas {
+            if delta.kind.is_add() {
+                added.extend_from_slice(&delta.elements);
+            } else {
+                deleted.extend_from_slice(&delta.elements);
+            }
+        }
+
+        let added = product(&added);
+        let deleted = product(&deleted);
+        let last = deltas.last().expect("compacting an empty range");
+
+        let common = from_root.modpow(&added, n);
+        let add_proof = proofs::ni_poe_prove(&added, from_root, &common, n);
+        let del_proof = proofs::ni_poe_prove(&deleted, &last.root, &common, n);
+
+        Checkpoint {
+            from_epoch,
+            from_root: from_root.clone(),
+            epoch: last.epoch,
+            root: last.root.clone(),
+            added,
+            deleted,
+            common,
+            add_proof,
+            del_proof,
+        }
+    }
+
+    /// Checks the consistency proof.
+    pub fn verify(&self, n: &BigUint) -> bool {
+        self.from_epoch < self.epoch
+            && proofs::ni_poe_verify(
+                &self.added,
+                &self.from_root,
+                &self.common,
+                &self.add_proof,
+                n,
+            )
+            && proofs::ni_poe_verify(&self.deleted, &self.root, &self.common, &self.del_proof, n)
+    }
+}
+
+/// A witness for the root at the end of `epoch`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EpochWitness<W> {
+    pub epoch: u64,
+    pub witness: W,
+}
+
+impl Accumulator {
+    /// Creates a membership witness for `x`, tagged with the current epoch.
+    /// Fails with `NotAMember` if `x` is not a member.
+    pub fn mem_wit_create_at_epoch(&self, x: &BigUint) -> Result<EpochWitness<BigUint>, Errors> {
+        if !self.contains(x) {
+            return Err(Errors::NotAMember);
+        }
+
+        Ok(EpochWitness {
+            epoch: self.epoch(),
+            witness: self.mem_wit_create(x),
+        })
+    }
+
+    /// Creates a non-membership witness for `x`, tagged with the current epoch.
+    pub fn non_mem_wit_create_at_epoch(
+        &self,
+        x: &BigUint,
+    ) -> Result<EpochWitness<(BigUint, BigInt)>, Errors> {
+        Ok(EpochWitness {
+            epoch: self.epoch(),
+            witness: self.non_mem_wit_create(x)?,
+        })
+    }
+}
+
+/// Which deltas a [`History`] keeps.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Retention {
+    /// Keep every delta.
+    All,
+    /// Keep only the roots of every `k`-th epoch, by compacting every `k` deltas.
+    EveryKth(u64),
+    /// Keep at least the last `w` deltas, older ones are compacted in ranges of `w`.
+    Window(u64),
+}
+
+/// Records the deltas of an accumulator, starting from the empty accumulator.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct History {
+    params: PublicParams,
+    retention: Retention,
+    /// Compacted ranges, from the empty accumulator up to the first delta.
+    checkpoints: Vec<Checkpoint>,
+    deltas: Vec<Delta>,
+}
+
+impl History {
+    /// Starts an empty history, the root of the empty accumulator is `g`.
+    pub fn new(params: PublicParams) -> Self {
+        Self::with_retention(params, Retention::All)
+    }
+
+    pub fn with_retention(params: PublicParams, retention: Retention) -> Self {
+        History {
+            params,
+            retention,
+            checkpoints: Vec::new(),
+            deltas: Vec::new(),
+        }
+    }
+
+    pub fn params(&self) -> &PublicParams {
+        &self.params
+    }
+
+    pub fn retention(&self) -> Retention {
+        self.retention
+    }
+
+    /// Changes the retention policy, and applies it.
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+        self.prune();
+    }
+
+    /// Returns the current epoch, the number of recorded changes.
+    pub fn epoch(&self) -> u64 {
+        self.deltas
+            .last()
+            .map(|d| d.epoch)
+            .unwrap_or_else(|| self.first_epoch())
+    }
+
+    /// Returns the epoch the retained deltas start after.
+    pub fn first_epoch(&self) -> u64 {
+        self.checkpoints.last().map(|c| c.epoch).unwrap_or(0)
+    }
+
+    /// Returns the current root.
+    pub fn root(&self) -> &BigUint {
+        match (self.deltas.last(), self.checkpoints.last()) {
+            (Some(d), _) => &d.root,
+            (None, Some(c)) => &c.root,
+            (None, None) => &self.params.g,
+        }
+    }
+
+    /// Returns the root at the end of `epoch`, if it was reached yet and wasn't compacted.
+    pub fn root_at(&self, epoch: u64) -> Option<&BigUint> {
+        let first = self.first_epoch();
+        if epoch == 0 {
+            Some(&self.params.g)
+        } else if epoch <= first {
+            self.checkpoints
+                .iter()
+                .find(|c| c.epoch == epoch)
+                .map(|c| &c.root)
+        } else if epoch <= self.epoch() {
+            Some(&self.deltas[(epoch - first) as usize - 1].root)
+        } else {
+            None
+        }
+    }
+
+    /// Checks the membership witness `w` of `x` against the root of its epoch.
+    ///
+    /// Fails with `UnknownEpoch` if the epoch wasn't reached yet, and with `Pruned` if its
+    /// root was compacted.
+    pub fn ver_mem_at(&self, w: &EpochWitness<BigUint>, x: &BigUint) -> Result<bool, Errors> {
+        let root = self.retained_root(w.epoch)?;
+        Ok(verify_mem(&self.params, root, &w.witness, x))
+    }
+
+    /// Checks the non-membership witness `w` of `x` against the root of its epoch.
+    /// Fails like `ver_mem_at`.
+    pub fn ver_non_mem_at(
+        &self,
+        w: &EpochWitness<(BigUint, BigInt)>,
+        x: &BigUint,
+    ) -> Result<bool, Errors> {
+        let root = self.retained_root(w.epoch)?;
+        Ok(verify_non_mem(&self.params, root, &w.witness, x))
+    }
+
+    /// Like `root_at`, failing with `UnknownEpoch` or `Pruned`.
+    fn retained_root(&self, epoch: u64) -> Result<&BigUint, Errors> {
+        if epoch > self.epoch() {
+            return Err(Errors::UnknownEpoch(epoch));
+        }
+
+        self.root_at(epoch).ok_or(Errors::Pruned(epoch))
+    }
+
+    /// Records a change reported by an accumulator observer.
+    pub fn record(&mut self, mutation: &Mutation<'_>) -> Result<(), Errors> {
+        if mutation.old_root != self.root() {
+            return Err(Errors::OutOfSync);
+        }
+        if mutation.elements.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        let x = product(mutation.elements);
+        let n = &self.params.n;
+        let proof = if mutation.kind.is_add() {
+            proofs::ni_poe_prove(&x, mutation.old_root, mutation.new_root, n)
+        } else {
+            proofs::ni_poe_prove(&x, mutation.new_root, mutation.old_root, n)
+        };
+
+        self.deltas.push(Delta {
+            epoch: self.epoch() + 1,
+            kind: mutation.kind,
+            elements: mutation.elements.to_vec(),
+            root: mutation.new_root.clone(),
+            proof,
+        });
+        self.prune();
+
+        Ok(())
+    }
+
+    /// Compacts all retained deltas up to and including `epoch` into a single checkpoint.
+    pub fn compact(&mut self, epoch: u64) -> Result<(), Errors> {
+        let first = self.first_epoch();
+        if epoch <= first || epoch > self.epoch() {
+            return Err(Errors::UnknownEpoch(epoch));
+        }
+
+        let k = (epoch - first) as usize;
+        let checkpoint = Checkpoint::compact(
+            first,
+            self.root_at(first).expect("first epoch is retained"),
+            &self.deltas[..k],
+            &self.params.n,
+        );
+
+        self.checkpoints.push(checkpoint);
+        self.deltas.drain(..k);
+
+        Ok(())
+    }
+
+    /// Drops all epochs after `epoch`, e.g. after an `Accumulator::rollback` to it.
+    ///
+    /// Fails with `UnknownEpoch` if `epoch` wasn't reached yet, and with `Pruned` if it was
+    /// compacted into the middle of a checkpoint.
+    pub fn truncate(&mut self, epoch: u64) -> Result<(), Errors> {
+        if epoch > self.epoch() {
+            return Err(Errors::UnknownEpoch(epoch));
+        }
+
+        let first = self.first_epoch();
+        if epoch >= first {
+            self.deltas.truncate((epoch - first) as usize);
+            return Ok(());
+        }
+
+        let kept = if epoch == 0 {
+            0
+        } else {
+            self.checkpoints
+                .iter()
+                .position(|c| c.epoch == epoch)
+                .ok_or(Errors::Pruned(epoch))?
+                + 1
+        };
+        self.checkpoints.truncate(kept);
+        self.deltas.clear();
+
+        Ok(())
+    }
+
+    /// Applies the retention policy.
+    fn prune(&mut self) {
+        let (keep, range) = match self.retention {
+            Retention::All => return,
+            Retention::EveryKth(k) => (0, k.max(1)),
+            Retention::Window(w) => (w, w.max(1)),
+        };
+
+        while self.deltas.len() as u64 >= keep + range {
+            let epoch = self.first_epoch() + range;
+            self.compact(epoch).expect("epoch is retained");
+        }
+    }
+
+    /// Returns the compacted ranges, in order.
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    /// Exports all retained deltas, in order.
+    pub fn export(&self) -> &[Delta] {
+        &self.deltas
+    }
+
+    /// Returns the deltas of all epochs after `epoch`, or `None` if some of them were compacted.
+    pub fn since(&self, epoch: u64) -> Option<&[Delta]> {
+        let first = self.first_epoch();
+        if epoch < first {
+            return None;
+        }
+
+        let start = ((epoch - first) as usize).min(self.deltas.len());
+        Some(&self.deltas[start..])
+    }
+}
+
+/// Checks the whole chain of `checkpoints` followed by `deltas` from the empty accumulator,
+/// and returns the final root.
+pub fn verify_history(
+    params: &PublicParams,
+    checkpoints: &[Checkpoint],
+    deltas: &[Delta],
+) -> Result<BigUint, Errors> {
+    let mut epoch = 0;
+    let mut root = &params.g;
+
+    for checkpoint in checkpoints {
+        if checkpoint.from_epoch != epoch
+            || &checkpoint.from_root != root
+            || !checkpoint.verify(&params.n)
+        {
+            return Err(Errors::InvalidDelta(epoch + 1));
+        }
+        epoch = checkpoint.epoch;
+        root = &checkpoint.root;
+    }
+
+    for delta in deltas {
+        epoch += 1;
+        if delta.epoch != epoch || !delta.verify(root, &params.n) {
+            return Err(Errors::InvalidDelta(epoch));
+        }
+        root = &delta.root;
+    }
+
+    Ok(root.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_history() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let history = Arc::new(Mutex::new(History::new(params.clone())));
+        let observer = history.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0]);
+        acc.batch_add(&xs[1..5]);
+        acc.del(&xs[2]).unwrap();
+        acc.batch_del_tracked(&xs[3..5]).unwrap();
+        acc.add(&xs[5]);
+
+        let history = history.lock().unwrap();
+        assert_eq!(history.epoch(), 5);
+        assert_eq!(history.root(), acc.state());
+        assert_eq!(history.since(3).unwrap().len(), 2);
+        assert!(history.since(7).unwrap().is_empty());
+
+        let deltas = history.export().to_vec();
+        assert_eq!(verify_history(&params, &[], &deltas).unwrap(), *acc.state());
+
+        // tampered elements
+        let mut bad = deltas.clone();
+        bad[2].elements[0] = xs[1].clone();
+        assert_eq!(
+            verify_history(&params, &[], &bad),
+            Err(Errors::InvalidDelta(3))
+        );
+
+        // missing epoch
+        let mut bad = deltas.clone();
+        bad.remove(1);
+        assert_eq!(
+            verify_history(&params, &[], &bad),
+            Err(Errors::InvalidDelta(2))
+        );
+
+        // reordered
+        let mut bad = deltas;
+        bad.swap(3, 4);
+        assert_eq!(
+            verify_history(&params, &[], &bad),
+            Err(Errors::InvalidDelta(4))
+        );
+    }
+
+    #[test]
+    fn test_historical_witnesses() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let history = Arc::new(Mutex::new(History::with_retention(
+            params.clone(),
+            Retention::Window(2),
+        )));
+        let observer = history.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..3)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0]);
+        acc.add(&xs[1]);
+        let w = acc.mem_wit_create_at_epoch(&xs[1]).unwrap();
+        let u = acc.non_mem_wit_create_at_epoch(&xs[2]).unwrap();
+        assert_eq!(w.epoch, 2);
+
+        // later, xs[1] is deleted and xs[2] added
+        acc.del(&xs[1]).unwrap();
+        acc.add(&xs[2]);
+        let history = history.lock().unwrap();
+        assert_eq!(history.ver_mem_at(&w, &xs[1]), Ok(true));
+        assert_eq!(history.ver_mem_at(&w, &xs[0]), Ok(false));
+        assert_eq!(history.ver_non_mem_at(&u, &xs[2]), Ok(true));
+        assert!(!acc.ver_mem(&w.witness, &xs[1]));
+        assert!(!acc.ver_non_mem(&u.witness, &xs[2]));
+
+        // claims about other epochs
+        let moved = EpochWitness {
+            epoch: 4,
+            witness: w.witness.clone(),
+        };
+        assert_eq!(history.ver_mem_at(&moved, &xs[1]), Ok(false));
+        let future = EpochWitness {
+            epoch: 5,
+            witness: w.witness.clone(),
+        };
+        assert_eq!(
+            history.ver_mem_at(&future, &xs[1]),
+            Err(Errors::UnknownEpoch(5))
+        );
+
+        // epochs 1 and 2 were compacted, only the checkpoint root of epoch 2 is kept
+        assert_eq!(history.first_epoch(), 2);
+        let first = EpochWitness {
+            epoch: 1,
+            witness: w.witness.clone(),
+        };
+        assert_eq!(history.ver_mem_at(&first, &xs[1]), Err(Errors::Pruned(1)));
+    }
+
+    #[test]
+    fn test_retention() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let policies = [Retention::All, Retention::EveryKth(3), Retention::Window(2)];
+        let histories = policies
+            .iter()
+            .map(|r| Arc::new(Mutex::new(History::with_retention(params.clone(), *r))))
+            .collect::<Vec<_>>();
+        for history in &histories {
+            let observer = history.clone();
+            acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+        }
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        for x in &xs {
+            acc.add(x);
+        }
+        acc.del(&xs[1]).unwrap();
+        acc.batch_del_tracked(&xs[5..7]).unwrap();
+
+        let retained = histories
+            .iter()
+            .map(|h| {
+                let h = h.lock().unwrap();
+                assert_eq!(h.epoch(), 10);
+                assert_eq!(h.root(), acc.state());
+                assert_eq!(
+                    verify_history(&params, h.checkpoints(), h.export()).unwrap(),
+                    *acc.state()
+                );
+                (h.checkpoints().len(), h.export().len())
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(retained, vec![(0, 10), (3, 1), (4, 2)]);
+
+        let mut h = histories[1].lock().unwrap().clone();
+        assert_eq!(h.root_at(6), Some(&h.checkpoints()[1].root));
+        assert_eq!(h.root_at(5), None);
+        assert!(h.since(5).is_none());
+
+        // compact the rest explicitly
+        h.compact(10).unwrap();
+        assert_eq!(h.compact(10), Err(Errors::UnknownEpoch(10)));
+        assert!(h.export().is_empty());
+        assert_eq!(h.root(), acc.state());
+        assert_eq!(
+            verify_history(&params, h.checkpoints(), &[]).unwrap(),
+            *acc.state()
+        );
+
+        // tampered checkpoint
+        let mut checkpoints = h.checkpoints().to_vec();
+        checkpoints[1].deleted = xs[0].clone();
+        assert_eq!(
+            verify_history(&params, &checkpoints, &[]),
+            Err(Errors::InvalidDelta(4))
+        );
+    }
+
+    #[test]
+    fn test_truncate() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+        acc.enable_undo(8);
+
+        let history = Arc::new(Mutex::new(History::with_retention(
+            params.clone(),
+            Retention::EveryKth(2),
+        )));
+        let observer = history.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        for x in &xs[..5] {
+            acc.add(x);
+        }
+
+        // the history follows the rollback, and keeps recording afterwards
+        acc.rollback(4).unwrap();
+        history.lock().unwrap().truncate(4).unwrap();
+        acc.add(&xs[5]);
+        let w = acc.mem_wit_create_at_epoch(&xs[5]).unwrap();
+        assert_eq!(w.epoch, 5);
+        {
+            let h = history.lock().unwrap();
+            assert_eq!(h.epoch(), 5);
+            assert_eq!(h.root(), acc.state());
+            assert_eq!(h.ver_mem_at(&w, &xs[5]), Ok(true));
+            assert_eq!(
+                verify_history(&params, h.checkpoints(), h.export()).unwrap(),
+                *acc.state()
+            );
+        }
+
+        // epochs 1 to 4 are compacted into two checkpoints, ending at 2 and 4
+        let mut h = history.lock().unwrap().clone();
+        assert_eq!(h.truncate(6), Err(Errors::UnknownEpoch(6)));
+        assert_eq!(h.truncate(3), Err(Errors::Pruned(3)));
+        h.truncate(2).unwrap();
+        assert_eq!(h.epoch(), 2);
+        assert_eq!(h.checkpoints().len(), 1);
+        assert!(h.export().is_empty());
+        h.truncate(0).unwrap();
+        assert_eq!(h.epoch(), 0);
+        assert_eq!(h.root(), &params.g);
+    }
+}
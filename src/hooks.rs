@@ -0,0 +1,88 @@
+//! Observers, which are notified about every change of an accumulator.
+//!
+//! This allows keeping external indexes (databases, logs, metrics) in sync with the
+//! accumulator, without wrapping every call site.
+
+use std::fmt;
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+
+/// The operation that changed the accumulator.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MutationKind {
+    Add,
+    Del,
+    BatchAdd,
+    BatchDel,
+    DelWithMem,
+}
+
+impl MutationKind {
+    /// Returns `true` if elements were added, `false` if they were deleted.
+    pub fn is_add(self) -> bool {
+        match self {
+            MutationKind::Add | MutationKind::BatchAdd => true,
+            MutationKind::Del | MutationKind::BatchDel | MutationKind::DelWithMem => false,
+        }
+    }
+}
+
+/// A single change of the accumulator.
+#[derive(Debug, Clone, Copy)]
+pub struct Mutation<'a> {
+    pub kind: MutationKind,
+    /// The elements that were added or deleted.
+    pub elements: &'a [BigUint],
+    /// The root before the change.
+    pub old_root: &'a BigUint,
+    /// The root after the change.
+    pub new_root: &'a BigUint,
+}
+
+/// Gets notified after every successful mutation of an accumulator.
+pub trait MutationObserver: Send + Sync {
+    fn on_mutation(&self, mutation: &Mutation<'_>);
+}
+
+impl<F> MutationObserver for F
+where
+    F: Fn(&Mutation<'_>) + Send + Sync,
+{
+    fn on_mutation(&self, mutation: &Mutation<'_>) {
+        self(mutation)
+    }
+}
+
+/// The list of registered observers.
+/// Clones share the same observers.
+#[derive(Clone, Default)]
+pub struct Hooks(Vec<Arc<dyn MutationObserver>>);
+
+impl Hooks {
+    pub fn push(&mut self, observer: Arc<dyn MutationObserver>) {
+        self.0.push(observer);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Notifies all observers, in the order they were registered.
+    pub fn notify(&self, mutation: &Mutation<'_>) {
+        for observer in &self.0 {
+            observer.on_mutation(mutation);
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hooks({} observers)", self.0.len())
+    }
+}
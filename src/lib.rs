@@ -6,19 +6,88 @@
 #[macro_use]
 extern crate serde;
 
-// #[macro_use] extern crate failure_derive;
+#[macro_use]
+extern crate failure_derive;
 
 #[cfg(feature = "class_group")]
 extern crate classygroup;
 
+#[cfg(feature = "parallel")]
+extern crate rayon;
+
+#[cfg(feature = "mmap")]
+extern crate memmap;
+
+#[cfg(feature = "backup")]
+extern crate argon2;
+#[cfg(feature = "backup")]
+extern crate chacha20poly1305;
+
+#[cfg(feature = "deflate")]
+extern crate flate2;
+#[cfg(feature = "zstd")]
+extern crate libzstd;
+
+#[cfg(feature = "structured")]
+extern crate bincode;
+#[cfg(feature = "metrics")]
+extern crate libmetrics;
+#[cfg(feature = "tracing")]
+extern crate libtracing;
+#[cfg(feature = "evm")]
+extern crate sha3;
+
 pub mod accumulator;
+pub mod aggregate;
+pub mod audit;
+#[cfg(feature = "backend")]
+pub mod backend;
+pub mod blind;
+pub mod cache;
+pub mod ceremony;
+pub mod distributed;
+pub mod dual;
+pub mod element;
+pub mod encoding;
+pub mod envelope;
+pub mod error;
+#[cfg(feature = "evm")]
+pub mod evm;
+pub mod expiring;
+pub mod feed;
+pub mod filter;
 pub mod group;
 pub mod hash;
+pub mod history;
+pub mod hooks;
 pub mod math;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+pub mod oplog;
+pub mod outsource;
+pub mod primes;
 pub mod proofs;
+pub mod reconcile;
+pub mod recovery;
+#[cfg(all(test, feature = "differential"))]
+mod reference;
+pub mod sized;
+pub mod snapshot;
+#[cfg(feature = "mmap")]
+pub mod storage;
+pub mod sync;
 pub mod traits;
+#[cfg(feature = "rsa_group")]
+pub mod trapdoor;
+pub mod undo;
+pub mod universal;
 pub mod vc;
+pub mod verify;
+pub mod version;
+pub mod witnesses;
 
 pub use self::accumulator::*;
+pub use self::encoding::{Decode, Encode};
+pub use self::error::Errors;
 pub use self::traits::*;
 pub use self::vc::*;
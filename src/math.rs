@@ -1,9 +1,17 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::many_single_char_names))]
 
+use std::borrow::Borrow;
+use std::ops::Range;
+
 use num_bigint::traits::{ExtendedGcd, ModInverse};
 use num_bigint::{BigInt, BigUint, Sign};
 use num_integer::Integer;
-use num_traits::{One, Signed, Zero};
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::error::Errors;
 
 /// Calculates a = a.pow(b).
 // TODO: this can be speed up using various techniques, like precomputations.
@@ -20,28 +28,36 @@ pub fn pow_assign(a: &mut BigUint, b: &BigUint) {
     }
 }
 
+/// Splits `x` into `(x < 0, |x|)`.
+pub fn sign_magnitude(x: &BigInt) -> (bool, BigUint) {
+    let (sign, bytes) = x.to_bytes_be();
+    (sign == Sign::Minus, BigUint::from_bytes_be(&bytes))
+}
+
 /// Calculates a ^ e % n.
-pub fn modpow_uint_int(a: &BigUint, e: &BigInt, n: &BigUint) -> Option<BigUint> {
+/// Fails if `e` is negative and `a` is not invertible `mod n`.
+pub fn modpow_uint_int(a: &BigUint, e: &BigInt, n: &BigUint) -> Result<BigUint, Errors> {
     match e.sign() {
         Sign::Plus => {
             // regular case
-            Some(a.clone().modpow(&e.to_biguint().unwrap(), n))
+            Ok(a.clone().modpow(&e.to_biguint().unwrap(), n))
         }
         Sign::Minus => {
             // exponent is negative, so we calculate the modular inverse of e.
             let a_signed = BigInt::from_biguint(Sign::Plus, a.clone());
             let n_signed = BigInt::from_biguint(Sign::Plus, n.clone());
 
-            if let Some(a_inv) = a_signed.mod_inverse(&n_signed) {
-                let e_abs = e.abs().to_biguint().unwrap();
-                Some(a_inv.to_biguint().unwrap().modpow(&e_abs, n))
-            } else {
-                None
-            }
+            let a_inv = a_signed
+                .mod_inverse(&n_signed)
+                .and_then(|a_inv| a_inv.to_biguint())
+                .ok_or(Errors::NotInvertible)?;
+            let e_abs = e.abs().to_biguint().unwrap();
+
+            Ok(a_inv.modpow(&e_abs, n))
         }
         Sign::NoSign => {
             // zero
-            Some(BigUint::one())
+            Ok(BigUint::one())
         }
     }
 }
@@ -66,54 +82,454 @@ pub fn shamir_trick(
     // a, b <- Bezout(x, y)
     let (_, a, b) = ExtendedGcd::extended_gcd(x, y);
 
-    let l = modpow_uint_int(&root_x, &b, n);
-    let r = modpow_uint_int(&root_y, &a, n);
+    let l = modpow_uint_int(root_x, &b, n).ok()?;
+    let r = modpow_uint_int(root_y, &a, n).ok()?;
 
-    if let Some(l) = l {
-        if let Some(r) = r {
-            return Some((l * r).mod_floor(n));
+    Some((l * r).mod_floor(n))
+}
+
+/// Below this many factors, products are calculated on the current thread.
+#[cfg(feature = "parallel")]
+const PARALLEL_PRODUCT_THRESHOLD: usize = 64;
+
+/// Calculates `\prod xs`, using a balanced product tree.
+///
+/// This keeps the operands of every multiplication at similar sizes, which is much
+/// faster than a sequential product for many factors. With the `parallel` feature
+/// enabled, the subtrees are multiplied on multiple threads.
+pub fn product(xs: &[BigUint]) -> BigUint {
+    match xs.len() {
+        0 => BigUint::one(),
+        1 => xs[0].clone(),
+        2 => &xs[0] * &xs[1],
+        m => {
+            let (l, r) = xs.split_at(m / 2);
+            let (l, r) = join_products(l, r);
+            l * r
         }
     }
+}
 
-    None
+/// Splits `xs` into chunks of at most `chunk_size` elements, yielding the length and the
+/// `product` of each chunk. Only one chunk is held at a time.
+pub fn chunk_products<I>(xs: I, chunk_size: usize) -> impl Iterator<Item = (usize, BigUint)>
+where
+    I: IntoIterator,
+    I::Item: Borrow<BigUint>,
+{
+    let mut xs = xs.into_iter();
+    std::iter::from_fn(move || {
+        let chunk = xs
+            .by_ref()
+            .take(chunk_size.max(1))
+            .map(|x| x.borrow().clone())
+            .collect::<Vec<_>>();
+        if chunk.is_empty() {
+            return None;
+        }
+
+        Some((chunk.len(), product(&chunk)))
+    })
+}
+
+#[cfg(feature = "parallel")]
+fn join_products(l: &[BigUint], r: &[BigUint]) -> (BigUint, BigUint) {
+    if l.len() + r.len() < PARALLEL_PRODUCT_THRESHOLD {
+        (product(l), product(r))
+    } else {
+        rayon::join(|| product(l), || product(r))
+    }
+}
+
+#[cfg(not(feature = "parallel"))]
+fn join_products(l: &[BigUint], r: &[BigUint]) -> (BigUint, BigUint) {
+    (product(l), product(r))
 }
 
 /// Given `y = g^x` and `x = \prod x_i`, calculates the `x_i`-th roots, for all `i`.
 /// All operations are `mod n`.
 pub fn root_factor(g: &BigUint, x: &[BigUint], n: &BigUint) -> Vec<BigUint> {
-    let m = x.len();
-    if m == 1 {
-        return vec![g.clone()];
+    ProductTree::new(x).root_factor(g, n)
+}
+
+/// Calculates `g^{\prod_{j \ne i} x_j}`, without dividing the full product by `x_i`.
+/// Panics if `i` is out of range.
+pub fn cofactor_pow(g: &BigUint, x: &[BigUint], i: usize, n: &BigUint) -> BigUint {
+    ProductTree::new(x).cofactor_pow(g, i, n)
+}
+
+/// The products of all subtrees of a balanced binary tree over `x_0, .., x_{m-1}`.
+///
+/// Every inner node splits its range in half, like `product`, and stores the product of it.
+/// Building the tree costs about as much as `product`, afterwards products of ranges and
+/// cofactors `\prod_{j \ne i} x_j` only need a few multiplications of stored products.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductTree {
+    /// The nodes, parents before their children, so the root is the first one.
+    nodes: Vec<ProductNode>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ProductNode {
+    product: BigUint,
+    /// The range of leaves below this node.
+    start: usize,
+    end: usize,
+    /// The indices of the children of inner nodes.
+    children: Option<(usize, usize)>,
+}
+
+impl ProductTree {
+    pub fn new(x: &[BigUint]) -> Self {
+        let mut nodes = Vec::with_capacity(2 * x.len());
+        if !x.is_empty() {
+            build_product_node(x, 0, &mut nodes);
+        }
+
+        ProductTree { nodes }
+    }
+
+    /// The number of leaves.
+    pub fn len(&self) -> usize {
+        self.nodes.first().map_or(0, |root| root.end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns `x_i`.
+    /// Panics if `i` is out of range.
+    pub fn leaf(&self, i: usize) -> &BigUint {
+        assert!(i < self.len(), "index out of range");
+
+        let mut node = &self.nodes[0];
+        while let Some((l, r)) = node.children {
+            node = if i < self.nodes[l].end {
+                &self.nodes[l]
+            } else {
+                &self.nodes[r]
+            };
+        }
+
+        &node.product
+    }
+
+    /// Calculates `\prod x_i`, which is `1` for no leaves.
+    pub fn root(&self) -> BigUint {
+        self.nodes
+            .first()
+            .map_or_else(BigUint::one, |root| root.product.clone())
     }
 
-    let m_prime = m.div_floor(&2);
+    /// Calculates `\prod_{i \in range} x_i`, from the products of the subtrees covering it.
+    /// Panics if `range` is out of bounds.
+    pub fn range_product(&self, range: Range<usize>) -> BigUint {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "range out of bounds"
+        );
+        if range.start == range.end {
+            return BigUint::one();
+        }
 
-    let (x_l, x_r) = x.split_at(m_prime);
+        self.range_product_at(0, &range)
+    }
 
-    let g_l = {
-        let mut p = BigUint::one();
-        // the paper uses the upper part for g_L
-        for x in x_r {
-            p *= x;
+    fn range_product_at(&self, i: usize, range: &Range<usize>) -> BigUint {
+        let node = &self.nodes[i];
+        if range.start <= node.start && node.end <= range.end {
+            return node.product.clone();
         }
 
-        g.modpow(&p, n)
-    };
+        let (l, r) = node.children.expect("leaves are covered or disjoint");
+        let mid = self.nodes[l].end;
+        if range.end <= mid {
+            self.range_product_at(l, range)
+        } else if range.start >= mid {
+            self.range_product_at(r, range)
+        } else {
+            self.range_product_at(l, range) * self.range_product_at(r, range)
+        }
+    }
 
-    let g_r = {
-        let mut p = BigUint::one();
-        // the paper uses the lower part for g_R
-        for x in x_l {
-            p *= x;
+    /// Calculates `\prod_{j \ne i} x_j`, from the products of the siblings on the path to `x_i`.
+    /// Panics if `i` is out of range.
+    pub fn cofactor(&self, i: usize) -> BigUint {
+        let mut res = BigUint::one();
+        self.walk_to(i, |sibling| res *= sibling);
+        res
+    }
+
+    /// Calculates `g^{\prod_{j \ne i} x_j} mod n`, exponentiating by the products of the
+    /// siblings on the path to `x_i`, which avoids multiplying them first.
+    /// Panics if `i` is out of range.
+    pub fn cofactor_pow(&self, g: &BigUint, i: usize, n: &BigUint) -> BigUint {
+        let mut res = g % n;
+        self.walk_to(i, |sibling| res = res.modpow(sibling, n));
+        res
+    }
+
+    /// Calls `f` with the products of the siblings on the path from the root to `x_i`.
+    fn walk_to<F: FnMut(&BigUint)>(&self, i: usize, mut f: F) {
+        assert!(i < self.len(), "index out of range");
+
+        let mut node = &self.nodes[0];
+        while let Some((l, r)) = node.children {
+            let (next, sibling) = if i < self.nodes[l].end {
+                (l, r)
+            } else {
+                (r, l)
+            };
+            f(&self.nodes[sibling].product);
+            node = &self.nodes[next];
         }
+    }
 
-        g.modpow(&p, n)
-    };
+    /// Given `y = g^{\prod x_i}`, calculates the `x_i`-th roots `g^{\prod_{j \ne i} x_j}`, for
+    /// all `i`. All operations are `mod n`.
+    pub fn root_factor(&self, g: &BigUint, n: &BigUint) -> Vec<BigUint> {
+        let mut res = Vec::with_capacity(self.len());
+        if !self.is_empty() {
+            self.root_factor_at(0, g.clone(), n, &mut res);
+        }
 
-    let mut res = root_factor(&g_l, x_l, n);
-    res.extend(root_factor(&g_r, x_r, n));
+        res
+    }
 
-    res
+    fn root_factor_at(&self, i: usize, g: BigUint, n: &BigUint, res: &mut Vec<BigUint>) {
+        match self.nodes[i].children {
+            None => res.push(g),
+            Some((l, r)) => {
+                // the paper uses the upper part for g_L, and the lower part for g_R
+                let g_l = g.modpow(&self.nodes[r].product, n);
+                let g_r = g.modpow(&self.nodes[l].product, n);
+
+                self.root_factor_at(l, g_l, n, res);
+                self.root_factor_at(r, g_r, n, res);
+            }
+        }
+    }
+}
+
+/// Appends the subtree over `x`, whose first leaf is `start`, returning the index of its root.
+fn build_product_node(x: &[BigUint], start: usize, nodes: &mut Vec<ProductNode>) -> usize {
+    let i = nodes.len();
+    nodes.push(ProductNode {
+        product: BigUint::one(),
+        start,
+        end: start + x.len(),
+        children: None,
+    });
+
+    if x.len() == 1 {
+        nodes[i].product = x[0].clone();
+    } else {
+        let (x_l, x_r) = x.split_at(x.len() / 2);
+        let l = build_product_node(x_l, start, nodes);
+        let r = build_product_node(x_r, start + x_l.len(), nodes);
+        nodes[i].product = &nodes[l].product * &nodes[r].product;
+        nodes[i].children = Some((l, r));
+    }
+
+    i
+}
+
+/// The Jacobi symbol `(a | n)`, for odd `n`.
+/// It is `1` for all quadratic residues `mod n`, but also for some non residues.
+///
+/// Panics if `n` is even.
+pub fn jacobi(a: &BigUint, n: &BigUint) -> i8 {
+    assert!(n.is_odd(), "the modulus must be odd");
+
+    let mut a = a % n;
+    let mut n = n.clone();
+    let mut res = 1;
+    while !a.is_zero() {
+        // (2 | n) = -1 iff n = 3, 5 mod 8
+        let n_mod_8 = (&n % 8u32).to_u32().unwrap();
+        while a.is_even() {
+            a >>= 1;
+            if n_mod_8 == 3 || n_mod_8 == 5 {
+                res = -res;
+            }
+        }
+
+        // quadratic reciprocity
+        std::mem::swap(&mut a, &mut n);
+        if (&a % 4u32).to_u32() == Some(3) && (&n % 4u32).to_u32() == Some(3) {
+            res = -res;
+        }
+        a %= &n;
+    }
+
+    if n.is_one() {
+        res
+    } else {
+        0
+    }
+}
+
+/// Calculates `\prod b_i^{e_i} mod n`, for `terms` `(b_i, e_i)`.
+///
+/// All terms share the squarings, and with sliding windows every base only costs about
+/// one multiplication per 6 bits of its exponent, so this is much cheaper than separate
+/// `modpow`s for many short exponents.
+pub fn multi_pow(terms: &[(&BigUint, &BigUint)], n: &BigUint) -> BigUint {
+    // b, b^3, .., b^31 for every base
+    let tables: Vec<Vec<BigUint>> = terms
+        .iter()
+        .map(|(b, _)| {
+            let b = *b % n;
+            let b2 = (&b * &b) % n;
+            let mut table = vec![b];
+            for i in 1..1 << (MULTI_POW_WINDOW - 1) {
+                let next = (&table[i - 1] * &b2) % n;
+                table.push(next);
+            }
+            table
+        })
+        .collect();
+    let digits: Vec<Vec<u8>> = terms
+        .iter()
+        .map(|(_, e)| sliding_window_digits(e, MULTI_POW_WINDOW))
+        .collect();
+    let bits = digits.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut acc = BigUint::one() % n;
+    for i in (0..bits).rev() {
+        acc = (&acc * &acc) % n;
+        for (table, d) in tables.iter().zip(&digits) {
+            match d.get(i) {
+                Some(&digit) if digit != 0 => {
+                    acc = (acc * &table[digit as usize / 2]) % n;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    acc
+}
+
+/// Powers `g^{2^{i k}}` of a fixed base, to split one long exponentiation into short ones.
+///
+/// With `e = \sum e_i 2^{i k}` for `k` bit digits `e_i`, `g^e = \prod (g^{2^{i k}})^{e_i}`, and
+/// the digits are exponentiated independently, on all cores with the `parallel` feature.
+/// Building the table costs about one exponentiation of its full size, so it pays off for
+/// repeated exponentiations of the same generator by the set product, like deletions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerTable {
+    n: BigUint,
+    /// `k`, the size of the digits.
+    digit_bits: usize,
+    /// `g^{2^{i k}}` for every digit `i`.
+    powers: Vec<BigUint>,
+}
+
+impl PowerTable {
+    /// Precomputes the powers of `g` to split exponents of up to `max_bits` bits into `digits`
+    /// digits, e.g. one per core.
+    /// Panics if `digits` is zero.
+    pub fn new(g: &BigUint, n: &BigUint, max_bits: usize, digits: usize) -> Self {
+        assert!(digits > 0, "at least one digit");
+
+        let digit_bits = ((max_bits + digits - 1) / digits).max(1);
+        let step = BigUint::one() << digit_bits;
+        let mut powers = vec![g % n];
+        for i in 1..digits {
+            let next = powers[i - 1].modpow(&step, n);
+            powers.push(next);
+        }
+
+        PowerTable {
+            n: n.clone(),
+            digit_bits,
+            powers,
+        }
+    }
+
+    pub fn base(&self) -> &BigUint {
+        &self.powers[0]
+    }
+
+    pub fn modulus(&self) -> &BigUint {
+        &self.n
+    }
+
+    /// The size of exponents which are split evenly, the last digit of longer ones is longer.
+    pub fn max_bits(&self) -> usize {
+        self.digit_bits * self.powers.len()
+    }
+
+    /// Calculates `g^e mod n`.
+    pub fn pow(&self, e: &BigUint) -> BigUint {
+        let mask = (BigUint::one() << self.digit_bits) - 1u32;
+        let last = self.powers.len() - 1;
+        let digits = (0..self.powers.len())
+            .map(|i| {
+                let d = e >> (i * self.digit_bits);
+                if i == last {
+                    d
+                } else {
+                    d & &mask
+                }
+            })
+            .collect::<Vec<_>>();
+
+        modpow_all(&self.powers, &digits, &self.n)
+            .iter()
+            .fold(BigUint::one() % &self.n, |acc, p| (acc * p) % &self.n)
+    }
+}
+
+/// Returns `bases[i]^exps[i] mod n`, for all `i`.
+#[cfg(feature = "parallel")]
+fn modpow_all(bases: &[BigUint], exps: &[BigUint], n: &BigUint) -> Vec<BigUint> {
+    bases
+        .par_iter()
+        .zip(exps.par_iter())
+        .map(|(b, e)| b.modpow(e, n))
+        .collect()
+}
+
+/// Returns `bases[i]^exps[i] mod n`, for all `i`.
+#[cfg(not(feature = "parallel"))]
+fn modpow_all(bases: &[BigUint], exps: &[BigUint], n: &BigUint) -> Vec<BigUint> {
+    bases
+        .iter()
+        .zip(exps.iter())
+        .map(|(b, e)| b.modpow(e, n))
+        .collect()
+}
+
+/// The window size of `multi_pow`.
+const MULTI_POW_WINDOW: usize = 5;
+
+/// Splits `e` into odd digits of at most `width` bits, so that `e = \sum d_i 2^i`.
+/// Returns `d_i` for every bit `i`, most of them are `0`.
+fn sliding_window_digits(e: &BigUint, width: usize) -> Vec<u8> {
+    let bytes = e.to_bytes_le();
+    let bit = |i: usize| (bytes[i / 8] >> (i % 8)) & 1;
+    let mut digits = vec![0u8; e.bits()];
+
+    let mut i = digits.len();
+    while i > 0 {
+        i -= 1;
+        if bit(i) == 0 {
+            continue;
+        }
+
+        // the window ends at its lowest set bit, so the digit is odd
+        let mut low = i.saturating_sub(width - 1);
+        while bit(low) == 0 {
+            low += 1;
+        }
+        digits[low] = (low..=i).rev().fold(0, |d, j| (d << 1) | bit(j));
+        i = low;
+    }
+
+    digits
 }
 
 #[cfg(test)]
@@ -166,6 +582,12 @@ mod tests {
 
             assert_eq!(expected, actual);
         }
+
+        // 2881 = 43 * 67, so 43 has no inverse
+        let a = BigUint::from_u32(43).unwrap();
+        let e = BigInt::from_i64(-3).unwrap();
+        let n = BigUint::from_u32(2881).unwrap();
+        assert_eq!(modpow_uint_int(&a, &e, &n), Err(Errors::NotInvertible));
     }
 
     #[test]
@@ -195,6 +617,73 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cofactor_pow() {
+        let mut rng = thread_rng();
+
+        for m in 1..40 {
+            let n = rng.gen_biguint(64) | BigUint::one();
+            let g = rng.gen_biguint(64);
+            let x = (0..m).map(|_| rng.gen_biguint(64)).collect::<Vec<_>>();
+
+            for i in 0..m {
+                let mut others = x.clone();
+                others.remove(i);
+                assert_eq!(cofactor_pow(&g, &x, i, &n), g.modpow(&product(&others), &n));
+            }
+        }
+    }
+
+    #[test]
+    fn test_product_tree() {
+        let mut rng = thread_rng();
+        let n = rng.gen_biguint(64) | BigUint::one();
+        let g = rng.gen_biguint_below(&n);
+
+        let empty = ProductTree::new(&[]);
+        assert!(empty.is_empty());
+        assert_eq!(empty.root(), BigUint::one());
+        assert!(empty.root_factor(&g, &n).is_empty());
+
+        for m in 1..24 {
+            let x = (0..m).map(|_| rng.gen_biguint(64)).collect::<Vec<_>>();
+            let tree = ProductTree::new(&x);
+            assert_eq!(tree.len(), m);
+            assert_eq!(tree.root(), product(&x));
+
+            for start in 0..=m {
+                for end in start..=m {
+                    assert_eq!(tree.range_product(start..end), product(&x[start..end]));
+                }
+            }
+
+            let ws = tree.root_factor(&g, &n);
+            for i in 0..m {
+                assert_eq!(tree.leaf(i), &x[i]);
+                let mut others = x.clone();
+                others.remove(i);
+                assert_eq!(tree.cofactor(i), product(&others));
+                assert_eq!(ws[i], g.modpow(&product(&others), &n));
+            }
+        }
+    }
+
+    #[test]
+    fn test_product() {
+        let mut rng = thread_rng();
+
+        for m in 0..300 {
+            let xs = (0..m).map(|_| rng.gen_biguint(128)).collect::<Vec<_>>();
+
+            let mut expected = BigUint::one();
+            for x in &xs {
+                expected *= x;
+            }
+
+            assert_eq!(product(&xs), expected);
+        }
+    }
+
     #[test]
     fn test_shamir_trick() {
         let mut rng = thread_rng();
@@ -232,4 +721,80 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_jacobi() {
+        let mut rng = thread_rng();
+
+        // Euler's criterion for primes
+        for _ in 0..10 {
+            let p = rng.gen_prime(64);
+            let a = rng.gen_biguint_below(&p);
+            let euler = a.modpow(&((&p - 1u32) >> 1), &p);
+            let expected = if a.is_zero() {
+                0
+            } else if euler.is_one() {
+                1
+            } else {
+                -1
+            };
+            assert_eq!(jacobi(&a, &p), expected);
+        }
+
+        // multiplicative in the modulus
+        for _ in 0..10 {
+            let p = rng.gen_prime(64);
+            let q = rng.gen_prime(64);
+            let a = rng.gen_biguint(128);
+            assert_eq!(jacobi(&a, &(&p * &q)), jacobi(&a, &p) * jacobi(&a, &q));
+        }
+
+        let n = BigUint::from(15u32);
+        assert_eq!(jacobi(&BigUint::from(2u32), &n), 1);
+        assert_eq!(jacobi(&BigUint::from(7u32), &n), -1);
+        assert_eq!(jacobi(&BigUint::from(10u32), &n), 0);
+        assert_eq!(jacobi(&BigUint::one(), &BigUint::one()), 1);
+    }
+
+    #[test]
+    fn test_multi_pow() {
+        let mut rng = thread_rng();
+
+        for i in 1..5 {
+            let n = rng.gen_biguint(i * 256) | BigUint::one();
+            let xs = (0..16)
+                .map(|_| rng.gen_biguint(i * 256 + 8))
+                .collect::<Vec<_>>();
+            let es = (0..16)
+                .map(|j| rng.gen_biguint(j * 24 + 1))
+                .collect::<Vec<_>>();
+
+            let mut expected = BigUint::one();
+            for (x, e) in xs.iter().zip(&es) {
+                expected = (expected * x.modpow(e, &n)) % &n;
+            }
+            let terms = xs.iter().zip(&es).collect::<Vec<_>>();
+            assert_eq!(multi_pow(&terms, &n), expected);
+            assert_eq!(multi_pow(&[], &n), BigUint::one() % &n);
+        }
+    }
+
+    #[test]
+    fn test_power_table() {
+        let mut rng = thread_rng();
+        let n = rng.gen_biguint(256) | BigUint::one();
+        let g = rng.gen_biguint(256);
+
+        for digits in 1..6 {
+            let table = PowerTable::new(&g, &n, 1000, digits);
+            assert!(table.max_bits() >= 1000);
+            assert_eq!(table.base(), &(&g % &n));
+
+            // shorter, exact and longer exponents than the table was built for
+            for bits in &[1, 200, 1000, 1500] {
+                let e = rng.gen_biguint(*bits);
+                assert_eq!(table.pow(&e), g.modpow(&e, &n));
+            }
+            assert_eq!(table.pow(&BigUint::zero()), BigUint::one());
+        }
+    }
 }
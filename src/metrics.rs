@@ -0,0 +1,159 @@
+//! Counters and timings of the accumulator hot paths, for monitoring production services.
+//!
+//! A [`Recorder`] is registered per accumulator with `Accumulator::set_recorder`, clones of
+//! the accumulator report to the same recorder. [`MetricsCrate`] forwards everything to the
+//! recorder installed globally for the `metrics` crate.
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Counter: modular exponentiations of the state and the witnesses, not counting proofs.
+pub const MODPOW: &str = "accumulator.modpow";
+/// Counter: elements added.
+pub const ELEMENTS_ADDED: &str = "accumulator.elements_added";
+/// Counter: elements deleted.
+pub const ELEMENTS_DELETED: &str = "accumulator.elements_deleted";
+/// Histogram: duration of `batch_add`, including the proof.
+pub const BATCH_ADD_SECONDS: &str = "accumulator.batch_add_seconds";
+/// Histogram: duration of `batch_del` and `batch_del_tracked`, including the proof.
+pub const BATCH_DEL_SECONDS: &str = "accumulator.batch_del_seconds";
+/// Histogram: duration of `create_all_mem_wit`.
+pub const CREATE_ALL_MEM_WIT_SECONDS: &str = "accumulator.create_all_mem_wit_seconds";
+/// Histogram: duration of proving a batch change.
+pub const PROVE_SECONDS: &str = "accumulator.prove_seconds";
+/// Histogram: size of the NI-PoE of `batch_add` and `batch_del`, in bytes.
+pub const PROOF_BYTES: &str = "accumulator.proof_bytes";
+
+/// Receives the measurements of an accumulator.
+pub trait Recorder: Send + Sync {
+    fn increment_counter(&self, name: &'static str, value: u64);
+    fn record_histogram(&self, name: &'static str, value: f64);
+}
+
+/// Forwards all measurements to the `metrics` crate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsCrate;
+
+impl Recorder for MetricsCrate {
+    fn increment_counter(&self, name: &'static str, value: u64) {
+        libmetrics::counter!(name).increment(value);
+    }
+
+    fn record_histogram(&self, name: &'static str, value: f64) {
+        libmetrics::histogram!(name).record(value);
+    }
+}
+
+/// The recorder of an accumulator, if any.
+/// Clones share the same recorder.
+#[derive(Clone, Default)]
+pub(crate) struct Metrics(Option<Arc<dyn Recorder>>);
+
+impl Metrics {
+    pub fn set(&mut self, recorder: Option<Arc<dyn Recorder>>) {
+        self.0 = recorder;
+    }
+
+    pub fn counter(&self, name: &'static str, value: u64) {
+        if let Some(recorder) = &self.0 {
+            recorder.increment_counter(name, value);
+        }
+    }
+
+    pub fn histogram(&self, name: &'static str, value: f64) {
+        if let Some(recorder) = &self.0 {
+            recorder.record_histogram(name, value);
+        }
+    }
+
+    /// Records the time since `start`, in seconds.
+    pub fn elapsed(&self, name: &'static str, start: Instant) {
+        let elapsed = start.elapsed();
+        self.histogram(
+            name,
+            elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9,
+        );
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(_) => write!(f, "Metrics(recording)"),
+            None => write!(f, "Metrics(off)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[derive(Default)]
+    struct Collect {
+        counters: Mutex<HashMap<&'static str, u64>>,
+        histograms: Mutex<HashMap<&'static str, Vec<f64>>>,
+    }
+
+    impl Recorder for Arc<Collect> {
+        fn increment_counter(&self, name: &'static str, value: u64) {
+            *self.counters.lock().unwrap().entry(name).or_insert(0) += value;
+        }
+
+        fn record_histogram(&self, name: &'static str, value: f64) {
+            let mut histograms = self.histograms.lock().unwrap();
+            histograms.entry(name).or_insert_with(Vec::new).push(value);
+        }
+    }
+
+    #[test]
+    fn test_metrics() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let collect = Arc::new(Collect::default());
+        acc.set_recorder(collect.clone());
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0]);
+        acc.batch_add(&xs[1..]);
+        let ws = acc.create_all_mem_wit(&xs);
+        let pairs = vec![
+            (xs[1].clone(), ws[1].clone()),
+            (xs[2].clone(), ws[2].clone()),
+            (xs[3].clone(), ws[3].clone()),
+        ];
+        acc.batch_del(&pairs).unwrap();
+        acc.del(&xs[4]).unwrap();
+
+        let counters = collect.counters.lock().unwrap().clone();
+        assert_eq!(counters[ELEMENTS_ADDED], 8);
+        assert_eq!(counters[ELEMENTS_DELETED], 4);
+        // add, batch_add, 7 inner nodes of 8 leaves, 2 shamir tricks and the check, del
+        assert_eq!(counters[MODPOW], 1 + 1 + 2 * 7 + 4 * 2 + 1 + 1);
+
+        let histograms = collect.histograms.lock().unwrap().clone();
+        assert_eq!(histograms[BATCH_ADD_SECONDS].len(), 1);
+        assert_eq!(histograms[BATCH_DEL_SECONDS].len(), 1);
+        assert_eq!(histograms[CREATE_ALL_MEM_WIT_SECONDS].len(), 1);
+        assert_eq!(histograms[PROVE_SECONDS].len(), 2);
+        assert!(histograms[PROOF_BYTES].iter().all(|b| *b > 0.0));
+
+        acc.clear_recorder();
+        acc.add(&xs[4]);
+        assert_eq!(collect.counters.lock().unwrap()[ELEMENTS_ADDED], 8);
+    }
+}
@@ -0,0 +1,273 @@
+//! A log of every mutation of an accumulator, for deterministic replay.
+//!
+//! An [`OpLog`] is attached as an observer right after the setup. Replaying it on a fresh
+//! accumulator reproduces the exact same root at every epoch, so replicas can be driven by
+//! the same log, e.g. in a replicated state machine. When replicas diverge anyway,
+//! [`OpLog::diverges_at`] finds the first operation their logs disagree on.
+
+use num_bigint::BigUint;
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::error::Errors;
+use crate::hooks::{Mutation, MutationKind};
+use crate::traits::*;
+
+/// A single logged mutation.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Op {
+    pub epoch: u64,
+    pub kind: MutationKind,
+    /// The elements that were added or deleted.
+    pub elements: Vec<BigUint>,
+    /// The root after the operation.
+    pub root: BigUint,
+}
+
+/// All mutations of an accumulator since its setup, in order.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpLog {
+    params: PublicParams,
+    ops: Vec<Op>,
+}
+
+impl OpLog {
+    pub fn new(params: PublicParams) -> Self {
+        OpLog {
+            params,
+            ops: Vec::new(),
+        }
+    }
+
+    pub fn params(&self) -> &PublicParams {
+        &self.params
+    }
+
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// The epoch after the last operation.
+    pub fn epoch(&self) -> u64 {
+        self.ops.last().map_or(0, |op| op.epoch)
+    }
+
+    /// The root after the last operation.
+    pub fn root(&self) -> &BigUint {
+        self.ops.last().map_or(&self.params.g, |op| &op.root)
+    }
+
+    /// Records a change reported by an accumulator observer.
+    /// Fails with `OutOfSync` if the log wasn't attached right after the setup.
+    pub fn record(&mut self, mutation: &Mutation<'_>) -> Result<(), Errors> {
+        if mutation.old_root != self.root() {
+            return Err(Errors::OutOfSync);
+        }
+
+        self.ops.push(Op {
+            epoch: self.epoch() + 1,
+            kind: mutation.kind,
+            elements: mutation.elements.to_vec(),
+            root: mutation.new_root.clone(),
+        });
+
+        Ok(())
+    }
+
+    /// Drops all operations after `epoch`, e.g. after an `Accumulator::rollback` to it.
+    /// Fails with `UnknownEpoch` if `epoch` wasn't reached yet.
+    pub fn truncate(&mut self, epoch: u64) -> Result<(), Errors> {
+        if epoch > self.epoch() {
+            return Err(Errors::UnknownEpoch(epoch));
+        }
+
+        self.ops.truncate(epoch as usize);
+        Ok(())
+    }
+
+    /// Replays all operations on a fresh accumulator, see `replay_to`.
+    pub fn replay(&self) -> Result<Accumulator, Errors> {
+        self.replay_to(self.epoch())
+    }
+
+    /// Replays the operations up to and including `epoch` on a fresh accumulator.
+    ///
+    /// Fails with `InvalidDelta(epoch)` if an operation can't be applied, and with
+    /// `Diverged(epoch)` if it doesn't reproduce the logged root.
+    pub fn replay_to(&self, epoch: u64) -> Result<Accumulator, Errors> {
+        if epoch > self.epoch() {
+            return Err(Errors::UnknownEpoch(epoch));
+        }
+
+        let mut acc = Accumulator::new_with_params(&self.params);
+        for op in self.ops.iter().take_while(|op| op.epoch <= epoch) {
+            apply(&mut acc, op)?;
+
+            if acc.epoch() != op.epoch {
+                return Err(Errors::InvalidDelta(op.epoch));
+            }
+            if acc.state() != &op.root {
+                return Err(Errors::Diverged(op.epoch));
+            }
+        }
+
+        Ok(acc)
+    }
+
+    /// Returns the epoch of the first operation that differs between both logs, `0` if they
+    /// use different parameters, or `None` if one log is a prefix of the other.
+    pub fn diverges_at(&self, other: &OpLog) -> Option<u64> {
+        if self.params != other.params {
+            return Some(0);
+        }
+
+        self.ops
+            .iter()
+            .zip(other.ops.iter())
+            .find(|(a, b)| a != b)
+            .map(|(a, _)| a.epoch)
+    }
+}
+
+/// Applies a single operation, the same way the accumulator originally did.
+fn apply(acc: &mut Accumulator, op: &Op) -> Result<(), Errors> {
+    let invalid = Errors::InvalidDelta(op.epoch);
+
+    match op.kind {
+        MutationKind::Add | MutationKind::Del | MutationKind::DelWithMem
+            if op.elements.len() != 1 =>
+        {
+            return Err(invalid);
+        }
+        MutationKind::Add => acc.add(&op.elements[0]),
+        MutationKind::Del | MutationKind::DelWithMem => {
+            acc.del(&op.elements[0]).ok_or(invalid)?;
+        }
+        MutationKind::BatchAdd => {
+            acc.batch_add(&op.elements);
+        }
+        MutationKind::BatchDel => {
+            acc.batch_del_tracked(&op.elements).map_err(|_| invalid)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::{Arc, Mutex};
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_oplog_replay() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let log = Arc::new(Mutex::new(OpLog::new(params.clone())));
+        let observer = log.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0]);
+        acc.batch_add(&xs[1..6]);
+        let root_2 = acc.state().clone();
+        acc.del(&xs[2]).unwrap();
+        let ws = acc.mem_wit_create_batch(&xs[3..5]).unwrap();
+        let pairs = vec![
+            (xs[3].clone(), ws[0].clone()),
+            (xs[4].clone(), ws[1].clone()),
+        ];
+        acc.batch_del(&pairs).unwrap();
+        let w = acc.mem_wit_create(&xs[5]);
+        acc.del_w_mem(&w, &xs[5]).unwrap();
+        acc.add(&xs[6]);
+
+        let log = log.lock().unwrap().clone();
+        assert_eq!(log.len(), 6);
+        assert_eq!(log.epoch(), acc.epoch());
+
+        let replayed = log.replay().unwrap();
+        assert_eq!(replayed.state(), acc.state());
+        assert_eq!(replayed.epoch(), acc.epoch());
+        assert_eq!(replayed.len(), acc.len());
+        assert_eq!(log.replay_to(2).unwrap().state(), &root_2);
+        assert_eq!(log.replay_to(7).unwrap_err(), Errors::UnknownEpoch(7));
+
+        // a replica that deleted a different element at epoch 3
+        let mut other = log.clone();
+        other.ops[2].elements = vec![xs[7].clone()];
+        assert_eq!(other.replay().unwrap_err(), Errors::InvalidDelta(3));
+        assert_eq!(log.diverges_at(&other), Some(3));
+        assert_eq!(log.diverges_at(&log), None);
+
+        let mut other = log.clone();
+        other.ops[4].root = root_2;
+        assert_eq!(other.replay().unwrap_err(), Errors::Diverged(5));
+
+        // attached too late
+        let mut late = OpLog::new(params);
+        let root = acc.state().clone();
+        acc.add(&xs[7]);
+        let mutation = Mutation {
+            kind: MutationKind::Add,
+            elements: &xs[7..],
+            old_root: &root,
+            new_root: acc.state(),
+        };
+        assert_eq!(late.record(&mutation).unwrap_err(), Errors::OutOfSync);
+    }
+
+    #[test]
+    fn test_oplog_truncate() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+        acc.enable_undo(4);
+
+        let log = Arc::new(Mutex::new(OpLog::new(params)));
+        let observer = log.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..4)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0]);
+        acc.batch_add(&xs[1..3]);
+        acc.del(&xs[1]).unwrap();
+
+        // the log follows the rollback, and keeps recording afterwards
+        acc.rollback(1).unwrap();
+        {
+            let mut log = log.lock().unwrap();
+            assert_eq!(log.truncate(4), Err(Errors::UnknownEpoch(4)));
+            log.truncate(1).unwrap();
+            assert_eq!(log.len(), 1);
+        }
+
+        acc.add(&xs[3]);
+        let log = log.lock().unwrap().clone();
+        assert_eq!(log.epoch(), acc.epoch());
+        assert_eq!(log.replay().unwrap().state(), acc.state());
+    }
+}
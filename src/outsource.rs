@@ -0,0 +1,152 @@
+//! Outsourcing the computation of membership witnesses to an untrusted helper.
+//!
+//! A witness `w_i = g^{X / x_i}` with `X = \prod S` needs an exponentiation by almost the
+//! whole set. A low-power holder who knows the set sends a [`WitnessRequest`] to a helper,
+//! which returns every witness with a NI-PoE of `g^{X / x_i} = w_i`. Checking a proof costs
+//! two exponentiations by `HASH_PRIME_BITS` bits, independent of the size of the set, and
+//! unlike `ver_mem` it doesn't require trusting the current root.
+
+use num_bigint::BigUint;
+
+use crate::accumulator::PublicParams;
+use crate::error::Errors;
+use crate::math::{product, root_factor};
+use crate::proofs::{ni_poe_prove, ni_poe_verify, ExponentProof};
+
+/// A request for the membership witnesses of some elements of a set.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitnessRequest {
+    pub params: PublicParams,
+    /// All elements of the set.
+    pub elements: Vec<BigUint>,
+    /// The positions of the elements to create witnesses for.
+    pub indices: Vec<usize>,
+}
+
+/// The witnesses created by the helper, in the order of the request, with their proofs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitnessResponse {
+    pub witnesses: Vec<(BigUint, ExponentProof)>,
+}
+
+impl WitnessRequest {
+    /// Requests the witnesses of all elements.
+    pub fn all(params: &PublicParams, elements: Vec<BigUint>) -> Self {
+        let indices = (0..elements.len()).collect();
+        WitnessRequest::for_indices(params, elements, indices)
+    }
+
+    /// Requests the witnesses of the elements at `indices`.
+    ///
+    /// Panics if an index is out of range.
+    pub fn for_indices(params: &PublicParams, elements: Vec<BigUint>, indices: Vec<usize>) -> Self {
+        assert!(
+            indices.iter().all(|i| *i < elements.len()),
+            "index out of range"
+        );
+
+        WitnessRequest {
+            params: params.clone(),
+            elements,
+            indices,
+        }
+    }
+
+    /// Computes the witnesses and their proofs, on the helper.
+    pub fn respond(&self) -> WitnessResponse {
+        let n = &self.params.n;
+        let g = &self.params.g;
+        let set = product(&self.elements);
+
+        // all witnesses at once, when most of them are requested
+        let witnesses = if 2 * self.indices.len() > self.elements.len() {
+            let all = root_factor(g, &self.elements, n);
+            self.indices.iter().map(|i| all[*i].clone()).collect()
+        } else {
+            self.indices
+                .iter()
+                .map(|i| g.modpow(&(&set / &self.elements[*i]), n))
+                .collect::<Vec<_>>()
+        };
+
+        let witnesses = self
+            .indices
+            .iter()
+            .zip(witnesses)
+            .map(|(i, w)| {
+                let proof = ni_poe_prove(&(&set / &self.elements[*i]), g, &w, n);
+                (w, proof)
+            })
+            .collect();
+
+        WitnessResponse { witnesses }
+    }
+
+    /// Checks the response of the helper, on the requester, and returns the witnesses.
+    ///
+    /// Fails with `InvalidWorkResult` if witnesses are missing, and with `InvalidProof` if any
+    /// proof doesn't verify.
+    pub fn verify(&self, response: &WitnessResponse) -> Result<Vec<BigUint>, Errors> {
+        if response.witnesses.len() != self.indices.len() {
+            return Err(Errors::InvalidWorkResult);
+        }
+
+        let n = &self.params.n;
+        let g = &self.params.g;
+        let set = product(&self.elements);
+
+        for (i, (w, proof)) in self.indices.iter().zip(response.witnesses.iter()) {
+            if !ni_poe_verify(&(&set / &self.elements[*i]), g, w, proof, n) {
+                return Err(Errors::InvalidProof);
+            }
+        }
+
+        Ok(response.witnesses.iter().map(|(w, _)| w.clone()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_outsourced_witnesses() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let expected = acc.create_all_mem_wit(&xs);
+
+        let request = WitnessRequest::all(&params, xs.clone());
+        let response = request.respond();
+        assert_eq!(request.verify(&response).unwrap(), expected);
+
+        let request = WitnessRequest::for_indices(&params, xs.clone(), vec![7, 2]);
+        let mut response = request.respond();
+        let ws = request.verify(&response).unwrap();
+        assert_eq!(ws, vec![expected[7].clone(), expected[2].clone()]);
+        assert!(acc.ver_mem(&ws[0], &xs[7]));
+
+        // a helper returning the wrong witnesses
+        response.witnesses.swap(0, 1);
+        assert_eq!(request.verify(&response), Err(Errors::InvalidProof));
+        response.witnesses[0].0 = acc.state().clone();
+        assert_eq!(request.verify(&response), Err(Errors::InvalidProof));
+        response.witnesses.pop();
+        assert_eq!(request.verify(&response), Err(Errors::InvalidWorkResult));
+    }
+}
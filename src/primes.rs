@@ -0,0 +1,88 @@
+//! A deterministic mapping from positions to primes, for vector commitments.
+//!
+//! The prime of index `i` is `hash_prime("index" || i)`, with the tag prefixed by its length and
+//! `i` encoded as `u64` big endian, so provers and verifiers address the same positions without
+//! sharing any state, and positions don't collide with hashed elements. Hashing to a prime takes
+//! dozens of primality tests, the primes of the first `CACHED_INDICES` are kept after their
+//! first use.
+
+use std::sync::Mutex;
+
+use blake2::Blake2b;
+use num_bigint::BigUint;
+
+use crate::encoding::write_part;
+use crate::hash::hash_prime;
+
+/// The number of leading indices whose primes are cached.
+pub const CACHED_INDICES: u64 = 1024;
+
+/// Prefixes the inputs of `hash_index`.
+const INDEX_TAG: &[u8] = b"index";
+
+static SMALL_PRIMES: Mutex<Vec<Option<BigUint>>> = Mutex::new(Vec::new());
+
+/// Returns the prime of position `i`.
+pub fn nth_prime_for_index(i: u64) -> BigUint {
+    if i >= CACHED_INDICES {
+        return hash_index(i);
+    }
+
+    let cached = {
+        let table = SMALL_PRIMES.lock().unwrap_or_else(|err| err.into_inner());
+        table.get(i as usize).cloned().flatten()
+    };
+    if let Some(p) = cached {
+        return p;
+    }
+
+    // hashing takes long, so other callers aren't blocked meanwhile
+    let p = hash_index(i);
+    let mut table = SMALL_PRIMES.lock().unwrap_or_else(|err| err.into_inner());
+    if table.is_empty() {
+        table.resize(CACHED_INDICES as usize, None);
+    }
+    table[i as usize] = Some(p.clone());
+
+    p
+}
+
+fn hash_index(i: u64) -> BigUint {
+    let mut to_hash = Vec::new();
+    write_part(&mut to_hash, INDEX_TAG);
+    to_hash.extend(&i.to_be_bytes());
+
+    hash_prime::<_, Blake2b>(&to_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::hash::HASH_PRIME_BITS;
+    use num_bigint::prime::probably_prime;
+
+    #[test]
+    fn test_nth_prime_for_index() {
+        let primes = (0..64).map(nth_prime_for_index).collect::<Vec<_>>();
+        for (i, p) in primes.iter().enumerate() {
+            assert_eq!(p.bits(), HASH_PRIME_BITS);
+            assert!(probably_prime(p, 20));
+            assert!(!primes[..i].contains(p));
+
+            // cached
+            assert_eq!(&nth_prime_for_index(i as u64), p);
+            assert_eq!(&hash_index(i as u64), p);
+        }
+
+        // separated from elements
+        assert_ne!(hash_index(7), hash_prime::<_, Blake2b>(&7u64.to_be_bytes()));
+
+        let i = CACHED_INDICES + 3;
+        assert_eq!(nth_prime_for_index(i), hash_index(i));
+        assert_eq!(
+            nth_prime_for_index(u64::max_value()),
+            hash_index(u64::max_value())
+        );
+    }
+}
@@ -1,8 +1,15 @@
-use crate::hash::{hash_group, hash_prime};
-use crate::math::modpow_uint_int;
+use std::borrow::Borrow;
+
+use crate::encoding::write_part;
+use crate::error::{Errors, VerifyFailure};
+use crate::group::{ModN, QrN, SignedModN};
+use crate::hash::{hash_prime, HashToPrime, HASH_PRIME_BITS};
+use crate::math::{chunk_products, sign_magnitude};
+use crate::traits::UnknownOrderGroup;
 use blake2::{Blake2b, Digest};
-use num_bigint::{BigInt, BigUint};
+use num_bigint::{BigInt, BigUint, Sign};
 use num_integer::Integer;
+use num_traits::{One, Zero};
 
 // Let G be a group of unknown order.
 // Here both the prover and verifier are given (u, w, x) and
@@ -12,24 +19,275 @@ pub type ExponentProof = BigUint;
 
 pub type KnowledgeProof = (BigUint, BigUint, BigInt);
 
+/// `(Q, r)` of a NI-PoKE*, see `ni_poke_star_prove`.
+pub type GeneratorKnowledgeProof = (BigUint, BigUint);
+
+/// A non-interactive proof system for `u^x = w`, in a group of unknown order.
+/// The batch operations of the accumulator can be parameterized over this.
+pub trait ProofOfExponentiation {
+    type Proof;
+
+    /// Assumes `u^x = w`.
+    /// All operations are `mod n`.
+    fn prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Self::Proof;
+
+    /// All operations are `mod n`.
+    fn verify(x: &BigUint, u: &BigUint, w: &BigUint, proof: &Self::Proof, n: &BigUint) -> bool;
+}
+
+/// Wesolowski's NI-PoE, using `ni_poe_prove` and `ni_poe_verify`.
+/// The proof is a single group element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Wesolowski;
+
+impl ProofOfExponentiation for Wesolowski {
+    type Proof = ExponentProof;
+
+    fn prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Self::Proof {
+        ni_poe_prove(x, u, w, n)
+    }
+
+    fn verify(x: &BigUint, u: &BigUint, w: &BigUint, proof: &Self::Proof, n: &BigUint) -> bool {
+        ni_poe_verify(x, u, w, proof, n)
+    }
+}
+
+/// Claims with exponents of at most this many bits are checked directly by `Pietrzak`.
+const PIETRZAK_THRESHOLD: usize = 64;
+
+/// Prefixes the transcripts of Pietrzak proofs.
+const PIETRZAK_TAG: &[u8] = b"pietrzak";
+
+/// Pietrzak's halving proof of exponentiation, generalized to arbitrary exponents.
+/// The proof is one group element per halving, so it grows logarithmically with the exponent,
+/// but proving needs neither hashing to a prime, nor dividing the exponent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pietrzak;
+
+impl ProofOfExponentiation for Pietrzak {
+    type Proof = Vec<BigUint>;
+
+    fn prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Self::Proof {
+        #[cfg(feature = "tracing")]
+        let _span = libtracing::debug_span!("pietrzak_prove", exponent_bits = x.bits()).entered();
+
+        debug_assert!(&u.modpow(x, n) == w, "invalid input");
+
+        let mut claim = HalvingClaim::new(x, u, w);
+        let mut proof = Vec::new();
+
+        while claim.t > PIETRZAK_THRESHOLD {
+            claim.make_even(n);
+            let m = claim.midpoint(n);
+            claim.halve(&m, n);
+            proof.push(m);
+        }
+
+        proof
+    }
+
+    fn verify(x: &BigUint, u: &BigUint, w: &BigUint, proof: &Self::Proof, n: &BigUint) -> bool {
+        if n.is_zero() {
+            return false;
+        }
+
+        let mut claim = HalvingClaim::new(x, u, w);
+        let mut proof = proof.iter();
+
+        while claim.t > PIETRZAK_THRESHOLD {
+            claim.make_even(n);
+            match proof.next() {
+                Some(m) => claim.halve(m, n),
+                None => return false,
+            }
+        }
+
+        proof.next().is_none() && claim.holds(n)
+    }
+}
+
+/// The claim `a^(2^t) b^q = w`, starting out as `1^(2^|x|) u^x = w`.
+///
+/// For `t = 2h` and `q = q_l 2^h + q_r`, the prover sends `m = a^(2^h) b^q_l`, which splits
+/// the claim into `a^(2^h) b^q_l = m` and `m^(2^h) b^q_r = w`. Both are merged with a random `r`
+/// into `(a^r m)^(2^h) b^(r q_l + q_r) = m^r w`. The base `b` never changes.
+struct HalvingClaim {
+    a: BigUint,
+    b: BigUint,
+    q: BigUint,
+    t: usize,
+    w: BigUint,
+}
+
+impl HalvingClaim {
+    fn new(x: &BigUint, u: &BigUint, w: &BigUint) -> Self {
+        HalvingClaim {
+            a: BigUint::one(),
+            b: u.clone(),
+            q: x.clone(),
+            t: x.bits(),
+            w: w.clone(),
+        }
+    }
+
+    /// `a^(2^t) = (a^2)^(2^(t - 1))`
+    fn make_even(&mut self, n: &BigUint) {
+        if self.t % 2 == 1 {
+            self.a = (&self.a * &self.a) % n;
+            self.t -= 1;
+        }
+    }
+
+    /// Returns `a^(2^h) b^q_l`.
+    fn midpoint(&self, n: &BigUint) -> BigUint {
+        let h = self.t / 2;
+        let q_l = &self.q >> h;
+
+        (self.a.modpow(&(BigUint::one() << h), n) * self.b.modpow(&q_l, n)) % n
+    }
+
+    fn halve(&mut self, m: &BigUint, n: &BigUint) {
+        let h = self.t / 2;
+        let q_l = &self.q >> h;
+        let q_r = &self.q - (&q_l << h);
+        let r = self.challenge(m, n);
+
+        self.a = (self.a.modpow(&r, n) * m) % n;
+        self.q = &r * q_l + q_r;
+        self.w = (m.modpow(&r, n) * &self.w) % n;
+        self.t = h;
+    }
+
+    /// r <- H("pietrzak", n, a, b, q, w, m, t)
+    fn challenge(&self, m: &BigUint, n: &BigUint) -> BigUint {
+        let mut to_hash = Vec::new();
+        write_part(&mut to_hash, PIETRZAK_TAG);
+        for part in &[n, &self.a, &self.b, &self.q, &self.w, m] {
+            write_part(&mut to_hash, &part.to_bytes_be());
+        }
+        write_part(&mut to_hash, &(self.t as u64).to_be_bytes());
+
+        BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..HASH_PRIME_BITS / 8])
+    }
+
+    fn holds(&self, n: &BigUint) -> bool {
+        let lhs = self.a.modpow(&(BigUint::one() << self.t), n) * self.b.modpow(&self.q, n);
+
+        lhs % n == &self.w % n
+    }
+}
+
+/// Prefixes the transcripts of NI-PoE proofs.
+const POE_TAG: &[u8] = b"poe";
+
+/// Prefixes the transcripts of NI-PoKE2 proofs.
+const POKE2_TAG: &[u8] = b"poke2";
+
+/// Prefixes the transcripts of NI-PoKE* proofs.
+const POKE_STAR_TAG: &[u8] = b"poke-star";
+
+/// Prefixes the transcripts of the weights of batched NI-PoKE2 proofs.
+const POKE2_BATCH_TAG: &[u8] = b"poke2-batch";
+
+/// Appends `part` to `hasher`, prefixed with its length like `write_part`.
+fn absorb(hasher: HashToPrime<Blake2b>, part: &[u8]) -> HashToPrime<Blake2b> {
+    hasher
+        .update(&(part.len() as u32).to_be_bytes())
+        .update(part)
+}
+
+/// The Fiat-Shamir transcript of NI-PoE proofs for the exponent `x`.
+///
+/// The challenge of `(x, u, w)` in a group with parameters `N` is
+/// `H_prime("poe" || x || N || u || w)`, with every part prefixed by its length, so the whole
+/// statement is bound and proofs can't be replayed in other groups or as other kinds of proofs.
+/// The transcript absorbs `x` once, so proofs of many statements with the same exponent, e.g.
+/// one per element of a batch, only hash `N || u || w` each.
+#[derive(Clone)]
+pub struct PoeTranscript<'a> {
+    x: &'a BigUint,
+    prefix: HashToPrime<Blake2b>,
+}
+
+impl<'a> PoeTranscript<'a> {
+    pub fn new(x: &'a BigUint) -> Self {
+        PoeTranscript {
+            x,
+            prefix: absorb(HashToPrime::new().update(POE_TAG), &x.to_bytes_be()),
+        }
+    }
+
+    pub fn exponent(&self) -> &BigUint {
+        self.x
+    }
+
+    /// `l <- H_prime(x, N, u, w)`
+    pub fn challenge<G: UnknownOrderGroup>(&self, group: &G, u: &G::Elem, w: &G::Elem) -> BigUint {
+        let hasher = absorb(self.prefix.clone(), &group.params_to_bytes());
+        let hasher = absorb(hasher, &group.elem_to_bytes(u));
+        absorb(hasher, &group.elem_to_bytes(w)).finalize()
+    }
+}
+
 /// NI-PoE Prove
 /// Assumes `u^x = w`
 /// All operations are `mod n`.
+/// For an exponent given by its factors, which is too large to calculate, see
+/// `ni_poe_prove_chunked`.
 pub fn ni_poe_prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> ExponentProof {
-    debug_assert!(&u.modpow(x, n) == w, "invalid input");
+    let group = ModN::new(n).expect("the modulus is zero");
+    ni_poe_prove_in(&group, x, u, w)
+}
 
-    // l <- H_prime(x, u, w)
-    let mut to_hash = x.to_bytes_be();
-    to_hash.extend(&u.to_bytes_be());
-    to_hash.extend(&w.to_bytes_be());
+/// NI-PoE Prove for several statements `u_i^x = w_i` with the same exponent.
+/// `x` is only hashed once, the proofs equal those of `ni_poe_prove`.
+/// All operations are `mod n`.
+pub fn ni_poe_prove_many(
+    x: &BigUint,
+    statements: &[(&BigUint, &BigUint)],
+    n: &BigUint,
+) -> Vec<ExponentProof> {
+    let group = ModN::new(n).expect("the modulus is zero");
+    let transcript = PoeTranscript::new(x);
+    statements
+        .iter()
+        .map(|(u, w)| ni_poe_prove_with(&group, &transcript, *u, *w))
+        .collect()
+}
 
-    let l = hash_prime::<_, Blake2b>(&to_hash);
+/// NI-PoE Prove, in any group of unknown order.
+/// Assumes `u^x = w`
+pub fn ni_poe_prove_in<G: UnknownOrderGroup>(
+    group: &G,
+    x: &BigUint,
+    u: &G::Elem,
+    w: &G::Elem,
+) -> G::Elem {
+    #[cfg(feature = "tracing")]
+    let _span = libtracing::debug_span!("ni_poe_prove", exponent_bits = x.bits()).entered();
+
+    ni_poe_prove_with(group, &PoeTranscript::new(x), u, w)
+}
+
+/// NI-PoE Prove, for the exponent of `transcript`.
+/// Assumes `u^x = w`
+pub fn ni_poe_prove_with<G: UnknownOrderGroup>(
+    group: &G,
+    transcript: &PoeTranscript,
+    u: &G::Elem,
+    w: &G::Elem,
+) -> G::Elem {
+    let x = transcript.exponent();
+    debug_assert!(&group.exp(u, x) == w, "invalid input");
+
+    // l <- H_prime(x, N, u, w)
+    let l = transcript.challenge(group, u, w);
 
     // q <- floor(x/l)
     let q = x.div_floor(&l);
 
     //Prover sends Q <- u^q ∈ G to the Verifier.
-    u.modpow(&q, n)
+    group.exp(u, &q)
 }
 
 /// NI-PoE Verify
@@ -42,18 +300,355 @@ pub fn ni_poe_verify(
     q: &ExponentProof,
     n: &BigUint,
 ) -> bool {
-    // l <- H_prime(x, u, w)
-    let mut to_hash = x.to_bytes_be();
-    to_hash.extend(&u.to_bytes_be());
-    to_hash.extend(&w.to_bytes_be());
+    ni_poe_verify_detailed(x, u, w, q, n).is_ok()
+}
 
-    let l = hash_prime::<_, Blake2b>(&to_hash);
+/// NI-PoE Verify, returning the check that failed.
+/// All operations are `mod n`.
+pub fn ni_poe_verify_detailed(
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    q: &ExponentProof,
+    n: &BigUint,
+) -> Result<(), VerifyFailure> {
+    let group = ModN::new(n).ok_or(VerifyFailure::ZeroModulus)?;
+    ni_poe_verify_in(&group, x, u, w, q)
+}
+
+/// NI-PoE Verify of proofs from `ni_poe_prove_many`, given as `(u_i, w_i, Q_i)`.
+/// All operations are `mod n`.
+pub fn ni_poe_verify_many(
+    x: &BigUint,
+    statements: &[(&BigUint, &BigUint, &ExponentProof)],
+    n: &BigUint,
+) -> bool {
+    let group = match ModN::new(n) {
+        Some(group) => group,
+        None => return false,
+    };
+    let transcript = PoeTranscript::new(x);
+    statements
+        .iter()
+        .all(|(u, w, q)| ni_poe_verify_with(&group, &transcript, *u, *w, *q).is_ok())
+}
+
+/// NI-PoE Verify, in any group of unknown order, returning the check that failed.
+pub fn ni_poe_verify_in<G: UnknownOrderGroup>(
+    group: &G,
+    x: &BigUint,
+    u: &G::Elem,
+    w: &G::Elem,
+    q: &G::Elem,
+) -> Result<(), VerifyFailure> {
+    ni_poe_verify_with(group, &PoeTranscript::new(x), u, w, q)
+}
+
+/// NI-PoE Verify, for the exponent of `transcript`.
+pub fn ni_poe_verify_with<G: UnknownOrderGroup>(
+    group: &G,
+    transcript: &PoeTranscript,
+    u: &G::Elem,
+    w: &G::Elem,
+    q: &G::Elem,
+) -> Result<(), VerifyFailure> {
+    let x = transcript.exponent();
+
+    // l <- H_prime(x, N, u, w)
+    let l = transcript.challenge(group, u, w);
 
     // r <- x mod l
     let r = x.mod_floor(&l);
 
     // Q^l u^r == w
-    &((q.modpow(&l, &n) * &u.modpow(&r, &n)) % n) == w
+    if &group.op(&group.exp(q, &l), &group.exp(u, &r)) != w {
+        return Err(VerifyFailure::ChallengeMismatch);
+    }
+
+    Ok(())
+}
+
+/// A NI-PoE with the challenge bound to a commitment to the exponent, see
+/// `ni_poe_prove_committed`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommittedExponentProof {
+    /// `c = H(x)`, see `exponent_commitment`.
+    pub commitment: Vec<u8>,
+    /// `Q = u^{floor(x / l)}`.
+    pub q: BigUint,
+    /// `x mod l`, which verifiers without `x` can't compute themselves.
+    pub r: BigUint,
+}
+
+/// `H(x)`, a short commitment to an exponent, e.g. the product of a batch.
+pub fn exponent_commitment(x: &BigUint) -> Vec<u8> {
+    Blake2b::digest(&x.to_bytes_be()).to_vec()
+}
+
+/// Wesolowski's NI-PoE for verifiers which only know a commitment to the exponent, using
+/// `ni_poe_prove_committed` and `ni_poe_verify_committed_full`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CommittedWesolowski;
+
+impl ProofOfExponentiation for CommittedWesolowski {
+    type Proof = CommittedExponentProof;
+
+    fn prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Self::Proof {
+        ni_poe_prove_committed(x, u, w, n)
+    }
+
+    fn verify(x: &BigUint, u: &BigUint, w: &BigUint, proof: &Self::Proof, n: &BigUint) -> bool {
+        ni_poe_verify_committed_full(x, u, w, proof, n)
+    }
+}
+
+/// Prefixes the challenges of committed NI-PoEs, so they never equal those of `PoeTranscript`.
+const COMMITTED_POE_TAG: &[u8] = b"committed-poe";
+
+/// l <- H_prime(c, n, u, w)
+fn committed_challenge(c: &[u8], u: &BigUint, w: &BigUint, n: &BigUint) -> BigUint {
+    let mut to_hash = Vec::new();
+    write_part(&mut to_hash, c);
+    write_part(&mut to_hash, &n.to_bytes_be());
+    write_part(&mut to_hash, &u.to_bytes_be());
+    write_part(&mut to_hash, &w.to_bytes_be());
+
+    HashToPrime::<Blake2b>::new()
+        .update(COMMITTED_POE_TAG)
+        .update(&to_hash)
+        .finalize()
+}
+
+/// NI-PoE Prove, for verifiers which only know the commitment `c = H(x)`.
+/// Assumes `u^x = w`
+/// All operations are `mod n`.
+///
+/// The challenge is `l <- H_prime(c, n, u, w)`, and the proof carries `r = x mod l` as well.
+pub fn ni_poe_prove_committed(
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> CommittedExponentProof {
+    debug_assert!(&u.modpow(x, n) == w, "invalid input");
+
+    let commitment = exponent_commitment(x);
+    let l = committed_challenge(&commitment, u, w, n);
+    let (q, r) = x.div_rem(&l);
+
+    CommittedExponentProof {
+        q: u.modpow(&q, n),
+        r,
+        commitment,
+    }
+}
+
+/// NI-PoE Verify, without the exponent.
+/// All operations are `mod n`.
+///
+/// This only shows that the prover knows an exponent, bound to the commitment by the challenge.
+/// That it is the committed one is up to whoever provides the commitment, e.g. a data
+/// availability layer, anyone with `x` can check it with `ni_poe_verify_committed_full`.
+pub fn ni_poe_verify_committed(
+    u: &BigUint,
+    w: &BigUint,
+    pi: &CommittedExponentProof,
+    n: &BigUint,
+) -> bool {
+    if n.is_zero() {
+        return false;
+    }
+
+    // the residue must be canonical, otherwise (Q u^{-1}, r + l) would be a second proof
+    let l = committed_challenge(&pi.commitment, u, w, n);
+    if pi.r >= l {
+        return false;
+    }
+
+    // Q^l u^r == w
+    (pi.q.modpow(&l, n) * u.modpow(&pi.r, n)) % n == w % n
+}
+
+/// NI-PoE Verify, checking that the proof is for the committed exponent `x`.
+/// All operations are `mod n`.
+pub fn ni_poe_verify_committed_full(
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    pi: &CommittedExponentProof,
+    n: &BigUint,
+) -> bool {
+    if pi.commitment != exponent_commitment(x) {
+        return false;
+    }
+
+    let l = committed_challenge(&pi.commitment, u, w, n);
+    x.mod_floor(&l) == pi.r && ni_poe_verify_committed(u, w, pi, n)
+}
+
+const CHUNKED_POE_TAG: &[u8] = b"chunked-poe";
+
+/// l <- H_prime(x_1, .., x_k, n, u, w)
+fn chunked_challenge<I>(xs: I, u: &BigUint, w: &BigUint, n: &BigUint) -> BigUint
+where
+    I: IntoIterator,
+    I::Item: Borrow<BigUint>,
+{
+    let mut hasher = HashToPrime::<Blake2b>::new().update(CHUNKED_POE_TAG);
+    let mut to_hash = Vec::new();
+    for x in xs {
+        to_hash.clear();
+        write_part(&mut to_hash, &x.borrow().to_bytes_be());
+        hasher = hasher.update(&to_hash);
+    }
+
+    to_hash.clear();
+    write_part(&mut to_hash, &n.to_bytes_be());
+    write_part(&mut to_hash, &u.to_bytes_be());
+    write_part(&mut to_hash, &w.to_bytes_be());
+    hasher.update(&to_hash).finalize()
+}
+
+/// NI-PoE Prove for `x = \prod x_i`, without calculating `x`.
+/// Assumes `u^x = w`
+/// All operations are `mod n`.
+///
+/// The challenge `l <- H_prime(x_1, .., x_k, n, u, w)` hashes the factors instead of their
+/// product, so the proofs differ from those of `ni_poe_prove`. The factors are iterated twice,
+/// `chunk_size` of them at a time, keeping `Q = u^{floor(x/l)}` and `x mod l` of the prefix.
+/// They can be borrowed, e.g. streamed from a slice or from storage, without any copies of
+/// more than a chunk.
+pub fn ni_poe_prove_chunked<I>(
+    xs: I,
+    chunk_size: usize,
+    u: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> ExponentProof
+where
+    I: IntoIterator,
+    I::Item: Borrow<BigUint>,
+    I::IntoIter: Clone,
+{
+    let xs = xs.into_iter();
+    let l = chunked_challenge(xs.clone(), u, w, n);
+
+    // with x' = x p: floor(x'/l) = floor(x/l) p + floor((x mod l) p / l)
+    let mut q = BigUint::one();
+    let mut r = BigUint::one();
+    for (_, p) in chunk_products(xs, chunk_size) {
+        let (carry, rest) = (r * &p).div_rem(&l);
+        q = (q.modpow(&p, n) * u.modpow(&carry, n)) % n;
+        r = rest;
+    }
+
+    q
+}
+
+/// NI-PoE Verify of proofs from `ni_poe_prove_chunked`.
+/// All operations are `mod n`.
+pub fn ni_poe_verify_chunked<I>(
+    xs: I,
+    u: &BigUint,
+    w: &BigUint,
+    q: &ExponentProof,
+    n: &BigUint,
+) -> bool
+where
+    I: IntoIterator,
+    I::Item: Borrow<BigUint>,
+    I::IntoIter: Clone,
+{
+    if n.is_zero() {
+        return false;
+    }
+
+    let xs = xs.into_iter();
+    let l = chunked_challenge(xs.clone(), u, w, n);
+    // r <- x mod l, one factor at a time
+    let r = xs.fold(BigUint::one(), |r, x| (r * x.borrow()) % &l);
+
+    // Q^l u^r == w
+    (q.modpow(&l, n) * u.modpow(&r, n)) % n == w % n
+}
+
+/// Wesolowski's NI-PoE in `QR_n`, using `ni_poe_prove_qr` and `ni_poe_verify_qr`.
+/// The proof is a single group element.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QrWesolowski;
+
+impl ProofOfExponentiation for QrWesolowski {
+    type Proof = ExponentProof;
+
+    fn prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Self::Proof {
+        ni_poe_prove_qr(x, u, w, n)
+    }
+
+    fn verify(x: &BigUint, u: &BigUint, w: &BigUint, proof: &Self::Proof, n: &BigUint) -> bool {
+        ni_poe_verify_qr(x, u, w, proof, n)
+    }
+}
+
+/// NI-PoE Prove, for `u^x = w` in `QR_n`, i.e. `(u^2)^x = w^2`.
+/// Assumes `u^x = w`
+/// All operations are `mod n`.
+///
+/// The challenge is `l <- H_prime(x, n, u^2, w^2)`, and the proof `Q = u^{floor(x/l)}`, which
+/// the verifier maps into `QR_n` as well.
+pub fn ni_poe_prove_qr(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> ExponentProof {
+    let group = QrN::new(n).expect("the modulus is zero");
+    debug_assert!(&u.modpow(x, n) == w, "invalid input");
+
+    let l = PoeTranscript::new(x).challenge(&group, &group.map(u), &group.map(w));
+
+    u.modpow(&x.div_floor(&l), n)
+}
+
+/// NI-PoE Verify of proofs from `ni_poe_prove_qr`.
+/// `u`, `w` and `q` are mapped into `QR_n` first, so proofs only show `u^x = w` up to an
+/// element of order 2, but can't be forged with one.
+/// All operations are `mod n`.
+pub fn ni_poe_verify_qr(
+    x: &BigUint,
+    u: &BigUint,
+    w: &BigUint,
+    q: &ExponentProof,
+    n: &BigUint,
+) -> bool {
+    let group = match QrN::new(n) {
+        Some(group) => group,
+        None => return false,
+    };
+
+    ni_poe_verify_in(&group, x, &group.map(u), &group.map(w), &group.map(q)).is_ok()
+}
+
+/// Wesolowski's NI-PoE in `Z_n^* / {±1}`, see `SignedModN`.
+///
+/// `u` and `w` are canonicalized, so the proof shows `u^x = ±w`. The proof is the canonical
+/// `Q`, verification rejects `-Q`, so proofs can't be altered with `-1` either.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignedWesolowski;
+
+impl ProofOfExponentiation for SignedWesolowski {
+    type Proof = ExponentProof;
+
+    /// Panics if `n` is even.
+    fn prove(x: &BigUint, u: &BigUint, w: &BigUint, n: &BigUint) -> Self::Proof {
+        let group = SignedModN::new(n).expect("the modulus is zero or even");
+        ni_poe_prove_in(&group, x, &group.canonicalize(u), &group.canonicalize(w))
+    }
+
+    fn verify(x: &BigUint, u: &BigUint, w: &BigUint, proof: &Self::Proof, n: &BigUint) -> bool {
+        let group = match SignedModN::new(n) {
+            Some(group) => group,
+            None => return false,
+        };
+        let (u, w) = (group.canonicalize(u), group.canonicalize(w));
+
+        group.is_canonical(proof) && ni_poe_verify_in(&group, x, &u, &w, proof).is_ok()
+    }
 }
 
 //proof of knowledge of exponent, i.e. a proof that a computationally bounded prover knows the discrete logarithm between two elements in a group of unknown order. The proof is succinct in that the proof size and verification time is independent of the size of the discrete-log.
@@ -61,40 +656,94 @@ pub fn ni_poe_verify(
 /// NI-PoKE2 Prove
 /// assumes `u^x = w`
 /// All operations are `mod n`.
+/// Fails if a negative power of a non invertible element is required.
 pub fn ni_poke2_prove(
     x: impl Into<BigInt>,
     u: &BigUint,
     w: &BigUint,
     n: &BigUint,
-) -> (BigUint, BigUint, BigInt) {
+) -> Result<KnowledgeProof, Errors> {
+    let group = ModN::new(n).expect("the modulus is zero");
+    ni_poke2_prove_in(&group, x, u, w)
+}
+
+/// NI-PoKE2 Prove, in any group of unknown order.
+/// assumes `u^x = w`
+/// Fails if a negative power of a non invertible element is required.
+pub fn ni_poke2_prove_in<G: UnknownOrderGroup>(
+    group: &G,
+    x: impl Into<BigInt>,
+    u: &G::Elem,
+    w: &G::Elem,
+) -> Result<(G::Elem, G::Elem, BigInt), Errors> {
     let x: BigInt = x.into();
+    #[cfg(feature = "tracing")]
+    let _span = libtracing::debug_span!("ni_poke2_prove", exponent_bits = x.bits()).entered();
+
+    debug_assert!(group.exp_signed(u, &x).as_ref() == Some(w), "invalid input");
 
-    debug_assert!(&modpow_uint_int(u, &x, n).unwrap() == w, "invalid input");
+    // x = ±|x|, everything below is computed for |x|, and the sign applied at the end
+    let (negative, x_abs) = sign_magnitude(&x);
 
-    // g <- H_G(u, w)
-    let mut to_hash = u.to_bytes_be();
-    to_hash.extend(&w.to_bytes_be());
-    let g = hash_group::<_, Blake2b>(&to_hash, n);
+    // g <- H_G(N, u, w)
+    let to_hash = poke2_transcript(group, u, w);
+    let g = group.hash_to_group(&to_hash);
 
     // z = g^x
-    let z = modpow_uint_int(&g, &x, n).expect("invalid state");
+    let z = exp_with_sign(group, &g, &x_abs, negative)?;
 
-    // l <- H_prime(u, w, z)
-    to_hash.extend(&z.to_bytes_be());
-    let l: BigInt = hash_prime::<_, Blake2b>(&to_hash).into();
+    // l <- H_prime(N, u, w, z), alpha = H(N, u, w, z, l)
+    let (l, alpha) = poke2_challenges(group, to_hash, &z);
 
-    // alpha = H(u, w, z, l)
-    to_hash.extend(&l.to_bytes_be().1);
-    let alpha = BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..]);
+    // |x| = q l + r, so x = ±q l ± r, with the remainder truncated towards zero
+    let (q, r) = x_abs.div_rem(&l);
+    let r = BigInt::from_biguint(if negative { Sign::Minus } else { Sign::Plus }, r);
 
-    // q <- floor(x/l)
-    // r <- x % l
-    let (q, r) = x.div_rem(&l);
+    // Q <- (ug^alpha)^{±q}
+    let u_g_alpha = group.op(u, &group.exp(&g, &alpha));
+    let q_big = exp_with_sign(group, &u_g_alpha, &q, negative)?;
 
-    // Q <- (ug^alpha)^q
-    let q_big = modpow_uint_int(&(u * &g.modpow(&alpha, n)), &q, n).expect("invalid state");
+    Ok((z, q_big, r))
+}
 
-    (z, q_big, r)
+/// `"poke2" || N || u || w`, with every part prefixed by its length.
+fn poke2_transcript<G: UnknownOrderGroup>(group: &G, u: &G::Elem, w: &G::Elem) -> Vec<u8> {
+    let mut to_hash = POKE2_TAG.to_vec();
+    write_part(&mut to_hash, &group.params_to_bytes());
+    write_part(&mut to_hash, &group.elem_to_bytes(u));
+    write_part(&mut to_hash, &group.elem_to_bytes(w));
+    to_hash
+}
+
+/// `(l, alpha)`, for the transcript of `poke2_transcript` and `z`.
+fn poke2_challenges<G: UnknownOrderGroup>(
+    group: &G,
+    mut to_hash: Vec<u8>,
+    z: &G::Elem,
+) -> (BigUint, BigUint) {
+    write_part(&mut to_hash, &group.elem_to_bytes(z));
+    let l = hash_prime::<_, Blake2b>(&to_hash);
+
+    write_part(&mut to_hash, &l.to_bytes_be());
+    let alpha = BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..]);
+
+    (l, alpha)
+}
+
+/// `a^{-e}` if `negative`, and `a^e` otherwise, inverting the result instead of the base.
+/// Fails with `NotInvertible` if `a^e` is not invertible.
+fn exp_with_sign<G: UnknownOrderGroup>(
+    group: &G,
+    a: &G::Elem,
+    e: &BigUint,
+    negative: bool,
+) -> Result<G::Elem, Errors> {
+    let a_e = group.exp(a, e);
+    if negative {
+        group.inv(&a_e).ok_or(Errors::NotInvertible)
+    } else {
+        Ok(a_e)
+    }
 }
 
 /// NI-PoKE2 Verify
@@ -106,39 +755,264 @@ pub fn ni_poke2_verify(
     pi: &(BigUint, BigUint, BigInt),
     n: &BigUint,
 ) -> bool {
+    ni_poke2_verify_detailed(u, w, pi, n).is_ok()
+}
+
+/// NI-PoKE2 Verify, returning the check that failed.
+/// All operations are `mod n`
+pub fn ni_poke2_verify_detailed(
+    u: &BigUint,
+    w: &BigUint,
+    pi: &(BigUint, BigUint, BigInt),
+    n: &BigUint,
+) -> Result<(), VerifyFailure> {
+    let group = ModN::new(n).ok_or(VerifyFailure::ZeroModulus)?;
+    ni_poke2_verify_in(&group, u, w, pi)
+}
+
+/// NI-PoKE2 Verify, in any group of unknown order, returning the check that failed.
+pub fn ni_poke2_verify_in<G: UnknownOrderGroup>(
+    group: &G,
+    u: &G::Elem,
+    w: &G::Elem,
+    pi: &(G::Elem, G::Elem, BigInt),
+) -> Result<(), VerifyFailure> {
     // {z, Q, r} <- pi
     let (z, q_big, r) = pi;
 
-    // g <- H_G(u, w)
-    let mut to_hash = u.to_bytes_be();
-    to_hash.extend(&w.to_bytes_be());
-    let g = hash_group::<_, Blake2b>(&to_hash, n);
+    // g <- H_G(N, u, w)
+    let to_hash = poke2_transcript(group, u, w);
+    let g = group.hash_to_group(&to_hash);
 
-    // l <- H_prime(u, w, z)
-    to_hash.extend(&z.to_bytes_be());
-    let l = hash_prime::<_, Blake2b>(&to_hash);
+    // l <- H_prime(N, u, w, z), alpha = H(N, u, w, z, l)
+    let (l, alpha) = poke2_challenges(group, to_hash, z);
 
-    // alpha = H(u, w, z, l)
-    to_hash.extend(&l.to_bytes_be());
-    let alpha = BigUint::from_bytes_be(&Blake2b::digest(&to_hash)[..]);
+    // Q^l(ug^alpha)^r == wz^alpha, for a negative r as Q^l == wz^alpha(ug^alpha)^{|r|},
+    // so nothing has to be inverted
+    let u_g_alpha = group.op(u, &group.exp(&g, &alpha));
+    let (negative, r_abs) = sign_magnitude(r);
+    let u_g_alpha_r = group.exp(&u_g_alpha, &r_abs);
+    let q_l = group.exp(q_big, &l);
+    let w_z_alpha = group.op(w, &group.exp(z, &alpha));
+
+    let (lhs, rhs) = if negative {
+        (q_l, group.op(&w_z_alpha, &u_g_alpha_r))
+    } else {
+        (group.op(&q_l, &u_g_alpha_r), w_z_alpha)
+    };
+
+    if lhs != rhs {
+        return Err(VerifyFailure::FinalEquality);
+    }
+
+    Ok(())
+}
+
+/// NI-PoKE* Prove
+/// assumes `g^x = w`, for a fixed generator `g`
+/// All operations are `mod n`.
+///
+/// This is the PoKE* of BBF: `l <- H_prime(N, g, w)`, and the proof is `Q = g^{floor(x/l)}`
+/// with `r = x mod l`. It is only sound if nobody knows a discrete logarithm relation involving `g`,
+/// e.g. the generator from `setup`, so unlike NI-PoKE2 the base can't come from the prover.
+/// Fails if `x` is negative and `g` is not invertible.
+pub fn ni_poke_star_prove(
+    x: impl Into<BigInt>,
+    g: &BigUint,
+    w: &BigUint,
+    n: &BigUint,
+) -> Result<GeneratorKnowledgeProof, Errors> {
+    let group = ModN::new(n).expect("the modulus is zero");
+    ni_poke_star_prove_in(&group, x, g, w)
+}
+
+/// NI-PoKE* Prove, in any group of unknown order.
+/// assumes `g^x = w`, for a fixed generator `g`
+/// Fails if `x` is negative and `g` is not invertible.
+pub fn ni_poke_star_prove_in<G: UnknownOrderGroup>(
+    group: &G,
+    x: impl Into<BigInt>,
+    g: &G::Elem,
+    w: &G::Elem,
+) -> Result<(G::Elem, BigUint), Errors> {
+    let x: BigInt = x.into();
+    debug_assert!(group.exp_signed(g, &x).as_ref() == Some(w), "invalid input");
+
+    // l <- H_prime(N, g, w)
+    let l: BigInt = poke_star_challenge(group, g, w).into();
+
+    // q <- floor(x/l)
+    // r <- x mod l, in [0, l) for negative x as well
+    let (q, r) = x.div_mod_floor(&l);
+
+    // Q <- g^q
+    let q_big = group.exp_signed(g, &q).ok_or(Errors::NotInvertible)?;
+
+    Ok((q_big, r.to_biguint().expect("floored remainder")))
+}
 
-    // Q^l(ug^alpha)^r
-    let lhs: BigInt = ((q_big.modpow(&l, n)
-        * modpow_uint_int(&(u * &g.modpow(&alpha, n)), &r, n).expect("invalid state"))
-        % n)
-        .into();
+/// NI-PoKE* Verify
+/// assumes `g^x = w`, for a fixed generator `g`
+/// All operations are `mod n`
+pub fn ni_poke_star_verify(
+    g: &BigUint,
+    w: &BigUint,
+    pi: &GeneratorKnowledgeProof,
+    n: &BigUint,
+) -> bool {
+    ni_poke_star_verify_detailed(g, w, pi, n).is_ok()
+}
+
+/// NI-PoKE* Verify, returning the check that failed.
+/// All operations are `mod n`
+pub fn ni_poke_star_verify_detailed(
+    g: &BigUint,
+    w: &BigUint,
+    pi: &GeneratorKnowledgeProof,
+    n: &BigUint,
+) -> Result<(), VerifyFailure> {
+    let group = ModN::new(n).ok_or(VerifyFailure::ZeroModulus)?;
+    ni_poke_star_verify_in(&group, g, w, pi)
+}
+
+/// NI-PoKE* Verify, in any group of unknown order, returning the check that failed.
+pub fn ni_poke_star_verify_in<G: UnknownOrderGroup>(
+    group: &G,
+    g: &G::Elem,
+    w: &G::Elem,
+    pi: &(G::Elem, BigUint),
+) -> Result<(), VerifyFailure> {
+    // {Q, r} <- pi
+    let (q_big, r) = pi;
 
-    // wz^alpha
-    let z_alpha = z.modpow(&alpha, n);
-    let rhs: BigInt = ((w * z_alpha) % n).into();
+    // l <- H_prime(N, g, w)
+    let l = poke_star_challenge(group, g, w);
 
-    lhs == rhs
+    // the verifier doesn't know x, so r must be reduced
+    if r >= &l {
+        return Err(VerifyFailure::ResidueOutOfRange);
+    }
+
+    // Q^l g^r == w
+    if &group.op(&group.exp(q_big, &l), &group.exp(g, r)) != w {
+        return Err(VerifyFailure::ChallengeMismatch);
+    }
+
+    Ok(())
+}
+
+/// l <- H_prime(N, g, w)
+fn poke_star_challenge<G: UnknownOrderGroup>(group: &G, g: &G::Elem, w: &G::Elem) -> BigUint {
+    let mut to_hash = POKE_STAR_TAG.to_vec();
+    write_part(&mut to_hash, &group.params_to_bytes());
+    write_part(&mut to_hash, &group.elem_to_bytes(g));
+    write_part(&mut to_hash, &group.elem_to_bytes(w));
+
+    hash_prime::<_, Blake2b>(&to_hash)
+}
+
+/// Batched NI-PoKE2 Prove
+/// assumes `u^{x_i} = w_i`, for all `i`, with the same base `u`.
+/// All operations are `mod n`.
+///
+/// The statements are combined with weights `gamma_i <- H(N, u, ws, i)` into the single statement
+/// `u^{\sum gamma_i x_i} = \prod w_i^{gamma_i}`, which is proven with a regular NI-PoKE2.
+/// Fails with `InvalidParams` if `xs` and `ws` have different lengths.
+pub fn ni_poke2_prove_batch(
+    xs: &[BigInt],
+    u: &BigUint,
+    ws: &[BigUint],
+    n: &BigUint,
+) -> Result<KnowledgeProof, Errors> {
+    if xs.len() != ws.len() {
+        return Err(Errors::InvalidParams);
+    }
+    if xs.is_empty() {
+        return Err(Errors::EmptyBatch);
+    }
+
+    let gammas = batch_weights(u, ws, n);
+
+    // x = \sum gamma_i x_i
+    let x: BigInt = xs
+        .iter()
+        .zip(&gammas)
+        .map(|(x, gamma)| x * BigInt::from(gamma.clone()))
+        .sum();
+
+    ni_poke2_prove(x, u, &combine_batch(ws, &gammas, n), n)
+}
+
+/// Batched NI-PoKE2 Verify
+/// assumes `u^{x_i} = w_i`, for all `i`, with the same base `u`.
+/// All operations are `mod n`
+///
+/// The weights are derived from all `ws`, so a proof for more or fewer statements fails.
+pub fn ni_poke2_verify_batch(
+    u: &BigUint,
+    ws: &[BigUint],
+    pi: &(BigUint, BigUint, BigInt),
+    n: &BigUint,
+) -> bool {
+    ni_poke2_verify_batch_detailed(u, ws, pi, n).is_ok()
+}
+
+/// Batched NI-PoKE2 Verify, returning the check that failed.
+/// All operations are `mod n`
+pub fn ni_poke2_verify_batch_detailed(
+    u: &BigUint,
+    ws: &[BigUint],
+    pi: &(BigUint, BigUint, BigInt),
+    n: &BigUint,
+) -> Result<(), VerifyFailure> {
+    if ws.is_empty() {
+        return Err(VerifyFailure::EmptyBatch);
+    }
+    if n.is_zero() {
+        return Err(VerifyFailure::ZeroModulus);
+    }
+
+    let gammas = batch_weights(u, ws, n);
+    ni_poke2_verify_detailed(u, &combine_batch(ws, &gammas, n), pi, n)
+}
+
+/// gamma_i <- H(H("poke2-batch" || N || u || w_1 || ..), i), truncated to 128 bits,
+/// with every part of the inner hash but the tag prefixed by its length.
+fn batch_weights(u: &BigUint, ws: &[BigUint], n: &BigUint) -> Vec<BigUint> {
+    let mut to_hash = POKE2_BATCH_TAG.to_vec();
+    write_part(&mut to_hash, &n.to_bytes_be());
+    write_part(&mut to_hash, &u.to_bytes_be());
+    for w in ws {
+        write_part(&mut to_hash, &w.to_bytes_be());
+    }
+    let seed = Blake2b::digest(&to_hash);
+
+    (0..ws.len() as u64)
+        .map(|i| {
+            let mut hasher = Blake2b::new();
+            hasher.input(seed.as_slice());
+            hasher.input(&i.to_be_bytes());
+            BigUint::from_bytes_be(&hasher.result()[..16])
+        })
+        .collect()
+}
+
+/// \prod w_i^{gamma_i}
+fn combine_batch(ws: &[BigUint], gammas: &[BigUint], n: &BigUint) -> BigUint {
+    ws.iter()
+        .zip(gammas)
+        .fold(BigUint::one(), |acc, (w, gamma)| {
+            (acc * w.modpow(gamma, n)) % n
+        })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    use crate::group::GroupElement;
+    use crate::hash::hash_group;
+    use crate::math::{modpow_uint_int, product};
     use num_bigint::{RandBigInt, RandPrime};
     use num_traits::One;
     use rand::thread_rng;
@@ -167,6 +1041,344 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ni_poe_many() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+
+        let us: Vec<_> = (0..4).map(|_| rng.gen_biguint(128)).collect();
+        let ws: Vec<_> = us.iter().map(|u| u.modpow(&x, &n)).collect();
+        let statements: Vec<_> = us.iter().zip(&ws).collect();
+
+        // same challenges as without the shared transcript
+        let qs = ni_poe_prove_many(&x, &statements, &n);
+        for ((u, w), q) in statements.iter().zip(&qs) {
+            assert_eq!(q, &ni_poe_prove(&x, u, w, &n));
+        }
+
+        let mut checks: Vec<_> = statements
+            .iter()
+            .zip(&qs)
+            .map(|((u, w), q)| (*u, *w, q))
+            .collect();
+        assert!(ni_poe_verify_many(&x, &checks, &n));
+        assert!(!ni_poe_verify_many(&(&x + 2u32), &checks, &n));
+        checks[0].2 = &qs[1];
+        assert!(!ni_poe_verify_many(&x, &checks, &n));
+
+        let transcript = PoeTranscript::new(&x);
+        let mut to_hash = b"poe".to_vec();
+        for part in &[&x, &n, &us[0], &ws[0]] {
+            write_part(&mut to_hash, &part.to_bytes_be());
+        }
+        assert_eq!(
+            transcript.challenge(&ModN::new(&n).unwrap(), &us[0], &ws[0]),
+            hash_prime::<_, Blake2b>(&to_hash)
+        );
+    }
+
+    #[test]
+    fn test_verify_detailed() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let x = rng.gen_prime(256);
+        let u = rng.gen_biguint(128);
+        let w = u.modpow(&x, &n);
+
+        let q = ni_poe_prove(&x, &u, &w, &n);
+        assert_eq!(ni_poe_verify_detailed(&x, &u, &w, &q, &n), Ok(()));
+        assert_eq!(
+            ni_poe_verify_detailed(&x, &u, &w, &(&q * &u), &n),
+            Err(VerifyFailure::ChallengeMismatch)
+        );
+        assert_eq!(
+            ni_poe_verify_detailed(&x, &u, &w, &q, &BigUint::zero()),
+            Err(VerifyFailure::ZeroModulus)
+        );
+
+        let pi = ni_poke2_prove(x.clone(), &u, &w, &n).unwrap();
+        assert_eq!(ni_poke2_verify_detailed(&u, &w, &pi, &n), Ok(()));
+        let (z, q_big, r) = pi.clone();
+        assert_eq!(
+            ni_poke2_verify_detailed(&u, &w, &(z.clone(), (&q_big * &u) % &n, r), &n),
+            Err(VerifyFailure::FinalEquality)
+        );
+
+        // a negative remainder on a base that shares a factor with n, which is never inverted
+        let p = rng.gen_prime(128);
+        let n = &p * rng.gen_prime(128);
+        assert_eq!(
+            ni_poke2_verify_detailed(&p, &w, &(z, q_big, BigInt::from(-1)), &n),
+            Err(VerifyFailure::FinalEquality)
+        );
+    }
+
+    #[test]
+    fn test_challenge_vectors() {
+        // n = 1000000007 * 998244353
+        let n = BigUint::from(998244359987710471u64);
+        let group = ModN::new(&n).unwrap();
+        let x = num_traits::pow(BigUint::from(65537u32), 20);
+        let u = BigUint::from(2u32);
+        let w = u.modpow(&x, &n);
+
+        let int = |s: &str| s.parse::<BigUint>().unwrap();
+
+        // changes of these break all proofs made before, and are format changes
+        let l = PoeTranscript::new(&x).challenge(&group, &u, &w);
+        assert_eq!(l, int("299913342119209355095857239466182747447"));
+        assert_eq!(ni_poe_prove(&x, &u, &w, &n), int("414747551554193632"));
+        let (z, q, r) = ni_poke2_prove(x.clone(), &u, &w, &n).unwrap();
+        assert_eq!(z, int("477249577629134594"));
+        assert_eq!(q, int("132882259859127777"));
+        assert_eq!(
+            r,
+            BigInt::from(int("99173623820515428392008764884369737044"))
+        );
+        let (q, r) = ni_poke_star_prove(x.clone(), &u, &w, &n).unwrap();
+        assert_eq!(q, int("434255688997848313"));
+        assert_eq!(r, int("200229301466990920646622858301158835928"));
+        assert_eq!(
+            batch_weights(&u, &[w.clone(), u.clone()], &n),
+            vec![
+                int("100096618310475106223338976637189281817"),
+                int("100838083772072318193074550231168041905")
+            ]
+        );
+        assert_eq!(
+            Pietrzak::prove(&x, &u, &w, &n),
+            vec![
+                int("836306726232751009"),
+                int("975185957461803251"),
+                int("196114780452233789")
+            ]
+        );
+
+        // the same statement in another group has another challenge
+        let other = ModN::new(&(&n + 2u32)).unwrap();
+        assert_ne!(PoeTranscript::new(&x).challenge(&other, &u, &w), l);
+        assert_ne!(poke_star_challenge(&group, &u, &w), l);
+        assert_ne!(
+            batch_weights(&u, &[w.clone()], &(&n + 2u32)),
+            batch_weights(&u, &[w.clone()], &n)
+        );
+        // the parts can't be shifted between each other
+        assert_ne!(
+            batch_weights(&u, &[BigUint::from(1u32), BigUint::from(2u32)], &n)[0],
+            batch_weights(&u, &[BigUint::from(258u32)], &n)[0]
+        );
+    }
+
+    /// `Z_n^*` over validated elements, which must give the same proofs as `ModN`.
+    struct Units(BigUint);
+
+    impl UnknownOrderGroup for Units {
+        type Elem = GroupElement;
+
+        fn id(&self) -> GroupElement {
+            GroupElement::new(BigUint::one(), &self.0).unwrap()
+        }
+
+        fn op(&self, a: &GroupElement, b: &GroupElement) -> GroupElement {
+            a.mul(b).unwrap()
+        }
+
+        fn exp(&self, a: &GroupElement, e: &BigUint) -> GroupElement {
+            a.pow(e)
+        }
+
+        fn inv(&self, a: &GroupElement) -> Option<GroupElement> {
+            Some(a.inverse())
+        }
+
+        fn hash_to_group(&self, input: &[u8]) -> GroupElement {
+            GroupElement::new(hash_group::<_, Blake2b>(input, &self.0), &self.0).unwrap()
+        }
+
+        fn elem_to_bytes(&self, a: &GroupElement) -> Vec<u8> {
+            a.value().to_bytes_be()
+        }
+
+        fn params_to_bytes(&self) -> Vec<u8> {
+            self.0.to_bytes_be()
+        }
+    }
+
+    #[test]
+    fn test_proofs_in_group() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let x = rng.gen_prime(256);
+        let u = rng.gen_prime(128);
+        let w = u.modpow(&x, &n);
+
+        let group = Units(n.clone());
+        let u_el = GroupElement::new(u.clone(), &n).unwrap();
+        let w_el = GroupElement::new(w.clone(), &n).unwrap();
+
+        let q = ni_poe_prove_in(&group, &x, &u_el, &w_el);
+        assert_eq!(q.value(), &ni_poe_prove(&x, &u, &w, &n));
+        assert_eq!(ni_poe_verify_in(&group, &x, &u_el, &w_el, &q), Ok(()));
+        assert_eq!(
+            ni_poe_verify_in(&group, &x, &u_el, &w_el, &u_el),
+            Err(VerifyFailure::ChallengeMismatch)
+        );
+
+        let (z, q_big, r) = ni_poke2_prove_in(&group, x.clone(), &u_el, &w_el).unwrap();
+        let pi = ni_poke2_prove(x.clone(), &u, &w, &n).unwrap();
+        assert_eq!((z.value(), q_big.value(), &r), (&pi.0, &pi.1, &pi.2));
+        assert_eq!(
+            ni_poke2_verify_in(&group, &u_el, &w_el, &(z, q_big, r)),
+            Ok(())
+        );
+        assert_eq!(
+            ni_poke2_verify_in(&ModN::new(&n).unwrap(), &u, &w, &pi),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_pietrzak() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+
+        for j in 0..6 {
+            let mut x = BigUint::one();
+            for _ in 0..j {
+                x *= rng.gen_prime(256);
+            }
+            let u = rng.gen_biguint(128);
+            let w = u.modpow(&x, &n);
+
+            let pi = Pietrzak::prove(&x, &u, &w, &n);
+            assert!(Pietrzak::verify(&x, &u, &w, &pi, &n));
+            assert!(!Pietrzak::verify(&(&x + 2u32), &u, &w, &pi, &n));
+            assert!(!Pietrzak::verify(&x, &u, &((&w * &u) % &n), &pi, &n));
+
+            if !pi.is_empty() {
+                let mut bad = pi.clone();
+                bad[0] = (&bad[0] * &u) % &n;
+                assert!(!Pietrzak::verify(&x, &u, &w, &bad, &n));
+                assert!(!Pietrzak::verify(&x, &u, &w, &pi[1..].to_vec(), &n));
+            }
+            let mut long = pi.clone();
+            long.push(BigUint::one());
+            assert!(!Pietrzak::verify(&x, &u, &w, &long, &n));
+        }
+    }
+
+    #[test]
+    fn test_ni_poe_committed() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+
+        for j in 1..6 {
+            let mut x = BigUint::one();
+            for _ in 0..j {
+                x *= rng.gen_prime(256);
+            }
+            let u = rng.gen_biguint(128);
+            let w = u.modpow(&x, &n);
+
+            let pi = CommittedWesolowski::prove(&x, &u, &w, &n);
+            assert_eq!(pi.commitment, exponent_commitment(&x));
+            assert!(ni_poe_verify_committed(&u, &w, &pi, &n));
+            assert!(CommittedWesolowski::verify(&x, &u, &w, &pi, &n));
+            assert!(!ni_poe_verify_committed(&u, &((&w * &u) % &n), &pi, &n));
+
+            // the commitment is bound to the challenge
+            let mut other = pi.clone();
+            other.commitment = exponent_commitment(&(&x + 2u32));
+            assert!(!ni_poe_verify_committed(&u, &w, &other, &n));
+
+            // a non-canonical residue
+            let l = committed_challenge(&pi.commitment, &u, &w, &n);
+            let mut shifted = pi.clone();
+            shifted.r = &pi.r + &l;
+            let u_inv = modpow_uint_int(&u, &BigInt::from(-1), &n).unwrap();
+            shifted.q = (&pi.q * u_inv) % &n;
+            assert!(!ni_poe_verify_committed(&u, &w, &shifted, &n));
+
+            // a proof for another exponent is caught by verifiers with the committed one
+            let y = &x + 2u32;
+            let wy = u.modpow(&y, &n);
+            let pi_y = ni_poe_prove_committed(&y, &u, &wy, &n);
+            assert!(ni_poe_verify_committed(&u, &wy, &pi_y, &n));
+            assert!(!ni_poe_verify_committed_full(&x, &u, &wy, &pi_y, &n));
+            let mut relabeled = pi_y.clone();
+            relabeled.commitment = pi.commitment.clone();
+            assert!(!ni_poe_verify_committed(&u, &wy, &relabeled, &n));
+        }
+    }
+
+    #[test]
+    fn test_ni_poe_qr() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+        let u = rng.gen_biguint(128);
+        let w = u.modpow(&x, &n);
+
+        let q = QrWesolowski::prove(&x, &u, &w, &n);
+        assert!(QrWesolowski::verify(&x, &u, &w, &q, &n));
+        assert!(!ni_poe_verify_qr(&x, &u, &((&w * &u) % &n), &q, &n));
+        assert!(!ni_poe_verify_qr(&(&x + 2u32), &u, &w, &q, &n));
+
+        // in Z_n^*, -1 proves the false u^x = -w, as the challenge is odd
+        let minus_w = &n - &w;
+        let l = PoeTranscript::new(&x).challenge(&ModN::new(&n).unwrap(), &u, &minus_w);
+        let forged = &n - u.modpow(&x.div_floor(&l), &n);
+        assert!(ni_poe_verify(&x, &u, &minus_w, &forged, &n));
+
+        // in QR_n, w and -w are the same element, and so are the proofs for either
+        assert!(ni_poe_verify_qr(&x, &u, &minus_w, &q, &n));
+        assert!(ni_poe_verify_qr(&x, &u, &w, &(&n - &q), &n));
+    }
+
+    #[test]
+    fn test_signed_wesolowski() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let x = rng.gen_prime(256) * rng.gen_prime(256);
+        let u = rng.gen_biguint(128);
+        let w = u.modpow(&x, &n);
+
+        let q = SignedWesolowski::prove(&x, &u, &w, &n);
+        assert!(SignedWesolowski::verify(&x, &u, &w, &q, &n));
+        assert!(!SignedWesolowski::verify(&x, &u, &((&w * &u) % &n), &q, &n));
+
+        // the same classes, but the proof has a single representation
+        let minus = |a: &BigUint| &n - a;
+        assert_eq!(SignedWesolowski::prove(&x, &minus(&u), &minus(&w), &n), q);
+        assert!(SignedWesolowski::verify(&x, &u, &minus(&w), &q, &n));
+        assert!(!SignedWesolowski::verify(&x, &u, &w, &minus(&q), &n));
+    }
+
+    #[test]
+    fn test_ni_poe_chunked() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint(128);
+
+        // the factors are generated on the fly, their product is never calculated
+        let xs = (0..2000u32).map(|i| (BigUint::from(i) << 200) + 1u32);
+        let w = chunk_products(xs.clone(), 100).fold(u.clone(), |w, (_, p)| w.modpow(&p, &n));
+
+        let q = ni_poe_prove_chunked(xs.clone(), 100, &u, &w, &n);
+        assert_eq!(ni_poe_prove_chunked(xs.clone(), 7, &u, &w, &n), q);
+        assert!(ni_poe_verify_chunked(xs.clone(), &u, &w, &q, &n));
+        assert!(!ni_poe_verify_chunked(xs.clone().skip(1), &u, &w, &q, &n));
+
+        // borrowed factors
+        let ys = xs.take(10).collect::<Vec<_>>();
+        let w = u.modpow(&product(&ys), &n);
+        let q = ni_poe_prove_chunked(&ys, 3, &u, &w, &n);
+        assert_eq!(ni_poe_prove_chunked(ys.clone(), 3, &u, &w, &n), q);
+        assert!(ni_poe_verify_chunked(&ys, &u, &w, &q, &n));
+        assert!(!ni_poe_verify_chunked(&ys[1..], &u, &w, &q, &n));
+    }
+
     #[test]
     fn test_ni_poke2() {
         let mut rng = thread_rng();
@@ -180,10 +1392,130 @@ mod tests {
                     let u = rng.gen_prime(k * 64);
                     let w = u.modpow(&x, &n);
 
-                    let pi = ni_poke2_prove(x.clone(), &u, &w, &n);
+                    let pi = ni_poke2_prove(x.clone(), &u, &w, &n).unwrap();
                     assert!(ni_poke2_verify(&u, &w, &pi, &n))
                 }
             }
         }
+        // negative exponents, the remainder has the sign of x
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint(128);
+        let x = -BigInt::from(rng.gen_prime(256));
+        let w = modpow_uint_int(&u, &x, &n).unwrap();
+        let (z, q_big, r) = ni_poke2_prove(x.clone(), &u, &w, &n).unwrap();
+        assert_eq!(r.sign(), Sign::Minus);
+        assert!(ni_poke2_verify(
+            &u,
+            &w,
+            &(z.clone(), q_big.clone(), r.clone()),
+            &n
+        ));
+        assert!(!ni_poke2_verify(&u, &w, &(z, q_big, -r), &n));
+    }
+
+    #[test]
+    fn test_ni_poke_star() {
+        let mut rng = thread_rng();
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let g = hash_group::<_, Blake2b>(b"generator", &n);
+
+        for j in 1..4 {
+            let x = BigInt::from(rng.gen_prime(j * 128));
+            for x in &[x.clone(), -x] {
+                let w = modpow_uint_int(&g, x, &n).unwrap();
+
+                let pi = ni_poke_star_prove(x.clone(), &g, &w, &n).unwrap();
+                assert!(ni_poke_star_verify(&g, &w, &pi, &n));
+                // another challenge, which r may not even be below
+                assert!(!ni_poke_star_verify(&g, &((&w * &g) % &n), &pi, &n));
+                assert_eq!(
+                    ni_poke_star_verify_detailed(&g, &w, &(pi.0.clone(), &pi.1 + 1u32), &n),
+                    Err(VerifyFailure::ChallengeMismatch)
+                );
+
+                // (Q g^{-1}, r + l) answers the challenge too, but r is not reduced
+                let l = poke_star_challenge(&ModN::new(&n).unwrap(), &g, &w);
+                let g_inv = modpow_uint_int(&g, &BigInt::from(-1), &n).unwrap();
+                let shifted = ((&pi.0 * g_inv) % &n, &pi.1 + &l);
+                assert_eq!(
+                    ni_poke_star_verify_detailed(&g, &w, &shifted, &n),
+                    Err(VerifyFailure::ResidueOutOfRange)
+                );
+            }
+        }
+
+        // the same proof in a generic group
+        let x = rng.gen_prime(256);
+        let w = g.modpow(&x, &n);
+        let group = Units(n.clone());
+        let g_el = GroupElement::new(g.clone(), &n).unwrap();
+        let w_el = GroupElement::new(w.clone(), &n).unwrap();
+        let (q, r) = ni_poke_star_prove_in(&group, x.clone(), &g_el, &w_el).unwrap();
+        assert_eq!(
+            (q.value().clone(), r.clone()),
+            ni_poke_star_prove(x, &g, &w, &n).unwrap()
+        );
+        assert_eq!(
+            ni_poke_star_verify_in(&group, &g_el, &w_el, &(q, r)),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_ni_poke2_batch() {
+        let mut rng = thread_rng();
+
+        let n = rng.gen_prime(128) * rng.gen_prime(128);
+        let u = rng.gen_biguint(128);
+
+        let xs = (0..8)
+            .map(|i| {
+                let x = BigInt::from(rng.gen_prime(128));
+                if i % 2 == 0 {
+                    x
+                } else {
+                    -x
+                }
+            })
+            .collect::<Vec<_>>();
+        let ws = xs
+            .iter()
+            .map(|x| modpow_uint_int(&u, x, &n).unwrap())
+            .collect::<Vec<_>>();
+
+        let pi = ni_poke2_prove_batch(&xs, &u, &ws, &n).unwrap();
+        assert!(ni_poke2_verify_batch(&u, &ws, &pi, &n));
+
+        // a single wrong statement fails the batch
+        let mut bad = ws.clone();
+        bad[3] = (&bad[3] * &u) % &n;
+        assert!(!ni_poke2_verify_batch(&u, &bad, &pi, &n));
+        assert!(!ni_poke2_verify_batch(&u, &ws[1..], &pi, &n));
+        let mut more = ws.clone();
+        more.push(u.clone());
+        assert!(!ni_poke2_verify_batch(&u, &more, &pi, &n));
+        assert!(!ni_poke2_verify_batch(&u, &[], &pi, &n));
+        assert_eq!(
+            ni_poke2_verify_batch_detailed(&u, &[], &pi, &n),
+            Err(VerifyFailure::EmptyBatch)
+        );
+
+        assert_eq!(
+            ni_poke2_prove_batch(&[], &u, &[], &n),
+            Err(Errors::EmptyBatch)
+        );
+        // mismatched lengths
+        assert_eq!(
+            ni_poke2_prove_batch(&xs[1..], &u, &ws, &n),
+            Err(Errors::InvalidParams)
+        );
+        assert_eq!(
+            ni_poke2_prove_batch(&xs, &u, &ws[1..], &n),
+            Err(Errors::InvalidParams)
+        );
+        assert_eq!(
+            ni_poke2_prove_batch(&[], &u, &ws, &n),
+            Err(Errors::InvalidParams)
+        );
     }
 }
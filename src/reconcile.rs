@@ -0,0 +1,280 @@
+//! Reconciling the sets of two accumulators with the same parameters, e.g. the revocation
+//! lists of two mirrors, by only transferring the buckets of elements in which they differ.
+//!
+//! Both parties split their elements into the same buckets, by the hash of the element, and
+//! exchange a [`SetSketch`] of their root and the roots `g^{\prod bucket}` of all buckets.
+//! Buckets with the same root hold the same elements. Each party sends its elements of the
+//! other buckets in a [`BucketTransfer`], and `Accumulator::reconcile` returns the symmetric
+//! difference. The number of buckets should be about the expected size of the difference, so
+//! the transfers carry few elements apart from it.
+//!
+//! A transfer is checked against the root of its sender: with `D_a` and `D_b` the products of
+//! the differing buckets of receiver and sender, `root_b^{D_a} = root_a^{D_b}` only holds if
+//! the sender's set is the receiver's with these buckets replaced. So the sender can neither
+//! hide differences behind forged bucket roots, nor send elements it didn't accumulate.
+
+use std::collections::HashSet;
+
+use blake2::{Blake2b, Digest};
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+
+use crate::accumulator::Accumulator;
+use crate::error::Errors;
+use crate::math::product;
+use crate::traits::StaticAccumulator;
+
+/// The root of a set, and of each of its buckets, see `Accumulator::sketch`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetSketch {
+    pub root: BigUint,
+    /// `g^{\prod bucket}`, for every bucket.
+    pub buckets: Vec<BigUint>,
+}
+
+impl SetSketch {
+    /// The buckets whose roots differ from those of `other`, in order.
+    /// Fails with `ParameterMismatch` if the number of buckets differs.
+    pub fn differing(&self, other: &SetSketch) -> Result<Vec<usize>, Errors> {
+        if self.buckets.len() != other.buckets.len() {
+            return Err(Errors::ParameterMismatch);
+        }
+
+        Ok((0..self.buckets.len())
+            .filter(|&i| self.buckets[i] != other.buckets[i])
+            .collect())
+    }
+
+    /// The elements of `members`, the set of this sketch, in the buckets which differ from
+    /// `other`, to be sent to the party of `other`.
+    /// Fails with `ParameterMismatch` if the number of buckets differs.
+    pub fn transfer(
+        &self,
+        other: &SetSketch,
+        members: &[BigUint],
+    ) -> Result<BucketTransfer, Errors> {
+        let differing = self.differing(other)?;
+        let count = self.buckets.len();
+
+        let mut buckets = differing
+            .iter()
+            .map(|&i| (i, Vec::new()))
+            .collect::<Vec<_>>();
+        for x in members {
+            let i = bucket_of(x, count);
+            if let Ok(pos) = differing.binary_search(&i) {
+                buckets[pos].1.push(x.clone());
+            }
+        }
+
+        Ok(BucketTransfer { buckets })
+    }
+}
+
+/// The elements of the buckets in which two sets differ, see `SetSketch::transfer`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BucketTransfer {
+    /// `(i, elements)`, ordered by the bucket `i`.
+    pub buckets: Vec<(usize, Vec<BigUint>)>,
+}
+
+/// The symmetric difference of two sets, see `Accumulator::reconcile`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Reconciliation {
+    /// Elements only the other party has.
+    pub missing: Vec<BigUint>,
+    /// Elements only this party has.
+    pub extra: Vec<BigUint>,
+}
+
+/// The bucket of `x`, the first 8 bytes of `H(x)` as `u64` BE, `mod buckets`.
+pub fn bucket_of(x: &BigUint, buckets: usize) -> usize {
+    let hash = Blake2b::digest(&x.to_bytes_be());
+    (BigEndian::read_u64(&hash[..8]) % buckets as u64) as usize
+}
+
+impl Accumulator {
+    /// Sketches the set, given by `members` in any order, split into `buckets` buckets.
+    ///
+    /// Fails with `OutOfSync` if `members` are not the elements of the accumulator, and with
+    /// `InvalidParams` if `buckets` is zero.
+    pub fn sketch(&self, members: &[BigUint], buckets: usize) -> Result<SetSketch, Errors> {
+        if buckets == 0 {
+            return Err(Errors::InvalidParams);
+        }
+        if members.len() != self.len() || &product(members) != self.set_product() {
+            return Err(Errors::OutOfSync);
+        }
+
+        let mut split = vec![Vec::new(); buckets];
+        for x in members {
+            split[bucket_of(x, buckets)].push(x.clone());
+        }
+
+        let (g, n) = (self.generator(), self.modulus());
+        Ok(SetSketch {
+            root: self.state().clone(),
+            buckets: split.iter().map(|xs| g.modpow(&product(xs), n)).collect(),
+        })
+    }
+
+    /// Checks the transfer of the party with the sketch `theirs`, and returns the elements
+    /// in which the sets differ. `ours` is the sketch of `members`, the elements of this
+    /// accumulator.
+    ///
+    /// Fails with `OutOfSync` if `ours` is not of the current root, with `ParameterMismatch`
+    /// if the sketches have different numbers of buckets, and with `InvalidProof` if the
+    /// transfer doesn't have exactly the differing buckets, or doesn't match the root of
+    /// `theirs`.
+    pub fn reconcile(
+        &self,
+        members: &[BigUint],
+        ours: &SetSketch,
+        theirs: &SetSketch,
+        transfer: &BucketTransfer,
+    ) -> Result<Reconciliation, Errors> {
+        if &ours.root != self.state() {
+            return Err(Errors::OutOfSync);
+        }
+
+        let differing = ours.differing(theirs)?;
+        let count = ours.buckets.len();
+        if transfer.buckets.len() != differing.len()
+            || transfer
+                .buckets
+                .iter()
+                .zip(&differing)
+                .any(|((i, xs), j)| i != j || xs.iter().any(|x| bucket_of(x, count) != *i))
+        {
+            return Err(Errors::InvalidProof);
+        }
+
+        let own = members
+            .iter()
+            .filter(|x| differing.binary_search(&bucket_of(x, count)).is_ok())
+            .cloned()
+            .collect::<Vec<_>>();
+        let other = transfer
+            .buckets
+            .iter()
+            .flat_map(|(_, xs)| xs.iter().cloned())
+            .collect::<Vec<_>>();
+
+        // root_b^{D_a} = root_a^{D_b}
+        let n = self.modulus();
+        if theirs.root.modpow(&product(&own), n) != ours.root.modpow(&product(&other), n) {
+            return Err(Errors::InvalidProof);
+        }
+
+        let (own_set, other_set) = (
+            own.iter().collect::<HashSet<_>>(),
+            other.iter().collect::<HashSet<_>>(),
+        );
+        Ok(Reconciliation {
+            missing: other
+                .iter()
+                .filter(|x| !own_set.contains(x))
+                .cloned()
+                .collect(),
+            extra: own
+                .iter()
+                .filter(|x| !other_set.contains(x))
+                .cloned()
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::PublicParams;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_reconcile() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..40)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+
+        // two mirrors, each missing some of the revocations of the other
+        let members_a = xs[..35].to_vec();
+        let members_b = [&xs[..30], &xs[35..]].concat();
+        let mut a = Accumulator::new_with_params(&params);
+        a.batch_add(&members_a);
+        let mut b = Accumulator::new_with_params(&params);
+        b.batch_add(&members_b);
+
+        let sketch_a = a.sketch(&members_a, 16).unwrap();
+        let sketch_b = b.sketch(&members_b, 16).unwrap();
+        let differing = sketch_a.differing(&sketch_b).unwrap();
+        assert!(!differing.is_empty() && differing.len() <= 10);
+        assert_eq!(sketch_b.differing(&sketch_a).unwrap(), differing);
+
+        let transfer_a = sketch_a.transfer(&sketch_b, &members_a).unwrap();
+        let transfer_b = sketch_b.transfer(&sketch_a, &members_b).unwrap();
+        let size = |t: &BucketTransfer| t.buckets.iter().map(|(_, xs)| xs.len()).sum::<usize>();
+        assert!(size(&transfer_a) < members_a.len() && size(&transfer_b) < members_b.len());
+
+        // dropped or foreign elements, and differences hidden behind forged bucket roots
+        let mut tampered = transfer_b.clone();
+        tampered.buckets[0].1.pop();
+        assert_eq!(
+            a.reconcile(&members_a, &sketch_a, &sketch_b, &tampered),
+            Err(Errors::InvalidProof)
+        );
+        assert_eq!(
+            a.reconcile(&members_a, &sketch_a, &sketch_b, &transfer_a),
+            Err(Errors::InvalidProof)
+        );
+        let mut hidden = sketch_b.clone();
+        hidden.buckets[differing[0]] = sketch_a.buckets[differing[0]].clone();
+        let partial = hidden.transfer(&sketch_a, &members_b).unwrap();
+        assert_eq!(
+            a.reconcile(&members_a, &sketch_a, &hidden, &partial),
+            Err(Errors::InvalidProof)
+        );
+
+        assert_eq!(a.sketch(&xs, 16), Err(Errors::OutOfSync));
+        assert_eq!(a.sketch(&members_a, 0), Err(Errors::InvalidParams));
+        assert_eq!(
+            sketch_a.differing(&a.sketch(&members_a, 8).unwrap()),
+            Err(Errors::ParameterMismatch)
+        );
+
+        let mut at_a = a
+            .reconcile(&members_a, &sketch_a, &sketch_b, &transfer_b)
+            .unwrap();
+        at_a.missing.sort();
+        at_a.extra.sort();
+        let mut expected = xs[35..].to_vec();
+        expected.sort();
+        assert_eq!(at_a.missing, expected);
+        let mut expected = xs[30..35].to_vec();
+        expected.sort();
+        assert_eq!(at_a.extra, expected);
+
+        // after exchanging the differences, both have the union
+        let at_b = b
+            .reconcile(&members_b, &sketch_b, &sketch_a, &transfer_a)
+            .unwrap();
+        a.batch_add(&at_a.missing);
+        b.batch_add(&at_b.missing);
+        assert_eq!(a.state(), b.state());
+        assert_eq!(
+            a.reconcile(&members_a, &sketch_a, &sketch_b, &transfer_b),
+            Err(Errors::OutOfSync)
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Bringing the witnesses of holders up to date after they were offline.
+//!
+//! A [`WitnessHolder`] keeps its element and an [`EpochWitness`]. Once back online, it asks
+//! for the changes since its epoch, and the publisher of the [`History`] answers with the
+//! cheapest [`Recovery`]: all checkpoints and deltas since then, combined into a single
+//! [`UpdateMessage`], or, if these were compacted or are larger than a new witness is worth,
+//! the advice to request a new witness from the manager.
+
+use num_bigint::BigUint;
+
+use crate::encoding::Encode;
+use crate::error::Errors;
+use crate::feed::UpdateMessage;
+use crate::history::{EpochWitness, History};
+use crate::math::product;
+
+/// How a holder brings its witness up to date, see `History::recovery`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    /// Nothing changed since the holder's epoch.
+    UpToDate,
+    /// All changes since the holder's epoch, up to the current one.
+    Update(UpdateMessage),
+    /// The changes were compacted, or are too large, a new witness has to be requested.
+    Rerequest,
+}
+
+impl History {
+    /// Combines all changes after `epoch` into a single update to the current epoch, which
+    /// witnesses from `epoch` can be updated with, see `feed::catch_up`.
+    ///
+    /// Fails with `UnknownEpoch` if the epoch wasn't reached yet, and with `Pruned` if it was
+    /// compacted.
+    pub fn combined_update(&self, epoch: u64) -> Result<UpdateMessage, Errors> {
+        if epoch > self.epoch() {
+            return Err(Errors::UnknownEpoch(epoch));
+        }
+        if self.root_at(epoch).is_none() {
+            return Err(Errors::Pruned(epoch));
+        }
+
+        // like the checkpoints, the roots satisfy `A_now^deleted = A_epoch^added`
+        let mut added = Vec::new();
+        let mut deleted = Vec::new();
+        for checkpoint in self.checkpoints().iter().filter(|c| c.from_epoch >= epoch) {
+            added.push(checkpoint.added.clone());
+            deleted.push(checkpoint.deleted.clone());
+        }
+        for delta in self.since(epoch).unwrap_or_else(|| self.export()) {
+            if delta.kind.is_add() {
+                added.extend_from_slice(&delta.elements);
+            } else {
+                deleted.extend_from_slice(&delta.elements);
+            }
+        }
+
+        Ok(UpdateMessage {
+            epoch: self.epoch(),
+            added: product(&added),
+            deleted: product(&deleted),
+            root: self.root().clone(),
+        })
+    }
+
+    /// Chooses how a holder of a witness from `epoch` catches up: with `combined_update`, or
+    /// by requesting a new witness if that is not retained or encodes to more than `max_bytes`.
+    /// A new witness costs the manager an exponentiation by the whole set, so `max_bytes`
+    /// should be well above the size of one.
+    ///
+    /// Fails with `UnknownEpoch` if the epoch wasn't reached yet.
+    pub fn recovery(&self, epoch: u64, max_bytes: usize) -> Result<Recovery, Errors> {
+        if epoch == self.epoch() {
+            return Ok(Recovery::UpToDate);
+        }
+
+        match self.combined_update(epoch) {
+            Ok(update) if update.to_bytes().len() <= max_bytes => Ok(Recovery::Update(update)),
+            Ok(_) | Err(Errors::Pruned(_)) => Ok(Recovery::Rerequest),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// The holder of an element, and its membership witness.
+/// This is small and can be persisted while the holder is offline.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WitnessHolder {
+    x: BigUint,
+    witness: EpochWitness<BigUint>,
+}
+
+impl WitnessHolder {
+    pub fn new(x: BigUint, witness: EpochWitness<BigUint>) -> Self {
+        WitnessHolder { x, witness }
+    }
+
+    pub fn element(&self) -> &BigUint {
+        &self.x
+    }
+
+    pub fn witness(&self) -> &EpochWitness<BigUint> {
+        &self.witness
+    }
+
+    /// The epoch to ask `History::recovery` for.
+    pub fn epoch(&self) -> u64 {
+        self.witness.epoch
+    }
+
+    /// Applies the answer of `History::recovery`. Returns `false` if a new witness has to be
+    /// requested from the manager, see `replace`.
+    ///
+    /// Fails with `NotAMember` if the element was deleted, and with `InvalidDelta` if the
+    /// updated witness doesn't match the root of the update. The witness is unchanged then.
+    /// All operations are `mod n`.
+    pub fn apply(&mut self, recovery: &Recovery, n: &BigUint) -> Result<bool, Errors> {
+        let update = match recovery {
+            Recovery::UpToDate => return Ok(true),
+            Recovery::Rerequest => return Ok(false),
+            Recovery::Update(update) => update,
+        };
+
+        let w = update
+            .apply(&self.witness.witness, &self.x, n)
+            .ok_or(Errors::NotAMember)?;
+        if w.modpow(&self.x, n) != update.root {
+            return Err(Errors::InvalidDelta(update.epoch));
+        }
+
+        self.witness = EpochWitness {
+            epoch: update.epoch,
+            witness: w,
+        };
+        Ok(true)
+    }
+
+    /// Replaces the witness by a new one from the manager, after checking it against `root`,
+    /// the root of its epoch.
+    /// Fails with `NotAMember` if it doesn't verify.
+    /// All operations are `mod n`.
+    pub fn replace(
+        &mut self,
+        witness: EpochWitness<BigUint>,
+        root: &BigUint,
+        n: &BigUint,
+    ) -> Result<(), Errors> {
+        if &witness.witness.modpow(&self.x, n) != root {
+            return Err(Errors::NotAMember);
+        }
+
+        self.witness = witness;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::{Accumulator, PublicParams};
+    use crate::group::RSAGroup;
+    use crate::hooks::Mutation;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_witness_recovery() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let n = params.n.clone();
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let history = Arc::new(Mutex::new(History::new(params)));
+        let observer = history.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..4]);
+        acc.add(&xs[4]);
+        let mut holder =
+            WitnessHolder::new(xs[0].clone(), acc.mem_wit_create_at_epoch(&xs[0]).unwrap());
+        let mut other =
+            WitnessHolder::new(xs[1].clone(), acc.mem_wit_create_at_epoch(&xs[1]).unwrap());
+        let mut late =
+            WitnessHolder::new(xs[2].clone(), acc.mem_wit_create_at_epoch(&xs[2]).unwrap());
+        assert_eq!(
+            history.lock().unwrap().recovery(holder.epoch(), 0),
+            Ok(Recovery::UpToDate)
+        );
+
+        // offline for a few epochs, some of which are compacted
+        acc.batch_add(&xs[5..8]);
+        acc.del(&xs[3]).unwrap();
+        acc.add(&xs[8]);
+        history.lock().unwrap().compact(2).unwrap();
+        history.lock().unwrap().compact(4).unwrap();
+        acc.del(&xs[1]).unwrap();
+        acc.add(&xs[9]);
+
+        let history = history.lock().unwrap();
+        let recovery = history.recovery(holder.epoch(), 1 << 20).unwrap();
+        assert!(holder.apply(&recovery, &n).unwrap());
+        assert_eq!(holder.epoch(), acc.epoch());
+        assert!(acc.ver_mem(&holder.witness().witness, &xs[0]));
+
+        // deleted meanwhile
+        let before = other.clone();
+        assert_eq!(other.apply(&recovery, &n), Err(Errors::NotAMember));
+        assert_eq!(other, before);
+
+        // too large to download, or compacted
+        assert_eq!(history.recovery(late.epoch(), 8), Ok(Recovery::Rerequest));
+        assert_eq!(history.combined_update(3), Err(Errors::Pruned(3)));
+        assert_eq!(history.recovery(3, 1 << 20), Ok(Recovery::Rerequest));
+        assert_eq!(
+            history.recovery(acc.epoch() + 1, 1 << 20),
+            Err(Errors::UnknownEpoch(acc.epoch() + 1))
+        );
+        assert_eq!(late.apply(&Recovery::Rerequest, &n), Ok(false));
+        assert_eq!(acc.mem_wit_create_at_epoch(&xs[3]), Err(Errors::NotAMember));
+        let mut w = acc.mem_wit_create_at_epoch(&xs[2]).unwrap();
+        w.witness = (&w.witness * &w.witness) % &n;
+        assert_eq!(late.replace(w, acc.state(), &n), Err(Errors::NotAMember));
+        late.replace(
+            acc.mem_wit_create_at_epoch(&xs[2]).unwrap(),
+            acc.state(),
+            &n,
+        )
+        .unwrap();
+        assert!(late
+            .apply(&history.recovery(late.epoch(), 0).unwrap(), &n)
+            .unwrap());
+    }
+}
@@ -0,0 +1,287 @@
+//! A deliberately naive implementation of the accumulator, for differential testing.
+//!
+//! It keeps the elements as a list and recomputes everything from the definitions in the
+//! BBF paper, using its own exponentiation, inversion and hashing to primes. Randomized
+//! operation sequences are run against both implementations, comparing roots, witnesses and
+//! proofs after every step.
+
+use blake2::{Blake2b, Digest};
+use num_bigint::{BigInt, BigUint, Sign};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+/// The reference accumulator `A = g^{\prod S}`.
+pub struct Reference {
+    n: BigUint,
+    g: BigUint,
+    elements: Vec<BigUint>,
+}
+
+impl Reference {
+    pub fn new(n: &BigUint, g: &BigUint) -> Self {
+        Reference {
+            n: n.clone(),
+            g: g.clone(),
+            elements: Vec::new(),
+        }
+    }
+
+    pub fn elements(&self) -> &[BigUint] {
+        &self.elements
+    }
+
+    pub fn add(&mut self, x: &BigUint) {
+        self.elements.push(x.clone());
+    }
+
+    /// Returns `false` if `x` is not an element.
+    pub fn del(&mut self, x: &BigUint) -> bool {
+        match self.elements.iter().position(|y| y == x) {
+            Some(i) => {
+                self.elements.remove(i);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn root(&self) -> BigUint {
+        self.pow_product(&self.g, self.elements.iter())
+    }
+
+    /// `w = g^{\prod S \ {x}}`, or `None` if `x` is not an element.
+    pub fn mem_wit(&self, x: &BigUint) -> Option<BigUint> {
+        let i = self.elements.iter().position(|y| y == x)?;
+        let others = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter(|(j, _)| *j != i)
+            .map(|(_, y)| y);
+
+        Some(self.pow_product(&self.g, others))
+    }
+
+    /// `d^x A^b = g`
+    pub fn ver_non_mem(&self, d: &BigUint, b: &BigInt, x: &BigUint) -> bool {
+        let a_b = match signed_pow(&self.root(), b, &self.n) {
+            Some(a_b) => a_b,
+            None => return false,
+        };
+
+        (pow(d, x, &self.n) * a_b) % &self.n == self.g
+    }
+
+    /// The NI-PoE `Q = u^{floor(x / l)}` for `u^x = w`, with `l = H_prime("poe", x, n, u, w)`
+    /// and every part but the tag prefixed by its length as `u32` BE.
+    pub fn poe(&self, x: &BigUint, u: &BigUint, w: &BigUint) -> BigUint {
+        let mut to_hash = b"poe".to_vec();
+        for part in &[x, &self.n, u, w] {
+            let bytes = part.to_bytes_be();
+            to_hash.extend(&(bytes.len() as u32).to_be_bytes());
+            to_hash.extend(bytes);
+        }
+        let l = hash_prime(&to_hash);
+
+        pow(u, &(x / l), &self.n)
+    }
+
+    /// Exponentiates one element at a time.
+    fn pow_product<'a>(&self, base: &BigUint, xs: impl Iterator<Item = &'a BigUint>) -> BigUint {
+        xs.fold(base.clone(), |acc, x| pow(&acc, x, &self.n))
+    }
+}
+
+/// The first `counter = 0, 1, ..` for which the leading 128 bits of `Blake2b(input || counter)`,
+/// with the top bit set, are prime.
+fn hash_prime(input: &[u8]) -> BigUint {
+    (0u64..)
+        .map(|counter| {
+            let mut to_hash = input.to_vec();
+            to_hash.extend(&counter.to_be_bytes());
+            let mut bytes = Blake2b::digest(&to_hash)[..16].to_vec();
+            bytes[0] |= 0x80;
+            BigUint::from_bytes_be(&bytes)
+        })
+        .find(is_prime)
+        .unwrap()
+}
+
+/// Miller-Rabin, with the primes up to 71 as bases.
+fn is_prime(p: &BigUint) -> bool {
+    let bases = [
+        2u32, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71,
+    ];
+    if bases.iter().any(|b| *p == BigUint::from(*b)) {
+        return true;
+    }
+    if *p < BigUint::from(73u32) || p.is_even() {
+        return false;
+    }
+
+    let one = BigUint::one();
+    let p_1 = p - &one;
+
+    // p - 1 = d 2^s
+    let s = (0usize..).find(|i| (&p_1 >> *i).is_odd()).unwrap();
+    let d = &p_1 >> s;
+    bases.iter().all(|b| {
+        let mut y = pow(&BigUint::from(*b), &d, p);
+        if y == one || y == p_1 {
+            return true;
+        }
+        for _ in 1..s {
+            y = (&y * &y) % p;
+            if y == p_1 {
+                return true;
+            }
+        }
+        false
+    })
+}
+
+/// Left to right square and multiply.
+fn pow(base: &BigUint, e: &BigUint, n: &BigUint) -> BigUint {
+    let mut res = BigUint::one() % n;
+    for i in (0..e.bits()).rev() {
+        res = (&res * &res) % n;
+        if ((e >> i) & BigUint::one()).is_one() {
+            res = (res * base) % n;
+        }
+    }
+    res
+}
+
+fn signed_pow(base: &BigUint, e: &BigInt, n: &BigUint) -> Option<BigUint> {
+    let (sign, abs) = e.to_bytes_be();
+    let abs = BigUint::from_bytes_be(&abs);
+    if sign == Sign::Minus {
+        Some(pow(&inverse(base, n)?, &abs, n))
+    } else {
+        Some(pow(base, &abs, n))
+    }
+}
+
+/// Extended Euclid on `(a, n)`.
+fn inverse(a: &BigUint, n: &BigUint) -> Option<BigUint> {
+    let n_int = BigInt::from(n.clone());
+    let (mut r0, mut r1) = (n_int.clone(), BigInt::from(a % n));
+    let (mut t0, mut t1) = (BigInt::zero(), BigInt::one());
+    while !r1.is_zero() {
+        let q = &r0 / &r1;
+        let r2 = &r0 - &q * &r1;
+        let t2 = &t0 - &q * &t1;
+        r0 = std::mem::replace(&mut r1, r2);
+        t0 = std::mem::replace(&mut t1, t2);
+    }
+    if !r0.is_one() {
+        return None;
+    }
+
+    (((t0 % &n_int) + &n_int) % &n_int).to_biguint()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    fn check(acc: &Accumulator, reference: &Reference, rng: &mut ChaChaRng, int_size_bits: usize) {
+        assert_eq!(acc.state(), &reference.root());
+        assert_eq!(acc.len(), reference.elements().len());
+
+        for x in reference.elements() {
+            let w = reference.mem_wit(x).unwrap();
+            assert_eq!(acc.mem_wit_create(x), w);
+            assert!(acc.ver_mem(&w, x));
+        }
+
+        let x = rng.gen_prime(int_size_bits);
+        let (d, b) = acc.non_mem_wit_create(&x).unwrap();
+        assert!(reference.ver_non_mem(&d, &b, &x));
+    }
+
+    #[test]
+    fn test_hash_prime() {
+        for input in &[&b""[..], b"poe", b"hello world", &[0u8; 64]] {
+            assert_eq!(
+                hash_prime(input),
+                crate::hash::hash_prime::<_, Blake2b>(input)
+            );
+        }
+
+        let primes = (0u32..200).filter(|p| is_prime(&BigUint::from(*p)));
+        assert_eq!(primes.count(), 46);
+        // Carmichael numbers and a strong pseudoprime to base 2
+        for c in &[561u32, 1105, 2047, 8911, 3215031751] {
+            assert!(!is_prime(&BigUint::from(*c)));
+        }
+    }
+
+    #[test]
+    fn test_differential() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+
+        for _ in 0..4 {
+            let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+            let n = acc.modulus().clone();
+            let mut reference = Reference::new(&n, acc.generator());
+
+            for _ in 0..20 {
+                let len = reference.elements().len();
+                let old_root = reference.root();
+
+                match rng.gen_range(0, 4) {
+                    0 => {
+                        let x = rng.gen_prime(int_size_bits);
+                        acc.add(&x);
+                        reference.add(&x);
+                    }
+                    1 => {
+                        let xs = (0..rng.gen_range(1, 4))
+                            .map(|_| rng.gen_prime(int_size_bits))
+                            .collect::<Vec<_>>();
+                        let proof = acc.batch_add(&xs);
+                        xs.iter().for_each(|x| reference.add(x));
+
+                        let x_star = xs.iter().product();
+                        assert_eq!(proof, reference.poe(&x_star, &old_root, &reference.root()));
+                        assert!(acc.ver_batch_add(&proof, &old_root, &xs));
+                    }
+                    2 if len > 0 => {
+                        let x = reference.elements()[rng.gen_range(0, len)].clone();
+                        acc.del(&x).unwrap();
+                        assert!(reference.del(&x));
+                    }
+                    3 if len > 1 => {
+                        let pairs = reference.elements()[..rng.gen_range(1, len)]
+                            .iter()
+                            .map(|x| (x.clone(), reference.mem_wit(x).unwrap()))
+                            .collect::<Vec<_>>();
+                        let proof = acc.batch_del(&pairs).unwrap();
+                        pairs.iter().for_each(|(x, _)| assert!(reference.del(x)));
+
+                        let xs = pairs.into_iter().map(|(x, _)| x).collect::<Vec<_>>();
+                        let x_star = xs.iter().product();
+                        assert_eq!(proof, reference.poe(&x_star, &reference.root(), &old_root));
+                        assert!(acc.ver_batch_del(&proof, &old_root, &xs));
+                    }
+                    _ => {
+                        let x = rng.gen_prime(int_size_bits);
+                        assert!(acc.del(&x).is_none());
+                        assert!(!reference.del(&x));
+                    }
+                }
+
+                check(&acc, &reference, rng, int_size_bits);
+            }
+        }
+    }
+}
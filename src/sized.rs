@@ -0,0 +1,205 @@
+//! Accumulators with the size of the modulus in their type.
+//!
+//! Roots, witnesses and proofs of a [`SizedAccumulator<BITS>`] are [`FixedUint<BITS>`], which
+//! always encode to `(BITS + 7) / 8` bytes, and passing the values of one parameter set to an
+//! accumulator of another size does not compile.
+
+use rand::{CryptoRng, Rng};
+
+use num_bigint::{BigInt, BigUint};
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::encoding::Encode;
+use crate::error::Errors;
+use crate::traits::*;
+
+/// An integer below `2^BITS`, e.g. an element of the group of a `BITS` bit modulus.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FixedUint<const BITS: usize>(BigUint);
+
+impl<const BITS: usize> FixedUint<BITS> {
+    /// The length of the encoding.
+    pub const BYTES: usize = (BITS + 7) / 8;
+
+    /// Returns `None` if `x` has more than `BITS` bits.
+    pub fn new(x: BigUint) -> Option<Self> {
+        if x.bits() > BITS {
+            return None;
+        }
+
+        Some(FixedUint(x))
+    }
+
+    pub fn as_biguint(&self) -> &BigUint {
+        &self.0
+    }
+
+    pub fn into_biguint(self) -> BigUint {
+        self.0
+    }
+}
+
+/// Big endian, left padded with zeros to exactly `BYTES` bytes.
+impl<const BITS: usize> Encode for FixedUint<BITS> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let bytes = self.0.to_bytes_be();
+        let mut res = vec![0u8; Self::BYTES - bytes.len()];
+        res.extend(bytes);
+        res
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Errors> {
+        if bytes.len() != Self::BYTES {
+            return Err(Errors::InvalidEncoding);
+        }
+
+        FixedUint::new(BigUint::from_bytes_be(bytes)).ok_or(Errors::InvalidEncoding)
+    }
+}
+
+/// An RSA accumulator whose modulus has `BITS` bits.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct SizedAccumulator<const BITS: usize> {
+    acc: Accumulator,
+}
+
+/// See [`ModulusSize::Rsa2048`](crate::accumulator::ModulusSize).
+pub type Accumulator2048 = SizedAccumulator<2048>;
+/// See [`ModulusSize::Rsa3072`](crate::accumulator::ModulusSize).
+pub type Accumulator3072 = SizedAccumulator<3072>;
+/// See [`ModulusSize::Rsa4096`](crate::accumulator::ModulusSize).
+pub type Accumulator4096 = SizedAccumulator<4096>;
+
+impl<const BITS: usize> SizedAccumulator<BITS> {
+    pub fn setup<G, R>(rng: &mut R) -> Self
+    where
+        G: PrimeGroup,
+        R: CryptoRng + Rng,
+    {
+        SizedAccumulator {
+            acc: Accumulator::new_with_params(&PublicParams::generate::<G, _>(rng, BITS)),
+        }
+    }
+
+    /// Fails with `ParameterMismatch` unless `acc` was set up with a `BITS` bit modulus.
+    pub fn from_accumulator(acc: Accumulator) -> Result<Self, Errors> {
+        if acc.params().int_size_bits != BITS || acc.modulus().bits() > BITS {
+            return Err(Errors::ParameterMismatch);
+        }
+
+        Ok(SizedAccumulator { acc })
+    }
+
+    pub fn inner(&self) -> &Accumulator {
+        &self.acc
+    }
+
+    pub fn into_inner(self) -> Accumulator {
+        self.acc
+    }
+
+    pub fn state(&self) -> FixedUint<BITS> {
+        self.elem(self.acc.state().clone())
+    }
+
+    pub fn add(&mut self, x: &BigUint) {
+        self.acc.add(x);
+    }
+
+    /// Returns `None` if `x` is not a member.
+    pub fn del(&mut self, x: &BigUint) -> Option<()> {
+        self.acc.del(x)
+    }
+
+    /// Adds all `xs`, returning the proof of the update, see `ver_batch_add`.
+    pub fn batch_add(&mut self, xs: &[BigUint]) -> FixedUint<BITS> {
+        let w = self.acc.batch_add(xs);
+        self.elem(w)
+    }
+
+    /// Verifies the update from `a_t` to the current root.
+    pub fn ver_batch_add(
+        &self,
+        w: &FixedUint<BITS>,
+        a_t: &FixedUint<BITS>,
+        xs: &[BigUint],
+    ) -> bool {
+        self.acc.ver_batch_add(&w.0, &a_t.0, xs)
+    }
+
+    pub fn mem_wit_create(&self, x: &BigUint) -> FixedUint<BITS> {
+        self.elem(self.acc.mem_wit_create(x))
+    }
+
+    pub fn ver_mem(&self, w: &FixedUint<BITS>, x: &BigUint) -> bool {
+        self.acc.ver_mem(&w.0, x)
+    }
+
+    pub fn non_mem_wit_create(&self, x: &BigUint) -> Result<(FixedUint<BITS>, BigInt), Errors> {
+        let (d, b) = self.acc.non_mem_wit_create(x)?;
+        Ok((self.elem(d), b))
+    }
+
+    pub fn ver_non_mem(&self, w: &(FixedUint<BITS>, BigInt), x: &BigUint) -> bool {
+        self.acc.ver_non_mem(&((w.0).0.clone(), w.1.clone()), x)
+    }
+
+    /// Values `mod n` always fit.
+    fn elem(&self, x: BigUint) -> FixedUint<BITS> {
+        FixedUint::new(x).expect("reduced mod a BITS bit modulus")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::RSAGroup;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_sized_accumulator() {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+
+        // insecure, but faster tests
+        let mut acc = SizedAccumulator::<256>::setup::<RSAGroup, _>(&mut rng);
+        let xs = vec![BigUint::from(7u32), BigUint::from(11u32)];
+        let a_t = acc.state();
+        let w = acc.batch_add(&xs);
+        assert!(acc.ver_batch_add(&w, &a_t, &xs));
+
+        let x = BigUint::from(13u32);
+        acc.add(&x);
+        let w = acc.mem_wit_create(&x);
+        assert!(acc.ver_mem(&w, &x));
+
+        let y = BigUint::from(17u32);
+        let u = acc.non_mem_wit_create(&y).unwrap();
+        assert!(acc.ver_non_mem(&u, &y));
+        assert!(!acc.ver_non_mem(&u, &x));
+
+        // fixed width encodings
+        assert_eq!(FixedUint::<256>::BYTES, 32);
+        let small = FixedUint::<256>::new(BigUint::from(1u32)).unwrap();
+        assert_eq!(small.to_bytes().len(), 32);
+        assert_eq!(FixedUint::<256>::from_bytes(&small.to_bytes()), Ok(small));
+        assert_eq!(acc.state().to_bytes().len(), 32);
+        assert_eq!(<(FixedUint<256>, BigInt)>::from_bytes(&u.to_bytes()), Ok(u));
+        assert_eq!(
+            FixedUint::<256>::from_bytes(&[1u8; 31]),
+            Err(Errors::InvalidEncoding)
+        );
+        assert_eq!(
+            FixedUint::<255>::from_bytes(&[0xff; 32]),
+            Err(Errors::InvalidEncoding)
+        );
+        assert!(FixedUint::<8>::new(BigUint::from(256u32)).is_none());
+
+        let inner = acc.into_inner();
+        assert!(SizedAccumulator::<512>::from_accumulator(inner.clone()).is_err());
+        let acc = SizedAccumulator::<256>::from_accumulator(inner).unwrap();
+        assert!(acc.ver_mem(&w, &x));
+    }
+}
@@ -0,0 +1,443 @@
+//! Snapshots of an accumulator together with its elements, for backups and fast restarts.
+//!
+//! The accumulator only keeps the product of its elements, so a [`Snapshot`] stores the
+//! element list next to the state. The elements are sorted and delta encoded, and the whole
+//! snapshot can be compressed with deflate (`deflate` feature) or zstd (`zstd` feature).
+//!
+//! Format: `magic || version || compression || body`, with the version as `u16` BE. The body is
+//! `int_size_bits || n || g || epoch || root || hash_key || qr` as length prefixed parts, with an
+//! empty `hash_key` for unkeyed parameters and `qr` a single byte, followed by the number of elements and the deltas between consecutive elements, each as its LEB128 length and big
+//! endian bytes. Snapshots of older versions are migrated on load, see [`crate::version`].
+
+use byteorder::{BigEndian, ByteOrder};
+use num_bigint::BigUint;
+use num_integer::Integer;
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::encoding::{read_part, write_part};
+use crate::error::Errors;
+use crate::traits::StaticAccumulator;
+use crate::version::{read_header, write_header, Migrations};
+
+#[cfg(any(feature = "deflate", feature = "zstd"))]
+use std::io::{Read, Write};
+
+const MAGIC: &[u8] = b"ACCSNAPV";
+
+/// Version 1 had no version field, its magic ended with the version instead.
+const MAGIC_V1: &[u8] = b"ACCSNAP1";
+
+/// The current snapshot format.
+pub const SNAPSHOT_VERSION: u16 = 4;
+
+/// The upgrades of older snapshot bodies.
+fn migrations() -> Migrations {
+    // version 2 only moved the version into its own field
+    Migrations::new()
+        .register(1, Ok)
+        .register(2, add_hash_key)
+        .register(3, add_qr)
+}
+
+/// Version 3 added the hash key after the root, older snapshots have none.
+fn add_hash_key(body: Vec<u8>) -> Result<Vec<u8>, Errors> {
+    let mut rest = &body[..];
+    for _ in 0..5 {
+        read_part(&mut rest)?;
+    }
+    let prefix = body.len() - rest.len();
+
+    let mut res = body[..prefix].to_vec();
+    write_part(&mut res, &[]);
+    res.extend(rest);
+    Ok(res)
+}
+
+/// Version 4 added the `QR_n` flag after the hash key, older snapshots are in `Z_n^*`.
+fn add_qr(body: Vec<u8>) -> Result<Vec<u8>, Errors> {
+    let mut rest = &body[..];
+    for _ in 0..6 {
+        read_part(&mut rest)?;
+    }
+    let prefix = body.len() - rest.len();
+
+    let mut res = body[..prefix].to_vec();
+    write_part(&mut res, &[0]);
+    res.extend(rest);
+    Ok(res)
+}
+
+/// The compression of a saved snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    #[cfg(feature = "deflate")]
+    Deflate,
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    fn tag(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => 1,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 2,
+        }
+    }
+
+    /// Fails with `InvalidEncoding` for unknown tags, and for compressions that weren't enabled.
+    fn from_tag(tag: u8) -> Result<Self, Errors> {
+        match tag {
+            0 => Ok(Compression::None),
+            #[cfg(feature = "deflate")]
+            1 => Ok(Compression::Deflate),
+            #[cfg(feature = "zstd")]
+            2 => Ok(Compression::Zstd),
+            _ => Err(Errors::InvalidEncoding),
+        }
+    }
+
+    fn compress(self, body: Vec<u8>) -> Vec<u8> {
+        match self {
+            Compression::None => body,
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                let mut encoder =
+                    flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+                encoder.write_all(&body).expect("compression failed");
+                encoder.finish().expect("compression failed")
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => libzstd::encode_all(&body[..], 0).expect("compression failed"),
+        }
+    }
+
+    fn decompress(self, bytes: &[u8]) -> Result<Vec<u8>, Errors> {
+        match self {
+            Compression::None => Ok(bytes.to_vec()),
+            #[cfg(feature = "deflate")]
+            Compression::Deflate => {
+                let mut body = Vec::new();
+                flate2::read::DeflateDecoder::new(bytes)
+                    .read_to_end(&mut body)
+                    .map_err(|_| Errors::InvalidEncoding)?;
+                Ok(body)
+            }
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => libzstd::decode_all(bytes).map_err(|_| Errors::InvalidEncoding),
+        }
+    }
+}
+
+/// The state of an accumulator, together with all its elements.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub params: PublicParams,
+    pub epoch: u64,
+    pub root: BigUint,
+    /// Sorted in ascending order.
+    pub elements: Vec<BigUint>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of `acc`, whose elements are `elements`, in any order.
+    /// Fails with `OutOfSync` if the number of elements doesn't match.
+    pub fn new(acc: &Accumulator, mut elements: Vec<BigUint>) -> Result<Self, Errors> {
+        if elements.len() != acc.len() {
+            return Err(Errors::OutOfSync);
+        }
+        elements.sort();
+
+        Ok(Snapshot {
+            params: acc.params(),
+            epoch: acc.epoch(),
+            root: acc.state().clone(),
+            elements,
+        })
+    }
+
+    /// Rebuilds the accumulator, see `Accumulator::rebuild`.
+    /// Fails with `Diverged(epoch)` if the elements don't match the root.
+    pub fn restore(&self) -> Result<Accumulator, Errors> {
+        if self.params.n.is_even() {
+            return Err(Errors::InvalidParams);
+        }
+
+        let mut acc = Accumulator::rebuild(&self.params, self.elements.iter().cloned());
+        if acc.state() != &self.root {
+            return Err(Errors::Diverged(self.epoch));
+        }
+        acc.set_epoch(self.epoch);
+
+        Ok(acc)
+    }
+
+    pub fn save(&self, compression: Compression) -> Vec<u8> {
+        let mut body = Vec::new();
+        write_part(&mut body, &u64_bytes(self.params.int_size_bits as u64));
+        write_part(&mut body, &self.params.n.to_bytes_be());
+        write_part(&mut body, &self.params.g.to_bytes_be());
+        write_part(&mut body, &u64_bytes(self.epoch));
+        write_part(&mut body, &self.root.to_bytes_be());
+        write_part(
+            &mut body,
+            self.params.hash_key.as_ref().map_or(&[], |key| &key[..]),
+        );
+        write_part(&mut body, &[u8::from(self.params.qr)]);
+
+        write_varint(&mut body, self.elements.len() as u64);
+        let mut prev = BigUint::from(0u32);
+        for x in &self.elements {
+            let delta = (x - &prev).to_bytes_be();
+            write_varint(&mut body, delta.len() as u64);
+            body.extend(&delta);
+            prev = x.clone();
+        }
+
+        let mut res = Vec::new();
+        write_header(&mut res, MAGIC, SNAPSHOT_VERSION);
+        res.push(compression.tag());
+        res.extend(compression.compress(body));
+        res
+    }
+
+    /// Loads a snapshot created by `save`, with any compression, of this or an older version.
+    /// Fails with `UnsupportedVersion` for newer versions.
+    pub fn load(bytes: &[u8]) -> Result<Self, Errors> {
+        let (version, rest) = if bytes.starts_with(MAGIC_V1) {
+            (1, &bytes[MAGIC_V1.len()..])
+        } else {
+            read_header(bytes, MAGIC)?
+        };
+        let (tag, rest) = rest.split_first().ok_or(Errors::InvalidEncoding)?;
+        let compression = Compression::from_tag(*tag)?;
+        let body = compression.decompress(rest)?;
+        let body = migrations().upgrade(version, body, SNAPSHOT_VERSION)?;
+
+        let mut bytes = &body[..];
+        let int_size_bits = read_u64(&mut bytes)? as usize;
+        let n = BigUint::from_bytes_be(read_part(&mut bytes)?);
+        let g = BigUint::from_bytes_be(read_part(&mut bytes)?);
+        let epoch = read_u64(&mut bytes)?;
+        let root = BigUint::from_bytes_be(read_part(&mut bytes)?);
+        let hash_key = match read_part(&mut bytes)? {
+            [] => None,
+            key if key.len() == 32 => {
+                let mut res = [0u8; 32];
+                res.copy_from_slice(key);
+                Some(res)
+            }
+            _ => return Err(Errors::InvalidEncoding),
+        };
+        let qr = match read_part(&mut bytes)? {
+            [0] => false,
+            [1] => true,
+            _ => return Err(Errors::InvalidEncoding),
+        };
+
+        let len = read_varint(&mut bytes)?;
+        let mut elements = Vec::new();
+        let mut prev = BigUint::from(0u32);
+        for _ in 0..len {
+            let size = read_varint(&mut bytes)? as usize;
+            if size > bytes.len() {
+                return Err(Errors::InvalidEncoding);
+            }
+            let (delta, rest) = bytes.split_at(size);
+            bytes = rest;
+
+            prev += BigUint::from_bytes_be(delta);
+            elements.push(prev.clone());
+        }
+        if !bytes.is_empty() {
+            return Err(Errors::InvalidEncoding);
+        }
+
+        Ok(Snapshot {
+            params: PublicParams {
+                int_size_bits,
+                n,
+                g,
+                hash_key,
+                qr,
+            },
+            epoch,
+            root,
+            elements,
+        })
+    }
+}
+
+fn u64_bytes(v: u64) -> [u8; 8] {
+    let mut res = [0u8; 8];
+    BigEndian::write_u64(&mut res, v);
+    res
+}
+
+fn read_u64(bytes: &mut &[u8]) -> Result<u64, Errors> {
+    let part = read_part(bytes)?;
+    if part.len() != 8 {
+        return Err(Errors::InvalidEncoding);
+    }
+
+    Ok(BigEndian::read_u64(part))
+}
+
+/// Appends `v` as LEB128.
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    loop {
+        let b = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            out.push(b);
+            return;
+        }
+        out.push(b | 0x80);
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<u64, Errors> {
+    let mut v = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (b, rest) = bytes.split_first().ok_or(Errors::InvalidEncoding)?;
+        *bytes = rest;
+
+        v |= u64::from(b & 0x7f) << shift;
+        if b & 0x80 == 0 {
+            return Ok(v);
+        }
+    }
+
+    Err(Errors::InvalidEncoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_snapshot() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..100)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..60]);
+        acc.batch_add(&xs[60..]);
+
+        assert_eq!(
+            Snapshot::new(&acc, xs[1..].to_vec()).unwrap_err(),
+            Errors::OutOfSync
+        );
+        let snapshot = Snapshot::new(&acc, xs.clone()).unwrap();
+
+        let mut compressions = vec![Compression::None];
+        #[cfg(feature = "deflate")]
+        compressions.push(Compression::Deflate);
+        #[cfg(feature = "zstd")]
+        compressions.push(Compression::Zstd);
+
+        for compression in compressions {
+            let bytes = snapshot.save(compression);
+            let loaded = Snapshot::load(&bytes).unwrap();
+            assert_eq!(loaded, snapshot);
+
+            let restored = loaded.restore().unwrap();
+            assert_eq!(restored.state(), acc.state());
+            assert_eq!(restored.epoch(), 2);
+            assert_eq!(restored.len(), 100);
+
+            assert_eq!(
+                Snapshot::load(&bytes[..bytes.len() - 1]).unwrap_err(),
+                Errors::InvalidEncoding
+            );
+        }
+
+        // the deltas are shorter than the elements
+        let plain = xs.iter().map(|x| 4 + x.to_bytes_be().len()).sum::<usize>();
+        assert!(snapshot.save(Compression::None).len() < plain);
+
+        let mut bad = snapshot.clone();
+        bad.elements[3] += 2u32;
+        assert_eq!(bad.restore().unwrap_err(), Errors::Diverged(2));
+        bad.params.n += 1u32;
+        assert_eq!(bad.restore().unwrap_err(), Errors::InvalidParams);
+        assert_eq!(
+            Snapshot::load(b"ACCSNAP1\x07").unwrap_err(),
+            Errors::InvalidEncoding
+        );
+
+        // older snapshots are still loaded, newer ones are rejected
+        let bytes = snapshot.save(Compression::None);
+        let header = MAGIC.len() + 2;
+        let body = &bytes[header + 1..];
+        let mut rest = body;
+        for _ in 0..5 {
+            read_part(&mut rest).unwrap();
+        }
+        // without the empty hash key and the QR_n flag
+        assert_eq!(&rest[..9], &[0, 0, 0, 0, 0, 0, 0, 1, 0]);
+        let mut legacy = body[..body.len() - rest.len()].to_vec();
+        let mut v3_body = legacy.clone();
+        legacy.extend(&rest[9..]);
+        v3_body.extend(&rest[..4]);
+        v3_body.extend(&rest[9..]);
+
+        let mut v1 = MAGIC_V1.to_vec();
+        v1.push(Compression::None.tag());
+        v1.extend(&legacy);
+        assert_eq!(Snapshot::load(&v1).unwrap(), snapshot);
+        let mut v2 = Vec::new();
+        write_header(&mut v2, MAGIC, 2);
+        v2.push(Compression::None.tag());
+        v2.extend(&legacy);
+        assert_eq!(Snapshot::load(&v2).unwrap(), snapshot);
+        let mut v3 = Vec::new();
+        write_header(&mut v3, MAGIC, 3);
+        v3.push(Compression::None.tag());
+        v3.extend(&v3_body);
+        assert_eq!(Snapshot::load(&v3).unwrap(), snapshot);
+
+        let mut future = Vec::new();
+        write_header(&mut future, MAGIC, SNAPSHOT_VERSION + 1);
+        future.extend(&bytes[header..]);
+        assert_eq!(
+            Snapshot::load(&future).unwrap_err(),
+            Errors::UnsupportedVersion(SNAPSHOT_VERSION + 1)
+        );
+
+        // keyed parameters
+        let params = acc.params().with_random_hash_key(rng);
+        let mut keyed = Accumulator::new_with_params(&params);
+        let x = params.hash_to_prime(b"x");
+        keyed.add(&x);
+        let snapshot = Snapshot::new(&keyed, vec![x]).unwrap();
+        let loaded = Snapshot::load(&snapshot.save(Compression::None)).unwrap();
+        assert_eq!(loaded, snapshot);
+        assert_eq!(loaded.restore().unwrap().params(), params);
+
+        // parameters in QR_n
+        let params = acc.params().into_qr();
+        let mut qr = Accumulator::new_with_params(&params);
+        qr.add(&xs[0]);
+        let snapshot = Snapshot::new(&qr, vec![xs[0].clone()]).unwrap();
+        let restored = Snapshot::load(&snapshot.save(Compression::None))
+            .unwrap()
+            .restore()
+            .unwrap();
+        assert!(restored.is_qr());
+        assert_eq!(restored.params(), params);
+    }
+}
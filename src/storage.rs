@@ -0,0 +1,411 @@
+//! File backed storage for the accumulated elements.
+//!
+//! Elements are stored as fixed size records in a memory-mapped file, so the element list
+//! of accumulators with tens of millions of members doesn't have to fit into memory.
+//! Changes become durable with `commit`, which flushes the dirty pages and records the
+//! epoch, typically once per published epoch.
+//!
+//! The store doesn't follow the accumulator by itself: the owner pushes added and removes
+//! deleted elements, commits with `Accumulator::epoch` after every published epoch, and
+//! after a restart continues with the accumulator from `restore`. The accumulator still
+//! holds the product of its elements in memory, only the element list lives in the file.
+//!
+//! File layout: a header `magic || element_bytes || len || epoch` (integers as `u64` BE),
+//! followed by `len` records `live flag || element`, with the element as zero padded BE bytes.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder};
+use memmap::MmapMut;
+use num_bigint::BigUint;
+use num_integer::Integer;
+use num_traits::One;
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::error::Errors;
+use crate::math::product;
+
+const MAGIC: &[u8; 8] = b"ACCELEMS";
+const HEADER_BYTES: usize = 32;
+/// Records allocated in a new file.
+const INITIAL_CAPACITY: usize = 1024;
+/// The number of elements multiplied at once, when computing the product of all elements.
+const PRODUCT_CHUNK: usize = 4096;
+
+/// A list of elements, stored in a memory-mapped file.
+#[derive(Debug)]
+pub struct ElementStore {
+    file: File,
+    map: MmapMut,
+    /// The size of a single element, in bytes.
+    element_bytes: usize,
+    /// The number of records, including deleted ones.
+    len: usize,
+    /// The number of records that fit into the file.
+    capacity: usize,
+    /// The last committed epoch.
+    epoch: u64,
+}
+
+impl ElementStore {
+    /// Creates a new store at `path`, for elements of at most `element_bytes` bytes.
+    /// Fails if the file exists already.
+    pub fn create<P: AsRef<Path>>(path: P, element_bytes: usize) -> io::Result<Self> {
+        if element_bytes == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "element size must not be zero",
+            ));
+        }
+
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(path)?;
+        file.set_len(file_size(INITIAL_CAPACITY, element_bytes)?)?;
+
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[..8].copy_from_slice(MAGIC);
+        BigEndian::write_u64(&mut map[8..16], element_bytes as u64);
+
+        let mut store = ElementStore {
+            file,
+            map,
+            element_bytes,
+            len: 0,
+            capacity: INITIAL_CAPACITY,
+            epoch: 0,
+        };
+        store.commit(0)?;
+
+        Ok(store)
+    }
+
+    /// Opens an existing store, at the state of the last commit.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let map = unsafe { MmapMut::map_mut(&file)? };
+
+        if map.len() < HEADER_BYTES || &map[..8] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not an element store",
+            ));
+        }
+
+        let corrupted =
+            || io::Error::new(io::ErrorKind::InvalidData, "corrupted element store header");
+
+        // the header is untrusted, so none of this may overflow
+        let element_bytes = BigEndian::read_u64(&map[8..16]) as usize;
+        let len = BigEndian::read_u64(&map[16..24]) as usize;
+        let epoch = BigEndian::read_u64(&map[24..32]);
+        let record_bytes = element_bytes
+            .checked_add(1)
+            .filter(|_| element_bytes > 0)
+            .ok_or_else(corrupted)?;
+        let capacity = (map.len() - HEADER_BYTES) / record_bytes;
+
+        if len > capacity {
+            return Err(corrupted());
+        }
+
+        Ok(ElementStore {
+            file,
+            map,
+            element_bytes,
+            len,
+            capacity,
+            epoch,
+        })
+    }
+
+    /// Returns the number of records, including deleted ones.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the last committed epoch.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Appends `x`, and returns its index.
+    pub fn push(&mut self, x: &BigUint) -> io::Result<usize> {
+        let bytes = x.to_bytes_be();
+        if bytes.len() > self.element_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "element is too large for this store",
+            ));
+        }
+
+        if self.len == self.capacity {
+            self.grow()?;
+        }
+
+        let i = self.len;
+        let record = self.record_mut(i);
+        for b in record.iter_mut() {
+            *b = 0;
+        }
+        record[0] = 1;
+        let offset = record.len() - bytes.len();
+        record[offset..].copy_from_slice(&bytes);
+
+        self.len += 1;
+        Ok(i)
+    }
+
+    /// Returns the element at index `i`, or `None` if it was deleted.
+    pub fn get(&self, i: usize) -> Option<BigUint> {
+        if i >= self.len {
+            return None;
+        }
+
+        let record = self.record(i);
+        if record[0] == 0 {
+            return None;
+        }
+        Some(BigUint::from_bytes_be(&record[1..]))
+    }
+
+    /// Marks the element at index `i` as deleted.
+    /// Returns `false` if there was no such element.
+    pub fn remove(&mut self, i: usize) -> bool {
+        if i >= self.len || self.record(i)[0] == 0 {
+            return false;
+        }
+
+        self.record_mut(i)[0] = 0;
+        true
+    }
+
+    /// Iterates over all elements, that were not deleted.
+    pub fn iter(&self) -> impl Iterator<Item = BigUint> + '_ {
+        (0..self.len).filter_map(move |i| self.get(i))
+    }
+
+    /// Rebuilds the accumulator over all elements, at the epoch of the last commit.
+    /// Fails with `InvalidParams` if `params.n` is even.
+    pub fn restore(&self, params: &PublicParams) -> Result<Accumulator, Errors> {
+        if params.n.is_even() {
+            return Err(Errors::InvalidParams);
+        }
+
+        let mut acc = Accumulator::new_with_params(params);
+        acc.accumulate_product(self.product(), self.iter().count());
+        acc.set_epoch(self.epoch);
+
+        Ok(acc)
+    }
+
+    /// Calculates the product of all elements, without loading all of them into memory.
+    pub fn product(&self) -> BigUint {
+        let mut chunks = Vec::new();
+        let mut chunk = Vec::with_capacity(PRODUCT_CHUNK);
+
+        for x in self.iter() {
+            chunk.push(x);
+            if chunk.len() == PRODUCT_CHUNK {
+                chunks.push(product(&chunk));
+                chunk.clear();
+            }
+        }
+        if !chunk.is_empty() {
+            chunks.push(product(&chunk));
+        }
+
+        if chunks.is_empty() {
+            return BigUint::one();
+        }
+        product(&chunks)
+    }
+
+    /// Concludes `epoch`, flushing all changes to disk.
+    ///
+    /// Only appends are undone when reopening after a crash, removals since the last commit
+    /// may or may not have reached the disk.
+    pub fn commit(&mut self, epoch: u64) -> io::Result<()> {
+        // flush the records before the header, so the header never points at missing records
+        self.map.flush()?;
+
+        BigEndian::write_u64(&mut self.map[16..24], self.len as u64);
+        BigEndian::write_u64(&mut self.map[24..32], epoch);
+        self.map.flush_range(0, HEADER_BYTES)?;
+
+        self.epoch = epoch;
+        Ok(())
+    }
+
+    /// Doubles the capacity of the file.
+    fn grow(&mut self) -> io::Result<()> {
+        // make sure nothing is lost when remapping
+        self.map.flush()?;
+
+        let capacity = self.capacity.checked_mul(2).ok_or_else(too_large)?;
+        self.file
+            .set_len(file_size(capacity, self.element_bytes)?)?;
+        self.map = unsafe { MmapMut::map_mut(&self.file)? };
+        self.capacity = capacity;
+
+        Ok(())
+    }
+
+    fn record(&self, i: usize) -> &[u8] {
+        let size = 1 + self.element_bytes;
+        let start = HEADER_BYTES + i * size;
+        &self.map[start..start + size]
+    }
+
+    fn record_mut(&mut self, i: usize) -> &mut [u8] {
+        let size = 1 + self.element_bytes;
+        let start = HEADER_BYTES + i * size;
+        &mut self.map[start..start + size]
+    }
+}
+
+/// The size of a file with `capacity` records of `element_bytes` bytes.
+fn file_size(capacity: usize, element_bytes: usize) -> io::Result<u64> {
+    element_bytes
+        .checked_add(1)
+        .and_then(|size| size.checked_mul(capacity))
+        .and_then(|size| size.checked_add(HEADER_BYTES))
+        .map(|size| size as u64)
+        .ok_or_else(too_large)
+}
+
+fn too_large() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, "element store is too large")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::fs;
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "accumulators-{}-{}.elems",
+            std::process::id(),
+            name
+        ))
+    }
+
+    #[test]
+    fn test_element_store() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let path = temp_path("store");
+        let _ = fs::remove_file(&path);
+
+        let xs = (0..INITIAL_CAPACITY + 10)
+            .map(|_| rng.gen_prime(128))
+            .collect::<Vec<_>>();
+
+        let mut store = ElementStore::create(&path, 16).unwrap();
+        assert!(ElementStore::create(&path, 16).is_err());
+        assert!(store.push(&rng.gen_prime(256)).is_err());
+
+        for (i, x) in xs.iter().enumerate() {
+            assert_eq!(store.push(x).unwrap(), i);
+        }
+        assert!(store.remove(3));
+        assert!(!store.remove(3));
+        assert_eq!(store.get(3), None);
+        assert_eq!(store.get(4).as_ref(), Some(&xs[4]));
+        store.commit(1).unwrap();
+
+        let mut expected = xs.clone();
+        expected.remove(3);
+        assert_eq!(store.iter().collect::<Vec<_>>(), expected);
+        assert_eq!(store.product(), product(&expected));
+
+        // uncommitted appends are dropped
+        store.push(&xs[0]).unwrap();
+        drop(store);
+
+        let store = ElementStore::open(&path).unwrap();
+        assert_eq!(store.epoch(), 1);
+        assert_eq!(store.len(), xs.len());
+        assert_eq!(store.iter().collect::<Vec<_>>(), expected);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_restore() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let path = temp_path("restore");
+        let _ = fs::remove_file(&path);
+
+        let xs = (0..20)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let mut store = ElementStore::create(&path, int_size_bits / 8).unwrap();
+        acc.batch_add(&xs);
+        for x in &xs {
+            store.push(x).unwrap();
+        }
+        store.commit(acc.epoch()).unwrap();
+
+        acc.del(&xs[7]).unwrap();
+        assert!(store.remove(7));
+        store.commit(acc.epoch()).unwrap();
+        drop(store);
+
+        let store = ElementStore::open(&path).unwrap();
+        let restored = store.restore(&acc.params()).unwrap();
+        assert_eq!(restored.state(), acc.state());
+        assert_eq!(restored.len(), acc.len());
+        assert_eq!(restored.epoch(), acc.epoch());
+        assert!(restored.ver_mem(&restored.mem_wit_create(&xs[3]), &xs[3]));
+
+        let mut params = acc.params();
+        params.n += 1u32;
+        assert_eq!(store.restore(&params).unwrap_err(), Errors::InvalidParams);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_corrupted_header() {
+        let path = temp_path("corrupted");
+        let _ = fs::remove_file(&path);
+        drop(ElementStore::create(&path, 16).unwrap());
+        let valid = fs::read(&path).unwrap();
+
+        let corrupt = |offset: usize, value: u64| {
+            let mut bytes = valid.clone();
+            BigEndian::write_u64(&mut bytes[offset..offset + 8], value);
+            fs::write(&path, &bytes).unwrap();
+            ElementStore::open(&path).unwrap_err().kind()
+        };
+        // element sizes whose records overflow
+        assert_eq!(corrupt(8, u64::max_value()), io::ErrorKind::InvalidData);
+        assert_eq!(corrupt(8, 0), io::ErrorKind::InvalidData);
+        // more records than fit into the file
+        assert_eq!(corrupt(16, u64::max_value()), io::ErrorKind::InvalidData);
+        assert_eq!(
+            corrupt(16, INITIAL_CAPACITY as u64 + 1),
+            io::ErrorKind::InvalidData
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,299 @@
+//! Bringing replicas of an accumulator up to date with its manager.
+//!
+//! The protocol is transport agnostic: a [`Replica`] sends an [`Announce`] with its
+//! parameters, epoch and root, the manager answers from its [`History`] with a
+//! [`SyncUpdate`] containing the missing deltas, and the replica verifies and applies them.
+//! Replicas behind the retained deltas receive the compacted checkpoints first.
+//! Both messages can be serialized with the `serde` feature.
+
+use num_bigint::BigUint;
+
+use crate::accumulator::PublicParams;
+use crate::error::Errors;
+use crate::history::{Checkpoint, Delta, History};
+
+/// Sent by a replica, to request the deltas it is missing.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Announce {
+    pub params: PublicParams,
+    pub epoch: u64,
+    pub root: BigUint,
+}
+
+/// The manager's answer to an [`Announce`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncUpdate {
+    /// The manager's current epoch.
+    pub epoch: u64,
+    /// The checkpoints following the announced epoch, applied before the deltas.
+    pub checkpoints: Vec<Checkpoint>,
+    /// The deltas following the announced epoch, or the last checkpoint.
+    pub deltas: Vec<Delta>,
+}
+
+impl History {
+    /// Answers an announcement, with at most `limit` checkpoints and deltas.
+    pub fn respond(&self, announce: &Announce, limit: usize) -> Result<SyncUpdate, Errors> {
+        if &announce.params != self.params() {
+            return Err(Errors::ParameterMismatch);
+        }
+
+        match self.root_at(announce.epoch) {
+            None if announce.epoch <= self.epoch() => {
+                return Err(Errors::Pruned(announce.epoch));
+            }
+            None => return Err(Errors::UnknownEpoch(announce.epoch)),
+            Some(root) if root != &announce.root => {
+                return Err(Errors::Diverged(announce.epoch));
+            }
+            Some(_) => {}
+        }
+
+        let checkpoints = self
+            .checkpoints()
+            .iter()
+            .filter(|c| c.from_epoch >= announce.epoch)
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let deltas = match self.since(announce.epoch) {
+            Some(deltas) => deltas,
+            None if checkpoints.len() < limit => self.export(),
+            None => &[],
+        };
+        let deltas = deltas[..(limit - checkpoints.len()).min(deltas.len())].to_vec();
+
+        Ok(SyncUpdate {
+            epoch: self.epoch(),
+            checkpoints,
+            deltas,
+        })
+    }
+}
+
+/// The state a replica needs to follow the manager.
+/// This is small and can be persisted, to resume after a restart.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Replica {
+    params: PublicParams,
+    epoch: u64,
+    root: BigUint,
+}
+
+impl Replica {
+    /// Starts at the empty accumulator.
+    pub fn new(params: PublicParams) -> Self {
+        Replica {
+            root: params.g.clone(),
+            params,
+            epoch: 0,
+        }
+    }
+
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns the verified root at the current epoch.
+    pub fn root(&self) -> &BigUint {
+        &self.root
+    }
+
+    pub fn announce(&self) -> Announce {
+        Announce {
+            params: self.params.clone(),
+            epoch: self.epoch,
+            root: self.root.clone(),
+        }
+    }
+
+    /// Verifies and applies an update. Nothing is applied if any delta is invalid.
+    /// Returns `true` if the replica caught up with the manager.
+    pub fn apply(&mut self, update: &SyncUpdate) -> Result<bool, Errors> {
+        let mut epoch = self.epoch;
+        let mut root = &self.root;
+
+        for checkpoint in &update.checkpoints {
+            if checkpoint.from_epoch != epoch {
+                return Err(Errors::Gap {
+                    expected: epoch,
+                    got: checkpoint.from_epoch,
+                });
+            }
+            if &checkpoint.from_root != root || !checkpoint.verify(&self.params.n) {
+                return Err(Errors::InvalidDelta(checkpoint.epoch));
+            }
+            epoch = checkpoint.epoch;
+            root = &checkpoint.root;
+        }
+
+        for delta in &update.deltas {
+            if delta.epoch != epoch + 1 {
+                return Err(Errors::Gap {
+                    expected: epoch + 1,
+                    got: delta.epoch,
+                });
+            }
+            if !delta.verify(root, &self.params.n) {
+                return Err(Errors::InvalidDelta(delta.epoch));
+            }
+            epoch = delta.epoch;
+            root = &delta.root;
+        }
+
+        self.root = root.clone();
+        self.epoch = epoch;
+
+        Ok(self.epoch >= update.epoch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use crate::history::Retention;
+    use crate::hooks::Mutation;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_sync() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let history = Arc::new(Mutex::new(History::new(params.clone())));
+        let observer = history.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..5)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        for x in &xs {
+            acc.add(x);
+        }
+        acc.del(&xs[1]).unwrap();
+
+        let mut replica = Replica::new(params.clone());
+
+        // two rounds, limited to 4 deltas each
+        let update = history
+            .lock()
+            .unwrap()
+            .respond(&replica.announce(), 4)
+            .unwrap();
+        assert_eq!(replica.apply(&update), Ok(false));
+        assert_eq!(replica.epoch(), 4);
+
+        // restart from the persisted state
+        let mut replica = replica.clone();
+        let update = history
+            .lock()
+            .unwrap()
+            .respond(&replica.announce(), 4)
+            .unwrap();
+        assert_eq!(replica.apply(&update), Ok(true));
+        assert_eq!(replica.root(), acc.state());
+
+        // gaps and invalid deltas are rejected, without changing the state
+        acc.add(&xs[1]);
+        acc.del(&xs[0]).unwrap();
+        let update = history
+            .lock()
+            .unwrap()
+            .respond(&replica.announce(), 10)
+            .unwrap();
+
+        let mut gap = update.clone();
+        gap.deltas.remove(0);
+        assert_eq!(
+            replica.apply(&gap),
+            Err(Errors::Gap {
+                expected: 7,
+                got: 8
+            })
+        );
+
+        let mut invalid = update.clone();
+        invalid.deltas[1].root = params.g.clone();
+        assert_eq!(replica.apply(&invalid), Err(Errors::InvalidDelta(8)));
+        assert_eq!(replica.epoch(), 6);
+
+        assert_eq!(replica.apply(&update), Ok(true));
+        assert_eq!(replica.root(), acc.state());
+
+        // mismatches on the manager side
+        let history = history.lock().unwrap();
+        let other = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(
+            history.respond(&Replica::new(other).announce(), 10),
+            Err(Errors::ParameterMismatch)
+        );
+
+        let mut announce = replica.announce();
+        announce.epoch += 1;
+        assert_eq!(history.respond(&announce, 10), Err(Errors::UnknownEpoch(9)));
+
+        announce.epoch = 3;
+        assert_eq!(history.respond(&announce, 10), Err(Errors::Diverged(3)));
+    }
+
+    #[test]
+    fn test_sync_compacted() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(rng, int_size_bits);
+        let mut acc = Accumulator::new_with_params(&params);
+
+        let history = Arc::new(Mutex::new(History::with_retention(
+            params.clone(),
+            Retention::EveryKth(2),
+        )));
+        let observer = history.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().record(m).unwrap());
+
+        let xs = (0..5)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.add(&xs[0]);
+        let mut behind = Replica::new(params.clone());
+        let update = history
+            .lock()
+            .unwrap()
+            .respond(&behind.announce(), 10)
+            .unwrap();
+        assert_eq!(behind.apply(&update), Ok(true));
+
+        for x in &xs[1..] {
+            acc.add(x);
+        }
+        acc.del(&xs[2]).unwrap();
+
+        // from the empty accumulator, through the checkpoints
+        let history = history.lock().unwrap();
+        let mut replica = Replica::new(params);
+        let update = history.respond(&replica.announce(), 2).unwrap();
+        assert_eq!(update.checkpoints.len(), 2);
+        assert_eq!(replica.apply(&update), Ok(false));
+        let update = history.respond(&replica.announce(), 2).unwrap();
+        assert_eq!(replica.apply(&update), Ok(true));
+        assert_eq!(replica.root(), acc.state());
+
+        // epoch 1 was compacted
+        assert_eq!(
+            history.respond(&behind.announce(), 10),
+            Err(Errors::Pruned(1))
+        );
+    }
+}
@@ -1,13 +1,17 @@
+use crate::error::Errors;
 use failure::Error;
 use num_bigint::{BigInt, BigUint};
 use rand::CryptoRng;
 use rand::Rng;
 
+/// The accumulator traits are object safe, except for `setup`, so implementations can be
+/// chosen at runtime, e.g. as `Box<dyn UniversalAccumulator>` or `Box<dyn DynAccumulator>`.
 pub trait StaticAccumulator {
     /// Setup generates a group of unknown order and initializes the group
     /// with a generator of that group.
     fn setup<T, R>(rng: &mut R, int_size_bits: usize) -> Self
     where
+        Self: Sized,
         T: PrimeGroup,
         R: CryptoRng + Rng;
 
@@ -31,8 +35,9 @@ pub trait DynamicAccumulator: StaticAccumulator {
 
 pub trait UniversalAccumulator: DynamicAccumulator {
     /// Create a non-membership proof.
-    /// Returns `None`, iff `x` is a member.
-    fn non_mem_wit_create(&self, x: &BigUint) -> (BigUint, BigInt);
+    /// Fails with `NotCoprime` if `x` shares a factor with the set, e.g. if it is a member,
+    /// and if a required inverse does not exist.
+    fn non_mem_wit_create(&self, x: &BigUint) -> Result<(BigUint, BigInt), Errors>;
 
     /// Verify a non-membership proof.
     fn ver_non_mem(&self, w: &(BigUint, BigInt), x: &BigUint) -> bool;
@@ -45,6 +50,8 @@ pub trait BatchedAccumulator: StaticAccumulator {
 
     /// Batch delete.
     /// Given a list of witnesses and members, deletes all of them.
+    /// Returns `None`, without deleting anything, if `pairs` is empty, the witnesses don't
+    /// match each other or the current state, or an element is not a member.
     fn batch_del(&mut self, pairs: &[(BigUint, BigUint)]) -> Option<BigUint>;
 
     /// Delete with member witness.
@@ -56,6 +63,16 @@ pub trait BatchedAccumulator: StaticAccumulator {
     /// Needs to be passed in, as we don't hold onto the whole set in the accumulator currently.
     fn create_all_mem_wit(&self, s: &[BigUint]) -> Vec<BigUint>;
 
+    /// Create the membership witness for `s[i]`, where `s` is the whole set.
+    /// Unlike `mem_wit_create` this doesn't divide the product of the set, which is expensive
+    /// for large sets. Returns `None` if `i` is out of range.
+    fn mem_wit_create_from(&self, s: &[BigUint], i: usize) -> Option<BigUint>;
+
+    /// Create membership witnesses for all `xs`, which can be any members.
+    /// Shares the work between the queries, instead of calling `mem_wit_create` for each.
+    /// Fails if not all `xs` are members.
+    fn mem_wit_create_batch(&self, xs: &[BigUint]) -> Result<Vec<BigUint>, Errors>;
+
     /// Verify Batch Add.
     /// Given the proof `w` from [batch_add] and the list of members `xs`,
     /// and the previous state of the accumulator `a_t` this verifies if the `add` was done correctly.
@@ -71,13 +88,14 @@ pub trait BatchedAccumulator: StaticAccumulator {
     fn ver_batch_del(&self, w: &BigUint, a_t: &BigUint, xs: &[BigUint]) -> bool;
 
     /// Aggregate two membership wittnesses, from the same accumulator.
+    /// Fails with `InvalidWitness` if the witnesses don't match.
     fn agg_mem_wit(
         &self,
         w_x: &BigUint,
         w_y: &BigUint,
         x: &BigUint,
         y: &BigUint,
-    ) -> (BigUint, BigUint);
+    ) -> Result<(BigUint, BigUint), Errors>;
 
     /// Verify an aggregated membership wittness.
     fn ver_agg_mem_wit(&self, w_xy: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool;
@@ -88,6 +106,19 @@ pub trait BatchedAccumulator: StaticAccumulator {
     /// Verify a membership wittness with a NI-PoE.
     fn ver_mem_star(&self, x: &BigUint, pi: &(BigUint, BigUint)) -> bool;
 
+    /// Verify many proofs of `mem_wit_create_star` against the current root, in one check.
+    /// The check is in `QR_n`, so it accepts proofs up to a factor of `-1`.
+    /// Returns `false` if `pis` is empty.
+    #[allow(clippy::type_complexity)]
+    fn ver_mem_star_batch(&self, pis: &[(BigUint, (BigUint, BigUint))]) -> bool;
+
+    /// Create a single membership witness for all `xs`, and a NI-PoE for it.
+    /// Fails if `xs` is empty, or not all of them are members.
+    fn mem_wit_create_for_subset(&self, xs: &[BigUint]) -> Result<(BigUint, BigUint), Errors>;
+
+    /// Verify a membership witness for all `xs`, created by `mem_wit_create_for_subset`.
+    fn ver_mem_for_subset(&self, xs: &[BigUint], pi: &(BigUint, BigUint)) -> bool;
+
     /// Aggregate two membership witness, from different accumulators.
     fn mem_wit_x(
         &self,
@@ -101,11 +132,42 @@ pub trait BatchedAccumulator: StaticAccumulator {
     /// Verify aggregated membership witness.
     fn ver_mem_x(&self, other: &BigUint, pi: &BigUint, x: &BigUint, y: &BigUint) -> bool;
 
+    /// Aggregate two membership witness, from different accumulators, with a NI-PoE
+    /// binding the aggregate to both roots.
+    /// Fails if either witness is invalid, or `x` and `y` are not coprime.
+    fn mem_wit_x_star(
+        &self,
+        other: &BigUint,
+        w_x: &BigUint,
+        w_y: &BigUint,
+        x: &BigUint,
+        y: &BigUint,
+    ) -> Result<(BigUint, BigUint), Errors>;
+
+    /// Verify aggregated membership witness, with a NI-PoE.
+    fn ver_mem_x_star(
+        &self,
+        other: &BigUint,
+        pi: &(BigUint, BigUint),
+        x: &BigUint,
+        y: &BigUint,
+    ) -> bool;
+
+    /// Aggregate membership witnesses, from any number of accumulators.
+    fn mem_wit_x_many(&self, ws: &[BigUint]) -> BigUint;
+
+    /// Verify a membership witness aggregated from this and the `others` accumulators.
+    /// `xs[0]` is the element of this accumulator, `xs[i + 1]` the one of `others[i]`,
+    /// all must be pairwise coprime.
+    fn ver_mem_x_many(&self, others: &[BigUint], pi: &BigUint, xs: &[BigUint]) -> bool;
+
     /// Efficient non membership proof.
+    /// Fails if a required inverse does not exist.
+    #[allow(clippy::type_complexity)]
     fn non_mem_wit_create_star(
         &self,
         x: &BigUint,
-    ) -> (BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint);
+    ) -> Result<(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint), Errors>;
 
     /// Verify non membership proof.
     fn ver_non_mem_star(
@@ -113,8 +175,28 @@ pub trait BatchedAccumulator: StaticAccumulator {
         x: &BigUint,
         pi: &(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
     ) -> bool;
+
+    /// A single efficient non membership proof, for all of `xs`.
+    /// Fails if any of `xs` is a member.
+    #[allow(clippy::type_complexity)]
+    fn non_mem_wit_create_star_batch(
+        &self,
+        xs: &[BigUint],
+    ) -> Result<(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint), Errors>;
+
+    /// Verify a non membership proof for all of `xs`.
+    fn ver_non_mem_star_batch(
+        &self,
+        xs: &[BigUint],
+        pi: &(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint),
+    ) -> bool;
 }
 
+/// All operations of a universal, batched accumulator, as a single trait object.
+pub trait DynAccumulator: UniversalAccumulator + BatchedAccumulator {}
+
+impl<A: UniversalAccumulator + BatchedAccumulator> DynAccumulator for A {}
+
 pub trait StaticVectorCommitment {
     type Domain;
     type Commitment;
@@ -122,16 +204,17 @@ pub trait StaticVectorCommitment {
 
     fn setup<T, R>(rng: &mut R, int_size_bits: usize, n: usize) -> Self
     where
+        Self: Sized,
         T: PrimeGroup,
         R: CryptoRng + Rng;
 
     fn commit(&mut self, m: &[Self::Domain]);
 
-    fn open(&self, b: &Self::Domain, i: usize) -> Self::Commitment;
+    fn open(&self, b: &Self::Domain, i: usize) -> Result<Self::Commitment, Errors>;
 
     fn verify(&self, b: &Self::Domain, i: usize, pi: &Self::Commitment) -> bool;
 
-    fn batch_open(&self, b: &[Self::Domain], i: &[usize]) -> Self::BatchCommitment;
+    fn batch_open(&self, b: &[Self::Domain], i: &[usize]) -> Result<Self::BatchCommitment, Errors>;
 
     fn batch_verify(&self, b: &[Self::Domain], i: &[usize], pi: &Self::BatchCommitment) -> bool;
 
@@ -145,6 +228,9 @@ pub trait DynamicVectorCommitment: StaticVectorCommitment {
 
 /// This trait abstracts the Group of unknown order that is used to sample our primes
 /// RSA or Class groups of imaginary quadratic order
+///
+/// Only generates the public parameters, the operations of a generated group are described
+/// by [`UnknownOrderGroup`].
 pub trait PrimeGroup {
     /// Generates the Prime elements from the group that is used
     /// Returns first the prime and second the generator used
@@ -153,3 +239,55 @@ pub trait PrimeGroup {
         int_size_bits: usize,
     ) -> Result<(BigUint, BigUint), Error>;
 }
+
+/// A strategy for generating the secret primes of a group, e.g. provably prime generation,
+/// or primes generated in advance on an offline machine.
+/// See `RSAGroup::generate_primes_with` and `RSAGroupWith`.
+pub trait PrimeSampler {
+    /// Returns a prime of `bits` bits, with the top two bits set, so that the product of two
+    /// of them has exactly twice as many bits.
+    fn sample_prime<R: Rng + CryptoRng>(
+        &mut self,
+        rng: &mut R,
+        bits: usize,
+    ) -> Result<BigUint, Error>;
+}
+
+/// An instance of a group of unknown order, e.g. `Z_n^*` for an RSA modulus `n`.
+///
+/// The proofs are written against this, so they only depend on the group operations.
+pub trait UnknownOrderGroup {
+    type Elem: Clone + PartialEq + std::fmt::Debug;
+
+    /// The neutral element.
+    fn id(&self) -> Self::Elem;
+
+    fn op(&self, a: &Self::Elem, b: &Self::Elem) -> Self::Elem;
+
+    fn exp(&self, a: &Self::Elem, e: &BigUint) -> Self::Elem;
+
+    /// Returns `None` if `a` is not invertible.
+    fn inv(&self, a: &Self::Elem) -> Option<Self::Elem>;
+
+    /// Hashes `input` to an element, e.g. a generator chosen by the Fiat-Shamir transcript.
+    fn hash_to_group(&self, input: &[u8]) -> Self::Elem;
+
+    /// The bytes of `a` in the Fiat-Shamir transcripts.
+    fn elem_to_bytes(&self, a: &Self::Elem) -> Vec<u8>;
+
+    /// The bytes of the parameters of the group, e.g. the modulus, in the Fiat-Shamir
+    /// transcripts, so proofs made for one group are not checked against challenges of another.
+    fn params_to_bytes(&self) -> Vec<u8>;
+
+    /// Like `exp`, for negative exponents as well.
+    /// Returns `None` if `e` is negative and `a` is not invertible.
+    fn exp_signed(&self, a: &Self::Elem, e: &BigInt) -> Option<Self::Elem> {
+        match e.to_biguint() {
+            Some(e) => Some(self.exp(a, &e)),
+            None => {
+                let e_abs = (-e).to_biguint().expect("negated a negative number");
+                Some(self.exp(&self.inv(a)?, &e_abs))
+            }
+        }
+    }
+}
@@ -0,0 +1,452 @@
+//! Managers that know the factorization of the RSA modulus.
+//!
+//! Whoever runs the trusted setup can keep the factors `p, q` as a [`Trapdoor`], which allows
+//! breaking the accumulator, but also speeding up the manager: a [`TrapdoorManager`] reduces
+//! the exponents of adds, deletes and rebuilds `mod φ(n)`, so exponentiating by the product of
+//! a large set costs as much as by a single `|n|` bit number. With the `backup` feature,
+//! the trapdoor can be exported encrypted with ChaCha20-Poly1305, using a key derived from a
+//! passphrase with Argon2, or a provided key.
+
+use std::fmt;
+
+use num_bigint::prime::probably_prime;
+use num_bigint::BigUint;
+use rand::{CryptoRng, Rng};
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::error::Errors;
+use crate::group::RSAGroup;
+
+#[cfg(feature = "backup")]
+use crate::encoding::{finish, read_part, write_part};
+
+/// The factors of an RSA modulus `n = p q`.
+/// This deliberately doesn't implement `Serialize`, see `TrapdoorManager::export_trapdoor`.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Trapdoor {
+    p: BigUint,
+    q: BigUint,
+}
+
+impl fmt::Debug for Trapdoor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Trapdoor {{ .. }}")
+    }
+}
+
+impl Trapdoor {
+    /// Fails with `InvalidParams` unless `p` and `q` are distinct primes, otherwise `phi`
+    /// would not be the order of the group.
+    pub fn new(p: BigUint, q: BigUint) -> Result<Self, Errors> {
+        if p == q || !probably_prime(&p, 20) || !probably_prime(&q, 20) {
+            return Err(Errors::InvalidParams);
+        }
+
+        Ok(Trapdoor { p, q })
+    }
+
+    /// Returns `n = p q`.
+    pub fn modulus(&self) -> BigUint {
+        &self.p * &self.q
+    }
+
+    /// Returns `φ(n) = (p - 1)(q - 1)`, the order of the group.
+    pub fn phi(&self) -> BigUint {
+        (&self.p - 1u32) * (&self.q - 1u32)
+    }
+}
+
+/// An accumulator, together with the trapdoor of its modulus.
+///
+/// The accumulator reduces its exponents `mod φ(n)` while it is owned by the manager. Clones
+/// of it, e.g. `accumulator().clone()`, and `into_accumulator` drop the trapdoor.
+#[derive(Debug)]
+pub struct TrapdoorManager {
+    acc: Accumulator,
+    trapdoor: Trapdoor,
+}
+
+impl Clone for TrapdoorManager {
+    fn clone(&self) -> Self {
+        TrapdoorManager::with_order(self.acc.clone(), self.trapdoor.clone())
+    }
+}
+
+impl TrapdoorManager {
+    /// Runs the setup of an RSA group, and keeps the trapdoor.
+    pub fn setup<R: CryptoRng + Rng>(rng: &mut R, int_size_bits: usize) -> Self {
+        let (p, q) = RSAGroup::generate_factors(rng, int_size_bits).unwrap();
+        let trapdoor = Trapdoor::new(p, q).expect("generated factors are distinct primes");
+        let params = PublicParams {
+            int_size_bits,
+            n: trapdoor.modulus(),
+            g: RSAGroup::generator(),
+            hash_key: None,
+            qr: false,
+        };
+
+        TrapdoorManager::with_order(Accumulator::new_with_params(&params), trapdoor)
+    }
+
+    /// Fails with `InvalidParams` if `trapdoor` doesn't factor the modulus of `acc`.
+    pub fn new(acc: Accumulator, trapdoor: Trapdoor) -> Result<Self, Errors> {
+        if &trapdoor.modulus() != acc.modulus() {
+            return Err(Errors::InvalidParams);
+        }
+
+        Ok(TrapdoorManager::with_order(acc, trapdoor))
+    }
+
+    /// Like `Accumulator::rebuild`, with the root computed by an exponent reduced `mod φ(n)`.
+    /// Fails with `InvalidParams` if `trapdoor` doesn't factor `params.n`.
+    pub fn rebuild(
+        params: &PublicParams,
+        trapdoor: Trapdoor,
+        elements: impl IntoIterator<Item = BigUint>,
+    ) -> Result<Self, Errors> {
+        if trapdoor.modulus() != params.n {
+            return Err(Errors::InvalidParams);
+        }
+
+        let mut manager =
+            TrapdoorManager::with_order(Accumulator::new_with_params(params), trapdoor);
+        manager.acc.accumulate_all(elements);
+        Ok(manager)
+    }
+
+    fn with_order(mut acc: Accumulator, trapdoor: Trapdoor) -> Self {
+        acc.set_group_order(Some(trapdoor.phi()));
+        TrapdoorManager { acc, trapdoor }
+    }
+
+    pub fn accumulator(&self) -> &Accumulator {
+        &self.acc
+    }
+
+    pub fn accumulator_mut(&mut self) -> &mut Accumulator {
+        &mut self.acc
+    }
+
+    /// Drops the trapdoor.
+    pub fn into_accumulator(self) -> Accumulator {
+        let mut acc = self.acc;
+        acc.set_group_order(None);
+        acc
+    }
+
+    pub fn trapdoor(&self) -> &Trapdoor {
+        &self.trapdoor
+    }
+}
+
+/// Identifies encrypted trapdoor backups.
+#[cfg(feature = "backup")]
+const BACKUP_MAGIC: &[u8] = b"ACCTRAP1";
+
+#[cfg(feature = "backup")]
+impl TrapdoorManager {
+    /// Encrypts the trapdoor with a key derived from `passphrase`.
+    /// The backup is bound to the modulus, and can only be imported with the same accumulator.
+    pub fn export_trapdoor(&self, passphrase: &[u8]) -> Vec<u8> {
+        let mut salt = [0u8; 16];
+        os_rng().fill(&mut salt);
+
+        self.seal(&derive_key(passphrase, &salt), &salt)
+    }
+
+    /// Encrypts the trapdoor with `key`, e.g. from a key management system.
+    pub fn export_trapdoor_with_key(&self, key: &[u8; 32]) -> Vec<u8> {
+        self.seal(key, &[])
+    }
+
+    /// Restores a manager from a backup created by `export_trapdoor`.
+    pub fn import_trapdoor(
+        acc: Accumulator,
+        backup: &[u8],
+        passphrase: &[u8],
+    ) -> Result<Self, Errors> {
+        let (salt, nonce, ciphertext) = parse_backup(backup)?;
+        if salt.len() < argon2::MIN_SALT_LEN {
+            return Err(Errors::InvalidEncoding);
+        }
+
+        Self::open(acc, &derive_key(passphrase, salt), nonce, ciphertext)
+    }
+
+    /// Restores a manager from a backup created by `export_trapdoor_with_key`.
+    pub fn import_trapdoor_with_key(
+        acc: Accumulator,
+        backup: &[u8],
+        key: &[u8; 32],
+    ) -> Result<Self, Errors> {
+        let (salt, nonce, ciphertext) = parse_backup(backup)?;
+        if !salt.is_empty() {
+            return Err(Errors::InvalidEncoding);
+        }
+
+        Self::open(acc, key, nonce, ciphertext)
+    }
+
+    fn seal(&self, key: &[u8; 32], salt: &[u8]) -> Vec<u8> {
+        use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+        use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+        let mut nonce = [0u8; 12];
+        os_rng().fill(&mut nonce);
+
+        let mut plaintext = Vec::new();
+        write_part(&mut plaintext, &self.trapdoor.p.to_bytes_be());
+        write_part(&mut plaintext, &self.trapdoor.q.to_bytes_be());
+
+        let aad = self.acc.modulus().to_bytes_be();
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &plaintext,
+                    aad: &aad,
+                },
+            )
+            .expect("encryption failed");
+
+        let mut res = BACKUP_MAGIC.to_vec();
+        write_part(&mut res, salt);
+        write_part(&mut res, &nonce);
+        write_part(&mut res, &ciphertext);
+        res
+    }
+
+    fn open(
+        acc: Accumulator,
+        key: &[u8; 32],
+        nonce: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<Self, Errors> {
+        use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+        use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+
+        let aad = acc.modulus().to_bytes_be();
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(key))
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| Errors::DecryptionFailed)?;
+
+        let mut bytes = &plaintext[..];
+        let p = BigUint::from_bytes_be(read_part(&mut bytes)?);
+        let q = BigUint::from_bytes_be(read_part(&mut bytes)?);
+        finish(bytes)?;
+
+        Self::new(acc, Trapdoor::new(p, q)?)
+    }
+}
+
+/// Splits a backup into the salt, nonce and ciphertext.
+#[cfg(feature = "backup")]
+#[allow(clippy::type_complexity)]
+fn parse_backup(backup: &[u8]) -> Result<(&[u8], &[u8], &[u8]), Errors> {
+    if !backup.starts_with(BACKUP_MAGIC) {
+        return Err(Errors::InvalidEncoding);
+    }
+
+    let mut bytes = &backup[BACKUP_MAGIC.len()..];
+    let salt = read_part(&mut bytes)?;
+    let nonce = read_part(&mut bytes)?;
+    let ciphertext = read_part(&mut bytes)?;
+    finish(bytes)?;
+
+    if nonce.len() != 12 {
+        return Err(Errors::InvalidEncoding);
+    }
+
+    Ok((salt, nonce, ciphertext))
+}
+
+#[cfg(feature = "backup")]
+fn derive_key(passphrase: &[u8], salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    argon2::Argon2::default()
+        .hash_password_into(passphrase, salt, &mut key)
+        .expect("invalid key derivation parameters");
+    key
+}
+
+#[cfg(feature = "backup")]
+fn os_rng() -> rand::rngs::OsRng {
+    rand::rngs::OsRng::new().expect("no secure randomness available")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_trapdoor_manager() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut manager = TrapdoorManager::setup(rng, int_size_bits);
+
+        let acc = manager.accumulator();
+        assert_eq!(acc.modulus().bits(), int_size_bits);
+        assert_eq!(format!("{:?}", manager.trapdoor()), "Trapdoor { .. }");
+
+        // the order of the group
+        let phi = manager.trapdoor().phi();
+        assert_eq!(
+            acc.generator().modpow(&phi, acc.modulus()),
+            BigUint::from(1u32)
+        );
+
+        let x = rng.gen_prime(int_size_bits);
+        manager.accumulator_mut().add(&x);
+        let acc = manager.accumulator();
+        assert!(acc.ver_mem(&acc.mem_wit_create(&x), &x));
+
+        let other = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(
+            TrapdoorManager::new(other, manager.trapdoor().clone()).unwrap_err(),
+            Errors::InvalidParams
+        );
+    }
+
+    #[test]
+    fn test_invalid_trapdoor() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let p = rng.gen_prime(128);
+        let q = rng.gen_prime(128);
+        let one = BigUint::from(1u32);
+        let zero = BigUint::from(0u32);
+
+        // factors of `n = 1 n`, `0 n`, `p p` and a composite `p q` give the wrong order
+        let invalid = vec![
+            (one.clone(), &p * &q),
+            (&p * &q, one),
+            (zero, p.clone()),
+            (p.clone(), p.clone()),
+            (&p * &q, BigUint::from(3u32)),
+            (p.clone(), BigUint::from(15u32)),
+        ];
+        for (a, b) in invalid {
+            assert_eq!(Trapdoor::new(a, b).unwrap_err(), Errors::InvalidParams);
+        }
+
+        assert_eq!(
+            Trapdoor::new(p.clone(), q.clone()).unwrap().modulus(),
+            p * q
+        );
+    }
+
+    #[test]
+    fn test_reduced_exponents() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut manager = TrapdoorManager::setup(rng, int_size_bits);
+        let mut public = manager.accumulator().clone();
+
+        // the set product is much larger than φ(n)
+        let xs = (0..16)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let pi = manager.accumulator_mut().batch_add(&xs);
+        public.batch_add(&xs);
+        assert_eq!(manager.accumulator().state(), public.state());
+        assert!(public.ver_batch_add(&pi, public.generator(), &xs));
+
+        manager.accumulator_mut().del(&xs[3]).unwrap();
+        public.del(&xs[3]).unwrap();
+        manager
+            .accumulator_mut()
+            .batch_del_tracked(&xs[..2])
+            .unwrap();
+        public.batch_del_tracked(&xs[..2]).unwrap();
+        let acc = manager.accumulator();
+        assert_eq!(acc.state(), public.state());
+        assert_eq!(acc.mem_wit_create(&xs[7]), public.mem_wit_create(&xs[7]));
+        assert!(acc.validate().is_valid());
+
+        let elements = xs.iter().cloned().filter(|x| public.contains(x));
+        let rebuilt =
+            TrapdoorManager::rebuild(&public.params(), manager.trapdoor().clone(), elements)
+                .unwrap();
+        assert_eq!(rebuilt.accumulator().state(), public.state());
+        assert_eq!(
+            TrapdoorManager::rebuild(
+                &Accumulator::setup::<RSAGroup, _>(rng, int_size_bits).params(),
+                manager.trapdoor().clone(),
+                Vec::new()
+            )
+            .unwrap_err(),
+            Errors::InvalidParams
+        );
+
+        // the order is not part of clones, or their debug output
+        let phi = manager.trapdoor().phi().to_string();
+        assert!(!format!("{:?}", manager).contains(&phi));
+        let mut released = manager.clone().into_accumulator();
+        released.add(&xs[0]);
+        public.add(&xs[0]);
+        assert_eq!(released.state(), public.state());
+    }
+
+    #[cfg(feature = "backup")]
+    #[test]
+    fn test_trapdoor_backup() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let manager = TrapdoorManager::setup(rng, int_size_bits);
+        let acc = manager.accumulator().clone();
+
+        let backup = manager.export_trapdoor(b"correct horse battery staple");
+        let restored =
+            TrapdoorManager::import_trapdoor(acc.clone(), &backup, b"correct horse battery staple")
+                .unwrap();
+        assert_eq!(restored.trapdoor(), manager.trapdoor());
+
+        // the backup doesn't contain the factors in plaintext
+        let p = manager.trapdoor().p.to_bytes_be();
+        assert!(!backup.windows(p.len()).any(|w| w == &p[..]));
+
+        assert_eq!(
+            TrapdoorManager::import_trapdoor(acc.clone(), &backup, b"wrong").unwrap_err(),
+            Errors::DecryptionFailed
+        );
+        let other = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        assert_eq!(
+            TrapdoorManager::import_trapdoor(other, &backup, b"correct horse battery staple")
+                .unwrap_err(),
+            Errors::DecryptionFailed
+        );
+
+        let key = [7u8; 32];
+        let backup = manager.export_trapdoor_with_key(&key);
+        let restored =
+            TrapdoorManager::import_trapdoor_with_key(acc.clone(), &backup, &key).unwrap();
+        assert_eq!(restored.trapdoor(), manager.trapdoor());
+        assert_eq!(
+            TrapdoorManager::import_trapdoor_with_key(acc.clone(), &backup, &[8u8; 32])
+                .unwrap_err(),
+            Errors::DecryptionFailed
+        );
+
+        let mut tampered = backup;
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert_eq!(
+            TrapdoorManager::import_trapdoor_with_key(acc.clone(), &tampered, &key).unwrap_err(),
+            Errors::DecryptionFailed
+        );
+        assert_eq!(
+            TrapdoorManager::import_trapdoor_with_key(acc, &tampered[..20], &key).unwrap_err(),
+            Errors::InvalidEncoding
+        );
+    }
+}
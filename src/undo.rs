@@ -0,0 +1,165 @@
+//! Reverting an accumulator to an earlier epoch.
+//!
+//! With an [`UndoLog`] enabled by `Accumulator::enable_undo`, every change records what is
+//! needed to revert it: the epoch, root and length before, and the products of the added and
+//! deleted elements. `Accumulator::rollback` then restores any retained epoch without
+//! recomputing the root, e.g. when the accumulator mirrors a chain that can reorg.
+
+use std::collections::VecDeque;
+
+use num_bigint::BigUint;
+
+/// How to revert a single change.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoEntry {
+    /// The epoch before the change.
+    pub from_epoch: u64,
+    /// The root before the change.
+    pub from_root: BigUint,
+    /// The number of members before the change.
+    pub from_len: usize,
+    /// Product of the elements added by the change.
+    pub added: BigUint,
+    /// Product of the elements deleted by the change.
+    pub deleted: BigUint,
+}
+
+/// The last changes of an accumulator, at most `depth` of them.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UndoLog {
+    depth: usize,
+    entries: VecDeque<UndoEntry>,
+}
+
+impl UndoLog {
+    pub fn new(depth: usize) -> Self {
+        UndoLog {
+            depth,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The retained entries, oldest first.
+    pub fn entries(&self) -> impl Iterator<Item = &UndoEntry> {
+        self.entries.iter()
+    }
+
+    /// The oldest epoch that can be restored, if any change was recorded.
+    pub fn oldest_epoch(&self) -> Option<u64> {
+        self.entries.front().map(|e| e.from_epoch)
+    }
+
+    /// Records a change, dropping the oldest one if the log is full.
+    pub(crate) fn push(&mut self, entry: UndoEntry) {
+        self.entries.push_back(entry);
+        while self.entries.len() > self.depth {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Removes the entries of all changes after `epoch`, returning them newest first.
+    /// Returns `None` if no retained change started at `epoch`.
+    pub(crate) fn take_since(&mut self, epoch: u64) -> Option<Vec<UndoEntry>> {
+        let start = self.entries.iter().position(|e| e.from_epoch == epoch)?;
+
+        Some(self.entries.drain(start..).rev().collect())
+    }
+
+    /// Forgets all changes, e.g. after the set was changed in a way that can't be reverted.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::accumulator::{Accumulator, PendingOps};
+    use crate::error::Errors;
+    use crate::group::RSAGroup;
+    use crate::traits::*;
+    use num_bigint::{BigUint, RandPrime};
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_rollback() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+
+        acc.add(&xs[0]);
+        assert_eq!(acc.rollback(0), Err(Errors::Pruned(0)));
+        acc.enable_undo(4);
+
+        // changes after epoch 1
+        let start = acc.clone();
+        acc.batch_add(&xs[1..4]);
+        let after_batch = acc.clone();
+        acc.del(&xs[2]).unwrap();
+        let mut ops = PendingOps::new();
+        ops.add(&xs[4]);
+        ops.del(&xs[1]);
+        acc.commit(&ops).unwrap();
+        acc.add(&xs[5]);
+        assert_eq!(acc.epoch(), 5);
+        assert_eq!(acc.undo_log().unwrap().len(), 4);
+
+        assert_eq!(acc.rollback(6), Err(Errors::UnknownEpoch(6)));
+        assert_eq!(acc.rollback(0), Err(Errors::Pruned(0)));
+
+        // back to the state after the batch, and replay a different fork
+        acc.rollback(2).unwrap();
+        assert_eq!(acc.epoch(), 2);
+        assert_eq!(acc.state(), after_batch.state());
+        assert_eq!(acc.len(), 4);
+        assert!(acc.contains(&xs[2]) && !acc.contains(&xs[4]));
+        assert!(acc.validate().is_valid());
+        assert_eq!(acc.undo_log().unwrap().len(), 1);
+
+        acc.add(&xs[6]);
+        assert!(acc.contains(&xs[6]));
+        acc.rollback(1).unwrap();
+        assert_eq!(acc.state(), start.state());
+        assert_eq!(acc.len(), start.len());
+        assert_eq!(acc.rollback(1), Ok(()));
+        assert!(acc.undo_log().unwrap().is_empty());
+
+        // batch deletions, without any observer
+        acc.batch_add(&xs[1..4]);
+        let before = acc.clone();
+        let pairs = xs[1..3]
+            .iter()
+            .map(|x| (x.clone(), acc.mem_wit_create(x)))
+            .collect::<Vec<_>>();
+        acc.batch_del(&pairs).unwrap();
+        acc.rollback(before.epoch()).unwrap();
+        assert_eq!(acc.state(), before.state());
+        assert_eq!(acc.len(), before.len());
+        assert!(acc.contains(&xs[1]) && acc.contains(&xs[2]));
+        assert!(acc.validate().is_valid());
+
+        // changes that can't be reverted clear the log
+        acc.add(&xs[7]);
+        acc.rerandomize(&BigUint::from(65537u32)).unwrap();
+        assert_eq!(acc.rollback(1), Err(Errors::Pruned(1)));
+        acc.disable_undo();
+        assert!(acc.undo_log().is_none());
+    }
+}
@@ -0,0 +1,374 @@
+//! Witnesses that prove either membership or non-membership of a single element.
+//!
+//! A [`UniversalWitness`] is kept up to date from the [`UpdateMessage`]s of the feed, and
+//! switches from non-membership to membership when its element is added. The opposite
+//! direction needs the whole set, so when the element is deleted, `update` fails with
+//! `NotAMember` and a fresh witness has to be requested from the manager.
+
+use num_bigint::traits::ExtendedGcd;
+use num_bigint::{BigInt, BigUint};
+use num_integer::Integer;
+use num_traits::{One, Zero};
+
+use crate::accumulator::Accumulator;
+use crate::error::Errors;
+use crate::feed::{self, check_consecutive, combined_products, update_witness, UpdateMessage};
+use crate::math::modpow_uint_int;
+use crate::traits::*;
+
+/// What a [`UniversalWitness`] proves.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Membership {
+    /// The membership witness `w`, with `w^x = A`.
+    Member(BigUint),
+    /// The non-membership witness `(d, b)`, with `d^x A^b = g`.
+    NonMember((BigUint, BigInt)),
+}
+
+/// A witness for the membership or non-membership of `x`, at the accumulator state `root`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniversalWitness {
+    pub x: BigUint,
+    pub root: BigUint,
+    pub membership: Membership,
+}
+
+impl UniversalWitness {
+    pub fn is_member(&self) -> bool {
+        match self.membership {
+            Membership::Member(_) => true,
+            Membership::NonMember(_) => false,
+        }
+    }
+
+    /// Checks the witness against the current state of `acc`, whichever of membership or
+    /// non-membership it proves, see `is_member`.
+    pub fn verify(&self, acc: &Accumulator) -> bool {
+        match &self.membership {
+            Membership::Member(w) => acc.ver_mem(w, &self.x),
+            Membership::NonMember(w) => acc.ver_non_mem(w, &self.x),
+        }
+    }
+
+    /// Updates the witness to the root of `msg`, which must be the next message of the feed.
+    ///
+    /// Fails with `NotAMember` if `x` was deleted, and with `InvalidDelta(epoch)` if a
+    /// non-member was deleted. The witness is unchanged in both cases.
+    /// All operations are `mod n`.
+    pub fn update(&mut self, msg: &UpdateMessage, n: &BigUint) -> Result<(), Errors> {
+        let membership = match &self.membership {
+            Membership::Member(w) => {
+                let w = update_witness(w, &self.x, &msg.added, &msg.deleted, &msg.root, n)
+                    .ok_or(Errors::NotAMember)?;
+                Membership::Member(w)
+            }
+            Membership::NonMember(_) if (&msg.added % &self.x).is_zero() => {
+                // x was added: w^x = A^added, with w = A^{added / x}
+                let w = self.root.modpow(&(&msg.added / &self.x), n);
+                let w = update_witness(&w, &self.x, &BigUint::one(), &msg.deleted, &msg.root, n)
+                    .ok_or(Errors::NotAMember)?;
+                Membership::Member(w)
+            }
+            Membership::NonMember((d, b)) => {
+                let invalid = || Errors::InvalidDelta(msg.epoch);
+                if (&msg.deleted % &self.x).is_zero() {
+                    return Err(invalid());
+                }
+
+                let mid = self.root.modpow(&msg.added, n);
+                let (d, b) = non_mem_add(d, b, &self.x, &msg.added, &self.root, &mid, n)
+                    .ok_or_else(invalid)?;
+                let (d, b) =
+                    non_mem_del(&d, &b, &self.x, &msg.deleted, &msg.root, n).ok_or_else(invalid)?;
+                Membership::NonMember((d, b))
+            }
+        };
+
+        self.membership = membership;
+        self.root = msg.root.clone();
+
+        Ok(())
+    }
+
+    /// Updates the witness over the consecutive epochs of `msgs`, which must follow the root
+    /// of the witness, in one pass, see `feed::catch_up`.
+    ///
+    /// Fails like `update` for the first epoch that can't be applied, and with `Gap` if the
+    /// epochs are not consecutive. The witness is unchanged in all cases.
+    /// All operations are `mod n`.
+    pub fn catch_up(&mut self, msgs: &[UpdateMessage], n: &BigUint) -> Result<(), Errors> {
+        check_consecutive(msgs)?;
+        let mut res = self.clone();
+
+        // a non-member is caught up to the epoch it is added in, which switches it to membership
+        let mut rest = msgs;
+        if !res.is_member() {
+            let added_at = msgs
+                .iter()
+                .position(|msg| (&msg.added % &res.x).is_zero())
+                .unwrap_or(msgs.len());
+            res.catch_up_non_member(&msgs[..added_at], n)?;
+            rest = &msgs[added_at..];
+            if let Some((msg, after)) = rest.split_first() {
+                res.update(msg, n)?;
+                rest = after;
+            }
+        }
+
+        if let (Membership::Member(w), Some(last)) = (&res.membership, rest.last()) {
+            res.membership = Membership::Member(feed::catch_up(w, &res.x, rest, n)?);
+            res.root = last.root.clone();
+        }
+
+        *self = res;
+        Ok(())
+    }
+
+    /// Like `catch_up`, for non-membership witnesses and epochs that don't add `x`.
+    fn catch_up_non_member(&mut self, msgs: &[UpdateMessage], n: &BigUint) -> Result<(), Errors> {
+        let last = match msgs.last() {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        if let Some(msg) = msgs.iter().find(|msg| (&msg.deleted % &self.x).is_zero()) {
+            return Err(Errors::InvalidDelta(msg.epoch));
+        }
+        let (d, b) = match &self.membership {
+            Membership::NonMember(w) => w,
+            Membership::Member(_) => unreachable!("only called for non-members"),
+        };
+
+        // A_k^deleted = A_0^added, like a single epoch
+        let invalid = || Errors::InvalidDelta(last.epoch);
+        let (added, deleted) = combined_products(msgs);
+        let mid = self.root.modpow(&added, n);
+        let (d, b) = non_mem_add(d, b, &self.x, &added, &self.root, &mid, n).ok_or_else(invalid)?;
+        let (d, b) = non_mem_del(&d, &b, &self.x, &deleted, &last.root, n).ok_or_else(invalid)?;
+
+        self.membership = Membership::NonMember((d, b));
+        self.root = last.root.clone();
+
+        Ok(())
+    }
+}
+
+impl Accumulator {
+    /// Creates a membership witness for `x` if it is a member, and a non-membership witness otherwise.
+    pub fn universal_wit_create(&self, x: &BigUint) -> Result<UniversalWitness, Errors> {
+        let membership = if self.contains(x) {
+            Membership::Member(self.mem_wit_create(x))
+        } else {
+            Membership::NonMember(self.non_mem_wit_create(x)?)
+        };
+
+        Ok(UniversalWitness {
+            x: x.clone(),
+            root: self.state().clone(),
+            membership,
+        })
+    }
+}
+
+/// Updates the non-membership witness `(d, b)` of `x` after adding the elements with product
+/// `added` to `root`, resulting in `new_root = root^added`.
+fn non_mem_add(
+    d: &BigUint,
+    b: &BigInt,
+    x: &BigUint,
+    added: &BigUint,
+    root: &BigUint,
+    new_root: &BigUint,
+    n: &BigUint,
+) -> Option<(BigUint, BigInt)> {
+    if added.is_one() {
+        return Some((d.clone(), b.clone()));
+    }
+
+    // alpha added + beta x = 1, so with b' = b alpha and d' = d A^{b beta}:
+    // d'^x A'^{b'} = d^x A^{b beta x + b alpha added} = d^x A^b = g
+    let (gcd, alpha, beta) = ExtendedGcd::extended_gcd(added, x);
+    if !gcd.is_one() {
+        return None;
+    }
+    let d = (d * modpow_uint_int(root, &(b * beta), n).ok()?) % n;
+
+    reduce(&d, &(b * alpha), x, new_root, n)
+}
+
+/// Updates the non-membership witness `(d, b)` of `x` after deleting the elements with
+/// product `deleted`, resulting in `new_root`, with `root = new_root^deleted`.
+fn non_mem_del(
+    d: &BigUint,
+    b: &BigInt,
+    x: &BigUint,
+    deleted: &BigUint,
+    new_root: &BigUint,
+    n: &BigUint,
+) -> Option<(BigUint, BigInt)> {
+    // d^x A^b = d^x A'^{deleted b}
+    reduce(d, &(b * BigInt::from(deleted.clone())), x, new_root, n)
+}
+
+/// Keeps `b` below `x`: with `b = m x + r`, `d^x A^b = (d A^m)^x A^r`.
+fn reduce(
+    d: &BigUint,
+    b: &BigInt,
+    x: &BigUint,
+    root: &BigUint,
+    n: &BigUint,
+) -> Option<(BigUint, BigInt)> {
+    let (m, r) = b.div_mod_floor(&BigInt::from(x.clone()));
+    let d = (d * modpow_uint_int(root, &m, n).ok()?) % n;
+
+    Some((d, r))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::feed::{Notification, UpdateFeed};
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_universal_witness() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let mut feed = UpdateFeed::new();
+
+        let xs = (0..8)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let x = &xs[0];
+        acc.batch_add(&xs[1..4]);
+
+        let mut w = acc.universal_wit_create(x).unwrap();
+        assert!(!w.is_member());
+        assert!(w.verify(&acc));
+        feed.subscribe(x.clone());
+
+        let mut poll = |feed: &mut UpdateFeed, w: &mut UniversalWitness| {
+            for notification in feed.poll(x).unwrap() {
+                match notification {
+                    Notification::Update(msg) => w.update(&msg, &n)?,
+                    Notification::Removed(_) => return Err(Errors::NotAMember),
+                }
+            }
+            Ok(())
+        };
+
+        // epoch 1: other elements are added and deleted
+        acc.batch_add(&xs[4..6]);
+        acc.del(&xs[2]).unwrap();
+        feed.publish(&xs[4..6], &xs[2..3], acc.state());
+        poll(&mut feed, &mut w).unwrap();
+        assert!(!w.is_member());
+        assert!(w.verify(&acc));
+
+        // epoch 2: x is added, the witness switches to membership
+        acc.batch_add(&xs[..1]);
+        acc.add(&xs[6]);
+        acc.del(&xs[3]).unwrap();
+        feed.publish(&[x.clone(), xs[6].clone()], &xs[3..4], acc.state());
+        poll(&mut feed, &mut w).unwrap();
+        assert!(w.is_member());
+        assert!(w.verify(&acc));
+
+        // epoch 3: x is deleted, which needs a fresh witness
+        acc.del(x).unwrap();
+        acc.add(&xs[7]);
+        let msg = UpdateMessage {
+            epoch: 3,
+            added: xs[7].clone(),
+            deleted: x.clone(),
+            root: acc.state().clone(),
+        };
+        let before = w.clone();
+        assert_eq!(w.update(&msg, &n).unwrap_err(), Errors::NotAMember);
+        assert_eq!(w, before);
+
+        let w = acc.universal_wit_create(x).unwrap();
+        assert!(!w.is_member());
+        assert!(w.verify(&acc));
+
+        // a witness never verifies against another state
+        acc.add(x);
+        assert!(!w.verify(&acc));
+        assert!(acc.universal_wit_create(x).unwrap().is_member());
+    }
+
+    #[test]
+    fn test_universal_catch_up() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let n = acc.modulus().clone();
+        let mut feed = UpdateFeed::new();
+
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let x = &xs[0];
+        acc.batch_add(&xs[1..3]);
+
+        let start = acc.universal_wit_create(x).unwrap();
+        feed.subscribe(x.clone());
+
+        // x is added in the third of six epochs
+        for i in 3..9 {
+            let mut added = vec![xs[i].clone()];
+            if i == 5 {
+                added.push(x.clone());
+            }
+            acc.batch_add(&added);
+            acc.del(&xs[i - 2]).unwrap();
+            feed.publish(&added, &xs[i - 2..i - 1], acc.state());
+        }
+        let msgs = feed
+            .poll(x)
+            .unwrap()
+            .into_iter()
+            .map(|notification| match notification {
+                Notification::Update(msg) => msg,
+                Notification::Removed(_) => panic!("x was not removed"),
+            })
+            .collect::<Vec<_>>();
+
+        let mut serial = start.clone();
+        for msg in &msgs {
+            serial.update(msg, &n).unwrap();
+        }
+        let mut w = start.clone();
+        w.catch_up(&msgs, &n).unwrap();
+        assert!(w.is_member());
+        assert!(w.verify(&acc));
+        assert_eq!(w, serial);
+
+        // only the epochs before the addition
+        let mut w = start.clone();
+        w.catch_up(&msgs[..2], &n).unwrap();
+        assert!(!w.is_member());
+        assert_eq!(w.root, msgs[1].root);
+
+        // a non-member can't be deleted, nothing changes on errors
+        let mut bad = msgs[..2].to_vec();
+        bad[1].deleted = x.clone();
+        let mut w = start.clone();
+        assert_eq!(w.catch_up(&bad, &n), Err(Errors::InvalidDelta(2)));
+        assert_eq!(
+            w.catch_up(&[msgs[0].clone(), msgs[2].clone()], &n),
+            Err(Errors::Gap {
+                expected: 2,
+                got: 3
+            })
+        );
+        assert_eq!(w, start);
+    }
+}
@@ -1,7 +1,6 @@
-use crate::hash::hash_prime;
+use crate::error::Errors;
+use crate::primes::nth_prime_for_index;
 use crate::traits::*;
-use blake2::Blake2b;
-use byteorder::{BigEndian, ByteOrder};
 use num_bigint::{BigInt, BigUint};
 use num_traits::{One, Zero};
 use rand::CryptoRng;
@@ -64,14 +63,14 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
         self.acc.batch_add(&primes);
     }
 
-    fn open(&self, b: &Self::Domain, i: usize) -> Self::Commitment {
+    fn open(&self, b: &Self::Domain, i: usize) -> Result<Self::Commitment, Errors> {
         let p_i = map_i_to_p_i(i);
 
         if *b {
-            Commitment::Mem(self.acc.mem_wit_create(&p_i))
+            Ok(Commitment::Mem(self.acc.mem_wit_create(&p_i)))
         } else {
-            let p = self.acc.non_mem_wit_create(&p_i);
-            Commitment::NonMem(p)
+            let p = self.acc.non_mem_wit_create(&p_i)?;
+            Ok(Commitment::NonMem(p))
         }
     }
 
@@ -91,7 +90,7 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
         }
     }
 
-    fn batch_open(&self, b: &[Self::Domain], i: &[usize]) -> Self::BatchCommitment {
+    fn batch_open(&self, b: &[Self::Domain], i: &[usize]) -> Result<Self::BatchCommitment, Errors> {
         debug_assert!(b.len() == i.len());
 
         let ones = b
@@ -130,14 +129,16 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment
                 BigUint::zero(),
             )
         } else {
-            self.acc.non_mem_wit_create_star(&p_zeros)
+            self.acc.non_mem_wit_create_star(&p_zeros)?
         };
 
-        BatchCommitment(pi_i, pi_e)
+        Ok(BatchCommitment(pi_i, pi_e))
     }
 
     fn batch_verify(&self, b: &[Self::Domain], i: &[usize], pi: &Self::BatchCommitment) -> bool {
-        debug_assert!(b.len() == i.len());
+        if b.len() != i.len() {
+            return false;
+        }
 
         let ones = b
             .iter()
@@ -192,9 +193,7 @@ impl<A: UniversalAccumulator + BatchedAccumulator> DynamicVectorCommitment
 }
 
 fn map_i_to_p_i(i: usize) -> BigUint {
-    let mut to_hash = [0u8; 8];
-    BigEndian::write_u64(&mut to_hash, i as u64);
-    hash_prime::<_, Blake2b>(&to_hash)
+    nth_prime_for_index(i as u64)
 }
 
 #[cfg(test)]
@@ -222,11 +221,11 @@ mod tests {
         vc.commit(&val);
 
         // open a set bit
-        let comm = vc.open(&true, 2);
+        let comm = vc.open(&true, 2).unwrap();
         assert!(vc.verify(&true, 2, &comm), "invalid commitment (bit set)");
 
         // open a set bit
-        let comm = vc.open(&false, 3);
+        let comm = vc.open(&false, 3).unwrap();
         assert!(
             vc.verify(&false, 3, &comm),
             "invalid commitment (bit not set)"
@@ -246,11 +245,12 @@ mod tests {
         vc.commit(&val);
 
         let committed = vec![val[2].clone(), val[3].clone(), val[9].clone()];
-        let comm = vc.batch_open(&committed, &[2, 3, 9]);
+        let comm = vc.batch_open(&committed, &[2, 3, 9]).unwrap();
         assert!(
             vc.batch_verify(&committed, &[2, 3, 9], &comm),
             "invalid commitment (bit set)"
         );
+        assert!(!vc.batch_verify(&committed, &[2, 3], &comm));
     }
 
     #[test]
@@ -269,7 +269,7 @@ mod tests {
 
         vc.commit(&val);
 
-        let comm = vc.open(&true, 2);
+        let comm = vc.open(&true, 2).unwrap();
         assert!(vc.verify(&true, 2, &comm), "invalid commitment (bit set)");
 
         vc.update(&false, &true, 2);
@@ -280,7 +280,7 @@ mod tests {
             "commitment should be invalid (bit set)"
         );
 
-        let comm_new = vc.open(&false, 2);
+        let comm_new = vc.open(&false, 2).unwrap();
         assert!(
             vc.verify(&false, 2, &comm_new),
             "invalid commitment (bit not set)"
@@ -8,6 +8,7 @@ use num_bigint::BigUint;
 use rand::rngs::OsRng;
 use rand::{CryptoRng, Rng};
 
+use crate::error::Errors;
 use crate::traits::*;
 use crate::vc::BinaryVectorCommitment;
 
@@ -56,7 +57,7 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment for Ve
         }
     }
 
-    fn open(&self, b: &Self::Domain, i: usize) -> Self::Commitment {
+    fn open(&self, b: &Self::Domain, i: usize) -> Result<Self::Commitment, Errors> {
         let comm = hash_binary(b, self.lambda).into_iter().collect::<Vec<_>>();
         let offset = i * self.lambda;
         let is = (0..comm.len()).map(|j| offset + j).collect::<Vec<_>>();
@@ -72,7 +73,11 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment for Ve
         self.vc.batch_verify(&comm, &is, pi)
     }
 
-    fn batch_open(&self, b: &[Self::Domain], is: &[usize]) -> Self::BatchCommitment {
+    fn batch_open(
+        &self,
+        b: &[Self::Domain],
+        is: &[usize],
+    ) -> Result<Self::BatchCommitment, Errors> {
         debug_assert!(b.len() == is.len());
 
         let mut comm = Vec::with_capacity(self.lambda * b.len());
@@ -89,7 +94,9 @@ impl<A: UniversalAccumulator + BatchedAccumulator> StaticVectorCommitment for Ve
     }
 
     fn batch_verify(&self, b: &[Self::Domain], is: &[usize], pi: &Self::BatchCommitment) -> bool {
-        debug_assert!(b.len() == is.len());
+        if b.len() != is.len() {
+            return false;
+        }
 
         let mut comm = Vec::with_capacity(self.lambda * b.len());
         let mut comm_is = Vec::with_capacity(self.lambda * is.len());
@@ -131,7 +138,7 @@ impl<A: UniversalAccumulator + BatchedAccumulator> DynamicVectorCommitment for V
 fn hash_binary(m: &BigUint, lambda: usize) -> BitVec<u8> {
     let bytes = &Blake2b::digest(&m.to_bytes_be())[..];
     let len = ::std::cmp::min(bytes.len(), lambda / 8);
-    return BitVec::from_vec(bytes[..len].to_vec()); 
+    return BitVec::from_vec(bytes[..len].to_vec());
 }
 
 #[cfg(test)]
@@ -155,7 +162,7 @@ mod tests {
         vc.commit(&val);
 
         for i in 0..3 {
-            let comm = vc.open(&val[i], i);
+            let comm = vc.open(&val[i], i).unwrap();
             assert!(vc.verify(&val[i], i, &comm), "invalid commitment {}", i);
         }
     }
@@ -172,7 +179,7 @@ mod tests {
         vc.commit(&val);
 
         let committed = vec![val[1].clone(), val[3].clone()];
-        let comm = vc.batch_open(&committed, &[1, 3]);
+        let comm = vc.batch_open(&committed, &[1, 3]).unwrap();
         assert!(
             vc.batch_verify(&committed, &[1, 3], &comm),
             "invalid commitment"
@@ -190,7 +197,7 @@ mod tests {
 
         vc.commit(&val);
 
-        let comm = vc.open(&val[2], 2);
+        let comm = vc.open(&val[2], 2).unwrap();
         assert!(vc.verify(&val[2], 2, &comm), "invalid commitment");
 
         let new_val = rng.gen_biguint(128);
@@ -202,7 +209,7 @@ mod tests {
             "commitment should be invalid"
         );
 
-        let comm_new = vc.open(&new_val, 2);
+        let comm_new = vc.open(&new_val, 2).unwrap();
         assert!(vc.verify(&new_val, 2, &comm_new), "invalid commitment");
     }
 }
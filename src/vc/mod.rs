@@ -1,5 +1,9 @@
 mod binary;
 mod general;
+mod paired;
+mod sparse;
 
 pub use crate::vc::binary::*;
 pub use crate::vc::general::*;
+pub use crate::vc::paired::*;
+pub use crate::vc::sparse::*;
@@ -0,0 +1,200 @@
+use num_bigint::BigUint;
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::error::Errors;
+use crate::primes::nth_prime_for_index;
+use crate::traits::*;
+
+/// A binary vector commitment, accumulating the positions of the zeros and the ones in two
+/// accumulators with the same public parameters.
+///
+/// Every position is a member of exactly one of them, so all values are opened with
+/// membership proofs only, which are cheaper to create and verify than non-membership proofs.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct PairedVectorCommitment {
+    zeros: Accumulator,
+    ones: Accumulator,
+    bits: Vec<bool>,
+}
+
+/// A constant size opening of any number of positions, with one aggregated membership proof
+/// per accumulator, see `mem_wit_create_for_subset`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairedOpening {
+    zeros: Option<(BigUint, BigUint)>,
+    ones: Option<(BigUint, BigUint)>,
+}
+
+impl PairedVectorCommitment {
+    /// Commits to the empty vector.
+    pub fn new(params: &PublicParams) -> Self {
+        PairedVectorCommitment {
+            zeros: Accumulator::new_with_params(params),
+            ones: Accumulator::new_with_params(params),
+            bits: Vec::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.bits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
+    pub fn get(&self, i: usize) -> Option<bool> {
+        self.bits.get(i).cloned()
+    }
+
+    /// The roots of the accumulators of the zeros and the ones.
+    pub fn state(&self) -> (&BigUint, &BigUint) {
+        (self.zeros.state(), self.ones.state())
+    }
+
+    /// Appends `bits`, with a single batch addition per accumulator.
+    pub fn commit(&mut self, bits: &[bool]) {
+        let (mut zeros, mut ones) = (Vec::new(), Vec::new());
+        for (i, bit) in bits.iter().enumerate() {
+            let p = nth_prime_for_index((self.bits.len() + i) as u64);
+            if *bit {
+                ones.push(p);
+            } else {
+                zeros.push(p);
+            }
+        }
+
+        if !zeros.is_empty() {
+            self.zeros.batch_add(&zeros);
+        }
+        if !ones.is_empty() {
+            self.ones.batch_add(&ones);
+        }
+        self.bits.extend_from_slice(bits);
+    }
+
+    /// Sets position `i` to `bit`, moving it from one accumulator to the other.
+    ///
+    /// Panics if `i` is out of range.
+    pub fn update(&mut self, i: usize, bit: bool) {
+        assert!(i < self.bits.len(), "index out of range");
+        if self.bits[i] == bit {
+            return;
+        }
+
+        let p = nth_prime_for_index(i as u64);
+        let (from, to) = if bit {
+            (&mut self.zeros, &mut self.ones)
+        } else {
+            (&mut self.ones, &mut self.zeros)
+        };
+        from.del(&p).expect("the position is accumulated");
+        to.add(&p);
+        self.bits[i] = bit;
+    }
+
+    /// Opens the positions `is`, which must be distinct.
+    /// Fails with `NotAMember` if an index is out of range or repeated.
+    pub fn open(&self, is: &[usize]) -> Result<PairedOpening, Errors> {
+        if is.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+        let bits = is
+            .iter()
+            .map(|i| self.get(*i).ok_or(Errors::NotAMember))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let open = |acc: &Accumulator, value: bool| {
+            let ps = positions(is, &bits, value);
+            if ps.is_empty() {
+                Ok(None)
+            } else {
+                acc.mem_wit_create_for_subset(&ps).map(Some)
+            }
+        };
+
+        Ok(PairedOpening {
+            zeros: open(&self.zeros, false)?,
+            ones: open(&self.ones, true)?,
+        })
+    }
+
+    /// Verifies that the positions `is` have the values `bits`.
+    pub fn verify(&self, is: &[usize], bits: &[bool], pi: &PairedOpening) -> bool {
+        if is.is_empty() || is.len() != bits.len() {
+            return false;
+        }
+
+        let verify = |acc: &Accumulator, value: bool, pi: &Option<(BigUint, BigUint)>| {
+            let ps = positions(is, bits, value);
+            match pi {
+                Some(pi) => !ps.is_empty() && acc.ver_mem_for_subset(&ps, pi),
+                None => ps.is_empty(),
+            }
+        };
+
+        verify(&self.zeros, false, &pi.zeros) && verify(&self.ones, true, &pi.ones)
+    }
+}
+
+/// The primes of the positions in `is` with the given value.
+fn positions(is: &[usize], bits: &[bool], value: bool) -> Vec<BigUint> {
+    is.iter()
+        .zip(bits)
+        .filter(|(_, bit)| **bit == value)
+        .map(|(i, _)| nth_prime_for_index(*i as u64))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::group::RSAGroup;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_paired_vc() {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let params = PublicParams::generate::<RSAGroup, _>(&mut rng, int_size_bits);
+
+        let mut vc = PairedVectorCommitment::new(&params);
+        let mut bits: Vec<bool> = (0..48).map(|_| rng.gen()).collect();
+        bits[2] = true;
+        bits[3] = false;
+        vc.commit(&bits[..32]);
+        vc.commit(&bits[32..]);
+        assert_eq!(vc.len(), 48);
+
+        let is = [2, 3, 9, 40, 47];
+        let values = is.iter().map(|i| bits[*i]).collect::<Vec<_>>();
+        let pi = vc.open(&is).unwrap();
+        assert!(vc.verify(&is, &values, &pi));
+        assert!(!vc.verify(&is[1..], &values[1..], &pi));
+
+        let mut flipped = values.clone();
+        flipped[0] = !flipped[0];
+        assert!(!vc.verify(&is, &flipped, &pi));
+
+        // only ones
+        let pi_one = vc.open(&[2]).unwrap();
+        assert!(vc.verify(&[2], &[true], &pi_one));
+        assert!(!vc.verify(&[2], &[false], &pi_one));
+
+        assert_eq!(vc.open(&[48]), Err(Errors::NotAMember));
+        assert_eq!(vc.open(&[2, 2]), Err(Errors::NotAMember));
+        assert_eq!(vc.open(&[]), Err(Errors::EmptyBatch));
+
+        vc.update(2, false);
+        vc.update(3, false);
+        assert_eq!(vc.get(2), Some(false));
+        assert!(!vc.verify(&is, &values, &pi));
+        assert!(!vc.verify(&[2], &[true], &pi_one));
+
+        let values = is.iter().map(|i| vc.get(*i).unwrap()).collect::<Vec<_>>();
+        assert!(vc.verify(&is, &values, &vc.open(&is).unwrap()));
+    }
+}
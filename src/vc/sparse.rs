@@ -0,0 +1,224 @@
+use std::collections::BTreeSet;
+
+use num_bigint::{BigInt, BigUint};
+use rand::{CryptoRng, Rng};
+
+use crate::error::Errors;
+use crate::primes::nth_prime_for_index;
+use crate::traits::*;
+
+/// A binary vector commitment over all `u64` positions, of which only few are set.
+///
+/// Position `i` is set iff `nth_prime_for_index(i)` is accumulated. Any number of positions
+/// is opened with a single membership and a single non-membership proof.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone)]
+pub struct SparseVectorCommitment<A: UniversalAccumulator + BatchedAccumulator> {
+    acc: A,
+    positions: BTreeSet<u64>,
+}
+
+/// The aggregated opening of some positions, see `SparseVectorCommitment::open`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseOpening {
+    /// The opened positions, sorted and without duplicates.
+    pub indices: Vec<u64>,
+    /// The value of each opened position.
+    pub values: Vec<bool>,
+    /// Membership proof for the set positions, see `mem_wit_create_for_subset`.
+    mem: Option<(BigUint, BigUint)>,
+    /// Non-membership proof for the unset positions, see `non_mem_wit_create_star_batch`.
+    #[allow(clippy::type_complexity)]
+    non_mem: Option<(BigUint, BigUint, (BigUint, BigUint, BigInt), BigUint)>,
+}
+
+impl SparseOpening {
+    /// Returns the opened value of position `i`, if it was opened.
+    pub fn get(&self, i: u64) -> Option<bool> {
+        self.indices
+            .binary_search(&i)
+            .ok()
+            .map(|pos| self.values[pos])
+    }
+
+    fn primes(&self, value: bool) -> Vec<BigUint> {
+        self.indices
+            .iter()
+            .zip(&self.values)
+            .filter(|(_, v)| **v == value)
+            .map(|(i, _)| nth_prime_for_index(*i))
+            .collect()
+    }
+}
+
+impl<A: UniversalAccumulator + BatchedAccumulator> SparseVectorCommitment<A> {
+    /// Commits to the all zero vector.
+    pub fn setup<G, R>(rng: &mut R, lambda: usize) -> Self
+    where
+        G: PrimeGroup,
+        R: CryptoRng + Rng,
+    {
+        SparseVectorCommitment::new(A::setup::<G, _>(rng, lambda))
+    }
+
+    /// Uses `acc`, which must be empty.
+    pub fn new(acc: A) -> Self {
+        SparseVectorCommitment {
+            acc,
+            positions: BTreeSet::new(),
+        }
+    }
+
+    /// Returns the number of set positions.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// Returns the set positions, in ascending order.
+    pub fn positions(&self) -> impl Iterator<Item = u64> + '_ {
+        self.positions.iter().cloned()
+    }
+
+    pub fn get(&self, i: u64) -> bool {
+        self.positions.contains(&i)
+    }
+
+    /// Sets all positions in `is`, with a single batch addition.
+    /// Positions that are set already are ignored.
+    pub fn set(&mut self, is: &[u64]) {
+        let new = is
+            .iter()
+            .filter(|i| self.positions.insert(**i))
+            .map(|i| nth_prime_for_index(*i))
+            .collect::<Vec<_>>();
+
+        if !new.is_empty() {
+            self.acc.batch_add(&new);
+        }
+    }
+
+    /// Clears position `i`.
+    /// Returns `false` if it was not set.
+    pub fn unset(&mut self, i: u64) -> bool {
+        if !self.positions.remove(&i) {
+            return false;
+        }
+
+        self.acc
+            .del(&nth_prime_for_index(i))
+            .expect("the position is accumulated");
+        true
+    }
+
+    /// Opens all positions in `is`, with a single proof for the set, and a single proof for the
+    /// unset positions.
+    pub fn open(&self, is: &[u64]) -> Result<SparseOpening, Errors> {
+        let indices = is.iter().cloned().collect::<BTreeSet<_>>();
+        if indices.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        let mut opening = SparseOpening {
+            values: indices.iter().map(|i| self.get(*i)).collect(),
+            indices: indices.into_iter().collect(),
+            mem: None,
+            non_mem: None,
+        };
+
+        let ones = opening.primes(true);
+        if !ones.is_empty() {
+            opening.mem = Some(self.acc.mem_wit_create_for_subset(&ones)?);
+        }
+        let zeros = opening.primes(false);
+        if !zeros.is_empty() {
+            opening.non_mem = Some(self.acc.non_mem_wit_create_star_batch(&zeros)?);
+        }
+
+        Ok(opening)
+    }
+
+    /// Verifies all values of `opening` against the current commitment.
+    pub fn verify_openings(&self, opening: &SparseOpening) -> bool {
+        if opening.indices.is_empty()
+            || opening.indices.len() != opening.values.len()
+            || opening.indices.windows(2).any(|w| w[0] >= w[1])
+        {
+            return false;
+        }
+
+        let ones = opening.primes(true);
+        let mem_ok = match &opening.mem {
+            Some(pi) => !ones.is_empty() && self.acc.ver_mem_for_subset(&ones, pi),
+            None => ones.is_empty(),
+        };
+        let zeros = opening.primes(false);
+        let non_mem_ok = match &opening.non_mem {
+            Some(pi) => !zeros.is_empty() && self.acc.ver_non_mem_star_batch(&zeros, pi),
+            None => zeros.is_empty(),
+        };
+
+        mem_ok && non_mem_ok
+    }
+
+    pub fn state(&self) -> &BigUint {
+        self.acc.state()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accumulator::Accumulator;
+    use crate::group::RSAGroup;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_sparse_vc() {
+        let mut rng = ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+
+        let mut vc =
+            SparseVectorCommitment::<Accumulator>::setup::<RSAGroup, _>(&mut rng, int_size_bits);
+        let set = [3, 1 << 40, u64::max_value(), 17];
+        vc.set(&set);
+        vc.set(&[3]);
+        assert_eq!(vc.len(), 4);
+        assert!(vc.unset(17));
+        assert!(!vc.unset(17));
+        assert_eq!(
+            vc.positions().collect::<Vec<_>>(),
+            vec![3, 1 << 40, u64::max_value()]
+        );
+
+        let opening = vc.open(&[u64::max_value(), 5, 3, 17, 3]).unwrap();
+        assert_eq!(opening.indices, vec![3, 5, 17, u64::max_value()]);
+        assert_eq!(opening.values, vec![true, false, false, true]);
+        assert_eq!(opening.get(17), Some(false));
+        assert_eq!(opening.get(1 << 40), None);
+        assert!(vc.verify_openings(&opening));
+
+        // only set or only unset positions
+        assert!(vc.verify_openings(&vc.open(&[1 << 40]).unwrap()));
+        assert!(vc.verify_openings(&vc.open(&[4, 1 << 41]).unwrap()));
+        assert_eq!(vc.open(&[]), Err(Errors::EmptyBatch));
+
+        // flipped values
+        let mut bad = opening.clone();
+        bad.values[1] = true;
+        assert!(!vc.verify_openings(&bad));
+        let mut bad = opening.clone();
+        bad.indices[1] = 6;
+        assert!(!vc.verify_openings(&bad));
+
+        // stale after an update
+        vc.set(&[5]);
+        assert!(!vc.verify_openings(&opening));
+        assert!(vc.verify_openings(&vc.open(&[5, 17]).unwrap()));
+    }
+}
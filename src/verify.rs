@@ -0,0 +1,277 @@
+//! Verifying large collections of proofs against an accumulator.
+//!
+//! [`verify_all`] checks a slice of [`Claim`]s, either stopping at the first failure with
+//! `all`, or reporting every failing claim with `failures`. With the `parallel` feature,
+//! `par` spreads the checks across all cores.
+//!
+//! [`verify_batch_add`] and [`verify_batch_del`] check a single transition between two
+//! explicit roots, and [`verify_mem`] and [`verify_non_mem`] a witness against an explicit
+//! root, so auditors don't need an accumulator object at all.
+
+use num_bigint::{BigInt, BigUint};
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+use crate::accumulator::{Accumulator, PublicParams};
+use crate::math::{modpow_uint_int, product};
+use crate::proofs;
+use crate::traits::*;
+
+/// A single proof to check against the current state of an accumulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Claim<'a> {
+    /// `w` is a membership witness for `x`.
+    Mem { w: &'a BigUint, x: &'a BigUint },
+    /// `w` is a non-membership witness for `x`.
+    NonMem {
+        w: &'a (BigUint, BigInt),
+        x: &'a BigUint,
+    },
+    /// `w` proves that adding `xs` to `a_t` resulted in the current state.
+    BatchAdd {
+        w: &'a BigUint,
+        a_t: &'a BigUint,
+        xs: &'a [BigUint],
+    },
+    /// `w` proves that deleting `xs` from `a_t` resulted in the current state.
+    BatchDel {
+        w: &'a BigUint,
+        a_t: &'a BigUint,
+        xs: &'a [BigUint],
+    },
+}
+
+impl<'a> Claim<'a> {
+    pub fn verify(&self, acc: &Accumulator) -> bool {
+        match *self {
+            Claim::Mem { w, x } => acc.ver_mem(w, x),
+            Claim::NonMem { w, x } => acc.ver_non_mem(w, x),
+            Claim::BatchAdd { w, a_t, xs } => acc.ver_batch_add(w, a_t, xs),
+            Claim::BatchDel { w, a_t, xs } => acc.ver_batch_del(w, a_t, xs),
+        }
+    }
+}
+
+/// Checks the membership witness `w` of `x` against `root`, see `ver_mem`.
+pub fn verify_mem(params: &PublicParams, root: &BigUint, w: &BigUint, x: &BigUint) -> bool {
+    params.map_element(&w.modpow(x, &params.n)) == params.map_element(root)
+}
+
+/// Checks the non-membership witness `(d, b)` of `x` against `root`, see `ver_non_mem`.
+pub fn verify_non_mem(
+    params: &PublicParams,
+    root: &BigUint,
+    w: &(BigUint, BigInt),
+    x: &BigUint,
+) -> bool {
+    let (d, b) = w;
+    let n = &params.n;
+
+    // d^x A^b == g
+    match modpow_uint_int(root, b, n) {
+        Ok(a_b) => {
+            params.map_element(&((d.modpow(x, n) * a_b) % n)) == params.map_element(&params.g)
+        }
+        Err(_) => false,
+    }
+}
+
+/// Checks the proof returned by `batch_add`, that adding `xs` to `old_root` resulted in `new_root`.
+pub fn verify_batch_add(
+    params: &PublicParams,
+    old_root: &BigUint,
+    new_root: &BigUint,
+    xs: &[BigUint],
+    proof: &BigUint,
+) -> bool {
+    verify_poe(params, &product(xs), old_root, new_root, proof)
+}
+
+/// Checks the proof returned by `batch_del`, that deleting `xs` from `old_root` resulted
+/// in `new_root`.
+pub fn verify_batch_del(
+    params: &PublicParams,
+    old_root: &BigUint,
+    new_root: &BigUint,
+    xs: &[BigUint],
+    proof: &BigUint,
+) -> bool {
+    verify_poe(params, &product(xs), new_root, old_root, proof)
+}
+
+/// `ni_poe_verify`, or `ni_poe_verify_qr` for parameters in `QR_n`.
+fn verify_poe(params: &PublicParams, x: &BigUint, u: &BigUint, w: &BigUint, q: &BigUint) -> bool {
+    if params.qr {
+        proofs::ni_poe_verify_qr(x, u, w, q, &params.n)
+    } else {
+        proofs::ni_poe_verify(x, u, w, q, &params.n)
+    }
+}
+
+/// Checks many claims at once, see [`verify_all`].
+#[derive(Debug, Clone)]
+pub struct VerifyAll<'a> {
+    acc: &'a Accumulator,
+    claims: &'a [Claim<'a>],
+    #[cfg(feature = "parallel")]
+    parallel: bool,
+}
+
+/// Prepares checking all `claims` against `acc`.
+pub fn verify_all<'a>(acc: &'a Accumulator, claims: &'a [Claim<'a>]) -> VerifyAll<'a> {
+    VerifyAll {
+        acc,
+        claims,
+        #[cfg(feature = "parallel")]
+        parallel: false,
+    }
+}
+
+impl<'a> VerifyAll<'a> {
+    /// Checks the claims on multiple threads.
+    #[cfg(feature = "parallel")]
+    pub fn par(mut self) -> Self {
+        self.parallel = true;
+        self
+    }
+
+    /// Returns `true` if all claims are valid, stopping at the first invalid one.
+    pub fn all(&self) -> bool {
+        let acc = self.acc;
+
+        #[cfg(feature = "parallel")]
+        {
+            if self.parallel {
+                return self.claims.par_iter().all(|c| c.verify(acc));
+            }
+        }
+
+        self.claims.iter().all(|c| c.verify(acc))
+    }
+
+    /// Checks all claims, and returns the indices of the invalid ones, in order.
+    pub fn failures(&self) -> Vec<usize> {
+        let acc = self.acc;
+
+        #[cfg(feature = "parallel")]
+        {
+            if self.parallel {
+                return self
+                    .claims
+                    .par_iter()
+                    .enumerate()
+                    .filter(|(_, c)| !c.verify(acc))
+                    .map(|(i, _)| i)
+                    .collect();
+            }
+        }
+
+        self.claims
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| !c.verify(acc))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+
+    #[test]
+    fn test_verify_all() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..10)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..4]);
+        let a_t = acc.state().clone();
+        let w_add = acc.batch_add(&xs[4..8]);
+
+        let ws = acc.create_all_mem_wit(&xs[..8]);
+        let u = acc.non_mem_wit_create(&xs[8]).unwrap();
+        let u_bad = acc.non_mem_wit_create(&xs[9]).unwrap();
+
+        let mut claims = xs[..8]
+            .iter()
+            .zip(ws.iter())
+            .map(|(x, w)| Claim::Mem { w, x })
+            .collect::<Vec<_>>();
+        claims.push(Claim::NonMem { w: &u, x: &xs[8] });
+        claims.push(Claim::BatchAdd {
+            w: &w_add,
+            a_t: &a_t,
+            xs: &xs[4..8],
+        });
+
+        assert!(verify_all(&acc, &claims).all());
+        assert!(verify_all(&acc, &claims).failures().is_empty());
+
+        // wrong witnesses
+        claims[2] = Claim::Mem {
+            w: &ws[3],
+            x: &xs[2],
+        };
+        claims.push(Claim::NonMem {
+            w: &u_bad,
+            x: &xs[8],
+        });
+
+        assert!(!verify_all(&acc, &claims).all());
+        assert_eq!(verify_all(&acc, &claims).failures(), vec![2, 10]);
+
+        #[cfg(feature = "parallel")]
+        {
+            assert!(!verify_all(&acc, &claims).par().all());
+            assert_eq!(verify_all(&acc, &claims).par().failures(), vec![2, 10]);
+        }
+    }
+
+    #[test]
+    fn test_verify_transitions() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+        let params = acc.params();
+
+        let xs = (0..6)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        let a_0 = acc.state().clone();
+        let add_proof = acc.batch_add(&xs);
+        let a_1 = acc.state().clone();
+        let del_proof = acc.batch_del_tracked(&xs[1..4]).unwrap();
+        let a_2 = acc.state().clone();
+
+        // any transition can be checked, not only the latest
+        assert!(verify_batch_add(&params, &a_0, &a_1, &xs, &add_proof));
+        assert!(verify_batch_del(&params, &a_1, &a_2, &xs[1..4], &del_proof));
+
+        assert!(!verify_batch_add(&params, &a_0, &a_2, &xs, &add_proof));
+        assert!(!verify_batch_add(&params, &a_0, &a_1, &xs[1..], &add_proof));
+        assert!(!verify_batch_del(
+            &params,
+            &a_2,
+            &a_1,
+            &xs[1..4],
+            &del_proof
+        ));
+        assert!(!verify_batch_del(
+            &params,
+            &a_1,
+            &a_2,
+            &xs[1..4],
+            &add_proof
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+//! Format versions of persisted values, and migrations from older versions.
+//!
+//! Versioned encodings start with a magic and the format version as `u16` BE, followed by the
+//! body in the format of that version. On load, the migrations from the stored version up to
+//! the current one are applied to the body, so values persisted before a format change, e.g. a
+//! new hash or encoding, can still be loaded.
+
+use std::collections::BTreeMap;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use crate::error::Errors;
+
+/// Prefixes versioned encodings of values, see `Encode::to_versioned_bytes`.
+pub const VERSION_MAGIC: &[u8] = b"ACCV";
+
+/// The current format of `Encode`.
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Upgrades a body by one version.
+pub type Migration = fn(Vec<u8>) -> Result<Vec<u8>, Errors>;
+
+/// The upgrades between consecutive versions of a format.
+#[derive(Debug, Clone, Default)]
+pub struct Migrations {
+    steps: BTreeMap<u16, Migration>,
+}
+
+impl Migrations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The migrations of `Encode`, there are none yet.
+    pub fn encoding() -> Self {
+        Self::new()
+    }
+
+    /// Registers the upgrade of bodies from version `from` to `from + 1`.
+    pub fn register(mut self, from: u16, migration: Migration) -> Self {
+        self.steps.insert(from, migration);
+        self
+    }
+
+    /// Upgrades `body` from `version` to `target`.
+    /// Fails with `UnsupportedVersion` if `version` is newer than `target`, or a step is missing.
+    pub fn upgrade(&self, version: u16, mut body: Vec<u8>, target: u16) -> Result<Vec<u8>, Errors> {
+        if version > target {
+            return Err(Errors::UnsupportedVersion(version));
+        }
+
+        for v in version..target {
+            let migration = self.steps.get(&v).ok_or(Errors::UnsupportedVersion(v))?;
+            body = migration(body)?;
+        }
+
+        Ok(body)
+    }
+}
+
+/// Appends `magic || version`.
+pub(crate) fn write_header(out: &mut Vec<u8>, magic: &[u8], version: u16) {
+    let mut v = [0u8; 2];
+    BigEndian::write_u16(&mut v, version);
+    out.extend(magic);
+    out.extend(&v);
+}
+
+/// Reads the header written by `write_header`, returning the version and the body.
+pub(crate) fn read_header<'a>(bytes: &'a [u8], magic: &[u8]) -> Result<(u16, &'a [u8]), Errors> {
+    if bytes.len() < magic.len() + 2 || !bytes.starts_with(magic) {
+        return Err(Errors::InvalidEncoding);
+    }
+    let version = BigEndian::read_u16(&bytes[magic.len()..]);
+
+    Ok((version, &bytes[magic.len() + 2..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::encoding::Encode;
+    use num_bigint::{BigInt, BigUint};
+
+    #[test]
+    fn test_migrations() {
+        // v1 -> v2 appends a byte, v2 -> v3 reverses the body
+        let migrations = Migrations::new()
+            .register(1, |mut body| {
+                body.push(0);
+                Ok(body)
+            })
+            .register(2, |mut body| {
+                body.reverse();
+                Ok(body)
+            });
+
+        assert_eq!(migrations.upgrade(1, vec![1, 2], 3), Ok(vec![0, 2, 1]));
+        assert_eq!(migrations.upgrade(2, vec![1, 2], 3), Ok(vec![2, 1]));
+        assert_eq!(migrations.upgrade(3, vec![1, 2], 3), Ok(vec![1, 2]));
+        assert_eq!(
+            migrations.upgrade(4, vec![1, 2], 3),
+            Err(Errors::UnsupportedVersion(4))
+        );
+        assert_eq!(
+            migrations.upgrade(0, vec![1, 2], 3),
+            Err(Errors::UnsupportedVersion(0))
+        );
+
+        let mut out = Vec::new();
+        write_header(&mut out, b"X", 258);
+        out.push(7);
+        assert_eq!(out, vec![b'X', 1, 2, 7]);
+        assert_eq!(read_header(&out, b"X"), Ok((258, &[7u8][..])));
+        assert_eq!(read_header(&out, b"Y"), Err(Errors::InvalidEncoding));
+        assert_eq!(read_header(b"X\x01", b"X"), Err(Errors::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_versioned_encoding() {
+        let pi = (BigUint::from(5u32), BigInt::from(-3));
+        let bytes = pi.to_versioned_bytes();
+        assert!(bytes.starts_with(VERSION_MAGIC));
+        assert_eq!(&bytes[VERSION_MAGIC.len() + 2..], &pi.to_bytes()[..]);
+        assert_eq!(
+            <(BigUint, BigInt)>::from_versioned_bytes(&bytes),
+            Ok(pi.clone())
+        );
+
+        // unversioned or from the future
+        assert_eq!(
+            <(BigUint, BigInt)>::from_versioned_bytes(&pi.to_bytes()),
+            Err(Errors::InvalidEncoding)
+        );
+        let mut future = Vec::new();
+        write_header(&mut future, VERSION_MAGIC, FORMAT_VERSION + 1);
+        future.extend(pi.to_bytes());
+        assert_eq!(
+            <(BigUint, BigInt)>::from_versioned_bytes(&future),
+            Err(Errors::UnsupportedVersion(FORMAT_VERSION + 1))
+        );
+    }
+}
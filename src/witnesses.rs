@@ -0,0 +1,444 @@
+//! Precomputed membership witnesses for all members, kept up to date incrementally.
+//!
+//! The witnesses are the leaves of a binary tree, every node stores the products of the
+//! elements added and deleted since, which still have to be applied to all leaves below it.
+//! A change only touches a single path of the tree, the pending updates of a witness are
+//! applied when it is read. Once more changes are pending than there are members, all
+//! witnesses are recomputed with `root_factor`, which keeps the amortized cost of a change
+//! at `O(log n)`, instead of `O(n log n)` for rerunning `create_all_mem_wit`.
+
+use std::collections::HashMap;
+use std::mem;
+
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::accumulator::Accumulator;
+use crate::error::Errors;
+use crate::feed::update_witness;
+use crate::hooks::Mutation;
+use crate::math::{product, root_factor, shamir_trick};
+use crate::proofs;
+use crate::traits::StaticAccumulator;
+
+/// Membership witnesses of all members of an accumulator.
+#[derive(Debug, Clone)]
+pub struct WitnessTree {
+    g: BigUint,
+    n: BigUint,
+    /// The root the witnesses are updated to.
+    root: BigUint,
+    /// The number of leaves, a power of two.
+    capacity: usize,
+    /// The element stored in each leaf.
+    members: Vec<Option<BigUint>>,
+    /// The leaf of every member.
+    index: HashMap<BigUint, usize>,
+    /// Unused leaves.
+    free: Vec<usize>,
+    /// The witness of every leaf, before applying the pending changes on its path.
+    witnesses: Vec<BigUint>,
+    /// Pending additions and deletions of every node.
+    /// Node `i` has the children `2i` and `2i + 1`, leaf `j` is node `capacity + j`.
+    added: Vec<BigUint>,
+    deleted: Vec<BigUint>,
+    /// The number of changes since the witnesses were last recomputed.
+    pending: usize,
+}
+
+/// Randomly chosen members with their witnesses, for auditors to spot check that the
+/// claimed set matches the root, see `WitnessTree::sample_members`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemberSample {
+    /// The sampled members and their witnesses.
+    pub members: Vec<(BigUint, BigUint)>,
+    /// The witness of all sampled members together, and the NI-PoE of it, as returned by
+    /// `mem_wit_create_for_subset`.
+    pub aggregate: (BigUint, BigUint),
+}
+
+impl MemberSample {
+    /// Checks every witness and the aggregated proof against `root`.
+    pub fn verify(&self, root: &BigUint, n: &BigUint) -> bool {
+        if self.members.is_empty() || !self.members.iter().all(|(x, w)| &w.modpow(x, n) == root) {
+            return false;
+        }
+
+        let xs = self
+            .members
+            .iter()
+            .map(|(x, _)| x.clone())
+            .collect::<Vec<_>>();
+        let (w, pi) = &self.aggregate;
+        proofs::ni_poe_verify(&product(&xs), w, root, pi, n)
+    }
+}
+
+impl WitnessTree {
+    /// Computes the witnesses of all `members`, which must be exactly the set accumulated in `acc`.
+    pub fn new(acc: &Accumulator, members: &[BigUint]) -> Self {
+        let mut tree = WitnessTree {
+            g: acc.generator().clone(),
+            n: acc.modulus().clone(),
+            root: acc.state().clone(),
+            capacity: 0,
+            members: Vec::new(),
+            index: HashMap::new(),
+            free: Vec::new(),
+            witnesses: Vec::new(),
+            added: Vec::new(),
+            deleted: Vec::new(),
+            pending: 0,
+        };
+        tree.rebuild_with(members.to_vec());
+        tree
+    }
+
+    /// Returns the number of members.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains(&self, x: &BigUint) -> bool {
+        self.index.contains_key(x)
+    }
+
+    /// Returns the root the witnesses are valid for.
+    pub fn root(&self) -> &BigUint {
+        &self.root
+    }
+
+    /// Returns the witness of `x`, or `None` if `x` is not a member.
+    pub fn witness(&mut self, x: &BigUint) -> Option<BigUint> {
+        let i = *self.index.get(x)?;
+        self.push_down(i);
+
+        let leaf = self.capacity + i;
+        let w = update_witness(
+            &self.witnesses[i],
+            x,
+            &self.added[leaf],
+            &self.deleted[leaf],
+            &self.root,
+            &self.n,
+        )?;
+
+        self.added[leaf] = BigUint::one();
+        self.deleted[leaf] = BigUint::one();
+        self.witnesses[i] = w.clone();
+
+        Some(w)
+    }
+
+    /// Picks `k` distinct members uniformly at random, or all of them if there are fewer,
+    /// and returns them with fresh witnesses for the current root.
+    /// Fails with `EmptyBatch` if `k` is zero or there are no members.
+    pub fn sample_members<R: Rng>(
+        &mut self,
+        rng: &mut R,
+        k: usize,
+    ) -> Result<MemberSample, Errors> {
+        if k == 0 || self.is_empty() {
+            return Err(Errors::EmptyBatch);
+        }
+
+        let live = self.live_members();
+        let chosen = live.choose_multiple(rng, k).cloned().collect::<Vec<_>>();
+        let members = chosen
+            .into_iter()
+            .map(|x| {
+                let w = self.witness(&x).expect("sampled from the members");
+                (x, w)
+            })
+            .collect::<Vec<_>>();
+
+        // combine the witnesses into the x_1 * .. * x_k-th root of the accumulator
+        let (x_0, w_0) = members[0].clone();
+        let (x_star, w_star) = members[1..]
+            .iter()
+            .try_fold((x_0, w_0), |(x_star, w_star), (x, w)| {
+                let w_star = shamir_trick(&w_star, w, &x_star, x, &self.n)?;
+                Some((x_star * x, w_star))
+            })
+            .ok_or(Errors::NotCoprime)?;
+        let pi = proofs::ni_poe_prove(&x_star, &w_star, &self.root, &self.n);
+
+        Ok(MemberSample {
+            members,
+            aggregate: (w_star, pi),
+        })
+    }
+
+    /// Adds `x`, resulting in the accumulator state `root`.
+    pub fn add(&mut self, x: &BigUint, root: &BigUint) {
+        self.batch_add(std::slice::from_ref(x), root);
+    }
+
+    /// Adds all `xs`, resulting in the accumulator state `root`.
+    pub fn batch_add(&mut self, xs: &[BigUint], root: &BigUint) {
+        if xs.is_empty() {
+            return;
+        }
+
+        let old_root = mem::replace(&mut self.root, root.clone());
+        self.pending += xs.len();
+
+        if self.free.len() < xs.len() || self.pending > self.len() + xs.len() {
+            let mut members = self.live_members();
+            members.extend_from_slice(xs);
+            self.rebuild_with(members);
+            return;
+        }
+
+        // all current members get the new elements
+        let x_star = product(xs);
+        self.added[1] *= &x_star;
+
+        // the new members start at the old root, and get the other new elements
+        for x in xs {
+            let i = self.free.pop().expect("checked above");
+            self.push_down(i);
+
+            let leaf = self.capacity + i;
+            self.added[leaf] = &x_star / x;
+            self.deleted[leaf] = BigUint::one();
+            self.witnesses[i] = old_root.clone();
+            self.members[i] = Some(x.clone());
+            self.index.insert(x.clone(), i);
+        }
+    }
+
+    /// Deletes `x`, resulting in the accumulator state `root`.
+    pub fn del(&mut self, x: &BigUint, root: &BigUint) -> Result<(), Errors> {
+        self.batch_del(std::slice::from_ref(x), root)
+    }
+
+    /// Deletes all `xs`, resulting in the accumulator state `root`.
+    pub fn batch_del(&mut self, xs: &[BigUint], root: &BigUint) -> Result<(), Errors> {
+        if !xs.iter().all(|x| self.contains(x)) {
+            return Err(Errors::NotAMember);
+        }
+        if xs.is_empty() {
+            return Ok(());
+        }
+
+        for x in xs {
+            let i = self.index.remove(x).expect("checked above");
+            self.members[i] = None;
+            self.witnesses[i] = BigUint::zero();
+            self.free.push(i);
+        }
+
+        self.root = root.clone();
+        self.pending += xs.len();
+
+        if self.pending > self.len() {
+            self.rebuild();
+        } else {
+            self.deleted[1] *= product(xs);
+        }
+
+        Ok(())
+    }
+
+    /// Applies a change reported by an accumulator observer.
+    pub fn apply(&mut self, mutation: &Mutation<'_>) -> Result<(), Errors> {
+        if mutation.old_root != &self.root {
+            return Err(Errors::OutOfSync);
+        }
+
+        if mutation.kind.is_add() {
+            self.batch_add(mutation.elements, mutation.new_root);
+            Ok(())
+        } else {
+            self.batch_del(mutation.elements, mutation.new_root)
+        }
+    }
+
+    /// Recomputes all witnesses, discarding the pending changes.
+    pub fn rebuild(&mut self) {
+        let members = self.live_members();
+        self.rebuild_with(members);
+    }
+
+    fn live_members(&self) -> Vec<BigUint> {
+        self.members.iter().flatten().cloned().collect()
+    }
+
+    fn rebuild_with(&mut self, members: Vec<BigUint>) {
+        // leave room to add as many members as there are, before growing again
+        let capacity = (2 * members.len()).max(1).next_power_of_two();
+
+        let mut witnesses = if members.is_empty() {
+            Vec::new()
+        } else {
+            root_factor(&self.g, &members, &self.n)
+        };
+        witnesses.resize(capacity, BigUint::zero());
+
+        self.index = members.iter().cloned().zip(0..).collect();
+        self.members = members.into_iter().map(Some).collect();
+        self.members.resize(capacity, None);
+        self.free = (self.index.len()..capacity).rev().collect();
+        self.witnesses = witnesses;
+        self.added = vec![BigUint::one(); 2 * capacity];
+        self.deleted = vec![BigUint::one(); 2 * capacity];
+        self.capacity = capacity;
+        self.pending = 0;
+    }
+
+    /// Moves the pending changes on the path to leaf `i` down to the leaf.
+    fn push_down(&mut self, i: usize) {
+        let leaf = self.capacity + i;
+        for level in (1..=self.capacity.trailing_zeros()).rev() {
+            let node = leaf >> level;
+            push(&mut self.added, node);
+            push(&mut self.deleted, node);
+        }
+    }
+}
+
+fn push(pending: &mut [BigUint], node: usize) {
+    if pending[node].is_one() {
+        return;
+    }
+
+    let p = mem::replace(&mut pending[node], BigUint::one());
+    pending[2 * node] *= &p;
+    pending[2 * node + 1] *= &p;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::group::RSAGroup;
+    use crate::hooks::MutationKind;
+    use crate::traits::*;
+    use num_bigint::RandPrime;
+    use rand::SeedableRng;
+    use rand_chacha::ChaChaRng;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_witness_tree() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..20)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs[..8]);
+
+        let tree = Arc::new(Mutex::new(WitnessTree::new(&acc, &xs[..8])));
+        let observer = tree.clone();
+        acc.add_observer(move |m: &Mutation<'_>| observer.lock().unwrap().apply(m).unwrap());
+
+        let check = |acc: &Accumulator, members: &[&BigUint]| {
+            let mut tree = tree.lock().unwrap();
+            assert_eq!(tree.len(), members.len());
+            assert_eq!(tree.root(), acc.state());
+            for x in members {
+                let w = tree.witness(x).unwrap();
+                assert!(acc.ver_mem(&w, x));
+            }
+        };
+
+        acc.add(&xs[8]);
+        acc.batch_add(&xs[9..12]);
+        acc.del(&xs[3]).unwrap();
+        check(
+            &acc,
+            &xs[..12].iter().filter(|x| *x != &xs[3]).collect::<Vec<_>>(),
+        );
+
+        // pending changes after witnesses were read
+        acc.batch_add(&xs[12..14]);
+        let w_0 = tree.lock().unwrap().witness(&xs[0]).unwrap();
+        acc.del_w_mem(&w_0, &xs[0]).unwrap();
+        let pairs = {
+            let mut tree = tree.lock().unwrap();
+            vec![
+                (xs[1].clone(), tree.witness(&xs[1]).unwrap()),
+                (xs[9].clone(), tree.witness(&xs[9]).unwrap()),
+            ]
+        };
+        acc.batch_del(&pairs).unwrap();
+
+        let deleted = [&xs[0], &xs[1], &xs[3], &xs[9]];
+        let members = xs[..14]
+            .iter()
+            .filter(|x| !deleted.contains(x))
+            .collect::<Vec<_>>();
+        check(&acc, &members);
+        assert!(tree.lock().unwrap().witness(&xs[3]).is_none());
+
+        // deleted elements can be added again, and the tree grows
+        acc.add(&xs[3]);
+        acc.batch_add(&xs[14..]);
+        let mut members = members;
+        members.push(&xs[3]);
+        members.extend(&xs[14..]);
+        check(&acc, &members);
+
+        // out of sync
+        let mut other = tree.lock().unwrap().clone();
+        let root = acc.state().clone();
+        acc.clear_observers();
+        acc.del(&xs[2]).unwrap();
+        assert_eq!(
+            other.apply(&Mutation {
+                kind: MutationKind::Del,
+                elements: std::slice::from_ref(&xs[4]),
+                old_root: acc.state(),
+                new_root: &root,
+            }),
+            Err(Errors::OutOfSync)
+        );
+        assert_eq!(other.del(&xs[0], &root), Err(Errors::NotAMember));
+    }
+
+    #[test]
+    fn test_sample_members() {
+        let rng = &mut ChaChaRng::from_seed([0u8; 32]);
+        let int_size_bits = 256; // insecure, but faster tests
+        let mut acc = Accumulator::setup::<RSAGroup, _>(rng, int_size_bits);
+
+        let xs = (0..12)
+            .map(|_| rng.gen_prime(int_size_bits))
+            .collect::<Vec<_>>();
+        acc.batch_add(&xs);
+        let mut tree = WitnessTree::new(&acc, &xs);
+        let n = &acc.modulus().clone();
+
+        let sample = tree.sample_members(rng, 4).unwrap();
+        assert_eq!(sample.members.len(), 4);
+        assert!(sample.members.iter().all(|(x, _)| xs.contains(x)));
+        assert!(sample.verify(acc.state(), n));
+
+        // all members, if fewer than requested
+        assert_eq!(tree.sample_members(rng, 20).unwrap().members.len(), 12);
+        assert_eq!(tree.sample_members(rng, 0), Err(Errors::EmptyBatch));
+
+        // a claimed member that is not accumulated
+        let mut bad = sample.clone();
+        bad.members[0].0 = rng.gen_prime(int_size_bits);
+        assert!(!bad.verify(acc.state(), n));
+        let mut bad = sample.clone();
+        bad.members.pop();
+        assert!(!bad.verify(acc.state(), n));
+
+        // stale after a change
+        let y = rng.gen_prime(int_size_bits);
+        acc.add(&y);
+        assert!(!sample.verify(acc.state(), n));
+    }
+}